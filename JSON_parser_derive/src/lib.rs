@@ -0,0 +1,679 @@
+//! `#[derive(FromJson, ToJson)]` for `JSON_parser::convert`.
+//!
+//! Written against the bare `proc_macro` API: the struct or enum is read from
+//! the token stream by hand and the impls are generated as source text.
+//! Behaviour is configured with `#[json(...)]` attributes:
+//!
+//! - on the container: `rename_all = "..."` (`lowercase`, `UPPERCASE`,
+//!   `PascalCase`, `camelCase`, `snake_case`, `SCREAMING_SNAKE_CASE`,
+//!   `kebab-case`, `SCREAMING-KEBAB-CASE`), `default` for structs, and for
+//!   enums `tag = "..."` (internally tagged), `tag = "...", content = "..."`
+//!   (adjacently tagged) or `untagged`. Enums are externally tagged otherwise.
+//!   On enums `rename_all` renames the variants, not their fields.
+//! - on fields: `rename = "..."`, `default`, `default = "path::to::function"`,
+//!   `skip` and `flatten`. A flattened field is read from the members the
+//!   other fields leave over.
+//! - on variants: `rename = "..."`.
+
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Spacing, TokenStream, TokenTree};
+
+const CONVERT: &str = "::JSON_parser::convert";
+const JSON: &str = "::JSON_parser::parser::JsonType";
+const RESULT: &str = "::std::result::Result<Self, ::JSON_parser::convert::ConvertError>";
+
+const CASE_STYLES: [&str; 8] = [
+    "lowercase",
+    "UPPERCASE",
+    "PascalCase",
+    "camelCase",
+    "snake_case",
+    "SCREAMING_SNAKE_CASE",
+    "kebab-case",
+    "SCREAMING-KEBAB-CASE"
+];
+
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    return expand(input, from_json)
+}
+
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    return expand(input, to_json)
+}
+
+fn expand(input: TokenStream, generate: fn(&Item) -> Result<String, String>) -> TokenStream {
+    let code = match parse_item(input).and_then(|item| generate(&item)) {
+        Ok(code) => code,
+        Err(message) => format!("::core::compile_error!({message:?});")
+    };
+    return code.parse().unwrap()
+}
+
+#[derive(Default)]
+struct Attributes {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    // `Some(None)` for `default`, `Some(Some(path))` for `default = "path"`
+    default: Option<Option<String>>,
+    skip: bool,
+    flatten: bool,
+    tag: Option<String>,
+    content: Option<String>,
+    untagged: bool
+}
+
+struct Field {
+    ident: String,
+    attributes: Attributes
+}
+
+enum Fields {
+    Named(Vec<Field>),
+    Tuple(Vec<Field>),
+    Unit
+}
+
+struct Variant {
+    ident: String,
+    fields: Fields,
+    attributes: Attributes
+}
+
+enum Data {
+    Struct(Fields),
+    Enum(Vec<Variant>)
+}
+
+struct Item {
+    ident: String,
+    attributes: Attributes,
+    // Generic parameters as written, and as arguments after the type name
+    parameters: Vec<String>,
+    arguments: Vec<String>,
+    type_parameters: Vec<String>,
+    predicates: Vec<String>,
+    data: Data
+}
+
+type Tokens = std::iter::Peekable<proc_macro::token_stream::IntoIter>;
+
+fn tokens(trees: Vec<TokenTree>) -> Tokens {
+    return trees.into_iter().collect::<TokenStream>().into_iter().peekable()
+}
+
+fn text(trees: &[TokenTree]) -> String {
+    return trees.iter().cloned().collect::<TokenStream>().to_string()
+}
+
+fn is_punct(tree: Option<&TokenTree>, c: char) -> bool {
+    return matches!(tree, Some(TokenTree::Punct(p)) if p.as_char() == c)
+}
+
+/// Splits on top-level commas, treating `<...>` as nested.
+fn split_commas(trees: Vec<TokenTree>) -> Vec<Vec<TokenTree>> {
+    let mut output = vec![Vec::new()];
+    let mut depth = 0;
+    let mut arrow = false;
+    for tree in trees {
+        if let TokenTree::Punct(p) = &tree {
+            match p.as_char() {
+                ',' if depth == 0 => {
+                    output.push(Vec::new());
+                    continue;
+                },
+                '<' => depth += 1,
+                // `->` in a function type is not a closing bracket
+                '>' if !arrow => depth -= 1,
+                _ => {}
+            }
+            arrow = p.as_char() == '-' && p.spacing() == Spacing::Joint;
+        } else {
+            arrow = false;
+        }
+        output.last_mut().unwrap().push(tree);
+    }
+    output.retain(|chunk| !chunk.is_empty());
+    return output
+}
+
+fn unquote(literal: &str) -> Result<String, String> {
+    match literal.strip_prefix('"').and_then(|s| s.strip_suffix('"')) {
+        Some(inner) => return Ok(inner.replace("\\\"", "\"").replace("\\\\", "\\")),
+        None => return Err(format!("expected a string literal, found `{literal}`"))
+    }
+}
+
+fn parse_json_attribute(stream: TokenStream, attributes: &mut Attributes) -> Result<(), String> {
+    for item in split_commas(stream.into_iter().collect()) {
+        let key = match item.first() {
+            Some(TokenTree::Ident(key)) => key.to_string(),
+            _ => return Err(format!("expected a json attribute, found `{}`", text(&item)))
+        };
+        let value = match item.get(1..) {
+            Some([]) => None,
+            Some([TokenTree::Punct(p), TokenTree::Literal(literal)]) if p.as_char() == '=' => Some(unquote(&literal.to_string())?),
+            _ => return Err(format!("malformed json attribute `{}`", text(&item)))
+        };
+        match (key.as_str(), value) {
+            ("rename", Some(value)) => attributes.rename = Some(value),
+            ("rename_all", Some(value)) => {
+                if !CASE_STYLES.contains(&value.as_str()) {
+                    return Err(format!("unknown rename_all style \"{value}\", expected one of {}", CASE_STYLES.join(", ")))
+                }
+                attributes.rename_all = Some(value);
+            },
+            ("default", value) => attributes.default = Some(value),
+            ("skip", None) => attributes.skip = true,
+            ("flatten", None) => attributes.flatten = true,
+            ("tag", Some(value)) => attributes.tag = Some(value),
+            ("content", Some(value)) => attributes.content = Some(value),
+            ("untagged", None) => attributes.untagged = true,
+            _ => return Err(format!("unknown json attribute `{}`", text(&item)))
+        }
+    }
+    return Ok(())
+}
+
+/// Reads the outer attributes in front of an item, field or variant, keeping `#[json(...)]`.
+fn parse_attributes(tokens: &mut Tokens) -> Result<Attributes, String> {
+    let mut attributes = Attributes::default();
+    while is_punct(tokens.peek(), '#') {
+        tokens.next();
+        let Some(TokenTree::Group(group)) = tokens.next() else {
+            return Err("expected an attribute after `#`".to_string())
+        };
+        let mut inner = group.stream().into_iter();
+        if let (Some(TokenTree::Ident(name)), Some(TokenTree::Group(arguments))) = (inner.next(), inner.next())
+            && name.to_string() == "json"
+        {
+            parse_json_attribute(arguments.stream(), &mut attributes)?;
+        }
+    }
+    return Ok(attributes)
+}
+
+fn skip_visibility(tokens: &mut Tokens) {
+    if matches!(tokens.peek(), Some(TokenTree::Ident(i)) if i.to_string() == "pub") {
+        tokens.next();
+        if matches!(tokens.peek(), Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis) {
+            tokens.next();
+        }
+    }
+}
+
+fn parse_ident(tokens: &mut Tokens, what: &str) -> Result<String, String> {
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) => return Ok(ident.to_string()),
+        other => return Err(format!("expected {what}, found `{}`", text(&other.into_iter().collect::<Vec<_>>())))
+    }
+}
+
+fn parse_fields(group: &proc_macro::Group) -> Result<Fields, String> {
+    let named = group.delimiter() == Delimiter::Brace;
+    let mut fields = Vec::new();
+    for (index, chunk) in split_commas(group.stream().into_iter().collect()).into_iter().enumerate() {
+        let mut tokens = tokens(chunk);
+        let attributes = parse_attributes(&mut tokens)?;
+        skip_visibility(&mut tokens);
+        let ident = match named {
+            true => {
+                let ident = parse_ident(&mut tokens, "a field name")?;
+                if !is_punct(tokens.next().as_ref(), ':') {
+                    return Err(format!("expected `:` after field `{ident}`"))
+                }
+                ident
+            },
+            false => index.to_string()
+        };
+        fields.push(Field { ident, attributes });
+    }
+    if !named && fields.iter().any(|f| f.attributes.rename.is_some() || f.attributes.flatten || f.attributes.skip || f.attributes.default.is_some()) {
+        return Err("json attributes are not supported on tuple fields".to_string())
+    }
+    match named {
+        true => return Ok(Fields::Named(fields)),
+        false => return Ok(Fields::Tuple(fields))
+    }
+}
+
+fn parse_variants(group: &proc_macro::Group) -> Result<Vec<Variant>, String> {
+    let mut variants = Vec::new();
+    for chunk in split_commas(group.stream().into_iter().collect()) {
+        let mut tokens = tokens(chunk);
+        let attributes = parse_attributes(&mut tokens)?;
+        let ident = parse_ident(&mut tokens, "a variant name")?;
+        let fields = match tokens.next() {
+            Some(TokenTree::Group(group)) => parse_fields(&group)?,
+            // No fields, or an explicit discriminant
+            _ => Fields::Unit
+        };
+        variants.push(Variant { ident, fields, attributes });
+    }
+    return Ok(variants)
+}
+
+/// Reads `<...>` after the type name, if any.
+fn parse_generics(tokens: &mut Tokens, item: &mut Item) {
+    if !is_punct(tokens.peek(), '<') {
+        return
+    }
+    tokens.next();
+    let mut inside = Vec::new();
+    let mut depth = 0;
+    for tree in tokens.by_ref() {
+        if is_punct(Some(&tree), '<') {
+            depth += 1;
+        }
+        if is_punct(Some(&tree), '>') {
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+        }
+        inside.push(tree);
+    }
+    for mut parameter in split_commas(inside) {
+        // Defaults are not allowed on impl parameters
+        if let Some(equals) = parameter.iter().position(|t| is_punct(Some(t), '=')) {
+            parameter.truncate(equals);
+        }
+        let argument = match parameter.as_slice() {
+            [TokenTree::Punct(p), lifetime, ..] if p.as_char() == '\'' => format!("'{lifetime}"),
+            [TokenTree::Ident(keyword), name, ..] if keyword.to_string() == "const" => name.to_string(),
+            [name, ..] => {
+                item.type_parameters.push(name.to_string());
+                name.to_string()
+            },
+            [] => continue
+        };
+        item.parameters.push(text(&parameter));
+        item.arguments.push(argument);
+    }
+}
+
+/// Reads a `where` clause made of `trees`.
+fn parse_where(trees: &[TokenTree], item: &mut Item) {
+    if let [TokenTree::Ident(keyword), rest @ ..] = trees
+        && keyword.to_string() == "where"
+    {
+        item.predicates.extend(split_commas(rest.to_vec()).iter().map(|p| text(p)));
+    }
+}
+
+fn parse_item(input: TokenStream) -> Result<Item, String> {
+    let mut tokens = input.into_iter().peekable();
+    let attributes = parse_attributes(&mut tokens)?;
+    skip_visibility(&mut tokens);
+    let kind = parse_ident(&mut tokens, "`struct` or `enum`")?;
+    let ident = parse_ident(&mut tokens, "a type name")?;
+    let mut item = Item {
+        ident,
+        attributes,
+        parameters: Vec::new(),
+        arguments: Vec::new(),
+        type_parameters: Vec::new(),
+        predicates: Vec::new(),
+        data: Data::Struct(Fields::Unit)
+    };
+    parse_generics(&mut tokens, &mut item);
+
+    let rest: Vec<TokenTree> = tokens.collect();
+    let body = rest.iter().position(|t| matches!(t, TokenTree::Group(g) if g.delimiter() != Delimiter::Bracket));
+    let (before, group, after) = match body {
+        Some(index) => {
+            let TokenTree::Group(group) = &rest[index] else {
+                unreachable!()
+            };
+            (&rest[..index], Some(group), &rest[index + 1..])
+        },
+        None => (&rest[..rest.len().saturating_sub(1)], None, &[][..])
+    };
+    parse_where(before, &mut item);
+    // A tuple struct has its where clause after the fields
+    let after: Vec<TokenTree> = after.iter().take_while(|t| !is_punct(Some(t), ';')).cloned().collect();
+    parse_where(&after, &mut item);
+
+    item.data = match (kind.as_str(), group) {
+        ("struct", Some(group)) => Data::Struct(parse_fields(group)?),
+        ("struct", None) => Data::Struct(Fields::Unit),
+        ("enum", Some(group)) => Data::Enum(parse_variants(group)?),
+        _ => return Err(format!("FromJson and ToJson can only be derived for structs and enums, not `{kind}`"))
+    };
+
+    let attributes = &item.attributes;
+    let is_enum = matches!(item.data, Data::Enum(_));
+    if !is_enum && (attributes.tag.is_some() || attributes.untagged) {
+        return Err("`tag` and `untagged` only apply to enums".to_string())
+    }
+    if is_enum && attributes.default.is_some() {
+        return Err("`default` on the container only applies to structs".to_string())
+    }
+    if attributes.content.is_some() && attributes.tag.is_none() {
+        return Err("`content` needs a `tag`".to_string())
+    }
+    if attributes.untagged && attributes.tag.is_some() {
+        return Err("an enum cannot be both `untagged` and tagged".to_string())
+    }
+    return Ok(item)
+}
+
+fn split_words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in name.split('_').filter(|p| !p.is_empty()) {
+        let mut word = String::new();
+        let mut previous_lower = false;
+        for c in part.chars() {
+            if c.is_uppercase() && previous_lower {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+            word.push(c);
+        }
+        words.push(word);
+    }
+    return words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => return first.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect(),
+        None => return String::new()
+    }
+}
+
+/// Renames a field (`snake_case`) or variant (`PascalCase`) to `style`.
+fn rename(name: &str, style: &str) -> String {
+    let words = split_words(name);
+    let join = |separator: &str, upper: bool| {
+        let words: Vec<String> = words.iter().map(|w| if upper { w.to_uppercase() } else { w.to_lowercase() }).collect();
+        words.join(separator)
+    };
+    match style {
+        "lowercase" => return name.to_lowercase(),
+        "UPPERCASE" => return name.to_uppercase(),
+        "PascalCase" => return words.iter().map(|w| capitalize(w)).collect(),
+        "camelCase" => {
+            let pascal: String = words.iter().map(|w| capitalize(w)).collect();
+            let mut chars = pascal.chars();
+            return chars.next().map(|c| c.to_lowercase().chain(chars).collect()).unwrap_or_default()
+        },
+        "snake_case" => return join("_", false),
+        "SCREAMING_SNAKE_CASE" => return join("_", true),
+        "kebab-case" => return join("-", false),
+        "SCREAMING-KEBAB-CASE" => return join("-", true),
+        _ => unreachable!()
+    }
+}
+
+/// The JSON name of a field or variant.
+fn key(ident: &str, attributes: &Attributes, rename_all: Option<&String>) -> String {
+    let ident = ident.strip_prefix("r#").unwrap_or(ident);
+    match (&attributes.rename, rename_all) {
+        (Some(name), _) => return format!("{name:?}"),
+        (None, Some(style)) => return format!("{:?}", rename(ident, style)),
+        (None, None) => return format!("{ident:?}")
+    }
+}
+
+fn impl_header(item: &Item, trait_name: &str) -> String {
+    let mut output = String::from("#[automatically_derived] impl");
+    if !item.parameters.is_empty() {
+        output.push_str(&format!("<{}>", item.parameters.join(", ")));
+    }
+    output.push_str(&format!(" {CONVERT}::{trait_name} for {}", item.ident));
+    if !item.arguments.is_empty() {
+        output.push_str(&format!("<{}>", item.arguments.join(", ")));
+    }
+    let mut predicates = item.predicates.clone();
+    predicates.extend(item.type_parameters.iter().map(|t| format!("{t}: {CONVERT}::{trait_name}")));
+    if !predicates.is_empty() {
+        output.push_str(&format!(" where {}", predicates.join(", ")));
+    }
+    return output
+}
+
+// FromJson
+
+/// Reads the named `fields` out of `object`, which came from `value`.
+fn named_from_json(fields: &[Field], constructor: &str, item: &Item) -> String {
+    let container_default = item.attributes.default.is_some() && matches!(item.data, Data::Struct(_));
+    let mut output = format!("let object = {CONVERT}::__private::object(value)?;");
+    if container_default {
+        let default = match &item.attributes.default {
+            Some(Some(path)) => format!("{path}()"),
+            _ => "::std::default::Default::default()".to_string()
+        };
+        output.push_str(&format!("let __default: Self = {default};"));
+    }
+    // Variant fields are not renamed by the container's rename_all
+    let rename_all = match item.data {
+        Data::Struct(_) => item.attributes.rename_all.as_ref(),
+        Data::Enum(_) => None
+    };
+    // A flattened field gets the members no other field reads
+    let keys: Vec<String> = fields.iter()
+        .filter(|f| !f.attributes.skip && !f.attributes.flatten)
+        .map(|f| key(&f.ident, &f.attributes, rename_all))
+        .collect();
+    output.push_str(&format!("return ::std::result::Result::Ok({constructor} {{"));
+    for field in fields {
+        let key = key(&field.ident, &field.attributes, rename_all);
+        let default = match (&field.attributes.default, container_default) {
+            (Some(Some(path)), _) => path.clone(),
+            (Some(None), _) => "::std::default::Default::default".to_string(),
+            (None, true) => format!("|| __default.{}", field.ident),
+            (None, false) => String::new()
+        };
+        let expression = if field.attributes.skip {
+            match default.is_empty() {
+                true => "::std::default::Default::default()".to_string(),
+                false => format!("({default})()")
+            }
+        } else if field.attributes.flatten {
+            format!("{CONVERT}::FromJson::from_json(&{CONVERT}::__private::without(object, &[{}]))?", keys.join(", "))
+        } else if default.is_empty() {
+            format!("{CONVERT}::__private::field(object, {key})?")
+        } else {
+            format!("{CONVERT}::__private::field_or(object, {key}, {default})?")
+        };
+        output.push_str(&format!("{}: {expression},", field.ident));
+    }
+    output.push_str("})");
+    return output
+}
+
+/// Statements reading `fields` out of `value` and returning `constructor`.
+fn fields_from_json(fields: &Fields, constructor: &str, item: &Item) -> String {
+    match fields {
+        Fields::Named(fields) => return named_from_json(fields, constructor, item),
+        Fields::Tuple(fields) if fields.len() == 1 => {
+            return format!("return {CONVERT}::FromJson::from_json(value).map({constructor});")
+        },
+        Fields::Tuple(fields) => {
+            let elements: Vec<String> = (0..fields.len()).map(|i| format!("{CONVERT}::__private::element(values, {i})?")).collect();
+            return format!(
+                "let values = {CONVERT}::__private::array(value, {})?; return ::std::result::Result::Ok({constructor}({}));",
+                fields.len(),
+                elements.join(", ")
+            )
+        },
+        Fields::Unit => return format!("return {CONVERT}::__private::unit(value).map(|_| {constructor});")
+    }
+}
+
+/// An expression converting `value` into the variant.
+fn variant_from_json(variant: &Variant, item: &Item) -> String {
+    let body = fields_from_json(&variant.fields, &format!("Self::{}", variant.ident), item);
+    return format!("(|| -> {RESULT} {{ {body} }})()")
+}
+
+fn enum_from_json(variants: &[Variant], item: &Item) -> Result<String, String> {
+    let attributes = &item.attributes;
+    let mut arms = String::new();
+    for variant in variants {
+        let key = key(&variant.ident, &variant.attributes, attributes.rename_all.as_ref());
+        let unit = matches!(variant.fields, Fields::Unit);
+        let arm = match (&attributes.tag, &attributes.content) {
+            _ if attributes.untagged => {
+                arms.push_str(&format!(
+                    "if let ::std::result::Result::Ok(variant) = {} {{ return ::std::result::Result::Ok(variant); }}",
+                    variant_from_json(variant, item)
+                ));
+                continue;
+            },
+            _ if unit => format!("::std::result::Result::Ok(Self::{})", variant.ident),
+            (Some(_), None) => {
+                if matches!(&variant.fields, Fields::Tuple(fields) if fields.len() > 1) {
+                    return Err(format!("internally tagged enums cannot have tuple variants like `{}`", variant.ident))
+                }
+                variant_from_json(variant, item)
+            },
+            (Some(_), Some(content)) => format!(
+                "{{ let value = {CONVERT}::__private::content(object, {content:?})?; {}.map_err(|e| e.at({content:?})) }}",
+                variant_from_json(variant, item)
+            ),
+            (None, _) => format!("{}.map_err(|e| e.at(name))", variant_from_json(variant, item))
+        };
+        arms.push_str(&format!("{key} => return {arm},"));
+    }
+
+    match &attributes.tag {
+        _ if attributes.untagged => {
+            return Ok(format!("{arms} return ::std::result::Result::Err({CONVERT}::ConvertError::NoVariantMatched {{ path: ::std::string::String::new() }});"))
+        },
+        Some(tag) => return Ok(format!(
+            "let object = {CONVERT}::__private::object(value)?; let name = {CONVERT}::__private::tag(object, {tag:?})?;
+            match name {{ {arms} _ => return ::std::result::Result::Err({CONVERT}::__private::unknown_variant(name).at({tag:?})) }}"
+        )),
+        None => return Ok(format!(
+            "let (name, value) = {CONVERT}::__private::external(value)?;
+            match name {{ {arms} _ => return ::std::result::Result::Err({CONVERT}::__private::unknown_variant(name)) }}"
+        ))
+    }
+}
+
+fn from_json(item: &Item) -> Result<String, String> {
+    let body = match &item.data {
+        Data::Struct(fields) => fields_from_json(fields, "Self", item),
+        Data::Enum(variants) => enum_from_json(variants, item)?
+    };
+    return Ok(format!(
+        "{} {{ fn from_json(value: &{JSON}) -> {RESULT} {{ {body} }} }}",
+        impl_header(item, "FromJson")
+    ))
+}
+
+// ToJson
+
+/// An object built from named fields, each available as `{access}{ident}`.
+fn named_to_json(fields: &[Field], access: &str, rename_all: Option<&String>) -> String {
+    let mut output = format!("{{ let mut object = {CONVERT}::__private::HashMap::new();");
+    for field in fields.iter().filter(|f| !f.attributes.skip) {
+        let value = format!("{CONVERT}::ToJson::to_json({access}{})", field.ident);
+        match field.attributes.flatten {
+            true => output.push_str(&format!("{CONVERT}::__private::flatten(&mut object, {value});")),
+            false => output.push_str(&format!(
+                "object.insert(::std::string::String::from({}), {value});",
+                key(&field.ident, &field.attributes, rename_all)
+            ))
+        }
+    }
+    output.push_str(&format!("{JSON}::JsonObject(object) }}"));
+    return output
+}
+
+/// The JSON for `fields`, each available as `{access}{ident}`.
+fn fields_to_json(fields: &Fields, access: &str, rename_all: Option<&String>) -> String {
+    match fields {
+        Fields::Named(fields) => return named_to_json(fields, access, rename_all),
+        Fields::Tuple(fields) if fields.len() == 1 => return format!("{CONVERT}::ToJson::to_json({access}0)"),
+        Fields::Tuple(fields) => {
+            let elements: Vec<String> = fields.iter().map(|f| format!("{CONVERT}::ToJson::to_json({access}{})", f.ident)).collect();
+            return format!("{JSON}::JsonArray(::std::vec![{}])", elements.join(", "))
+        },
+        Fields::Unit => return format!("{JSON}::JsonNull")
+    }
+}
+
+fn enum_to_json(variants: &[Variant], item: &Item) -> String {
+    let attributes = &item.attributes;
+    let mut arms = String::new();
+    for variant in variants {
+        let pattern = match &variant.fields {
+            Fields::Named(fields) => {
+                let bindings: Vec<String> = fields.iter().map(|f| match f.attributes.skip {
+                    true => format!("{}: _", f.ident),
+                    false => f.ident.clone()
+                }).collect();
+                format!("Self::{} {{ {} }}", variant.ident, bindings.join(", "))
+            },
+            Fields::Tuple(fields) => {
+                let bindings: Vec<String> = (0..fields.len()).map(|i| format!("__{i}")).collect();
+                format!("Self::{}({})", variant.ident, bindings.join(", "))
+            },
+            Fields::Unit => format!("Self::{}", variant.ident)
+        };
+        let access = match variant.fields {
+            Fields::Tuple(_) => "__",
+            _ => ""
+        };
+        // Variant fields are not renamed by the container's rename_all
+        let content = fields_to_json(&variant.fields, access, None);
+        let key = key(&variant.ident, &variant.attributes, attributes.rename_all.as_ref());
+        let unit = matches!(variant.fields, Fields::Unit);
+        let value = match (&attributes.tag, &attributes.content) {
+            _ if attributes.untagged => content,
+            (Some(tag), None) => format!("{CONVERT}::__private::internal_to_json({tag:?}, {key}, {content})"),
+            (Some(tag), Some(content_key)) => match unit {
+                true => format!("{CONVERT}::__private::adjacent_to_json({tag:?}, {key}, {content_key:?}, ::std::option::Option::None)"),
+                false => format!("{CONVERT}::__private::adjacent_to_json({tag:?}, {key}, {content_key:?}, ::std::option::Option::Some({content}))")
+            },
+            (None, _) => match unit {
+                true => format!("{JSON}::JsonString(::std::string::String::from({key}))"),
+                false => format!("{CONVERT}::__private::external_to_json({key}, {content})")
+            }
+        };
+        arms.push_str(&format!("{pattern} => {value},"));
+    }
+    return format!("match self {{ {arms} }}")
+}
+
+fn to_json(item: &Item) -> Result<String, String> {
+    let body = match &item.data {
+        Data::Struct(fields) => fields_to_json(fields, "&self.", item.attributes.rename_all.as_ref()),
+        Data::Enum(variants) => enum_to_json(variants, item)
+    };
+    return Ok(format!(
+        "{} {{ fn to_json(&self) -> {JSON} {{ {body} }} }}",
+        impl_header(item, "ToJson")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case_styles() {
+        let cases = [
+            ("lowercase", "user_id", "user_id", "UserId", "userid"),
+            ("UPPERCASE", "user_id", "USER_ID", "UserId", "USERID"),
+            ("PascalCase", "user_id", "UserId", "UserId", "UserId"),
+            ("camelCase", "user_id", "userId", "UserId", "userId"),
+            ("snake_case", "user_id", "user_id", "UserId", "user_id"),
+            ("SCREAMING_SNAKE_CASE", "user_id", "USER_ID", "UserId", "USER_ID"),
+            ("kebab-case", "user_id", "user-id", "UserId", "user-id"),
+            ("SCREAMING-KEBAB-CASE", "user_id", "USER-ID", "UserId", "USER-ID")
+        ];
+        for (style, field, renamed_field, variant, renamed_variant) in cases {
+            assert_eq!(rename(field, style), renamed_field, "{style}");
+            assert_eq!(rename(variant, style), renamed_variant, "{style}");
+        }
+        assert_eq!(split_words("HTTPServer2Go"), vec!["HTTPServer2", "Go"]);
+        assert_eq!(rename("v2_name", "camelCase"), "v2Name");
+    }
+}
@@ -0,0 +1,40 @@
+//! `jsonp codegen`: Rust types from sample documents or a JSON Schema.
+
+use std::process::ExitCode;
+
+use JSON_parser::codegen::{generate, generate_from_samples};
+use JSON_parser::parser::ParserOptions;
+
+use crate::{read_documents, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp codegen [--schema] [--name NAME] [files]
+
+Prints Rust structs and enums deriving `FromJson` and `ToJson`. The inputs are
+sample documents, merged into one schema first, or with `--schema` a single
+JSON Schema.
+
+options:
+    --schema       the input is a JSON Schema instead of samples
+    --name NAME    name of the root type, `Root` by default";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    let arguments = Arguments::parse(args, &["--name"], &["--schema", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let name = arguments.value("--name").unwrap_or("Root");
+    let documents = read_documents(&arguments, ParserOptions::default())?;
+
+    let code = if arguments.flag("--schema") {
+        let [schema] = documents.as_slice() else {
+            return Err(Failure::Usage("`--schema` takes exactly one input".to_string()))
+        };
+        generate(schema, name).map_err(|e| Failure::Error(format!("invalid schema: {e:?}")))?
+    } else {
+        generate_from_samples(&documents, name)
+    };
+    print!("{code}");
+    return Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,82 @@
+//! `jsonp diff`: the differences between two documents.
+
+use std::process::ExitCode;
+
+use JSON_parser::diff::{diff_with, render, render_colored, DiffOptions};
+use JSON_parser::merge::{create_merge_patch, merge_patch};
+use JSON_parser::patch;
+use JSON_parser::serializer::to_string_pretty;
+
+use crate::{parse_input, parser_options, read_input, with_error_status, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp diff [--format FORMAT] [--ignore-order] [--color] [--quiet] <old> <new>
+
+Compares two documents. As with diff(1) the exit status is 0 if they are equal,
+1 if they differ and 2 if something went wrong.
+
+options:
+    --format FORMAT    `text` for a readable diff (the default), `patch` for a
+                       JSON Patch (RFC 6902) or `merge` for a merge patch (RFC 7396)
+    --ignore-order     compare arrays regardless of the order of their elements,
+                       only with the text format
+    --color            color the text format
+    --quiet            print nothing, only set the exit status
+    --json5            accept JSON5
+    --strict           reject the legacy quirks of the default parser";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    // Trouble is 2 for diff, the status 1 means the documents differ
+    return with_error_status(compare(args))
+}
+
+fn compare(args: &[String]) -> Result<ExitCode, Failure> {
+    let flags = ["--ignore-order", "--color", "--quiet", "--json5", "--strict", "--help"];
+    let arguments = Arguments::parse(args, &["--format"], &flags)?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let [old_path, new_path] = arguments.positional.as_slice() else {
+        return Err(Failure::Usage("`diff` takes exactly two inputs".to_string()))
+    };
+    if old_path == "-" && new_path == "-" {
+        return Err(Failure::Usage("only one input can be standard input".to_string()))
+    }
+    let format = arguments.value("--format").unwrap_or("text");
+    match format {
+        "text" => {},
+        "patch" | "merge" if arguments.flag("--ignore-order") => {
+            return Err(Failure::Usage(format!("`--ignore-order` cannot be used with the {format} format")))
+        },
+        "patch" | "merge" => {},
+        other => return Err(Failure::Usage(format!("unknown format `{other}`")))
+    }
+    let options = parser_options(&arguments);
+    let old = parse_input(old_path, &read_input(old_path)?, options).map_err(Failure::Error)?;
+    let new = parse_input(new_path, &read_input(new_path)?, options).map_err(Failure::Error)?;
+
+    let changes = diff_with(&old, &new, DiffOptions { ignore_array_order: arguments.flag("--ignore-order") });
+    if changes.is_empty() {
+        return Ok(ExitCode::SUCCESS)
+    }
+    if !arguments.flag("--quiet") {
+        match format {
+            "patch" => println!("{}", to_string_pretty(&patch::diff(&old, &new), 2)),
+            "merge" => {
+                // Merge patches cannot add a `null` to an object
+                let patch = create_merge_patch(&old, &new);
+                let mut patched = old.clone();
+                merge_patch(&mut patched, &patch);
+                if patched != new {
+                    return Err(Failure::Error("the change cannot be expressed as a merge patch, use `--format patch`".to_string()))
+                }
+                println!("{}", to_string_pretty(&patch, 2));
+            },
+            _ if arguments.flag("--color") => print!("{}", render_colored(&changes)),
+            _ => print!("{}", render(&changes))
+        }
+    }
+    return Ok(ExitCode::FAILURE)
+}
+
@@ -0,0 +1,79 @@
+//! `jsonp fmt`: pretty-printing, in place or as a check.
+
+use std::process::ExitCode;
+
+use JSON_parser::serializer::node_to_string_indented;
+
+use crate::{display_name, parse_node, parser_options, read_input, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp fmt [--indent N | --tab] [--write | --check] [--json5] [--strict] [files]
+
+Prints every input with one value per line. Object members keep their order,
+numbers and strings keep their spelling. Comments are dropped, JSON5 input is
+written as plain JSON.
+
+options:
+    --indent N    indent by N spaces, 2 by default
+    --tab         indent with tabs
+    --write       rewrite the files in place
+    --check       print nothing, exit with 1 if any file is not formatted
+    --json5       accept JSON5
+    --strict      reject the legacy quirks of the default parser";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    let arguments = Arguments::parse(args, &["--indent"], &["--tab", "--write", "--check", "--json5", "--strict", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let indent = match (arguments.value("--indent"), arguments.flag("--tab")) {
+        (Some(_), true) => return Err(Failure::Usage("`--indent` and `--tab` cannot be combined".to_string())),
+        (Some(n), false) => match n.parse::<usize>() {
+            Ok(n) => " ".repeat(n),
+            Err(_) => return Err(Failure::Usage(format!("invalid indent `{n}`")))
+        },
+        (None, true) => "\t".to_string(),
+        (None, false) => "  ".to_string()
+    };
+    let write = arguments.flag("--write");
+    let check = arguments.flag("--check");
+    if write && check {
+        return Err(Failure::Usage("`--write` and `--check` cannot be combined".to_string()))
+    }
+    let inputs = arguments.inputs();
+    if write && inputs.iter().any(|path| path == "-") {
+        return Err(Failure::Usage("`--write` needs files, not standard input".to_string()))
+    }
+    let options = parser_options(&arguments);
+
+    let mut success = true;
+    for path in inputs {
+        let text = read_input(&path)?;
+        let node = match parse_node(&path, &text, options) {
+            Ok(node) => node,
+            Err(diagnostic) => {
+                eprintln!("{diagnostic}");
+                success = false;
+                continue
+            }
+        };
+        let formatted = node_to_string_indented(&node, &indent) + "\n";
+        if check {
+            if formatted != text {
+                eprintln!("{}: not formatted", display_name(&path));
+                success = false;
+            }
+        } else if write {
+            if formatted != text {
+                std::fs::write(&path, formatted).map_err(|e| Failure::Error(format!("{path}: {e}")))?;
+            }
+        } else {
+            print!("{formatted}");
+        }
+    }
+    if success {
+        return Ok(ExitCode::SUCCESS)
+    }
+    return Ok(ExitCode::FAILURE)
+}
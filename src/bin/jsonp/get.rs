@@ -0,0 +1,49 @@
+//! `jsonp get`: the value at a JSON Pointer.
+
+use std::process::ExitCode;
+
+use JSON_parser::pointer::parse_pointer;
+
+use crate::{format_value, matched, parser_options, read_documents, with_error_status, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp get [--raw] [--pretty] [--ndjson] [--json5] <pointer> [files]
+
+Prints the value at a JSON Pointer such as `/users/0/name` in every input, the
+empty pointer selects the whole document. The exit status is 1 if no input has
+a value there and 2 if something went wrong.
+
+options:
+    --raw       print strings without quotes
+    --pretty    print values indented
+    --ndjson    read one document per line
+    --json5     accept JSON5
+    --strict    reject the legacy quirks of the default parser";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    return with_error_status(extract(args))
+}
+
+fn extract(args: &[String]) -> Result<ExitCode, Failure> {
+    let mut arguments = Arguments::parse(args, &[], &["--raw", "--pretty", "--ndjson", "--json5", "--strict", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    if arguments.positional.is_empty() {
+        return Err(Failure::Usage("missing pointer".to_string()))
+    }
+    let pointer = arguments.positional.remove(0);
+    if let Err(e) = parse_pointer(&pointer) {
+        return Err(Failure::Usage(format!("invalid pointer `{pointer}`: {e:?}")))
+    }
+
+    let mut found = false;
+    for document in read_documents(&arguments, parser_options(&arguments))? {
+        if let Some(value) = document.pointer(&pointer) {
+            println!("{}", format_value(value, &arguments));
+            found = true;
+        }
+    }
+    return Ok(matched(found))
+}
@@ -0,0 +1,114 @@
+//! `jsonp keys` and `jsonp paths`: the structure of documents.
+
+use std::process::ExitCode;
+
+use JSON_parser::parser::JsonType;
+use JSON_parser::pointer::escape_token;
+
+use crate::{format_value, matched, parser_options, read_documents, with_error_status, Arguments, Failure};
+
+const KEYS_HELP: &str = "\
+usage: jsonp keys [--raw] [--ndjson] [--json5] [files]
+
+Prints the keys of every input that is an object, in sorted order, or the
+indices of an array. The exit status is 1 if there were none and 2 if something
+went wrong.
+
+options:
+    --raw       print keys without quotes
+    --ndjson    read one document per line
+    --json5     accept JSON5
+    --strict    reject the legacy quirks of the default parser";
+
+const PATHS_HELP: &str = "\
+usage: jsonp paths [--raw] [--leaves] [--ndjson] [--json5] [files]
+
+Prints the JSON Pointer of every value below the root of every input, parents
+before their children and object keys in sorted order. The exit status is 1 if
+there were none and 2 if something went wrong.
+
+options:
+    --raw       print pointers without quotes
+    --leaves    only print values that are not a non-empty array or object
+    --ndjson    read one document per line
+    --json5     accept JSON5
+    --strict    reject the legacy quirks of the default parser";
+
+pub fn run_keys(args: &[String]) -> Result<ExitCode, Failure> {
+    return with_error_status(keys(args))
+}
+
+fn keys(args: &[String]) -> Result<ExitCode, Failure> {
+    let arguments = Arguments::parse(args, &[], &["--raw", "--ndjson", "--json5", "--strict", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{KEYS_HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let mut found = false;
+    for document in read_documents(&arguments, parser_options(&arguments))? {
+        match document {
+            JsonType::JsonObject(map) => {
+                let mut keys: Vec<String> = map.into_keys().collect();
+                keys.sort();
+                for key in keys {
+                    println!("{}", format_value(&JsonType::JsonString(key), &arguments));
+                    found = true;
+                }
+            },
+            JsonType::JsonArray(values) => {
+                for i in 0..values.len() {
+                    println!("{i}");
+                    found = true;
+                }
+            },
+            _ => {}
+        }
+    }
+    return Ok(matched(found))
+}
+
+/// Pushes the pointers below `value`, which sits at `pointer`.
+fn collect_paths(value: &JsonType, pointer: &str, leaves: bool, paths: &mut Vec<String>) {
+    let children: Vec<(String, &JsonType)> = match value {
+        JsonType::JsonObject(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            keys.into_iter().map(|key| (format!("{pointer}/{}", escape_token(key)), &map[key])).collect()
+        },
+        JsonType::JsonArray(values) => values.iter().enumerate().map(|(i, value)| (format!("{pointer}/{i}"), value)).collect(),
+        _ => Vec::new()
+    };
+    for (child_pointer, child) in children {
+        let is_leaf = match child {
+            JsonType::JsonObject(map) => map.is_empty(),
+            JsonType::JsonArray(values) => values.is_empty(),
+            _ => true
+        };
+        if is_leaf || !leaves {
+            paths.push(child_pointer.clone());
+        }
+        collect_paths(child, &child_pointer, leaves, paths);
+    }
+}
+
+pub fn run_paths(args: &[String]) -> Result<ExitCode, Failure> {
+    return with_error_status(paths(args))
+}
+
+fn paths(args: &[String]) -> Result<ExitCode, Failure> {
+    let arguments = Arguments::parse(args, &[], &["--raw", "--leaves", "--ndjson", "--json5", "--strict", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{PATHS_HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let mut found = false;
+    for document in read_documents(&arguments, parser_options(&arguments))? {
+        let mut paths = Vec::new();
+        collect_paths(&document, "", arguments.flag("--leaves"), &mut paths);
+        for path in paths {
+            println!("{}", format_value(&JsonType::JsonString(path), &arguments));
+            found = true;
+        }
+    }
+    return Ok(matched(found))
+}
@@ -0,0 +1,300 @@
+//! `jsonp`, command line tools on top of the `JSON_parser` library.
+//!
+//! Every command reads its documents from the files given, or from standard
+//! input when there are none or a file is `-`, and writes to standard output.
+//! Problems go to standard error with the exit status 1, or 2 for invalid
+//! arguments. Commands that use 1 to report a result, such as `diff` for
+//! documents that differ or `get` for no match, exit with 2 on any problem.
+
+mod codegen;
+mod diff;
+mod fmt;
+mod get;
+mod keys;
+mod minify;
+mod patch;
+mod query;
+mod stats;
+mod validate;
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::ExitCode;
+
+use JSON_parser::cst::{self, Node, Span};
+use JSON_parser::parser::{Dialect, JsonType, ParseError, ParserOptions};
+use JSON_parser::serializer::{to_string, to_string_pretty};
+use JSON_parser::spans::position;
+
+const USAGE: &str = "\
+usage: jsonp <command> [options] [files]
+
+commands:
+    validate   check that documents parse, and optionally match a JSON Schema
+    fmt        pretty-print documents, or check that they already are
+    minify     print documents without whitespace
+    get        print the value at a JSON Pointer
+    query      print the values selected by a JSONPath query
+    keys       print the keys of objects, or the indices of arrays
+    paths      print the JSON Pointer of every value
+    diff       compare two documents
+    patch      apply a JSON Patch or a merge patch
+    stats      summarize the structure of large or unfamiliar files
+    codegen    generate Rust types from sample documents or a JSON Schema
+
+Run `jsonp <command> --help` for the options of a command.";
+
+/// Why a command stopped.
+pub enum Failure {
+    /// The arguments are wrong, the message is followed by a usage hint.
+    Usage(String),
+    /// The command could not do its work, such as an unreadable file.
+    Error(String)
+}
+
+/// Command line arguments, split into options and positional arguments.
+pub struct Arguments {
+    options: HashMap<String, String>,
+    pub positional: Vec<String>
+}
+
+impl Arguments {
+    /// Splits `args`. Options in `with_value` take a value, as `--name X` or
+    /// `--name=X`, those in `flags` stand alone. `--` ends the options.
+    pub fn parse(args: &[String], with_value: &[&str], flags: &[&str]) -> Result<Arguments, Failure> {
+        let mut arguments = Arguments { options: HashMap::new(), positional: Vec::new() };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                arguments.positional.extend(args.by_ref().cloned());
+                break
+            }
+            if !arg.starts_with('-') || arg == "-" {
+                arguments.positional.push(arg.clone());
+                continue
+            }
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None)
+            };
+            if with_value.contains(&name) {
+                let value = match inline {
+                    Some(value) => value,
+                    None => args.next().cloned().ok_or_else(|| Failure::Usage(format!("`{name}` needs a value")))?
+                };
+                arguments.options.insert(name.to_string(), value);
+            } else if flags.contains(&name) && inline.is_none() {
+                arguments.options.insert(name.to_string(), String::new());
+            } else {
+                return Err(Failure::Usage(format!("unknown option `{arg}`")))
+            }
+        }
+        return Ok(arguments)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        return self.options.contains_key(name)
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        return self.options.get(name).map(String::as_str)
+    }
+
+    /// The input files, `-` for standard input if none were given.
+    pub fn inputs(&self) -> Vec<String> {
+        if self.positional.is_empty() {
+            return vec!["-".to_string()]
+        }
+        return self.positional.clone()
+    }
+}
+
+/// The parser options selected by `--json5` and `--strict`.
+pub fn parser_options(arguments: &Arguments) -> ParserOptions {
+    let mut options = if arguments.flag("--json5") {
+        ParserOptions::from(Dialect::Json5)
+    } else {
+        ParserOptions::default()
+    };
+    options.strict = arguments.flag("--strict");
+    return options
+}
+
+/// The text of a file, or of standard input for `-`.
+pub fn read_input(path: &str) -> Result<String, Failure> {
+    if path == "-" {
+        let mut text = String::new();
+        return match std::io::stdin().read_to_string(&mut text) {
+            Ok(_) => Ok(text),
+            Err(e) => Err(Failure::Error(format!("<stdin>: {e}")))
+        }
+    }
+    return std::fs::read_to_string(path).map_err(|e| Failure::Error(format!("{path}: {e}")))
+}
+
+/// The name of an input in messages.
+pub fn display_name(path: &str) -> &str {
+    if path == "-" {
+        return "<stdin>"
+    }
+    return path
+}
+
+pub fn describe_parse_error(error: &ParseError) -> String {
+    match error {
+        ParseError::UnexpectedChar { expected, found } => return format!("unexpected {found:?}, expected {expected}"),
+        ParseError::UnexpectedEof => return "unexpected end of input".to_string(),
+        ParseError::MalformedNumber => return "malformed number".to_string(),
+        ParseError::MalformedNull => return "malformed null".to_string(),
+        ParseError::MalformedBool => return "malformed boolean".to_string(),
+        ParseError::MalformedString => return "malformed string".to_string(),
+        ParseError::MalformedArray => return "malformed array".to_string(),
+        ParseError::MaxDepthExceeded => return "nesting is too deep".to_string()
+    }
+}
+
+/// Parses a whole document, reporting errors as `file:line:column: message`.
+pub fn parse_input(path: &str, text: &str, options: ParserOptions) -> Result<JsonType, String> {
+    match cst::parse_with(text, options) {
+        Ok(node) => return Ok(node.to_json()),
+        Err((error, offset)) => {
+            let at = position(text, offset);
+            return Err(format!("{}:{}:{}: {}", display_name(path), at.line, at.column, describe_parse_error(&error)))
+        }
+    }
+}
+
+/// Renders an error at `span` of `text` with the offending line and the
+/// span underlined:
+///
+/// ```text
+/// error: unexpected ']', expected value
+///  --> data.json:2:8
+///   |
+/// 2 |   "a": ]
+///   |        ^
+/// ```
+///
+/// A span reaching past its first line is marked at its start only.
+pub fn render_diagnostic(path: &str, text: &str, span: Span, message: &str) -> String {
+    let start = position(text, span.start);
+    let end = position(text, span.end);
+    let line = text.lines().nth(start.line - 1).unwrap_or("");
+    // Tabs are kept so that the marker lines up with the source line
+    let padding: String = line.chars().take(start.column - 1).map(|c| if c == '\t' {'\t'} else {' '}).collect();
+    let width = if end.line == start.line && end.column > start.column {end.column - start.column} else {1};
+    let gutter = " ".repeat(start.line.to_string().len());
+    return format!(
+        "error: {message}\n{gutter}--> {}:{}:{}\n{gutter} |\n{} | {line}\n{gutter} | {padding}{}\n",
+        display_name(path), start.line, start.column, start.line, "^".repeat(width)
+    )
+}
+
+/// Parses a whole document into a syntax tree. The error is a rendered diagnostic.
+pub fn parse_node(path: &str, text: &str, options: ParserOptions) -> Result<Node, String> {
+    match cst::parse_with(text, options) {
+        Ok(node) => return Ok(node),
+        Err((error, offset)) => {
+            let span = Span { start: offset, end: offset };
+            return Err(render_diagnostic(path, text, span, &describe_parse_error(&error)))
+        }
+    }
+}
+
+/// Parses one document per non-blank line, as in NDJSON. Errors are
+/// reported at their line in the whole input.
+fn parse_lines(path: &str, text: &str, options: ParserOptions) -> Result<Vec<JsonType>, String> {
+    let mut documents = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue
+        }
+        match cst::parse_with(line, options) {
+            Ok(node) => documents.push(node.to_json()),
+            Err((error, offset)) => {
+                let column = position(line, offset).column;
+                return Err(format!("{}:{}:{}: {}", display_name(path), i + 1, column, describe_parse_error(&error)))
+            }
+        }
+    }
+    return Ok(documents)
+}
+
+/// Reads and parses every input, line by line with `--ndjson`.
+pub fn read_documents(arguments: &Arguments, options: ParserOptions) -> Result<Vec<JsonType>, Failure> {
+    let mut documents = Vec::new();
+    for path in arguments.inputs() {
+        let text = read_input(&path)?;
+        if arguments.flag("--ndjson") {
+            documents.extend(parse_lines(&path, &text, options).map_err(Failure::Error)?);
+        } else {
+            documents.push(parse_input(&path, &text, options).map_err(Failure::Error)?);
+        }
+    }
+    return Ok(documents)
+}
+
+/// A result as printed by the extracting commands: compact JSON, indented
+/// with `--pretty`, and strings without quotes or escapes with `--raw`.
+pub fn format_value(value: &JsonType, arguments: &Arguments) -> String {
+    match value {
+        JsonType::JsonString(s) if arguments.flag("--raw") => return s.clone(),
+        value if arguments.flag("--pretty") => return to_string_pretty(value, 2),
+        value => return to_string(value)
+    }
+}
+
+/// Reports an error with the exit status 2, for commands that give the status
+/// 1 a meaning of its own such as "nothing matched".
+pub fn with_error_status(result: Result<ExitCode, Failure>) -> Result<ExitCode, Failure> {
+    match result {
+        Err(Failure::Error(message)) => {
+            eprintln!("jsonp: {message}");
+            return Ok(ExitCode::from(2))
+        },
+        result => return result
+    }
+}
+
+/// The exit status of the extracting commands, 1 when nothing matched.
+pub fn matched(found: bool) -> ExitCode {
+    if found {
+        return ExitCode::SUCCESS
+    }
+    return ExitCode::FAILURE
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let rest = args.get(1..).unwrap_or_default();
+    let result = match args.first().map(String::as_str) {
+        Some("validate") => validate::run(rest),
+        Some("fmt") => fmt::run(rest),
+        Some("minify") => minify::run(rest),
+        Some("get") => get::run(rest),
+        Some("query") => query::run(rest),
+        Some("keys") => keys::run_keys(rest),
+        Some("paths") => keys::run_paths(rest),
+        Some("diff") => diff::run(rest),
+        Some("patch") => patch::run(rest),
+        Some("stats") => stats::run(rest),
+        Some("codegen") => codegen::run(rest),
+        Some("help" | "-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(ExitCode::SUCCESS)
+        },
+        Some(other) => Err(Failure::Usage(format!("unknown command `{other}`"))),
+        None => Err(Failure::Usage("missing command".to_string()))
+    };
+    match result {
+        Ok(code) => return code,
+        Err(Failure::Usage(message)) => {
+            eprintln!("jsonp: {message}\n\n{USAGE}");
+            return ExitCode::from(2)
+        },
+        Err(Failure::Error(message)) => {
+            eprintln!("jsonp: {message}");
+            return ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! `jsonp minify`: documents without insignificant whitespace.
+
+use std::process::ExitCode;
+
+use JSON_parser::serializer::node_to_string;
+
+use crate::{parse_node, parser_options, read_input, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp minify [--json5] [--strict] [files]
+
+Prints every input on a single line, keeping the order of object members.
+Comments are dropped, JSON5 input is written as plain JSON.
+
+options:
+    --json5     accept JSON5
+    --strict    reject the legacy quirks of the default parser";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    let arguments = Arguments::parse(args, &[], &["--json5", "--strict", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let options = parser_options(&arguments);
+    let mut success = true;
+    for path in arguments.inputs() {
+        let text = read_input(&path)?;
+        match parse_node(&path, &text, options) {
+            Ok(node) => println!("{}", node_to_string(&node)),
+            Err(diagnostic) => {
+                eprintln!("{diagnostic}");
+                success = false;
+            }
+        }
+    }
+    if success {
+        return Ok(ExitCode::SUCCESS)
+    }
+    return Ok(ExitCode::FAILURE)
+}
@@ -0,0 +1,73 @@
+//! `jsonp patch`: applies a JSON Patch or a merge patch.
+
+use std::process::ExitCode;
+
+use JSON_parser::merge::merge_patch;
+use JSON_parser::patch::{apply_patch_text_with, PatchError};
+
+use crate::{describe_parse_error, display_name, format_value, parse_input, parser_options, read_input, with_error_status, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp patch [--merge] [--write] [--pretty] <target> <patch>
+
+Applies a JSON Patch (RFC 6902), or with `--merge` a merge patch (RFC 7396), to
+the target and prints the result. As with patch(1) the exit status is 1 if the
+patch does not apply, the target is then left unchanged, and 2 if something
+else went wrong.
+
+options:
+    --merge     the patch is a merge patch
+    --write     rewrite the target in place instead of printing it
+    --pretty    print the result indented
+    --json5     accept JSON5
+    --strict    reject the legacy quirks of the default parser";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    return with_error_status(apply(args))
+}
+
+fn describe(error: &PatchError) -> String {
+    match error {
+        PatchError::NotAnArray => return "the patch is not an array of operations".to_string(),
+        PatchError::InvalidOperation(i) => return format!("operation {i} is invalid"),
+        PatchError::Pointer(i, e) => return format!("operation {i}: the path cannot be followed ({e:?})"),
+        PatchError::TestFailed(i) => return format!("operation {i}: test failed"),
+        PatchError::MoveIntoChild(i) => return format!("operation {i}: cannot move a value into itself"),
+        PatchError::Parse { error, offset } => return format!("byte {offset}: {}", describe_parse_error(error))
+    }
+}
+
+fn apply(args: &[String]) -> Result<ExitCode, Failure> {
+    let arguments = Arguments::parse(args, &[], &["--merge", "--write", "--pretty", "--json5", "--strict", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let [target_path, patch_path] = arguments.positional.as_slice() else {
+        return Err(Failure::Usage("`patch` takes a target and a patch".to_string()))
+    };
+    if target_path == "-" && patch_path == "-" {
+        return Err(Failure::Usage("only one input can be standard input".to_string()))
+    }
+    if arguments.flag("--write") && target_path == "-" {
+        return Err(Failure::Usage("`--write` needs a target file, not standard input".to_string()))
+    }
+    let options = parser_options(&arguments);
+    let mut target = parse_input(target_path, &read_input(target_path)?, options).map_err(Failure::Error)?;
+    let patch_text = read_input(patch_path)?;
+    let patch = parse_input(patch_path, &patch_text, options).map_err(Failure::Error)?;
+
+    if arguments.flag("--merge") {
+        merge_patch(&mut target, &patch);
+    } else if let Err(e) = apply_patch_text_with(&mut target, &patch_text, options) {
+        eprintln!("jsonp: {}: {}", display_name(patch_path), describe(&e));
+        return Ok(ExitCode::FAILURE)
+    }
+    let output = format_value(&target, &arguments) + "\n";
+    if arguments.flag("--write") {
+        std::fs::write(target_path, output).map_err(|e| Failure::Error(format!("{target_path}: {e}")))?;
+    } else {
+        print!("{output}");
+    }
+    return Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,53 @@
+//! `jsonp query`: values selected by JSONPath.
+
+use std::process::ExitCode;
+
+use JSON_parser::jsonpath::JsonPath;
+
+use crate::{format_value, matched, parser_options, read_documents, with_error_status, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp query [--raw] [--pretty] [--locations] [--ndjson] [--json5] <query> [files]
+
+Prints every value a JSONPath (RFC 9535) query such as `$.users[?@.age > 30].name`
+selects, one per line. The exit status is 1 if nothing matched and 2 if
+something went wrong.
+
+options:
+    --raw          print strings without quotes
+    --pretty       print values indented
+    --locations    print the normalized path of each value instead
+    --ndjson       read one document per line
+    --json5        accept JSON5
+    --strict       reject the legacy quirks of the default parser";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    return with_error_status(select(args))
+}
+
+fn select(args: &[String]) -> Result<ExitCode, Failure> {
+    let flags = ["--raw", "--pretty", "--locations", "--ndjson", "--json5", "--strict", "--help"];
+    let mut arguments = Arguments::parse(args, &[], &flags)?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    if arguments.positional.is_empty() {
+        return Err(Failure::Usage("missing query".to_string()))
+    }
+    let text = arguments.positional.remove(0);
+    let path = JsonPath::parse(&text).map_err(|e| Failure::Usage(format!("invalid query `{text}`: {e:?}")))?;
+
+    let mut found = false;
+    for document in read_documents(&arguments, parser_options(&arguments))? {
+        for node in path.query(&document) {
+            if arguments.flag("--locations") {
+                println!("{}", node.location);
+            } else {
+                println!("{}", format_value(node.value, &arguments));
+            }
+            found = true;
+        }
+    }
+    return Ok(matched(found))
+}
@@ -0,0 +1,122 @@
+//! `jsonp stats`: a structural summary of large or unfamiliar files.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::process::ExitCode;
+
+use JSON_parser::convert::ToJson;
+use JSON_parser::serializer::{format_number, to_string_pretty};
+use JSON_parser::stats::{AnalyzeError, Stats};
+
+use crate::{describe_parse_error, display_name, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp stats [--json] [--top N] [files]
+
+Summarizes all inputs together: values by type, the deepest nesting, the longest
+arrays, the most frequent keys, string lengths, the numbers at each path and
+keys that occur twice in one object. Inputs are streamed one value at a time,
+so they do not have to fit in memory, however they are nested. Several documents
+per input, such as NDJSON, are fine.
+
+options:
+    --json     print the summary as JSON
+    --top N    how many keys to list, 10 by default";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    let arguments = Arguments::parse(args, &["--top"], &["--json", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let top = match arguments.value("--top").map(str::parse::<usize>) {
+        Some(Ok(n)) => n,
+        Some(Err(_)) => return Err(Failure::Usage("`--top` needs a number".to_string())),
+        None => 10
+    };
+
+    let mut stats = Stats::default();
+    for path in arguments.inputs() {
+        let result = if path == "-" {
+            stats.add_reader(std::io::stdin().lock())
+        } else {
+            let file = File::open(&path).map_err(|e| Failure::Error(format!("{path}: {e}")))?;
+            stats.add_reader(BufReader::new(file))
+        };
+        match result {
+            Ok(()) => {},
+            Err(AnalyzeError::Parse { error, offset }) => {
+                return Err(Failure::Error(format!("{}: byte {offset}: {}", display_name(&path), describe_parse_error(&error))))
+            },
+            Err(AnalyzeError::Io(message)) => return Err(Failure::Error(format!("{}: {message}", display_name(&path))))
+        }
+    }
+
+    if arguments.flag("--json") {
+        println!("{}", to_string_pretty(&stats.to_json(), 2));
+    } else {
+        print!("{}", render(&stats, top));
+    }
+    return Ok(ExitCode::SUCCESS)
+}
+
+/// The range of string lengths counted by a bucket of `Stats::string_lengths`.
+fn bucket_range(bucket: usize) -> String {
+    match bucket {
+        0 => return "0".to_string(),
+        1 => return "1".to_string(),
+        i => return format!("{}-{}", 1usize << (i - 1), (1usize << i) - 1)
+    }
+}
+
+fn render(stats: &Stats, top: usize) -> String {
+    let mut output = String::new();
+    output.push_str(&format!("documents       {}\n", stats.documents));
+    let types: Vec<String> = stats.types.iter().map(|(name, count)| format!("{name} {count}")).collect();
+    output.push_str(&format!("values          {} ({})\n", stats.nodes(), types.join(", ")));
+    output.push_str(&format!("max depth       {}\n", stats.max_depth));
+
+    if !stats.largest_arrays.is_empty() {
+        output.push_str("\nlargest arrays\n");
+        for array in &stats.largest_arrays {
+            let pointer = if array.pointer.is_empty() {"(root)"} else {&array.pointer};
+            output.push_str(&format!("    {:>8}  document {}  {pointer}\n", array.length, array.document));
+        }
+    }
+
+    if !stats.keys.is_empty() {
+        let mut keys: Vec<(&String, &usize)> = stats.keys.iter().collect();
+        // Most frequent first, ties alphabetically
+        keys.sort_by(|a, b| b.1.cmp(a.1));
+        output.push_str(&format!("\nkeys ({} distinct)\n", keys.len()));
+        for (key, count) in keys.into_iter().take(top) {
+            output.push_str(&format!("    {count:>8}  {key}\n"));
+        }
+    }
+
+    if !stats.string_lengths.is_empty() {
+        output.push_str(&format!("\nstring lengths (longest {})\n", stats.longest_string));
+        for (bucket, count) in stats.string_lengths.iter().enumerate() {
+            output.push_str(&format!("    {count:>8}  {}\n", bucket_range(bucket)));
+        }
+    }
+
+    if !stats.numbers.is_empty() {
+        output.push_str("\nnumbers\n");
+        for (path, numbers) in &stats.numbers {
+            let path = if path.is_empty() {"(root)"} else {path};
+            output.push_str(&format!(
+                "    {:>8}  {path}  min {}  max {}  mean {}\n",
+                numbers.count, format_number(numbers.min), format_number(numbers.max), format_number(numbers.mean())
+            ));
+        }
+    }
+
+    if !stats.duplicate_keys.is_empty() {
+        output.push_str("\nduplicate keys\n");
+        for duplicate in &stats.duplicate_keys {
+            output.push_str(&format!("    document {}  {}\n", duplicate.document, duplicate.pointer));
+        }
+    }
+    return output
+}
@@ -0,0 +1,81 @@
+//! `jsonp validate`: syntax checks, and optionally a JSON Schema.
+
+use std::process::ExitCode;
+
+use JSON_parser::cst::Span;
+use JSON_parser::parser::ParserOptions;
+use JSON_parser::schema::Schema;
+use JSON_parser::spans::parse_with_spans_with;
+
+use crate::{describe_parse_error, display_name, parse_input, parser_options, read_input, render_diagnostic, Arguments, Failure};
+
+const HELP: &str = "\
+usage: jsonp validate [--json5] [--strict] [--schema FILE] [--quiet] [files]
+
+Checks every input and reports each problem with its line and column. The exit
+status is 1 if any input is invalid.
+
+options:
+    --json5          accept JSON5
+    --strict         reject the legacy quirks of the default parser
+    --schema FILE    also validate against this JSON Schema
+    --quiet          do not print `ok` for valid inputs";
+
+pub fn run(args: &[String]) -> Result<ExitCode, Failure> {
+    let arguments = Arguments::parse(args, &["--schema"], &["--json5", "--strict", "--quiet", "--help"])?;
+    if arguments.flag("--help") {
+        println!("{HELP}");
+        return Ok(ExitCode::SUCCESS)
+    }
+    let options = parser_options(&arguments);
+    let schema = match arguments.value("--schema") {
+        Some(path) => {
+            let document = parse_input(path, &read_input(path)?, ParserOptions::default()).map_err(Failure::Error)?;
+            let schema = Schema::new(&document).map_err(|e| Failure::Error(format!("{path}: invalid schema: {e:?}")))?;
+            Some(schema)
+        },
+        None => None
+    };
+
+    let mut valid = true;
+    for path in arguments.inputs() {
+        let text = read_input(&path)?;
+        let diagnostics = check(&path, &text, options, schema.as_ref());
+        if diagnostics.is_empty() {
+            if !arguments.flag("--quiet") {
+                println!("{}: ok", display_name(&path));
+            }
+        } else {
+            valid = false;
+            for diagnostic in diagnostics {
+                eprintln!("{diagnostic}");
+            }
+        }
+    }
+    if valid {
+        return Ok(ExitCode::SUCCESS)
+    }
+    return Ok(ExitCode::FAILURE)
+}
+
+/// The rendered problems of one input, empty when it is valid.
+fn check(path: &str, text: &str, options: ParserOptions, schema: Option<&Schema>) -> Vec<String> {
+    let (value, spans) = match parse_with_spans_with(text, options) {
+        Ok(parsed) => parsed,
+        Err((error, offset)) => {
+            let span = Span { start: offset, end: offset };
+            return vec![render_diagnostic(path, text, span, &describe_parse_error(&error))]
+        }
+    };
+    let Some(schema) = schema else {
+        return Vec::new()
+    };
+    let mut diagnostics = Vec::new();
+    for error in schema.validate(&value).errors {
+        // Errors are located at the failing value, the whole document if it has no span
+        let span = spans.get(&error.instance_location).map(|location| location.span).unwrap_or(Span { start: 0, end: 0 });
+        let message = format!("{} (at {})", error.error, error.keyword_location);
+        diagnostics.push(render_diagnostic(path, text, span, &message));
+    }
+    return diagnostics
+}
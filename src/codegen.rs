@@ -0,0 +1,583 @@
+//! Rust type definitions from a JSON Schema or from sample documents.
+//!
+//! The generated code derives `FromJson` and `ToJson`, so it compiles against
+//! this crate and converts straight from a parsed `JsonType`:
+//!
+//! - Objects with `properties` become structs. Fields get `snake_case` names,
+//!   with `#[json(rename = "...")]` whenever that differs from the key, and
+//!   properties that are not `required` are wrapped in `Option`.
+//! - Objects without `properties` become a `HashMap<String, T>` of their
+//!   `additionalProperties`, arrays a `Vec` of their `items`.
+//! - `string`, `integer`, `number` and `boolean` map to `String`, `i64`,
+//!   `f64` and `bool`. A `null` alongside one other type makes it an `Option`.
+//! - String `enum`s become enums of unit variants. Several types, `anyOf` and
+//!   `oneOf` become `untagged` enums with one variant per alternative.
+//! - `$ref`s to `#/...` pointers, such as `#/$defs/Node`, become named types,
+//!   boxed where the type would otherwise contain itself. A named type that is
+//!   not a struct or enum is an alias, or a newtype struct if it contains itself.
+//! - Anything else, such as `const` or a schema without `type`, stays a `JsonType`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::infer::infer;
+use crate::parser::JsonType;
+use crate::pointer::parse_pointer;
+use crate::schema::{Schema, SchemaError};
+
+const KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn", "else", "enum",
+    "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut",
+    "override", "priv", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while"
+];
+
+// Names the generated code uses itself, a type with one of them would shadow it
+const RESERVED_TYPES: [&str; 10] = ["String", "Vec", "Option", "HashMap", "Box", "JsonType", "FromJson", "ToJson", "Result", "Self"];
+
+const DERIVES: &str = "#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]";
+
+/// Splits a key into words at separators and at lowercase-to-uppercase changes.
+fn split_words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in key.split(|c: char| !c.is_alphanumeric()).filter(|p| !p.is_empty()) {
+        let mut word = String::new();
+        let mut previous_lower = false;
+        for c in part.chars() {
+            if c.is_uppercase() && previous_lower {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lower = c.is_lowercase() || c.is_ascii_digit();
+            word.push(c);
+        }
+        words.push(word);
+    }
+    return words
+}
+
+fn pascal_case(key: &str) -> String {
+    let mut output = String::new();
+    for word in split_words(key) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            output.extend(first.to_uppercase());
+            output.extend(chars.flat_map(char::to_lowercase));
+        }
+    }
+    return output
+}
+
+fn snake_case(key: &str) -> String {
+    let words: Vec<String> = split_words(key).iter().map(|w| w.to_lowercase()).collect();
+    return words.join("_")
+}
+
+/// A Rust identifier for a field, `r#` for keywords that allow it.
+fn field_ident(key: &str) -> String {
+    let name = snake_case(key);
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) || !name.is_ascii() {
+        let name: String = name.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '_').collect();
+        return format!("field_{name}").trim_end_matches('_').to_string()
+    }
+    if KEYWORDS.contains(&name.as_str()) {
+        match name.as_str() {
+            "crate" | "self" | "super" | "Self" => return format!("{name}_"),
+            _ => return format!("r#{name}")
+        }
+    }
+    return name
+}
+
+/// A string literal the derive macros read back unchanged.
+fn literal(text: &str) -> String {
+    return format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn doc_comment(schema: &JsonType, indent: &str) -> String {
+    let mut output = String::new();
+    if let Some(description) = schema.get("description").and_then(JsonType::as_str) {
+        for line in description.lines() {
+            output.push_str(&format!("{indent}///{}{line}\n", if line.is_empty() {""} else {" "}));
+        }
+    }
+    return output
+}
+
+/// The declared types of a schema, `"type"` as a string or a list.
+fn declared_types(map: &HashMap<String, JsonType>) -> Vec<&str> {
+    match map.get("type") {
+        Some(JsonType::JsonString(name)) => return vec![name.as_str()],
+        Some(JsonType::JsonArray(names)) => return names.iter().filter_map(JsonType::as_str).collect(),
+        _ => {}
+    }
+    // Keywords that only make sense for one type
+    if map.contains_key("properties") || map.contains_key("additionalProperties") {
+        return vec!["object"]
+    }
+    if map.contains_key("items") || map.contains_key("prefixItems") {
+        return vec!["array"]
+    }
+    return Vec::new()
+}
+
+struct Generator<'a> {
+    root: &'a JsonType,
+    /// Finished declarations, in the order their types were first seen
+    items: Vec<String>,
+    names: HashSet<String>,
+    /// Types of the referenced subschemas, by pointer
+    definitions: HashMap<String, String>,
+    /// Types being generated, a reference back to one of them needs a `Box`
+    stack: Vec<String>,
+    /// Names given to a struct or enum
+    declared: HashSet<String>,
+    /// Names referred to while their type was being generated
+    recursive: HashSet<String>
+}
+
+impl Generator<'_> {
+    /// A type name based on `hint` that is not taken yet.
+    fn unique_name(&mut self, hint: &str) -> String {
+        let mut base = pascal_case(hint);
+        if base.is_empty() {
+            base = "Type".to_string();
+        }
+        if base.starts_with(|c: char| c.is_ascii_digit()) {
+            base = format!("T{base}");
+        }
+        if RESERVED_TYPES.contains(&base.as_str()) {
+            base.push_str("Type");
+        }
+        let mut name = base.clone();
+        let mut counter = 2;
+        while self.names.contains(&name) {
+            name = format!("{base}{counter}");
+            counter += 1;
+        }
+        self.names.insert(name.clone());
+        return name
+    }
+
+    /// The Rust type for `schema`. New structs and enums are named after
+    /// `hint`, or exactly `hint` if `exact` is set because the name is already
+    /// reserved. `direct` is false inside a `Vec` or `HashMap`, which need no `Box`.
+    fn type_of(&mut self, schema: &JsonType, hint: &str, exact: bool, direct: bool) -> String {
+        let JsonType::JsonObject(map) = schema else {
+            return "JsonType".to_string()
+        };
+        if let Some(JsonType::JsonString(reference)) = map.get("$ref") {
+            return self.reference(reference, direct)
+        }
+        let name = |generator: &mut Self| if exact { hint.to_string() } else { generator.unique_name(hint) };
+
+        if let Some(JsonType::JsonArray(values)) = map.get("enum") {
+            let strings: Vec<&str> = values.iter().filter_map(JsonType::as_str).collect();
+            let nullable = values.contains(&JsonType::JsonNull);
+            if strings.is_empty() || strings.len() + usize::from(nullable) != values.len() {
+                return "JsonType".to_string()
+            }
+            let name = name(self);
+            self.string_enum(&name, schema, &strings);
+            return optional(name, nullable)
+        }
+        if map.contains_key("const") {
+            return "JsonType".to_string()
+        }
+        for keyword in ["oneOf", "anyOf"] {
+            if let Some(JsonType::JsonArray(alternatives)) = map.get(keyword) {
+                let null = json!({"type": "null"});
+                let nullable = alternatives.contains(&null);
+                let alternatives: Vec<&JsonType> = alternatives.iter().filter(|a| **a != null).collect();
+                if let [single] = alternatives.as_slice() {
+                    let inner = self.type_of(single, hint, exact, direct);
+                    return optional(inner, nullable)
+                }
+                let name = name(self);
+                self.untagged_enum(&name, schema, &alternatives);
+                return optional(name, nullable)
+            }
+        }
+
+        let types = declared_types(map);
+        let nullable = types.contains(&"null");
+        let mut types: Vec<&str> = types.into_iter().filter(|t| *t != "null").collect();
+        // Integers are numbers too, `number` alone covers both
+        if types.contains(&"number") {
+            types.retain(|t| *t != "integer");
+        }
+        match types.as_slice() {
+            [] => return "JsonType".to_string(),
+            [single] => {
+                let inner = self.single_type(single, map, schema, hint, exact);
+                return optional(inner, nullable)
+            },
+            _ => {
+                let name = name(self);
+                let alternatives: Vec<JsonType> = types.iter().map(|t| {
+                    let mut alternative = map.clone();
+                    alternative.insert("type".to_string(), json!(*t));
+                    alternative.remove("description");
+                    JsonType::JsonObject(alternative)
+                }).collect();
+                self.untagged_enum(&name, schema, &alternatives.iter().collect::<Vec<_>>());
+                return optional(name, nullable)
+            }
+        }
+    }
+
+    fn single_type(&mut self, name: &str, map: &HashMap<String, JsonType>, schema: &JsonType, hint: &str, exact: bool) -> String {
+        match name {
+            "object" => {
+                if let Some(JsonType::JsonObject(properties)) = map.get("properties") && !properties.is_empty() {
+                    let name = if exact { hint.to_string() } else { self.unique_name(hint) };
+                    self.structure(&name, schema, properties);
+                    return name
+                }
+                let values = match map.get("additionalProperties") {
+                    Some(JsonType::JsonObject(_)) => self.type_of(&map["additionalProperties"], &format!("{hint} Value"), false, false),
+                    _ => "JsonType".to_string()
+                };
+                return format!("HashMap<String, {values}>")
+            },
+            "array" => {
+                let items = match map.get("items") {
+                    Some(items) => self.type_of(items, &format!("{hint} Item"), false, false),
+                    None => "JsonType".to_string()
+                };
+                return format!("Vec<{items}>")
+            },
+            "string" => return "String".to_string(),
+            "integer" => return "i64".to_string(),
+            "number" => return "f64".to_string(),
+            "boolean" => return "bool".to_string(),
+            _ => return "JsonType".to_string()
+        }
+    }
+
+    /// The type of a `$ref`, generating it the first time it is seen.
+    fn reference(&mut self, reference: &str, direct: bool) -> String {
+        let Some(pointer) = reference.strip_prefix('#').filter(|p| p.is_empty() || p.starts_with('/')) else {
+            return "JsonType".to_string()
+        };
+        if let Some(name) = self.definitions.get(pointer) {
+            let name = name.clone();
+            if self.stack.contains(&name) {
+                self.recursive.insert(name.clone());
+                if direct {
+                    return format!("Box<{name}>")
+                }
+            }
+            return name
+        }
+        let Some(target) = self.root.pointer(pointer) else {
+            return "JsonType".to_string()
+        };
+        let hint = parse_pointer(pointer).ok().and_then(|tokens| tokens.last().cloned()).unwrap_or_default();
+        let name = self.unique_name(&hint);
+        self.definitions.insert(pointer.to_string(), name.clone());
+        let ty = self.declare(target, &name);
+        self.definitions.insert(pointer.to_string(), ty.clone());
+        return ty
+    }
+
+    /// Generates `schema` as the type `name` and returns the type to use for
+    /// it, which is `Option<name>` for a nullable struct or enum. Other types
+    /// get an alias, or a newtype struct if they contain themselves.
+    fn declare(&mut self, schema: &JsonType, name: &str) -> String {
+        let slot = self.items.len();
+        self.items.push(String::new());
+        self.stack.push(name.to_string());
+        let ty = self.type_of(schema, name, true, true);
+        self.stack.pop();
+        if ty == name || self.declared.contains(name) {
+            self.items.remove(slot);
+            return ty
+        }
+        if self.recursive.contains(name) {
+            self.declared.insert(name.to_string());
+            self.items[slot] = format!("{}{DERIVES}\npub struct {name}(pub {ty});\n", doc_comment(schema, ""));
+            return name.to_string()
+        }
+        self.items[slot] = format!("{}pub type {name} = {ty};\n", doc_comment(schema, ""));
+        return name.to_string()
+    }
+
+    fn structure(&mut self, name: &str, schema: &JsonType, properties: &HashMap<String, JsonType>) {
+        self.declared.insert(name.to_string());
+        let slot = self.items.len();
+        self.items.push(String::new());
+        self.stack.push(name.to_string());
+
+        let required: Vec<&str> = match schema.get("required") {
+            Some(JsonType::JsonArray(required)) => required.iter().filter_map(JsonType::as_str).collect(),
+            _ => Vec::new()
+        };
+        let mut keys: Vec<&String> = properties.keys().collect();
+        keys.sort();
+        let mut idents = HashSet::new();
+        let mut fields = Vec::new();
+        for key in keys {
+            let property = &properties[key];
+            let mut ty = self.type_of(property, &format!("{name} {key}"), false, true);
+            if !required.contains(&key.as_str()) && !ty.starts_with("Option<") && ty != "JsonType" {
+                ty = format!("Option<{ty}>");
+            }
+            let base = field_ident(key);
+            let mut ident = base.clone();
+            let mut counter = 2;
+            while !idents.insert(ident.clone()) {
+                ident = format!("{}_{counter}", base.trim_start_matches("r#"));
+                counter += 1;
+            }
+            let mut field = doc_comment(property, "    ");
+            if ident.trim_start_matches("r#") != key {
+                field.push_str(&format!("    #[json(rename = {})]\n", literal(key)));
+            }
+            field.push_str(&format!("    pub {ident}: {ty}"));
+            fields.push(field);
+        }
+
+        self.stack.pop();
+        self.items[slot] = format!("{}{DERIVES}\npub struct {name} {{\n{}\n}}\n", doc_comment(schema, ""), fields.join(",\n"));
+    }
+
+    fn string_enum(&mut self, name: &str, schema: &JsonType, values: &[&str]) {
+        self.declared.insert(name.to_string());
+        let mut variants = Vec::new();
+        let mut used = HashSet::new();
+        for value in values {
+            let mut base = pascal_case(value);
+            if base.is_empty() || base.starts_with(|c: char| c.is_ascii_digit()) {
+                base = format!("Value{base}");
+            }
+            let mut variant = base.clone();
+            let mut counter = 2;
+            while !used.insert(variant.clone()) {
+                variant = format!("{base}{counter}");
+                counter += 1;
+            }
+            match variant == *value {
+                true => variants.push(format!("    {variant}")),
+                false => variants.push(format!("    #[json(rename = {})]\n    {variant}", literal(value)))
+            }
+        }
+        self.items.push(format!("{}{DERIVES}\npub enum {name} {{\n{}\n}}\n", doc_comment(schema, ""), variants.join(",\n")));
+    }
+
+    fn untagged_enum(&mut self, name: &str, schema: &JsonType, alternatives: &[&JsonType]) {
+        self.declared.insert(name.to_string());
+        let slot = self.items.len();
+        self.items.push(String::new());
+        self.stack.push(name.to_string());
+
+        let mut variants = Vec::new();
+        let mut used = HashSet::new();
+        for (i, alternative) in alternatives.iter().enumerate() {
+            let hint = match alternative.get("$ref").and_then(JsonType::as_str) {
+                Some(reference) => reference.rsplit('/').next().unwrap_or_default().to_string(),
+                None => {
+                    match alternative.get("title").and_then(JsonType::as_str) {
+                        Some(title) => title.to_string(),
+                        None => alternative.get("type").and_then(JsonType::as_str).unwrap_or("variant").to_string()
+                    }
+                }
+            };
+            let mut base = pascal_case(&hint);
+            if base.is_empty() || base.starts_with(|c: char| c.is_ascii_digit()) {
+                base = format!("Variant{i}");
+            }
+            let mut variant = base.clone();
+            let mut counter = 2;
+            while !used.insert(variant.clone()) {
+                variant = format!("{base}{counter}");
+                counter += 1;
+            }
+            let ty = self.type_of(alternative, &format!("{name} {variant}"), false, true);
+            variants.push(format!("    {variant}({ty})"));
+        }
+
+        self.stack.pop();
+        self.items[slot] = format!("{}{DERIVES}\n#[json(untagged)]\npub enum {name} {{\n{}\n}}\n", doc_comment(schema, ""), variants.join(",\n"));
+    }
+}
+
+fn optional(ty: String, nullable: bool) -> String {
+    if nullable && !ty.starts_with("Option<") && ty != "JsonType" {
+        return format!("Option<{ty}>")
+    }
+    return ty
+}
+
+/// Rust source defining `root_name` and every type it needs, checked against
+/// the schema rules of `schema::Schema` first.
+pub fn generate(schema: &JsonType, root_name: &str) -> Result<String, SchemaError> {
+    Schema::new(schema)?;
+    let mut generator = Generator {
+        root: schema,
+        items: Vec::new(),
+        names: HashSet::new(),
+        definitions: HashMap::new(),
+        stack: Vec::new(),
+        declared: HashSet::new(),
+        recursive: HashSet::new()
+    };
+    let root = generator.unique_name(root_name);
+    generator.definitions.insert(String::new(), root.clone());
+    generator.declare(schema, &root);
+
+    let body = generator.items.join("\n");
+    let mut output = String::new();
+    if body.contains("HashMap<") {
+        output.push_str("use std::collections::HashMap;\n\n");
+    }
+    output.push_str("use JSON_parser::convert::{FromJson, ToJson};\n");
+    if body.contains("JsonType") {
+        output.push_str("use JSON_parser::parser::JsonType;\n");
+    }
+    output.push('\n');
+    output.push_str(&body);
+    return Ok(output)
+}
+
+/// Infers a schema from the samples with `infer::infer` and generates types for it.
+pub fn generate_from_samples(samples: &[JsonType], root_name: &str) -> String {
+    return generate(&infer(samples), root_name).expect("inferred schemas are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(pascal_case("user_id"), "UserId");
+        assert_eq!(pascal_case("createdAt-time"), "CreatedAtTime");
+        assert_eq!(snake_case("userID"), "user_id");
+        assert_eq!(field_ident("HTTPStatus"), "httpstatus");
+        assert_eq!(field_ident("type"), "r#type");
+        assert_eq!(field_ident("self"), "self_");
+        assert_eq!(field_ident("2fa"), "field_2fa");
+        assert_eq!(field_ident("@id"), "id");
+        assert_eq!(field_ident("ünïcode"), "field_ncode");
+        assert_eq!(literal(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+
+    #[test]
+    fn structs_and_options() {
+        let schema = json!({
+            "description": "A user.",
+            "type": "object",
+            "properties": {
+                "userId": {"type": "integer"},
+                "name": {"type": "string", "description": "Display name"},
+                "type": {"type": "string"},
+                "score": {"type": ["number", "null"]},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "meta": {"type": "object", "additionalProperties": {"type": "boolean"}},
+                "address": {"type": "object", "properties": {"city": {"type": "string"}}, "required": ["city"]},
+                "extra": {}
+            },
+            "required": ["userId", "name", "type", "score"]
+        });
+        assert_eq!(generate(&schema, "user").unwrap(), r#"use std::collections::HashMap;
+
+use JSON_parser::convert::{FromJson, ToJson};
+use JSON_parser::parser::JsonType;
+
+/// A user.
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct User {
+    pub address: Option<UserAddress>,
+    pub extra: JsonType,
+    pub meta: Option<HashMap<String, bool>>,
+    /// Display name
+    pub name: String,
+    pub score: Option<f64>,
+    pub tags: Option<Vec<String>>,
+    pub r#type: String,
+    #[json(rename = "userId")]
+    pub user_id: i64
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct UserAddress {
+    pub city: String
+}
+"#);
+    }
+
+    #[test]
+    fn enums_and_references() {
+        let schema = json!({
+            "type": "array",
+            "items": {"$ref": "#/$defs/node"},
+            "$defs": {
+                "node": {
+                    "type": "object",
+                    "properties": {
+                        "status": {"enum": ["in-progress", "done", null]},
+                        "value": {"type": ["string", "integer", "object"], "properties": {"x": {"type": "number"}}},
+                        "parent": {"$ref": "#/$defs/node"},
+                        "children": {"type": "array", "items": {"$ref": "#/$defs/node"}},
+                        "either": {"oneOf": [{"$ref": "#/$defs/leaf"}, {"type": "null"}]},
+                        "shape": {"anyOf": [{"title": "circle", "type": "number"}, {"type": "array", "items": {"type": "number"}}]}
+                    },
+                    "required": ["value", "children"]
+                },
+                "leaf": {"type": "string"}
+            }
+        });
+        assert_eq!(generate(&schema, "Tree").unwrap(), r#"use JSON_parser::convert::{FromJson, ToJson};
+
+pub type Tree = Vec<Node>;
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct Node {
+    pub children: Vec<Node>,
+    pub either: Option<Leaf>,
+    pub parent: Option<Box<Node>>,
+    pub shape: Option<NodeShape>,
+    pub status: Option<NodeStatus>,
+    pub value: NodeValue
+}
+
+pub type Leaf = String;
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+#[json(untagged)]
+pub enum NodeShape {
+    Circle(f64),
+    Array(Vec<f64>)
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub enum NodeStatus {
+    #[json(rename = "in-progress")]
+    InProgress,
+    #[json(rename = "done")]
+    Done
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+#[json(untagged)]
+pub enum NodeValue {
+    String(String),
+    Integer(i64),
+    Object(NodeValueObject)
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct NodeValueObject {
+    pub x: Option<f64>
+}
+"#);
+        assert_eq!(generate(&json!({"$ref": "#/missing"}), "Root"), Err(SchemaError::UnresolvedRef { path: "/$ref".to_string(), reference: "#/missing".to_string() }));
+    }
+
+    #[test]
+    fn from_samples() {
+        let samples = [json!({"id": 1, "kind": "a"}), json!({"id": 2, "kind": "a", "note": "x"})];
+        let code = generate_from_samples(&samples, "Event");
+        assert!(code.contains("pub struct Event {\n    pub id: i64,\n    pub kind: EventKind,\n    pub note: Option<String>\n}"), "{code}");
+        assert!(code.contains("pub enum EventKind {\n    #[json(rename = \"a\")]\n    A\n}"), "{code}");
+    }
+}
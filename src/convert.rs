@@ -0,0 +1,520 @@
+//! Typed conversion between Rust values and `JsonType`.
+//!
+//! `FromJson` reads a Rust value out of a `JsonType` and reports where and why
+//! it did not fit, `ToJson` builds the `JsonType` for a Rust value. Both are
+//! implemented for the primitives, `String`, `Option`, `Vec`, arrays, tuples,
+//! `HashMap`, `BTreeMap` and `Box`, and can be derived for structs and enums
+//! with `#[derive(FromJson, ToJson)]`, see `JSON_parser_derive`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parser::JsonType;
+use crate::pointer::escape_token;
+
+/// Why a value could not be converted. `path` is the JSON Pointer of the
+/// offending value, `""` for the value passed in.
+#[derive(PartialEq, Debug, Clone)]
+pub enum ConvertError {
+    /// Found a value of the JSON type `found` where `expected` was needed.
+    TypeMismatch { path: String, expected: &'static str, found: &'static str },
+    /// A number that is not a whole number, or out of range, for the integer type `expected`.
+    NumberOutOfRange { path: String, expected: &'static str, value: f64 },
+    /// An array of `found` elements where a fixed-size array or tuple needs `expected`.
+    WrongLength { path: String, expected: usize, found: usize },
+    /// The object lacks the member `field`, which has no default.
+    MissingField { path: String, field: String },
+    /// The enum has no variant named `found`.
+    UnknownVariant { path: String, found: String },
+    /// The value matches none of the variants of an untagged enum.
+    NoVariantMatched { path: String }
+}
+
+impl ConvertError {
+    pub fn path(&self) -> &str {
+        match self {
+            ConvertError::TypeMismatch { path, .. } => return path,
+            ConvertError::NumberOutOfRange { path, .. } => return path,
+            ConvertError::WrongLength { path, .. } => return path,
+            ConvertError::MissingField { path, .. } => return path,
+            ConvertError::UnknownVariant { path, .. } => return path,
+            ConvertError::NoVariantMatched { path } => return path
+        }
+    }
+
+    /// The same error for the container one level up, where the failed value
+    /// is the member `token`.
+    pub fn at(mut self, token: &str) -> ConvertError {
+        let path = match &mut self {
+            ConvertError::TypeMismatch { path, .. } => path,
+            ConvertError::NumberOutOfRange { path, .. } => path,
+            ConvertError::WrongLength { path, .. } => path,
+            ConvertError::MissingField { path, .. } => path,
+            ConvertError::UnknownVariant { path, .. } => path,
+            ConvertError::NoVariantMatched { path } => path
+        };
+        *path = format!("/{}{path}", escape_token(token));
+        return self
+    }
+
+    pub fn mismatch(expected: &'static str, found: &JsonType) -> ConvertError {
+        return ConvertError::TypeMismatch { path: String::new(), expected, found: found.type_name() }
+    }
+}
+
+pub use JSON_parser_derive::{FromJson, ToJson};
+
+pub trait FromJson: Sized {
+    fn from_json(value: &JsonType) -> Result<Self, ConvertError>;
+}
+
+pub trait ToJson {
+    fn to_json(&self) -> JsonType;
+}
+
+impl FromJson for JsonType {
+    fn from_json(value: &JsonType) -> Result<JsonType, ConvertError> {
+        return Ok(value.clone())
+    }
+}
+
+impl ToJson for JsonType {
+    fn to_json(&self) -> JsonType {
+        return self.clone()
+    }
+}
+
+impl FromJson for bool {
+    fn from_json(value: &JsonType) -> Result<bool, ConvertError> {
+        match value {
+            JsonType::JsonBool(b) => return Ok(*b),
+            value => return Err(ConvertError::mismatch("boolean", value))
+        }
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> JsonType {
+        return JsonType::JsonBool(*self)
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &JsonType) -> Result<f64, ConvertError> {
+        match value {
+            JsonType::JsonNumber(n) => return Ok(*n),
+            value => return Err(ConvertError::mismatch("number", value))
+        }
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(value: &JsonType) -> Result<f32, ConvertError> {
+        return Ok(f64::from_json(value)? as f32)
+    }
+}
+
+// Integers must be whole numbers within the range of the type
+macro_rules! integer {
+    ($($integer:ty),*) => {
+        $(
+            impl FromJson for $integer {
+                fn from_json(value: &JsonType) -> Result<$integer, ConvertError> {
+                    let n = f64::from_json(value)?;
+                    // MAX as f64 rounds up for 64-bit types, so the upper bound is exclusive
+                    if n.fract() != 0.0 || n < <$integer>::MIN as f64 || n >= <$integer>::MAX as f64 + 1.0 {
+                        return Err(ConvertError::NumberOutOfRange { path: String::new(), expected: stringify!($integer), value: n })
+                    }
+                    return Ok(n as $integer)
+                }
+            }
+        )*
+    };
+}
+
+integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! number_to_json {
+    ($($number:ty),*) => {
+        $(
+            impl ToJson for $number {
+                fn to_json(&self) -> JsonType {
+                    return JsonType::JsonNumber(*self as f64)
+                }
+            }
+        )*
+    };
+}
+
+number_to_json!(f64, f32, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromJson for String {
+    fn from_json(value: &JsonType) -> Result<String, ConvertError> {
+        match value {
+            JsonType::JsonString(s) => return Ok(s.clone()),
+            value => return Err(ConvertError::mismatch("string", value))
+        }
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> JsonType {
+        return JsonType::JsonString(self.clone())
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> JsonType {
+        return JsonType::JsonString(self.to_string())
+    }
+}
+
+/// A string of exactly one character.
+impl FromJson for char {
+    fn from_json(value: &JsonType) -> Result<char, ConvertError> {
+        let mut chars = value.as_str().unwrap_or_default().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => return Ok(c),
+            _ => return Err(ConvertError::mismatch("char", value))
+        }
+    }
+}
+
+impl ToJson for char {
+    fn to_json(&self) -> JsonType {
+        return JsonType::JsonString(self.to_string())
+    }
+}
+
+/// `null` is `None`.
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &JsonType) -> Result<Option<T>, ConvertError> {
+        match value {
+            JsonType::JsonNull => return Ok(None),
+            value => return T::from_json(value).map(Some)
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> JsonType {
+        match self {
+            Some(value) => return value.to_json(),
+            None => return JsonType::JsonNull
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Box<T> {
+    fn from_json(value: &JsonType) -> Result<Box<T>, ConvertError> {
+        return T::from_json(value).map(Box::new)
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> JsonType {
+        return (**self).to_json()
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn to_json(&self) -> JsonType {
+        return (**self).to_json()
+    }
+}
+
+fn elements(value: &JsonType) -> Result<&Vec<JsonType>, ConvertError> {
+    return value.as_array().ok_or(ConvertError::mismatch("array", value))
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &JsonType) -> Result<Vec<T>, ConvertError> {
+        let mut output = Vec::new();
+        for (i, element) in elements(value)?.iter().enumerate() {
+            output.push(T::from_json(element).map_err(|e| e.at(&i.to_string()))?);
+        }
+        return Ok(output)
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> JsonType {
+        return self.as_slice().to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> JsonType {
+        return JsonType::JsonArray(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: FromJson, const N: usize> FromJson for [T; N] {
+    fn from_json(value: &JsonType) -> Result<[T; N], ConvertError> {
+        let found = elements(value)?.len();
+        let output = Vec::<T>::from_json(value)?;
+        return output.try_into().map_err(|_| ConvertError::WrongLength { path: String::new(), expected: N, found })
+    }
+}
+
+impl<T: ToJson, const N: usize> ToJson for [T; N] {
+    fn to_json(&self) -> JsonType {
+        return self.as_slice().to_json()
+    }
+}
+
+// Tuples are arrays with one element per field
+macro_rules! tuple {
+    ($length:literal: $($name:ident $index:tt),+) => {
+        impl<$($name: FromJson),+> FromJson for ($($name,)+) {
+            fn from_json(value: &JsonType) -> Result<($($name,)+), ConvertError> {
+                let values = elements(value)?;
+                if values.len() != $length {
+                    return Err(ConvertError::WrongLength { path: String::new(), expected: $length, found: values.len() })
+                }
+                return Ok(($($name::from_json(&values[$index]).map_err(|e| e.at(stringify!($index)))?,)+))
+            }
+        }
+
+        impl<$($name: ToJson),+> ToJson for ($($name,)+) {
+            fn to_json(&self) -> JsonType {
+                return JsonType::JsonArray(vec![$(self.$index.to_json()),+])
+            }
+        }
+    };
+}
+
+tuple!(1: A 0);
+tuple!(2: A 0, B 1);
+tuple!(3: A 0, B 1, C 2);
+tuple!(4: A 0, B 1, C 2, D 3);
+tuple!(5: A 0, B 1, C 2, D 3, E 4);
+tuple!(6: A 0, B 1, C 2, D 3, E 4, F 5);
+
+fn members(value: &JsonType) -> Result<&HashMap<String, JsonType>, ConvertError> {
+    return value.as_object().ok_or(ConvertError::mismatch("object", value))
+}
+
+impl<T: FromJson> FromJson for HashMap<String, T> {
+    fn from_json(value: &JsonType) -> Result<HashMap<String, T>, ConvertError> {
+        let mut output = HashMap::new();
+        for (key, member) in members(value)? {
+            output.insert(key.clone(), T::from_json(member).map_err(|e| e.at(key))?);
+        }
+        return Ok(output)
+    }
+}
+
+impl<T: ToJson> ToJson for HashMap<String, T> {
+    fn to_json(&self) -> JsonType {
+        return JsonType::JsonObject(self.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+    }
+}
+
+impl<T: FromJson> FromJson for BTreeMap<String, T> {
+    fn from_json(value: &JsonType) -> Result<BTreeMap<String, T>, ConvertError> {
+        let mut output = BTreeMap::new();
+        for (key, member) in members(value)? {
+            output.insert(key.clone(), T::from_json(member).map_err(|e| e.at(key))?);
+        }
+        return Ok(output)
+    }
+}
+
+impl<T: ToJson> ToJson for BTreeMap<String, T> {
+    fn to_json(&self) -> JsonType {
+        return JsonType::JsonObject(self.iter().map(|(k, v)| (k.clone(), v.to_json())).collect())
+    }
+}
+
+/// Used by the code `#[derive(FromJson, ToJson)]` generates.
+#[doc(hidden)]
+pub mod __private {
+    pub use std::collections::HashMap;
+
+    use super::*;
+
+    static NULL: JsonType = JsonType::JsonNull;
+
+    pub fn object(value: &JsonType) -> Result<&HashMap<String, JsonType>, ConvertError> {
+        return members(value)
+    }
+
+    pub fn array(value: &JsonType, len: usize) -> Result<&[JsonType], ConvertError> {
+        let values = elements(value)?;
+        if values.len() != len {
+            return Err(ConvertError::WrongLength { path: String::new(), expected: len, found: values.len() })
+        }
+        return Ok(values)
+    }
+
+    pub fn element<T: FromJson>(values: &[JsonType], index: usize) -> Result<T, ConvertError> {
+        return T::from_json(&values[index]).map_err(|e| e.at(&index.to_string()))
+    }
+
+    /// A missing member is read as `null`, so `Option` fields may be left out.
+    pub fn field<T: FromJson>(object: &HashMap<String, JsonType>, name: &str) -> Result<T, ConvertError> {
+        match object.get(name) {
+            Some(value) => return T::from_json(value).map_err(|e| e.at(name)),
+            None => return T::from_json(&NULL).map_err(|_| ConvertError::MissingField { path: String::new(), field: name.to_string() })
+        }
+    }
+
+    pub fn field_or<T: FromJson>(object: &HashMap<String, JsonType>, name: &str, default: impl FnOnce() -> T) -> Result<T, ConvertError> {
+        match object.get(name) {
+            Some(value) => return T::from_json(value).map_err(|e| e.at(name)),
+            None => return Ok(default())
+        }
+    }
+
+    /// `object` less the members named in `keys`, for a flattened field.
+    pub fn without(object: &HashMap<String, JsonType>, keys: &[&str]) -> JsonType {
+        let rest = object.iter().filter(|(key, _)| !keys.contains(&key.as_str()));
+        return JsonType::JsonObject(rest.map(|(key, value)| (key.clone(), value.clone())).collect())
+    }
+
+    pub fn unit(value: &JsonType) -> Result<(), ConvertError> {
+        match value {
+            JsonType::JsonNull => return Ok(()),
+            value => return Err(ConvertError::mismatch("null", value))
+        }
+    }
+
+    /// The name and content of an externally tagged variant: `"Name"` or `{"Name": content}`.
+    pub fn external(value: &JsonType) -> Result<(&str, &JsonType), ConvertError> {
+        match value {
+            JsonType::JsonString(name) => return Ok((name, &NULL)),
+            JsonType::JsonObject(map) if map.len() == 1 => {
+                let (name, content) = map.iter().next().unwrap();
+                return Ok((name, content))
+            },
+            value => return Err(ConvertError::mismatch("string or object with one member", value))
+        }
+    }
+
+    pub fn tag<'a>(object: &'a HashMap<String, JsonType>, tag: &str) -> Result<&'a str, ConvertError> {
+        match object.get(tag) {
+            Some(JsonType::JsonString(name)) => return Ok(name),
+            Some(value) => return Err(ConvertError::mismatch("string", value).at(tag)),
+            None => return Err(ConvertError::MissingField { path: String::new(), field: tag.to_string() })
+        }
+    }
+
+    pub fn content<'a>(object: &'a HashMap<String, JsonType>, name: &str) -> Result<&'a JsonType, ConvertError> {
+        return object.get(name).ok_or(ConvertError::MissingField { path: String::new(), field: name.to_string() })
+    }
+
+    pub fn unknown_variant(name: &str) -> ConvertError {
+        return ConvertError::UnknownVariant { path: String::new(), found: name.to_string() }
+    }
+
+    /// Moves the members of `value` into `object`; anything but an object adds nothing.
+    pub fn flatten(object: &mut HashMap<String, JsonType>, value: JsonType) {
+        if let JsonType::JsonObject(map) = value {
+            object.extend(map);
+        }
+    }
+
+    pub fn external_to_json(name: &str, content: JsonType) -> JsonType {
+        return JsonType::JsonObject(HashMap::from([(name.to_string(), content)]))
+    }
+
+    /// Adds the tag to the variant's object. Content that is not an object,
+    /// from a newtype variant, cannot carry the tag and is returned as it is.
+    pub fn internal_to_json(tag: &str, name: &str, content: JsonType) -> JsonType {
+        match content {
+            JsonType::JsonObject(mut map) => {
+                map.insert(tag.to_string(), JsonType::JsonString(name.to_string()));
+                return JsonType::JsonObject(map)
+            },
+            JsonType::JsonNull => return JsonType::JsonObject(HashMap::from([(tag.to_string(), JsonType::JsonString(name.to_string()))])),
+            content => return content
+        }
+    }
+
+    pub fn adjacent_to_json(tag: &str, name: &str, content_key: &str, content: Option<JsonType>) -> JsonType {
+        let mut map = HashMap::from([(tag.to_string(), JsonType::JsonString(name.to_string()))]);
+        if let Some(content) = content {
+            map.insert(content_key.to_string(), content);
+        }
+        return JsonType::JsonObject(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mismatch(path: &str, expected: &'static str, found: &'static str) -> ConvertError {
+        return ConvertError::TypeMismatch { path: path.to_string(), expected, found }
+    }
+
+    #[test]
+    fn primitives() {
+        assert_eq!(bool::from_json(&json!(true)), Ok(true));
+        assert_eq!(f64::from_json(&json!(1.5)), Ok(1.5));
+        assert_eq!(u8::from_json(&json!(255)), Ok(255));
+        assert_eq!(i64::from_json(&json!(-3)), Ok(-3));
+        assert_eq!(String::from_json(&json!("s")), Ok("s".to_string()));
+        assert_eq!(char::from_json(&json!("é")), Ok('é'));
+        assert_eq!(Option::<i32>::from_json(&json!(null)), Ok(None));
+        assert_eq!(Option::<i32>::from_json(&json!(4)), Ok(Some(4)));
+        assert_eq!(Box::<bool>::from_json(&json!(false)), Ok(Box::new(false)));
+
+        assert_eq!(u8::from_json(&json!(256)), Err(ConvertError::NumberOutOfRange { path: String::new(), expected: "u8", value: 256.0 }));
+        assert_eq!(u32::from_json(&json!(-1)), Err(ConvertError::NumberOutOfRange { path: String::new(), expected: "u32", value: -1.0 }));
+        assert_eq!(i32::from_json(&json!(1.5)), Err(ConvertError::NumberOutOfRange { path: String::new(), expected: "i32", value: 1.5 }));
+        assert!(u64::from_json(&json!(18446744073709551616.0)).is_err());
+        assert_eq!(bool::from_json(&json!("true")), Err(mismatch("", "boolean", "string")));
+        assert_eq!(char::from_json(&json!("ab")), Err(mismatch("", "char", "string")));
+        assert_eq!(String::from_json(&json!(null)), Err(mismatch("", "string", "null")));
+    }
+
+    #[test]
+    fn containers() {
+        assert_eq!(Vec::<u8>::from_json(&json!([1, 2])), Ok(vec![1, 2]));
+        assert_eq!(<[bool; 2]>::from_json(&json!([true, false])), Ok([true, false]));
+        assert_eq!(<(i32, String, Option<bool>)>::from_json(&json!([1, "a", null])), Ok((1, "a".to_string(), None)));
+        let map = HashMap::<String, Vec<i32>>::from_json(&json!({"a": [1], "b": []})).unwrap();
+        assert_eq!(map, HashMap::from([("a".to_string(), vec![1]), ("b".to_string(), vec![])]));
+        let tree = BTreeMap::<String, f64>::from_json(&json!({"y": 2, "x": 1})).unwrap();
+        assert_eq!(tree.keys().collect::<Vec<_>>(), vec!["x", "y"]);
+
+        assert_eq!(<[u8; 3]>::from_json(&json!([1, 2])), Err(ConvertError::WrongLength { path: String::new(), expected: 3, found: 2 }));
+        assert_eq!(<(u8, u8)>::from_json(&json!([1])), Err(ConvertError::WrongLength { path: String::new(), expected: 2, found: 1 }));
+        assert_eq!(Vec::<u8>::from_json(&json!({})), Err(mismatch("", "array", "object")));
+        assert_eq!(HashMap::<String, u8>::from_json(&json!([])), Err(mismatch("", "object", "array")));
+    }
+
+    #[test]
+    fn errors_point_at_the_value() {
+        let users = json!({"users": [{"tags": ["a"]}, {"tags": ["b", 7]}]});
+        assert_eq!(HashMap::<String, Vec<HashMap<String, Vec<String>>>>::from_json(&users), Err(mismatch("/users/1/tags/1", "string", "number")));
+        assert_eq!(HashMap::<String, HashMap<String, u8>>::from_json(&json!({"a/b": {"~": "x"}})), Err(mismatch("/a~1b/~0", "number", "string")));
+        assert_eq!(<(u8, (bool, u8))>::from_json(&json!([1, [true, 300]])), Err(ConvertError::NumberOutOfRange { path: "/1/1".to_string(), expected: "u8", value: 300.0 }));
+        assert_eq!(mismatch("/x", "string", "null").at("a").path(), "/a/x");
+    }
+
+    #[derive(FromJson, ToJson, PartialEq, Debug)]
+    struct Entry {
+        key: String,
+        values: Vec<u8>
+    }
+
+    #[test]
+    fn derive_inside_the_crate() {
+        let entry = Entry { key: "k".to_string(), values: vec![1] };
+        assert_eq!(entry.to_json(), json!({"key": "k", "values": [1]}));
+        assert_eq!(Entry::from_json(&json!({"key": "k", "values": [1]})), Ok(entry));
+    }
+
+    #[test]
+    fn to_json() {
+        assert_eq!(vec![Some(1u8), None].to_json(), json!([1, null]));
+        assert_eq!((1, "a", [true]).to_json(), json!([1, "a", [true]]));
+        assert_eq!(HashMap::from([("k".to_string(), 'c')]).to_json(), json!({"k": "c"}));
+        assert_eq!(BTreeMap::from([("k".to_string(), Box::new(2.5))]).to_json(), json!({"k": 2.5}));
+        assert_eq!("s".to_json(), json!("s"));
+        let value = json!({"a": [1, 2]});
+        assert_eq!(HashMap::<String, Vec<u8>>::from_json(&value).unwrap().to_json(), value);
+    }
+}
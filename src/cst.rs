@@ -0,0 +1,416 @@
+//! Lossless concrete syntax tree.
+//!
+//! Unlike `JsonType`, the tree keeps every token of the input: whitespace and
+//! comments are stored as trivia around the values, scalars keep their exact
+//! source text and every node records its byte span. Printing a tree with
+//! `Display` gives back the input byte for byte.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parser::{
+    JsonType,
+    ParseError,
+    ParserOptions,
+    parse_identifier,
+    parse_string_raw_with,
+    parse_value_with,
+    skip_whitespace,
+};
+
+/// Byte range `start..end` in the parsed input.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment
+}
+
+/// A run of whitespace or a single comment.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span
+}
+
+/// A value together with the trivia on either side of it.
+///
+/// `leading` holds everything between the previous token and the value,
+/// `trailing` everything between the value and the next token.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Node {
+    pub leading: Vec<Trivia>,
+    pub kind: NodeKind,
+    pub span: Span,
+    pub trailing: Vec<Trivia>
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum NodeKind {
+    /// A string, number, boolean or null, with its exact source text.
+    Scalar { text: String, value: JsonType },
+    /// `dangling` is the trivia before `]` that belongs to no element: the
+    /// inside of an empty array or whatever follows a trailing comma.
+    Array { elements: Vec<Node>, trailing_comma: bool, dangling: Vec<Trivia> },
+    Object { members: Vec<Member>, trailing_comma: bool, dangling: Vec<Trivia> }
+}
+
+/// One `key: value` entry of an object. The key is a scalar node holding
+/// either a quoted string or, if allowed, a bare identifier.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Member {
+    pub key: Node,
+    pub value: Node
+}
+
+impl Member {
+    /// The key as a plain string.
+    pub fn name(&self) -> &str {
+        match &self.key.kind {
+            NodeKind::Scalar { value: JsonType::JsonString(name), .. } => return name,
+            _ => return ""
+        }
+    }
+}
+
+impl Node {
+    /// Lowers the tree into a `JsonType`, dropping all formatting.
+    pub fn to_json(&self) -> JsonType {
+        match &self.kind {
+            NodeKind::Scalar { value, .. } => return value.clone(),
+            NodeKind::Array { elements, .. } => {
+                return JsonType::JsonArray(elements.iter().map(|e| e.to_json()).collect())
+            },
+            NodeKind::Object { members, .. } => {
+                let mut output = HashMap::new();
+                for member in members {
+                    output.insert(member.name().to_string(), member.value.to_json());
+                }
+                return JsonType::JsonObject(output)
+            }
+        }
+    }
+
+    /// Span of the node including its leading and trailing trivia.
+    pub fn full_span(&self) -> Span {
+        let start = self.leading.first().map(|t| t.span.start).unwrap_or(self.span.start);
+        let end = self.trailing.last().map(|t| t.span.end).unwrap_or(self.span.end);
+        return Span { start, end }
+    }
+}
+
+fn write_trivia(f: &mut fmt::Formatter, trivia: &[Trivia]) -> fmt::Result {
+    for t in trivia {
+        f.write_str(&t.text)?;
+    }
+    return Ok(())
+}
+
+impl fmt::Display for Node {
+    /// Writes the node back out exactly as it was parsed.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_trivia(f, &self.leading)?;
+        match &self.kind {
+            NodeKind::Scalar { text, .. } => f.write_str(text)?,
+            NodeKind::Array { elements, trailing_comma, dangling } => {
+                f.write_str("[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                if *trailing_comma {
+                    f.write_str(",")?;
+                }
+                write_trivia(f, dangling)?;
+                f.write_str("]")?;
+            },
+            NodeKind::Object { members, trailing_comma, dangling } => {
+                f.write_str("{")?;
+                for (i, member) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}:{}", member.key, member.value)?;
+                }
+                if *trailing_comma {
+                    f.write_str(",")?;
+                }
+                write_trivia(f, dangling)?;
+                f.write_str("}")?;
+            }
+        }
+        return write_trivia(f, &self.trailing)
+    }
+}
+
+/// Parses a complete document into a concrete syntax tree.
+///
+/// On failure the error is returned with the byte offset where it was found.
+pub fn parse(data: &str) -> Result<Node, (ParseError, usize)> {
+    return parse_with(data, ParserOptions::default())
+}
+
+pub fn parse_with(data: &str, options: ParserOptions) -> Result<Node, (ParseError, usize)> {
+    let parser = Parser { source: data, options };
+    let (node, rest) = parser.node(data, options)?;
+    if let Some(c) = rest.chars().next() {
+        let error = ParseError::UnexpectedChar { expected: "end of input".to_string(), found: c };
+        return Err((error, parser.offset(rest)))
+    }
+    return Ok(node)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    options: ParserOptions
+}
+
+impl<'a> Parser<'a> {
+    fn offset(&self, rest: &str) -> usize {
+        return self.source.len() - rest.len()
+    }
+
+    fn span(&self, from: &str, to: &str) -> Span {
+        return Span { start: self.offset(from), end: self.offset(to) }
+    }
+
+    /// Splits the whitespace and comments at the start of `data` into trivia.
+    fn trivia(&self, data: &'a str) -> Result<(Vec<Trivia>, &'a str), (ParseError, usize)> {
+        let plain = ParserOptions { allow_comments: false, ..self.options };
+        let mut output = Vec::new();
+        let mut rest = data;
+        loop {
+            let after = skip_whitespace(rest, plain).map_err(|e| (e, self.offset(rest)))?;
+            let (kind, end) = if after.len() != rest.len() {
+                (TriviaKind::Whitespace, after)
+            } else if self.options.allow_comments && rest.starts_with("//") {
+                let end = rest.find(['\n', '\r']).unwrap_or(rest.len());
+                (TriviaKind::LineComment, rest.get(end..).unwrap())
+            } else if self.options.allow_comments && rest.starts_with("/*") {
+                match rest.get(2..).unwrap().find("*/") {
+                    Some(i) => (TriviaKind::BlockComment, rest.get((i + 4)..).unwrap()),
+                    None => {return Err((ParseError::UnexpectedEof, self.offset(rest)))}
+                }
+            } else {
+                return Ok((output, rest))
+            };
+            let text = rest.get(..(rest.len() - end.len())).unwrap().to_string();
+            output.push(Trivia { kind, text, span: self.span(rest, end) });
+            rest = end;
+        }
+    }
+
+    /// Parses a value with its leading trivia and the trivia up to the next token.
+    fn node(&self, data: &'a str, options: ParserOptions) -> Result<(Node, &'a str), (ParseError, usize)> {
+        let (leading, start) = self.trivia(data)?;
+        let (kind, end) = match start.chars().next() {
+            Some('[') => self.array(start, options.nested().map_err(|e| (e, self.offset(start)))?)?,
+            Some('{') => self.object(start, options.nested().map_err(|e| (e, self.offset(start)))?)?,
+            Some(_) => self.scalar(start)?,
+            None => {return Err((ParseError::UnexpectedEof, self.offset(start)))}
+        };
+        let span = self.span(start, end);
+        let (trailing, rest) = self.trivia(end)?;
+        return Ok((Node { leading, kind, span, trailing }, rest))
+    }
+
+    fn scalar(&self, data: &'a str) -> Result<(NodeKind, &'a str), (ParseError, usize)> {
+        let (value, rest) = parse_value_with(data, self.options).map_err(|e| (e, self.offset(data)))?;
+        let text = data.get(..(data.len() - rest.len())).unwrap().to_string();
+        return Ok((NodeKind::Scalar { text, value }, rest))
+    }
+
+    fn key(&self, data: &'a str) -> Result<(Node, &'a str), (ParseError, usize)> {
+        let (leading, start) = self.trivia(data)?;
+        let (name, end) = match start.chars().next() {
+            Some('"') => parse_string_raw_with(start, self.options).map_err(|e| (e, self.offset(start)))?,
+            Some('\'') if self.options.allow_single_quotes => {
+                parse_string_raw_with(start, self.options).map_err(|e| (e, self.offset(start)))?
+            },
+            Some(c) => {
+                match parse_identifier(start) {
+                    Some(r) if self.options.allow_unquoted_keys => r,
+                    _ => {
+                        let error = ParseError::UnexpectedChar { expected: "\"".to_string(), found: c };
+                        return Err((error, self.offset(start)))
+                    }
+                }
+            },
+            None => {return Err((ParseError::UnexpectedEof, self.offset(start)))}
+        };
+        let text = start.get(..(start.len() - end.len())).unwrap().to_string();
+        let kind = NodeKind::Scalar { text, value: JsonType::JsonString(name) };
+        let span = self.span(start, end);
+        let (trailing, rest) = self.trivia(end)?;
+        return Ok((Node { leading, kind, span, trailing }, rest))
+    }
+
+    fn array(&self, data: &'a str, options: ParserOptions) -> Result<(NodeKind, &'a str), (ParseError, usize)> {
+        let mut elements = Vec::new();
+        let mut rest = data.get(1..).unwrap();
+
+        loop {
+            // Empty array, or the end after a trailing comma
+            let (dangling, after) = self.trivia(rest)?;
+            if let Some(r) = after.strip_prefix(']') {
+                let trailing_comma = !elements.is_empty();
+                if trailing_comma && !options.allow_trailing_commas {
+                    return Err((ParseError::MalformedArray, self.offset(after)))
+                }
+                return Ok((NodeKind::Array { elements, trailing_comma, dangling }, r))
+            }
+
+            let element;
+            (element, rest) = self.node(rest, options)?;
+            elements.push(element);
+
+            match rest.chars().next() {
+                Some(']') => {
+                    let kind = NodeKind::Array { elements, trailing_comma: false, dangling: Vec::new() };
+                    return Ok((kind, rest.get(1..).unwrap()))
+                },
+                Some(',') => {rest = rest.get(1..).unwrap()},
+                None => {return Err((ParseError::UnexpectedEof, self.offset(rest)))},
+                _ => {return Err((ParseError::MalformedArray, self.offset(rest)))}
+            }
+        }
+    }
+
+    fn object(&self, data: &'a str, options: ParserOptions) -> Result<(NodeKind, &'a str), (ParseError, usize)> {
+        let mut members = Vec::new();
+        let mut rest = data.get(1..).unwrap();
+
+        loop {
+            // Empty object, or the end after a trailing comma
+            let (dangling, after) = self.trivia(rest)?;
+            if let Some(r) = after.strip_prefix('}') {
+                let trailing_comma = !members.is_empty();
+                if trailing_comma && !options.allow_trailing_commas {
+                    let error = ParseError::UnexpectedChar { expected: "\"".to_string(), found: '}' };
+                    return Err((error, self.offset(after)))
+                }
+                return Ok((NodeKind::Object { members, trailing_comma, dangling }, r))
+            }
+
+            let key;
+            (key, rest) = self.key(rest)?;
+            match rest.chars().next() {
+                Some(':') => {rest = rest.get(1..).unwrap()},
+                Some(c) => {
+                    let error = ParseError::UnexpectedChar { expected: ":".to_string(), found: c };
+                    return Err((error, self.offset(rest)))
+                },
+                None => {return Err((ParseError::UnexpectedEof, self.offset(rest)))}
+            }
+
+            let value;
+            (value, rest) = self.node(rest, options)?;
+            members.push(Member { key, value });
+
+            match rest.chars().next() {
+                Some('}') => {
+                    let kind = NodeKind::Object { members, trailing_comma: false, dangling: Vec::new() };
+                    return Ok((kind, rest.get(1..).unwrap()))
+                },
+                Some(',') => {rest = rest.get(1..).unwrap()},
+                Some(c) => {
+                    let error = ParseError::UnexpectedChar { expected: "},,".to_string(), found: c };
+                    return Err((error, self.offset(rest)))
+                },
+                None => {return Err((ParseError::UnexpectedEof, self.offset(rest)))}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Dialect, parse_document, parse_document_with};
+
+    fn round_trip(data: &str, options: ParserOptions) {
+        let node = parse_with(data, options).unwrap();
+        assert_eq!(node.to_string(), data);
+        assert_eq!(node.to_json(), parse_document_with(data, options).unwrap());
+    }
+
+    #[test]
+    fn prints_back_identically() {
+        let options = ParserOptions::default();
+        round_trip("1", options);
+        round_trip("  \"x\"\n", options);
+        round_trip("[]", options);
+        round_trip("[ ]", options);
+        round_trip("{ \n }", options);
+        round_trip("{\n  \"name\" : \"Alice\",\n  \"tags\": [ 1,2 ,3 ],\n  \"nested\": {\"a\": null, \"b\": true}\n}\n", options);
+        round_trip("[\"esc\\\"aped\\n\", -1.5e3, false]", options);
+    }
+
+    #[test]
+    fn keeps_json5_trivia() {
+        let options = Dialect::Json5.into();
+        round_trip("// header\n{\n  /* block */ a: 1, // after a\n  'b': [0x1F, .5,],\n}\n// footer", options);
+
+        let node = parse_with("[1, // one\n 2]", options).unwrap();
+        match &node.kind {
+            NodeKind::Array { elements, .. } => {
+                assert_eq!(elements[0].trailing, vec![]);
+                assert_eq!(elements[1].leading.len(), 3);
+                assert_eq!(elements[1].leading[1].kind, TriviaKind::LineComment);
+                assert_eq!(elements[1].leading[1].text, "// one");
+                assert_eq!(elements[1].leading[1].span, Span { start: 4, end: 10 });
+            },
+            _ => panic!("expected an array")
+        }
+    }
+
+    #[test]
+    fn records_spans() {
+        let data = "{\"a\": [10, \"x\"], \"b\" :null }";
+        let node = parse(data).unwrap();
+        assert_eq!(node.span, Span { start: 0, end: data.len() });
+        match &node.kind {
+            NodeKind::Object { members, .. } => {
+                assert_eq!(members[0].name(), "a");
+                assert_eq!(members[0].key.span, Span { start: 1, end: 4 });
+                assert_eq!(members[0].value.span, Span { start: 6, end: 15 });
+                match &members[0].value.kind {
+                    NodeKind::Array { elements, .. } => {
+                        assert_eq!(elements[1].span, Span { start: 11, end: 14 });
+                        assert_eq!(&data[elements[1].span.start..elements[1].span.end], "\"x\"");
+                    },
+                    _ => panic!("expected an array")
+                }
+                assert_eq!(members[1].key.trailing[0].text, " ");
+                assert_eq!(members[1].value.span, Span { start: 22, end: 26 });
+                assert_eq!(members[1].value.full_span(), Span { start: 22, end: 27 });
+            },
+            _ => panic!("expected an object")
+        }
+    }
+
+    #[test]
+    fn lowers_to_json() {
+        let data = "{\"users\": [{\"name\": \"Alice\", \"scores\": [10, 20]}], \"count\": 1}";
+        assert_eq!(parse(data).unwrap().to_json(), parse_document(data).unwrap());
+    }
+
+    #[test]
+    fn reports_error_offsets() {
+        assert_eq!(parse("[1, 2,]"), Err((ParseError::MalformedArray, 6)));
+        assert_eq!(parse("{\"a\" 1}"), Err((ParseError::UnexpectedChar { expected: ":".to_string(), found: '1' }, 5)));
+        assert_eq!(parse("[1, tru]").unwrap_err().1, 4);
+        assert_eq!(parse("[1] x").unwrap_err().1, 4);
+        assert_eq!(parse("[1, // no comments\n 2]").unwrap_err().1, 4);
+        assert_eq!(parse("[1,").unwrap_err(), (ParseError::UnexpectedEof, 3));
+    }
+}
@@ -0,0 +1,251 @@
+//! Structural differences between two values, for readable test failures.
+//!
+//! `diff` lists every change keyed by the JSON Pointer of the value it
+//! concerns, `render` and `render_colored` print the list like a unified diff.
+//! Unlike `patch::diff` the result is meant to be read, not applied.
+
+use crate::parser::JsonType;
+use crate::patch::{edit_script, Edit};
+use crate::pointer::escape_token;
+use crate::serializer::to_string_pretty;
+
+/// One difference between the old and the new value.
+///
+/// Removed values are located in the old value, everything else in the new
+/// one, which only matters when array elements were inserted or removed
+/// before them.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Change {
+    Added { path: String, value: JsonType },
+    Removed { path: String, value: JsonType },
+    /// Same type, different value.
+    Changed { path: String, old: JsonType, new: JsonType },
+    /// The value became another type, e.g. a number became a string.
+    TypeChanged { path: String, old: JsonType, new: JsonType }
+}
+
+impl Change {
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. } => return path,
+            Change::Removed { path, .. } => return path,
+            Change::Changed { path, .. } => return path,
+            Change::TypeChanged { path, .. } => return path
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct DiffOptions {
+    /// Arrays are compared as multisets: equal elements match wherever they
+    /// are, and only the elements left over are reported.
+    pub ignore_array_order: bool
+}
+
+fn diff_arrays(a: &[JsonType], b: &[JsonType], path: &str, options: DiffOptions, output: &mut Vec<Change>) {
+    if options.ignore_array_order {
+        let mut unmatched: Vec<Option<&JsonType>> = b.iter().map(Some).collect();
+        let mut removed = Vec::new();
+        for (i, value) in a.iter().enumerate() {
+            match unmatched.iter_mut().find(|other| *other == &Some(value)) {
+                Some(other) => *other = None,
+                None => removed.push(i)
+            }
+        }
+        let mut added: Vec<usize> = (0..b.len()).filter(|j| unmatched[*j].is_some()).collect();
+        // A leftover is paired with the next leftover of the same type, so an
+        // edited element shows as changed instead of as removed and added
+        let mut unpaired = Vec::new();
+        for i in removed {
+            match added.iter().position(|j| b[*j].type_name() == a[i].type_name()) {
+                Some(k) => {
+                    let j = added.remove(k);
+                    diff_into(&a[i], &b[j], &format!("{path}/{j}"), options, output);
+                },
+                None => unpaired.push(i)
+            }
+        }
+        for i in unpaired {
+            output.push(Change::Removed { path: format!("{path}/{i}"), value: a[i].clone() });
+        }
+        for j in added {
+            output.push(Change::Added { path: format!("{path}/{j}"), value: b[j].clone() });
+        }
+        return
+    }
+
+    let mut old = 0;
+    for edit in edit_script(a, b) {
+        match edit {
+            Edit::Keep => old += 1,
+            Edit::Replace(j) => {
+                diff_into(&a[old], &b[j], &format!("{path}/{j}"), options, output);
+                old += 1;
+            },
+            Edit::Delete => {
+                output.push(Change::Removed { path: format!("{path}/{old}"), value: a[old].clone() });
+                old += 1;
+            },
+            Edit::Insert(j) => output.push(Change::Added { path: format!("{path}/{j}"), value: b[j].clone() })
+        }
+    }
+}
+
+fn diff_into(a: &JsonType, b: &JsonType, path: &str, options: DiffOptions, output: &mut Vec<Change>) {
+    if a == b {
+        return
+    }
+    match (a, b) {
+        (JsonType::JsonObject(a), JsonType::JsonObject(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))).collect();
+            keys.sort();
+            for key in keys {
+                let child = format!("{path}/{}", escape_token(key));
+                match (a.get(key), b.get(key)) {
+                    (Some(old), Some(new)) => diff_into(old, new, &child, options, output),
+                    (Some(old), None) => output.push(Change::Removed { path: child, value: old.clone() }),
+                    (None, Some(new)) => output.push(Change::Added { path: child, value: new.clone() }),
+                    (None, None) => {}
+                }
+            }
+        },
+        (JsonType::JsonArray(a), JsonType::JsonArray(b)) => diff_arrays(a, b, path, options, output),
+        (a, b) if a.type_name() != b.type_name() => {
+            output.push(Change::TypeChanged { path: path.to_string(), old: a.clone(), new: b.clone() })
+        },
+        (a, b) => output.push(Change::Changed { path: path.to_string(), old: a.clone(), new: b.clone() })
+    }
+}
+
+/// Every change turning `a` into `b`, objects in sorted key order.
+pub fn diff(a: &JsonType, b: &JsonType) -> Vec<Change> {
+    return diff_with(a, b, DiffOptions::default())
+}
+
+pub fn diff_with(a: &JsonType, b: &JsonType, options: DiffOptions) -> Vec<Change> {
+    let mut output = Vec::new();
+    diff_into(a, b, "", options, &mut output);
+    return output
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Writes `value` pretty-printed, every line starting with `sign`.
+fn write_value(output: &mut String, sign: char, value: &JsonType, color: Option<&str>) {
+    for line in to_string_pretty(value, 2).lines() {
+        match color {
+            Some(color) => output.push_str(&format!("{color}{sign} {line}{RESET}\n")),
+            None => output.push_str(&format!("{sign} {line}\n"))
+        }
+    }
+}
+
+fn render_with(changes: &[Change], color: bool) -> String {
+    let paint = |code: &'static str| if color { Some(code) } else { None };
+    let mut output = String::new();
+    if changes.is_empty() {
+        return output
+    }
+    if color {
+        output.push_str(&format!("{RED}--- old{RESET}\n{GREEN}+++ new{RESET}\n"));
+    } else {
+        output.push_str("--- old\n+++ new\n");
+    }
+    for change in changes {
+        let path = match change.path() {
+            "" => "(root)",
+            path => path
+        };
+        let header = match change {
+            Change::TypeChanged { old, new, .. } => format!("@@ {path} ({} -> {}) @@", old.type_name(), new.type_name()),
+            _ => format!("@@ {path} @@")
+        };
+        match paint(CYAN) {
+            Some(cyan) => output.push_str(&format!("{cyan}{header}{RESET}\n")),
+            None => output.push_str(&format!("{header}\n"))
+        }
+        match change {
+            Change::Added { value, .. } => write_value(&mut output, '+', value, paint(GREEN)),
+            Change::Removed { value, .. } => write_value(&mut output, '-', value, paint(RED)),
+            Change::Changed { old, new, .. } | Change::TypeChanged { old, new, .. } => {
+                write_value(&mut output, '-', old, paint(RED));
+                write_value(&mut output, '+', new, paint(GREEN));
+            }
+        }
+    }
+    return output
+}
+
+/// Prints the changes as a unified diff, one `@@ pointer @@` hunk per change
+/// with the old value on `-` lines and the new one on `+` lines. Empty when
+/// there are no changes.
+pub fn render(changes: &[Change]) -> String {
+    return render_with(changes, false)
+}
+
+/// `render` with ANSI colors, for terminal output.
+pub fn render_colored(changes: &[Change]) -> String {
+    return render_with(changes, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_document;
+
+    fn json(data: &str) -> JsonType {
+        return parse_document(data).unwrap()
+    }
+
+    fn changes(a: &str, b: &str, options: DiffOptions) -> Vec<Change> {
+        return diff_with(&json(a), &json(b), options)
+    }
+
+    #[test]
+    fn changes_are_keyed_by_pointer() {
+        let a = r#"{"name": "Alice", "age": 31, "tags": ["a", "b"], "meta": {"x": 1}, "gone": true}"#;
+        let b = r#"{"name": "Alice", "age": "31", "tags": ["a", "c", "d"], "meta": {"x": 2}, "new~/": null}"#;
+        assert_eq!(changes(a, b, DiffOptions::default()), vec![
+            Change::TypeChanged { path: "/age".to_string(), old: json("31"), new: json("\"31\"") },
+            Change::Removed { path: "/gone".to_string(), value: json("true") },
+            Change::Changed { path: "/meta/x".to_string(), old: json("1"), new: json("2") },
+            Change::Added { path: "/new~0~1".to_string(), value: json("null") },
+            Change::Changed { path: "/tags/1".to_string(), old: json("\"b\""), new: json("\"c\"") },
+            Change::Added { path: "/tags/2".to_string(), value: json("\"d\"") }
+        ]);
+        assert_eq!(diff(&json(a), &json(a)), vec![]);
+        assert_eq!(diff(&json("[1]"), &json("{}")), vec![Change::TypeChanged { path: String::new(), old: json("[1]"), new: json("{}") }]);
+    }
+
+    #[test]
+    fn array_order() {
+        let ordered = DiffOptions::default();
+        let unordered = DiffOptions { ignore_array_order: true };
+        assert_eq!(changes("[0, 1, 2, 3]", "[1, 2, 3]", ordered), vec![Change::Removed { path: "/0".to_string(), value: json("0") }]);
+        assert_eq!(changes("[1, 2]", "[0, 1, 2]", ordered), vec![Change::Added { path: "/0".to_string(), value: json("0") }]);
+        assert_eq!(changes("[1, 2, 3]", "[3, 1, 2]", ordered).len(), 2);
+        assert_eq!(changes("[1, 2, 3]", "[3, 1, 2]", unordered), vec![]);
+        assert_eq!(changes("[1, 1, 2]", "[1, 2, 2]", unordered), vec![Change::Changed { path: "/2".to_string(), old: json("1"), new: json("2") }]);
+        assert_eq!(changes(r#"[{"id": 1, "v": 1}, 5]"#, r#"[7, 5, {"id": 1, "v": 2}]"#, unordered), vec![
+            Change::Changed { path: "/2/v".to_string(), old: json("1"), new: json("2") },
+            Change::Added { path: "/0".to_string(), value: json("7") }
+        ]);
+        assert_eq!(changes("[[1, 2]]", "[[2, 1]]", unordered), vec![]);
+    }
+
+    #[test]
+    fn renders_unified_text() {
+        let changes = changes(r#"{"a": 1, "b": [true], "c": "x"}"#, r#"{"a": 2, "b": [true, {"k": null}]}"#, DiffOptions::default());
+        let expected = "--- old\n+++ new\n\
+            @@ /a @@\n- 1\n+ 2\n\
+            @@ /b/1 @@\n+ {\n+   \"k\": null\n+ }\n\
+            @@ /c @@\n- \"x\"\n";
+        assert_eq!(render(&changes), expected);
+        assert_eq!(render_colored(&changes[..1]), "\x1b[31m--- old\x1b[0m\n\x1b[32m+++ new\x1b[0m\n\x1b[36m@@ /a @@\x1b[0m\n\x1b[31m- 1\x1b[0m\n\x1b[32m+ 2\x1b[0m\n");
+        assert_eq!(render(&diff(&json("1"), &json("\"1\""))), "--- old\n+++ new\n@@ (root) (number -> string) @@\n- 1\n+ \"1\"\n");
+        assert_eq!(render(&[]), "");
+    }
+}
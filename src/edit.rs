@@ -0,0 +1,418 @@
+//! Format-preserving edits on JSON text.
+//!
+//! A `Document` keeps the original source next to its concrete syntax tree.
+//! Edits splice only the affected bytes of the source, so whitespace, key
+//! order and comments outside the edited value stay exactly as they were.
+//!
+//! Paths are given as a list of segments, object keys or array indices written
+//! as decimal strings, e.g. `&["users", "0", "name"]`.
+
+use crate::cst::{self, Member, Node, NodeKind, Span};
+use crate::parser::{JsonType, ParseError, ParserOptions};
+use crate::serializer::{escape_string, to_string, to_string_indented};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum EditError {
+    /// The segment at this position of the path does not exist.
+    NotFound(usize),
+    /// The segment at this position tries to look inside a string, number, bool or null.
+    NotAContainer(usize),
+    /// The segment at this position is not a valid index for its array.
+    InvalidIndex(usize),
+    /// `insert` was asked to add a key that is already present.
+    AlreadyExists(usize),
+    /// The operation needs a parent value, but the path is empty.
+    EmptyPath,
+    /// The source could not be parsed.
+    Parse(ParseError, usize)
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    source: String,
+    options: ParserOptions,
+    root: Node
+}
+
+/// Where a new entry goes inside a container.
+enum Slot<'a> {
+    Member(&'a str),
+    Element(usize)
+}
+
+impl Document {
+    pub fn parse(source: &str) -> Result<Document, EditError> {
+        return Document::parse_with(source, ParserOptions::default())
+    }
+
+    pub fn parse_with(source: &str, options: ParserOptions) -> Result<Document, EditError> {
+        let root = cst::parse_with(source, options).map_err(|(e, offset)| EditError::Parse(e, offset))?;
+        return Ok(Document { source: source.to_string(), options, root })
+    }
+
+    /// The current text of the document.
+    pub fn as_str(&self) -> &str {
+        return &self.source
+    }
+
+    pub fn root(&self) -> &Node {
+        return &self.root
+    }
+
+    pub fn to_json(&self) -> JsonType {
+        return self.root.to_json()
+    }
+
+    /// Looks up the value at `path`.
+    pub fn get(&self, path: &[&str]) -> Result<JsonType, EditError> {
+        return Ok(self.resolve(path)?.to_json())
+    }
+
+    /// Replaces the value at `path`. A missing last key is added to its object.
+    pub fn set(&mut self, path: &[&str], value: &JsonType) -> Result<(), EditError> {
+        let Some((last, parent_path)) = path.split_last() else {
+            let span = self.root.span;
+            let text = self.render(value, span.start);
+            return self.splice(span, &text)
+        };
+        let parent = self.resolve(parent_path)?;
+        match &parent.kind {
+            NodeKind::Object { members, .. } => {
+                match members.iter().rev().find(|m| m.name() == *last) {
+                    Some(member) => {
+                        let span = member.value.span;
+                        let text = self.render(value, span.start);
+                        return self.splice(span, &text)
+                    },
+                    None => {
+                        let (span, text) = self.add(parent, Slot::Member(last), value);
+                        return self.splice(span, &text)
+                    }
+                }
+            },
+            NodeKind::Array { elements, .. } => {
+                let index = parse_index(last, elements.len()).ok_or(EditError::InvalidIndex(path.len() - 1))?;
+                let span = elements[index].span;
+                let text = self.render(value, span.start);
+                return self.splice(span, &text)
+            },
+            NodeKind::Scalar { .. } => return Err(EditError::NotAContainer(path.len() - 1))
+        }
+    }
+
+    /// Adds a new key to an object, or inserts into an array before the given
+    /// index, where an index equal to the length appends.
+    pub fn insert(&mut self, path: &[&str], value: &JsonType) -> Result<(), EditError> {
+        let (last, parent_path) = path.split_last().ok_or(EditError::EmptyPath)?;
+        let parent = self.resolve(parent_path)?;
+        match &parent.kind {
+            NodeKind::Object { members, .. } => {
+                if members.iter().any(|m| m.name() == *last) {
+                    return Err(EditError::AlreadyExists(path.len() - 1))
+                }
+                let (span, text) = self.add(parent, Slot::Member(last), value);
+                return self.splice(span, &text)
+            },
+            NodeKind::Array { elements, .. } => {
+                let index = parse_index(last, elements.len() + 1).ok_or(EditError::InvalidIndex(path.len() - 1))?;
+                let (span, text) = self.add(parent, Slot::Element(index), value);
+                return self.splice(span, &text)
+            },
+            NodeKind::Scalar { .. } => return Err(EditError::NotAContainer(path.len() - 1))
+        }
+    }
+
+    /// Removes the value at `path` together with its separator and returns it.
+    pub fn remove(&mut self, path: &[&str]) -> Result<JsonType, EditError> {
+        let (last, parent_path) = path.split_last().ok_or(EditError::EmptyPath)?;
+        let parent = self.resolve(parent_path)?;
+        // The leading trivia, start and end of each entry
+        let entries: Vec<(&[cst::Trivia], Span)> = match &parent.kind {
+            NodeKind::Object { members, .. } => {
+                members.iter().map(|m| (m.key.leading.as_slice(), Span { start: m.key.span.start, end: m.value.span.end })).collect()
+            },
+            NodeKind::Array { elements, .. } => elements.iter().map(|e| (e.leading.as_slice(), e.span)).collect(),
+            NodeKind::Scalar { .. } => return Err(EditError::NotAContainer(path.len() - 1))
+        };
+        let index = match &parent.kind {
+            NodeKind::Object { members, .. } => {
+                members.iter().rposition(|m| m.name() == *last).ok_or(EditError::NotFound(path.len() - 1))?
+            },
+            _ => parse_index(last, entries.len()).ok_or(EditError::InvalidIndex(path.len() - 1))?
+        };
+        let removed = self.resolve(path)?.to_json();
+
+        let (span, text) = if entries.len() == 1 {
+            // Leave an empty container behind
+            (Span { start: parent.span.start + 1, end: parent.span.end - 1 }, String::new())
+        } else if index + 1 < entries.len() {
+            // The entry with its comments and comma, the next entry moves into
+            // its place. That one keeps its own leading trivia only if it
+            // carries comments, otherwise it takes over the removed spacing.
+            let (leading, entry) = entries[index];
+            let (next_leading, next) = entries[index + 1];
+            let start = leading.first().map(|t| t.span.start).unwrap_or(entry.start);
+            let text = if next_leading.iter().any(|t| t.kind != cst::TriviaKind::Whitespace) {
+                next_leading.iter().map(|t| t.text.as_str()).collect()
+            } else {
+                whitespace(leading)
+            };
+            (Span { start, end: next.start }, text)
+        } else {
+            // The last entry goes together with the comma in front of it
+            (Span { start: entries[index - 1].1.end, end: entries[index].1.end }, String::new())
+        };
+        self.splice(span, &text)?;
+        return Ok(removed)
+    }
+
+    fn resolve(&self, path: &[&str]) -> Result<&Node, EditError> {
+        let mut node = &self.root;
+        for (i, segment) in path.iter().enumerate() {
+            node = match &node.kind {
+                NodeKind::Object { members, .. } => {
+                    &members.iter().rev().find(|m| m.name() == *segment).ok_or(EditError::NotFound(i))?.value
+                },
+                NodeKind::Array { elements, .. } => {
+                    &elements[parse_index(segment, elements.len()).ok_or(EditError::InvalidIndex(i))?]
+                },
+                NodeKind::Scalar { .. } => return Err(EditError::NotAContainer(i))
+            };
+        }
+        return Ok(node)
+    }
+
+    /// Works out the splice that adds a member or element to `parent`, copying
+    /// the layout of its siblings.
+    fn add(&self, parent: &Node, slot: Slot, value: &JsonType) -> (Span, String) {
+        let (members, elements): (&[Member], &[Node]) = match &parent.kind {
+            NodeKind::Object { members, .. } => (members, &[]),
+            NodeKind::Array { elements, .. } => (&[], elements),
+            NodeKind::Scalar { .. } => unreachable!("only called with containers")
+        };
+        let entry = |this: &Document, at: usize| -> String {
+            let rendered = this.render(value, at);
+            match slot {
+                Slot::Member(key) => {
+                    // Reuse the spacing around the colon of the last member
+                    let colon = match members.last() {
+                        Some(m) => format!("{}:{}", whitespace(&m.key.trailing), whitespace(&m.value.leading)),
+                        None if this.is_multiline() => ": ".to_string(),
+                        None => ":".to_string()
+                    };
+                    format!("{}{colon}{rendered}", escape_string(key))
+                },
+                Slot::Element(_) => rendered
+            }
+        };
+        let starts: Vec<Span> = match slot {
+            Slot::Member(_) => members.iter().map(|m| Span { start: m.key.span.start, end: m.value.span.end }).collect(),
+            Slot::Element(_) => elements.iter().map(|e| e.span).collect()
+        };
+        let separator = match starts.len() {
+            0 => String::new(),
+            1 => {
+                let first = match slot {
+                    Slot::Member(_) => whitespace(&members[0].key.leading),
+                    Slot::Element(_) => whitespace(&elements[0].leading)
+                };
+                if first.contains('\n') { first } else { " ".to_string() }
+            },
+            _ => match slot {
+                Slot::Member(_) => whitespace(&members[1].key.leading),
+                Slot::Element(_) => whitespace(&elements[1].leading)
+            }
+        };
+
+        match slot {
+            Slot::Element(index) if index < starts.len() => {
+                // Insert in front of the element currently at `index`
+                let at = starts[index].start;
+                let text = format!("{},{separator}", entry(self, at));
+                return (Span { start: at, end: at }, text)
+            },
+            _ if !starts.is_empty() => {
+                let at = starts.last().unwrap().end;
+                let text = format!(",{separator}{}", entry(self, at));
+                return (Span { start: at, end: at }, text)
+            },
+            _ => {
+                // First entry of an empty container, replaces whatever is inside it
+                let inside = Span { start: parent.span.start + 1, end: parent.span.end - 1 };
+                if self.is_multiline() {
+                    let indent = self.line_indent(parent.span.start).to_string();
+                    let inner = format!("{indent}{}", self.indent_unit());
+                    let text = format!("\n{inner}{}\n{indent}", entry(self, inside.start).replace('\n', &format!("\n{}", self.indent_unit())));
+                    return (inside, text)
+                }
+                return (inside, entry(self, inside.start))
+            }
+        }
+    }
+
+    /// Replaces `span` of the source and parses the result again. The document
+    /// is left untouched if the new text does not parse.
+    fn splice(&mut self, span: Span, text: &str) -> Result<(), EditError> {
+        let mut source = String::with_capacity(self.source.len() + text.len());
+        source.push_str(&self.source[..span.start]);
+        source.push_str(text);
+        source.push_str(&self.source[span.end..]);
+        self.root = cst::parse_with(&source, self.options).map_err(|(e, offset)| EditError::Parse(e, offset))?;
+        self.source = source;
+        return Ok(())
+    }
+
+    fn is_multiline(&self) -> bool {
+        return self.source.trim_end().contains('\n')
+    }
+
+    /// The whitespace at the start of the line containing `offset`.
+    fn line_indent(&self, offset: usize) -> &str {
+        let line_start = self.source[..offset].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = &self.source[line_start..];
+        return &line[..(line.len() - line.trim_start_matches([' ', '\t']).len())]
+    }
+
+    /// The indentation step used by the document, two spaces if it has none.
+    fn indent_unit(&self) -> String {
+        let mut smallest: Option<&str> = None;
+        for line in self.source.lines().skip(1) {
+            let indent = &line[..(line.len() - line.trim_start_matches([' ', '\t']).len())];
+            if indent.starts_with('\t') {
+                return "\t".to_string()
+            }
+            if !indent.is_empty() && smallest.is_none_or(|s| indent.len() < s.len()) {
+                smallest = Some(indent);
+            }
+        }
+        return smallest.unwrap_or("  ").to_string()
+    }
+
+    /// Serializes `value` to sit at `offset`, pretty printed in multi-line documents.
+    fn render(&self, value: &JsonType, offset: usize) -> String {
+        if !self.is_multiline() {
+            return to_string(value)
+        }
+        let indent = self.line_indent(offset);
+        return to_string_indented(value, &self.indent_unit()).replace('\n', &format!("\n{indent}"))
+    }
+}
+
+/// Only the whitespace trivia of a list, comments are not copied to new entries.
+fn whitespace(trivia: &[cst::Trivia]) -> String {
+    match trivia.iter().rev().find(|t| t.kind == cst::TriviaKind::Whitespace) {
+        Some(t) => return t.text.clone(),
+        None => return String::new()
+    }
+}
+
+/// An array index without sign or leading zeros, below `len`.
+fn parse_index(segment: &str, len: usize) -> Option<usize> {
+    if segment.is_empty() || (segment.len() > 1 && segment.starts_with('0')) || !segment.bytes().all(|b| b.is_ascii_digit()) {
+        return None
+    }
+    return segment.parse::<usize>().ok().filter(|i| *i < len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Dialect, parse_document};
+
+    const CONFIG: &str = "{\n  \"name\": \"deploy\",\n  \"version\": \"1.2.3\",\n  // pinned\n  \"tags\": [\"a\", \"b\"],\n  \"nested\": {\n    \"x\": 1\n  }\n}\n";
+
+    fn json5(source: &str) -> Document {
+        return Document::parse_with(source, Dialect::Json5.into()).unwrap()
+    }
+
+    #[test]
+    fn set_keeps_formatting() {
+        let mut doc = json5(CONFIG);
+        doc.set(&["version"], &JsonType::JsonString("1.2.4".to_string())).unwrap();
+        assert_eq!(doc.as_str(), CONFIG.replace("1.2.3", "1.2.4"));
+
+        doc.set(&["tags", "1"], &JsonType::JsonNumber(2.0)).unwrap();
+        assert_eq!(doc.as_str(), CONFIG.replace("1.2.3", "1.2.4").replace("\"b\"", "2"));
+    }
+
+    #[test]
+    fn set_adds_missing_key() {
+        let mut doc = json5(CONFIG);
+        doc.set(&["nested", "y"], &JsonType::JsonBool(true)).unwrap();
+        assert_eq!(doc.as_str(), CONFIG.replace("\"x\": 1\n", "\"x\": 1,\n    \"y\": true\n"));
+    }
+
+    #[test]
+    fn set_pretty_prints_containers() {
+        let mut doc = json5(CONFIG);
+        let value = parse_document(r#"{"x": 1, "z": [2]}"#).unwrap();
+        doc.set(&["nested"], &value).unwrap();
+        assert_eq!(doc.as_str(), CONFIG.replace("\"x\": 1\n  }", "\"x\": 1,\n    \"z\": [\n      2\n    ]\n  }"));
+        assert_eq!(doc.get(&["nested"]), Ok(value));
+    }
+
+    #[test]
+    fn insert_into_arrays() {
+        let mut doc = Document::parse("[1, 2, 3]").unwrap();
+        doc.insert(&["0"], &JsonType::JsonNumber(0.0)).unwrap();
+        doc.insert(&["4"], &JsonType::JsonNumber(4.0)).unwrap();
+        doc.insert(&["2"], &JsonType::JsonString("x".to_string())).unwrap();
+        assert_eq!(doc.as_str(), "[0, 1, \"x\", 2, 3, 4]");
+        assert_eq!(doc.insert(&["9"], &JsonType::JsonNull), Err(EditError::InvalidIndex(0)));
+
+        let mut doc = Document::parse("{\"list\": []}").unwrap();
+        doc.insert(&["list", "0"], &JsonType::JsonNull).unwrap();
+        assert_eq!(doc.as_str(), "{\"list\": [null]}");
+    }
+
+    #[test]
+    fn insert_into_objects() {
+        let mut doc = json5(CONFIG);
+        assert_eq!(doc.insert(&["name"], &JsonType::JsonNull), Err(EditError::AlreadyExists(0)));
+        doc.insert(&["enabled"], &JsonType::JsonBool(false)).unwrap();
+        assert_eq!(doc.as_str(), CONFIG.replace("    \"x\": 1\n  }\n", "    \"x\": 1\n  },\n  \"enabled\": false\n"));
+
+        let mut doc = Document::parse("{\n  \"empty\": {}\n}").unwrap();
+        doc.insert(&["empty", "a"], &JsonType::JsonNumber(1.0)).unwrap();
+        assert_eq!(doc.as_str(), "{\n  \"empty\": {\n    \"a\": 1\n  }\n}");
+    }
+
+    #[test]
+    fn remove_entries() {
+        let mut doc = json5(CONFIG);
+        assert_eq!(doc.remove(&["version"]), Ok(JsonType::JsonString("1.2.3".to_string())));
+        assert_eq!(doc.as_str(), CONFIG.replace("  \"version\": \"1.2.3\",\n", ""));
+
+        // The comment belongs to "tags" and goes with it
+        doc.remove(&["tags"]).unwrap();
+        assert_eq!(doc.as_str(), "{\n  \"name\": \"deploy\",\n  \"nested\": {\n    \"x\": 1\n  }\n}\n");
+
+        doc.remove(&["nested"]).unwrap();
+        assert_eq!(doc.as_str(), "{\n  \"name\": \"deploy\"\n}\n");
+        doc.remove(&["name"]).unwrap();
+        assert_eq!(doc.as_str(), "{}\n");
+
+        let mut doc = Document::parse("[1, 2, 3]").unwrap();
+        doc.remove(&["2"]).unwrap();
+        doc.remove(&["0"]).unwrap();
+        assert_eq!(doc.as_str(), "[2]");
+    }
+
+    #[test]
+    fn round_trip_untouched() {
+        let doc = json5(CONFIG);
+        assert_eq!(doc.as_str(), CONFIG);
+        assert_eq!(doc.root().to_string(), CONFIG);
+    }
+
+    #[test]
+    fn errors() {
+        let mut doc = json5(CONFIG);
+        assert_eq!(doc.set(&["missing", "x"], &JsonType::JsonNull), Err(EditError::NotFound(0)));
+        assert_eq!(doc.set(&["name", "x"], &JsonType::JsonNull), Err(EditError::NotAContainer(1)));
+        assert_eq!(doc.remove(&["tags", "01"]), Err(EditError::InvalidIndex(1)));
+        assert_eq!(doc.remove(&[]), Err(EditError::EmptyPath));
+        assert_eq!(doc.as_str(), CONFIG);
+        assert!(matches!(Document::parse("[1,"), Err(EditError::Parse(ParseError::UnexpectedEof, 3))));
+    }
+}
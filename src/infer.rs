@@ -0,0 +1,259 @@
+//! JSON Schema inference from sample documents.
+//!
+//! Every sample is folded into one `Shape` per position, counting the types
+//! seen there, and the merged shape is then written out as a draft 2020-12
+//! schema that all samples validate against:
+//!
+//! - Objects merge their properties. A property is `required` when it is
+//!   present in at least `required_ratio` of the objects.
+//! - Positions holding several types get a `type` list, and the keywords of
+//!   each type side by side.
+//! - Numbers are `integer` unless some sample has a fraction or lies outside
+//!   the range of `i64`.
+//! - Strings get a `format` when every sample is a date-time, date, uuid or
+//!   email, and an `enum` when they have few distinct values that repeat.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::parser::JsonType;
+use crate::schema::format_matches;
+
+/// Tuning for `infer_with`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct InferOptions {
+    /// Fraction of the objects at a position that must contain a property for
+    /// it to be `required`, `1.0` means all of them.
+    pub required_ratio: f64,
+    /// Strings become an `enum` when they have at most this many distinct
+    /// values and, on average, every value occurs at least twice. `0` turns
+    /// enums off.
+    pub max_enum_values: usize,
+    /// Add `format` for strings that all look like a date-time, date, uuid or email.
+    pub detect_formats: bool
+}
+
+impl Default for InferOptions {
+    fn default() -> Self {
+        return InferOptions { required_ratio: 1.0, max_enum_values: 5, detect_formats: true }
+    }
+}
+
+// Checked in order, the first format every string matches wins
+const FORMATS: [&str; 4] = ["date-time", "date", "uuid", "email"];
+
+/// Everything seen at one position of the samples.
+#[derive(Debug, Default)]
+struct Shape {
+    objects: usize,
+    /// Each property with the number of objects it was present in as its `present`
+    properties: BTreeMap<String, Shape>,
+    present: usize,
+    arrays: usize,
+    items: Option<Box<Shape>>,
+    strings: usize,
+    /// Distinct strings, only kept until there are too many for an enum
+    values: BTreeSet<String>,
+    too_many_values: bool,
+    /// The formats that every string so far matches
+    formats: Vec<&'static str>,
+    integers: usize,
+    numbers: usize,
+    booleans: usize,
+    nulls: usize
+}
+
+impl Shape {
+    fn add(&mut self, value: &JsonType, options: InferOptions) {
+        match value {
+            JsonType::JsonObject(map) => {
+                self.objects += 1;
+                for (key, value) in map {
+                    let property = self.properties.entry(key.clone()).or_default();
+                    property.present += 1;
+                    property.add(value, options);
+                }
+            },
+            JsonType::JsonArray(values) => {
+                self.arrays += 1;
+                for value in values {
+                    self.items.get_or_insert_with(Box::default).add(value, options);
+                }
+            },
+            JsonType::JsonString(s) => {
+                if self.strings == 0 && options.detect_formats {
+                    self.formats = FORMATS.to_vec();
+                }
+                self.strings += 1;
+                self.formats.retain(|format| format_matches(format, s) == Some(true));
+                if !self.too_many_values {
+                    self.values.insert(s.clone());
+                    if self.values.len() > options.max_enum_values {
+                        self.too_many_values = true;
+                        self.values.clear();
+                    }
+                }
+            },
+            JsonType::JsonNumber(n) if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 + 1.0 => self.integers += 1,
+            JsonType::JsonNumber(_) => self.numbers += 1,
+            JsonType::JsonBool(_) => self.booleans += 1,
+            JsonType::JsonNull => self.nulls += 1
+        }
+    }
+
+    fn to_schema(&self, options: InferOptions) -> JsonType {
+        let mut schema = json!({});
+        let output = schema.as_object_mut().unwrap();
+
+        let mut types = Vec::new();
+        if self.objects > 0 {
+            types.push("object");
+            if !self.properties.is_empty() {
+                let properties = self.properties.iter().map(|(key, shape)| (key.clone(), shape.to_schema(options))).collect();
+                output.insert("properties".to_string(), JsonType::JsonObject(properties));
+            }
+            let required: Vec<&str> = self.properties.iter()
+                .filter(|(_, shape)| shape.present as f64 >= options.required_ratio * self.objects as f64)
+                .map(|(key, _)| key.as_str())
+                .collect();
+            if !required.is_empty() {
+                output.insert("required".to_string(), json!(required));
+            }
+        }
+        if self.arrays > 0 {
+            types.push("array");
+            if let Some(items) = &self.items {
+                output.insert("items".to_string(), items.to_schema(options));
+            }
+        }
+        if self.strings > 0 {
+            types.push("string");
+            let only_strings = self.objects + self.arrays + self.integers + self.numbers + self.booleans == 0;
+            let is_enum = !self.too_many_values && self.values.len() * 2 <= self.strings;
+            if is_enum && only_strings {
+                let mut values: Vec<JsonType> = self.values.iter().map(JsonType::from).collect();
+                if self.nulls > 0 {
+                    values.push(JsonType::JsonNull);
+                }
+                output.insert("enum".to_string(), JsonType::JsonArray(values));
+            } else if let Some(format) = self.formats.first() {
+                output.insert("format".to_string(), json!(*format));
+            }
+        }
+        if self.numbers > 0 {
+            types.push("number");
+        } else if self.integers > 0 {
+            types.push("integer");
+        }
+        if self.booleans > 0 {
+            types.push("boolean");
+        }
+        if self.nulls > 0 {
+            types.push("null");
+        }
+
+        match types.as_slice() {
+            [] => {},
+            [name] => {output.insert("type".to_string(), json!(*name));},
+            names => {output.insert("type".to_string(), json!(names.to_vec()));}
+        }
+        return schema
+    }
+}
+
+pub fn infer(samples: &[JsonType]) -> JsonType {
+    return infer_with(samples, InferOptions::default())
+}
+
+/// A schema that every sample validates against. Without samples it is `{}`,
+/// which allows anything.
+pub fn infer_with(samples: &[JsonType], options: InferOptions) -> JsonType {
+    let mut shape = Shape::default();
+    for sample in samples {
+        shape.add(sample, options);
+    }
+    let mut schema = shape.to_schema(options);
+    schema.as_object_mut().unwrap().insert("$schema".to_string(), json!("https://json-schema.org/draft/2020-12/schema"));
+    return schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Schema;
+
+    fn without_dialect(mut schema: JsonType) -> JsonType {
+        schema.as_object_mut().unwrap().remove("$schema");
+        return schema
+    }
+
+    #[test]
+    fn merged_objects() {
+        let samples = [
+            json!({"id": 1, "name": "Alice", "score": 9.5, "tags": ["a"], "address": {"city": "Oslo"}}),
+            json!({"id": 2, "name": "Bob", "score": 7, "tags": [], "address": null, "admin": true}),
+            json!({"id": 3, "name": "Carol", "score": null, "tags": ["b", 1], "address": {"city": "Rome", "zip": "00100"}})
+        ];
+        let schema = infer(&samples);
+        assert_eq!(schema["$schema"], json!("https://json-schema.org/draft/2020-12/schema"));
+        assert_eq!(without_dialect(schema.clone()), json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "integer"},
+                "name": {"type": "string"},
+                "score": {"type": ["number", "null"]},
+                "tags": {"type": "array", "items": {"type": ["string", "integer"]}},
+                "address": {"type": ["object", "null"], "properties": {"city": {"type": "string"}, "zip": {"type": "string"}}, "required": ["city"]},
+                "admin": {"type": "boolean"}
+            },
+            "required": ["address", "id", "name", "score", "tags"]
+        }));
+        let compiled = Schema::new(&schema).unwrap();
+        assert!(samples.iter().all(|sample| compiled.is_valid(sample)));
+
+        let relaxed = InferOptions { required_ratio: 0.5, ..InferOptions::default() };
+        assert_eq!(infer_with(&samples, relaxed)["properties"]["address"]["required"], json!(["city", "zip"]));
+    }
+
+    #[test]
+    fn strings() {
+        let statuses: Vec<JsonType> = ["open", "closed", "open", "open", "closed", "open"].into_iter().map(JsonType::from).collect();
+        assert_eq!(without_dialect(infer(&statuses)), json!({"type": "string", "enum": ["closed", "open"]}));
+
+        let mut with_null = statuses.clone();
+        with_null.push(JsonType::JsonNull);
+        assert_eq!(infer(&with_null)["enum"], json!(["closed", "open", null]));
+        assert!(Schema::new(&infer(&with_null)).unwrap().is_valid(&JsonType::JsonNull));
+
+        // Every value distinct: not an enum
+        let names: Vec<JsonType> = ["a", "b", "c"].into_iter().map(JsonType::from).collect();
+        assert_eq!(without_dialect(infer(&names)), json!({"type": "string"}));
+        assert_eq!(infer_with(&statuses, InferOptions { max_enum_values: 0, ..InferOptions::default() }).get("enum"), None);
+
+        let formats = [
+            (vec!["2024-01-02T03:04:05Z", "1999-12-31T23:59:59+01:00"], Some("date-time")),
+            (vec!["2024-01-02", "1999-12-31"], Some("date")),
+            (vec!["123e4567-e89b-12d3-a456-426614174000"], Some("uuid")),
+            (vec!["a@example.com", "b.c@example.org"], Some("email")),
+            (vec!["a@example.com", "not an email"], None)
+        ];
+        for (values, format) in formats {
+            let samples: Vec<JsonType> = values.into_iter().map(JsonType::from).collect();
+            let options = InferOptions { max_enum_values: 0, ..InferOptions::default() };
+            assert_eq!(infer_with(&samples, options).get("format").and_then(JsonType::as_str), format);
+            assert_eq!(infer_with(&samples, InferOptions { detect_formats: false, ..options }).get("format"), None);
+        }
+    }
+
+    #[test]
+    fn unions_and_edges() {
+        assert_eq!(without_dialect(infer(&[])), json!({}));
+        assert_eq!(without_dialect(infer(&[json!([])])), json!({"type": "array"}));
+        assert_eq!(without_dialect(infer(&[json!(1), json!(2.5), json!("x"), json!({})])), json!({"type": ["object", "string", "number"]}));
+        // An enum would reject the numbers, so mixed positions get none
+        let mixed = [json!("a"), json!("a"), json!(1)];
+        assert_eq!(without_dialect(infer(&mixed)), json!({"type": ["string", "integer"]}));
+        // Too large for an `i64`
+        assert_eq!(without_dialect(infer(&[json!(1), json!(1e20)])), json!({"type": "number"}));
+        assert_eq!(without_dialect(infer(&[json!(-9223372036854775808.0)])), json!({"type": "integer"}));
+    }
+}
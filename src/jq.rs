@@ -0,0 +1,1396 @@
+//! A small jq interpreter.
+//!
+//! Supports the everyday subset of the jq language: `.`, `.foo`, `.[n]`,
+//! `.[a:b]`, `.[]`, `..`, `?`, pipes, `,`, literals, array and object
+//! construction, string interpolation, arithmetic and comparisons, `and`,
+//! `or`, `//`, `if`, `as $name`, `reduce` and a set of builtins (`length`,
+//! `keys`, `has`, `map`, `select`, `to_entries`, `from_entries`, ...).
+//!
+//! A filter turns one input into any number of outputs. Object members are
+//! produced in sorted key order, since `JsonObject` does not keep the parsed
+//! order. Errors carry the byte offset in the filter where they happened.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::parser::JsonType;
+use crate::serializer::{format_number, to_string};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum JqError {
+    UnexpectedChar { position: usize, found: char },
+    UnexpectedEnd,
+    /// No builtin with this name and number of arguments, called at this position.
+    UnknownFunction { position: usize, name: String },
+    /// The variable used at this position is not bound.
+    UndefinedVariable { position: usize, name: String },
+    /// Running the expression at this position failed.
+    Runtime { position: usize, message: String }
+}
+
+/// A parsed filter, see `Filter::parse`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    expr: Expr
+}
+
+#[derive(Debug, Clone)]
+struct Expr {
+    kind: ExprKind,
+    position: usize
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
+}
+
+#[derive(Debug, Clone)]
+enum StringPart {
+    Text(String),
+    Interpolation(Expr)
+}
+
+#[derive(Debug, Clone)]
+enum ObjectKey {
+    Name(String),
+    Computed(Expr)
+}
+
+#[derive(Debug, Clone)]
+enum ExprKind {
+    Identity,
+    RecurseAll,
+    Literal(JsonType),
+    String(Vec<StringPart>),
+    Variable(String),
+    /// `target[index]`, `.foo` is `.["foo"]`
+    Index(Box<Expr>, Box<Expr>),
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    Iterate(Box<Expr>),
+    Try(Box<Expr>),
+    Array(Option<Box<Expr>>),
+    Object(Vec<(ObjectKey, Expr)>),
+    Negate(Box<Expr>),
+    Pipe(Box<Expr>, Box<Expr>),
+    Comma(Box<Expr>, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Alternative(Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+    /// `source as $name | body`
+    Bind(Box<Expr>, String, Box<Expr>),
+    /// `reduce source as $name (init; update)`
+    Reduce(Box<Expr>, String, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>)
+}
+
+/// Builtins and their number of arguments.
+const BUILTINS: [(&str, usize); 26] = [
+    ("empty", 0),
+    ("not", 0),
+    ("length", 0),
+    ("keys", 0),
+    ("values", 0),
+    ("add", 0),
+    ("type", 0),
+    ("tostring", 0),
+    ("tonumber", 0),
+    ("to_entries", 0),
+    ("from_entries", 0),
+    ("sort", 0),
+    ("reverse", 0),
+    ("min", 0),
+    ("max", 0),
+    ("unique", 0),
+    ("floor", 0),
+    ("has", 1),
+    ("map", 1),
+    ("select", 1),
+    ("with_entries", 1),
+    ("sort_by", 1),
+    ("join", 1),
+    ("range", 1),
+    ("range", 2),
+    ("error", 1)
+];
+
+/// Longest string `string * number` may build, in bytes.
+const MAX_STRING_LENGTH: usize = 1 << 28;
+
+/// Most numbers one `range` call may produce.
+const MAX_RANGE_LENGTH: usize = 1 << 24;
+
+const KEYWORDS: [&str; 10] = ["and", "or", "if", "then", "elif", "else", "end", "as", "reduce", "not"];
+
+fn is_ident_start(c: char) -> bool {
+    return c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    return c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn boxed(kind: ExprKind, position: usize) -> Box<Expr> {
+    return Box::new(Expr { kind, position })
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+    // Variables bound around the current position
+    scope: Vec<String>,
+    // A term `pipe` parsed while looking for `as`, taken by the next `unary`
+    term: Option<Expr>
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        return self.source.get(self.pos..).unwrap()
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.rest().chars().next()
+    }
+
+    fn unexpected(&self) -> JqError {
+        match self.peek() {
+            Some(found) => return JqError::UnexpectedChar { position: self.pos, found },
+            None => return JqError::UnexpectedEnd
+        }
+    }
+
+    /// Skips whitespace and `#` comments.
+    fn skip(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if !trimmed.starts_with('#') {
+                return
+            }
+            self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    /// Consumes `token` after skipping whitespace.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            return true
+        }
+        return false
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), JqError> {
+        if !self.eat(token) {
+            return Err(self.unexpected())
+        }
+        return Ok(())
+    }
+
+    fn peek_ident(&mut self) -> Option<&'a str> {
+        self.skip();
+        let rest = self.rest();
+        if !rest.starts_with(is_ident_start) {
+            return None
+        }
+        let end = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+        return rest.get(..end)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_ident() == Some(keyword) {
+            self.pos += keyword.len();
+            return true
+        }
+        return false
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), JqError> {
+        if !self.eat_keyword(keyword) {
+            return Err(self.unexpected())
+        }
+        return Ok(())
+    }
+
+    /// `$name` after skipping whitespace.
+    fn variable_name(&mut self) -> Result<String, JqError> {
+        self.expect("$")?;
+        match self.peek_ident() {
+            Some(name) if self.rest().starts_with(name) => {
+                self.pos += name.len();
+                return Ok(name.to_string())
+            },
+            _ => return Err(self.unexpected())
+        }
+    }
+
+    fn pipe(&mut self) -> Result<Expr, JqError> {
+        self.skip();
+        let start = self.pos;
+
+        // `term as $name | body`. Anything but a negation starts with a term,
+        // which is parsed once and handed on when no `as` follows
+        if !self.rest().starts_with('-') {
+            let source = self.postfix()?;
+            if self.eat_keyword("as") {
+                let name = self.variable_name()?;
+                self.expect("|")?;
+                self.scope.push(name.clone());
+                let body = self.pipe();
+                self.scope.pop();
+                return Ok(Expr { kind: ExprKind::Bind(Box::new(source), name, Box::new(body?)), position: start })
+            }
+            self.term = Some(source);
+        }
+
+        let left = self.comma()?;
+        if self.eat("|") {
+            let right = self.pipe()?;
+            return Ok(Expr { kind: ExprKind::Pipe(Box::new(left), Box::new(right)), position: start })
+        }
+        return Ok(left)
+    }
+
+    fn comma(&mut self) -> Result<Expr, JqError> {
+        let mut left = self.alternative()?;
+        while self.eat(",") {
+            let position = self.pos;
+            let right = self.alternative()?;
+            left = Expr { kind: ExprKind::Comma(Box::new(left), Box::new(right)), position };
+        }
+        return Ok(left)
+    }
+
+    fn alternative(&mut self) -> Result<Expr, JqError> {
+        let left = self.or()?;
+        let position = self.pos;
+        if self.eat("//") {
+            let right = self.alternative()?;
+            return Ok(Expr { kind: ExprKind::Alternative(Box::new(left), Box::new(right)), position })
+        }
+        return Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, JqError> {
+        let mut left = self.and()?;
+        while self.eat_keyword("or") {
+            let position = self.pos;
+            let right = self.and()?;
+            left = Expr { kind: ExprKind::Or(Box::new(left), Box::new(right)), position };
+        }
+        return Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, JqError> {
+        let mut left = self.comparison()?;
+        while self.eat_keyword("and") {
+            let position = self.pos;
+            let right = self.comparison()?;
+            left = Expr { kind: ExprKind::And(Box::new(left), Box::new(right)), position };
+        }
+        return Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, JqError> {
+        let left = self.additive()?;
+        self.skip();
+        let position = self.pos;
+        let ops = [
+            ("==", BinaryOp::Equal),
+            ("!=", BinaryOp::NotEqual),
+            ("<=", BinaryOp::LessEqual),
+            (">=", BinaryOp::GreaterEqual),
+            ("<", BinaryOp::Less),
+            (">", BinaryOp::Greater)
+        ];
+        for (token, op) in ops {
+            if self.eat(token) {
+                let right = self.additive()?;
+                return Ok(Expr { kind: ExprKind::Binary(op, Box::new(left), Box::new(right)), position })
+            }
+        }
+        return Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, JqError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            self.skip();
+            let position = self.pos;
+            let op = if self.eat("+") {
+                BinaryOp::Add
+            } else if self.eat("-") {
+                BinaryOp::Subtract
+            } else {
+                return Ok(left)
+            };
+            let right = self.multiplicative()?;
+            left = Expr { kind: ExprKind::Binary(op, Box::new(left), Box::new(right)), position };
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, JqError> {
+        let mut left = self.unary()?;
+        loop {
+            self.skip();
+            let position = self.pos;
+            let op = if self.rest().starts_with("//") {
+                return Ok(left)
+            } else if self.eat("*") {
+                BinaryOp::Multiply
+            } else if self.eat("/") {
+                BinaryOp::Divide
+            } else if self.eat("%") {
+                BinaryOp::Modulo
+            } else {
+                return Ok(left)
+            };
+            let right = self.unary()?;
+            left = Expr { kind: ExprKind::Binary(op, Box::new(left), Box::new(right)), position };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, JqError> {
+        if let Some(term) = self.term.take() {
+            return Ok(term)
+        }
+        self.skip();
+        let position = self.pos;
+        if self.eat("-") {
+            let operand = self.postfix()?;
+            return Ok(Expr { kind: ExprKind::Negate(Box::new(operand)), position })
+        }
+        return self.postfix()
+    }
+
+    /// A `.name`, `."name"`, `.[...]` or `[...]` suffix applied to `target`,
+    /// or `target` itself if there is none.
+    fn suffix(&mut self, target: Expr) -> Result<Expr, JqError> {
+        let position = self.pos;
+        let rest = self.rest();
+        if rest.starts_with('.') && !rest.starts_with("..") {
+            let after = rest.get(1..).unwrap();
+            if after.starts_with(is_ident_start) || after.starts_with('"') {
+                self.pos += 1;
+                let key = self.field_name()?;
+                return Ok(Expr { kind: ExprKind::Index(Box::new(target), key), position })
+            }
+            if after.starts_with('[') {
+                self.pos += 1;
+                return self.bracket_suffix(target, position)
+            }
+            return Ok(target)
+        }
+        if rest.starts_with('[') {
+            return self.bracket_suffix(target, position)
+        }
+        if rest.starts_with('?') {
+            self.pos += 1;
+            return Ok(Expr { kind: ExprKind::Try(Box::new(target)), position })
+        }
+        return Ok(target)
+    }
+
+    /// The name after a `.`, as an index expression.
+    fn field_name(&mut self) -> Result<Box<Expr>, JqError> {
+        let position = self.pos;
+        if self.peek() == Some('"') {
+            return Ok(Box::new(self.string()?))
+        }
+        let name = self.peek_ident().ok_or(self.unexpected())?;
+        self.pos += name.len();
+        return Ok(boxed(ExprKind::Literal(JsonType::JsonString(name.to_string())), position))
+    }
+
+    fn bracket_suffix(&mut self, target: Expr, position: usize) -> Result<Expr, JqError> {
+        self.expect("[")?;
+        if self.eat("]") {
+            return Ok(Expr { kind: ExprKind::Iterate(Box::new(target)), position })
+        }
+        if self.eat(":") {
+            let end = self.pipe()?;
+            self.expect("]")?;
+            return Ok(Expr { kind: ExprKind::Slice(Box::new(target), None, Some(Box::new(end))), position })
+        }
+        let index = self.pipe()?;
+        if self.eat(":") {
+            let end = match self.eat("]") {
+                true => return Ok(Expr { kind: ExprKind::Slice(Box::new(target), Some(Box::new(index)), None), position }),
+                false => self.pipe()?
+            };
+            self.expect("]")?;
+            return Ok(Expr { kind: ExprKind::Slice(Box::new(target), Some(Box::new(index)), Some(Box::new(end))), position })
+        }
+        self.expect("]")?;
+        return Ok(Expr { kind: ExprKind::Index(Box::new(target), Box::new(index)), position })
+    }
+
+    fn postfix(&mut self) -> Result<Expr, JqError> {
+        let mut expr = self.primary()?;
+        loop {
+            // Every suffix consumes something
+            let before = self.pos;
+            expr = self.suffix(expr)?;
+            if self.pos == before {
+                return Ok(expr)
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, JqError> {
+        self.skip();
+        let position = self.pos;
+        let rest = self.rest();
+        let Some(c) = rest.chars().next() else {
+            return Err(JqError::UnexpectedEnd)
+        };
+        let expr = |kind| Ok(Expr { kind, position });
+
+        if rest.starts_with("..") {
+            self.pos += 2;
+            return expr(ExprKind::RecurseAll)
+        }
+        if c == '.' {
+            self.pos += 1;
+            let identity = Expr { kind: ExprKind::Identity, position };
+            let after = self.rest();
+            if after.starts_with(is_ident_start) || after.starts_with('"') {
+                let key = self.field_name()?;
+                return expr(ExprKind::Index(Box::new(identity), key))
+            }
+            if after.starts_with('[') {
+                return self.bracket_suffix(identity, position)
+            }
+            return Ok(identity)
+        }
+        if c == '"' {
+            return self.string()
+        }
+        if c.is_ascii_digit() {
+            let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(rest.len());
+            let mut end = end;
+            // An exponent, with an optional sign
+            if rest.get(end..).is_some_and(|r| r.starts_with(['e', 'E'])) {
+                end += 1;
+                if rest.get(end..).is_some_and(|r| r.starts_with(['+', '-'])) {
+                    end += 1;
+                }
+                end += rest.get(end..).unwrap().find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - end);
+            }
+            let number = rest.get(..end).unwrap().parse::<f64>().map_err(|_| JqError::UnexpectedChar { position, found: c })?;
+            self.pos += end;
+            return expr(ExprKind::Literal(JsonType::JsonNumber(number)))
+        }
+        if c == '$' {
+            let name = self.variable_name()?;
+            if !self.scope.contains(&name) {
+                return Err(JqError::UndefinedVariable { position, name })
+            }
+            return expr(ExprKind::Variable(name))
+        }
+        if c == '(' {
+            self.pos += 1;
+            let inner = self.pipe()?;
+            self.expect(")")?;
+            return Ok(inner)
+        }
+        if c == '[' {
+            self.pos += 1;
+            if self.eat("]") {
+                return expr(ExprKind::Array(None))
+            }
+            let inner = self.pipe()?;
+            self.expect("]")?;
+            return expr(ExprKind::Array(Some(Box::new(inner))))
+        }
+        if c == '{' {
+            self.pos += 1;
+            return self.object(position)
+        }
+
+        let Some(name) = self.peek_ident() else {
+            return Err(self.unexpected())
+        };
+        self.pos += name.len();
+        match name {
+            "true" => return expr(ExprKind::Literal(JsonType::JsonBool(true))),
+            "false" => return expr(ExprKind::Literal(JsonType::JsonBool(false))),
+            "null" => return expr(ExprKind::Literal(JsonType::JsonNull)),
+            "if" => return self.conditional(position),
+            "reduce" => {
+                let source = self.postfix()?;
+                self.expect_keyword("as")?;
+                let variable = self.variable_name()?;
+                self.expect("(")?;
+                let init = self.pipe()?;
+                self.expect(";")?;
+                self.scope.push(variable.clone());
+                let update = self.pipe();
+                self.scope.pop();
+                let update = update?;
+                self.expect(")")?;
+                return expr(ExprKind::Reduce(Box::new(source), variable, Box::new(init), Box::new(update)))
+            },
+            "not" => {},
+            name if KEYWORDS.contains(&name) => {
+                self.pos = position;
+                return Err(self.unexpected())
+            },
+            _ => {}
+        }
+
+        let mut args = Vec::new();
+        if self.rest().starts_with('(') {
+            self.pos += 1;
+            loop {
+                args.push(self.pipe()?);
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(";")?;
+            }
+        }
+        if !BUILTINS.contains(&(name, args.len())) {
+            return Err(JqError::UnknownFunction { position, name: format!("{name}/{}", args.len()) })
+        }
+        return expr(ExprKind::Call(name.to_string(), args))
+    }
+
+    fn conditional(&mut self, position: usize) -> Result<Expr, JqError> {
+        let condition = self.pipe()?;
+        self.expect_keyword("then")?;
+        let then = self.pipe()?;
+        let otherwise = if self.eat_keyword("elif") {
+            let elif = self.pos;
+            Some(Box::new(self.conditional(elif)?))
+        } else if self.eat_keyword("else") {
+            let otherwise = self.pipe()?;
+            self.expect_keyword("end")?;
+            Some(Box::new(otherwise))
+        } else {
+            self.expect_keyword("end")?;
+            None
+        };
+        return Ok(Expr { kind: ExprKind::If(Box::new(condition), Box::new(then), otherwise), position })
+    }
+
+    fn object(&mut self, position: usize) -> Result<Expr, JqError> {
+        let mut entries = Vec::new();
+        if self.eat("}") {
+            return Ok(Expr { kind: ExprKind::Object(entries), position })
+        }
+        loop {
+            self.skip();
+            let key_position = self.pos;
+            let (key, shorthand) = match self.peek() {
+                Some('"') => {
+                    let key = self.string()?;
+                    let shorthand = Expr {
+                        kind: ExprKind::Index(boxed(ExprKind::Identity, key_position), Box::new(key.clone())),
+                        position: key_position
+                    };
+                    (ObjectKey::Computed(key), Some(shorthand))
+                },
+                Some('$') => {
+                    let variable = self.primary()?;
+                    let ExprKind::Variable(name) = &variable.kind else {
+                        unreachable!()
+                    };
+                    (ObjectKey::Name(name.clone()), Some(variable))
+                },
+                Some('(') => {
+                    self.pos += 1;
+                    let key = self.pipe()?;
+                    self.expect(")")?;
+                    (ObjectKey::Computed(key), None)
+                },
+                _ => {
+                    let name = self.peek_ident().ok_or(self.unexpected())?;
+                    self.pos += name.len();
+                    let field = boxed(ExprKind::Literal(JsonType::JsonString(name.to_string())), key_position);
+                    let shorthand = Expr { kind: ExprKind::Index(boxed(ExprKind::Identity, key_position), field), position: key_position };
+                    (ObjectKey::Name(name.to_string()), Some(shorthand))
+                }
+            };
+            let value = if self.eat(":") {
+                self.alternative()?
+            } else {
+                match shorthand {
+                    Some(value) => value,
+                    None => return Err(self.unexpected())
+                }
+            };
+            entries.push((key, value));
+            if self.eat("}") {
+                return Ok(Expr { kind: ExprKind::Object(entries), position })
+            }
+            self.expect(",")?;
+        }
+    }
+
+    /// A string literal, possibly with `\(...)` interpolations.
+    fn string(&mut self) -> Result<Expr, JqError> {
+        let position = self.pos;
+        self.expect("\"")?;
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        loop {
+            let c = self.peek().ok_or(JqError::UnexpectedEnd)?;
+            self.pos += c.len_utf8();
+            match c {
+                '"' => break,
+                '\\' => {
+                    let escape_position = self.pos;
+                    let escape = self.peek().ok_or(JqError::UnexpectedEnd)?;
+                    self.pos += escape.len_utf8();
+                    match escape {
+                        '"' | '\\' | '/' => text.push(escape),
+                        'n' => text.push('\n'),
+                        't' => text.push('\t'),
+                        'r' => text.push('\r'),
+                        'b' => text.push('\u{8}'),
+                        'f' => text.push('\u{c}'),
+                        'u' => {
+                            let digits = self.rest().get(..4).ok_or(JqError::UnexpectedEnd)?;
+                            let code = u32::from_str_radix(digits, 16).ok().and_then(char::from_u32);
+                            let Some(code) = code else {
+                                return Err(JqError::UnexpectedChar { position: escape_position, found: escape })
+                            };
+                            self.pos += 4;
+                            text.push(code);
+                        },
+                        '(' => {
+                            if !text.is_empty() {
+                                parts.push(StringPart::Text(std::mem::take(&mut text)));
+                            }
+                            parts.push(StringPart::Interpolation(self.pipe()?));
+                            self.expect(")")?;
+                        },
+                        _ => return Err(JqError::UnexpectedChar { position: escape_position, found: escape })
+                    }
+                },
+                c => text.push(c)
+            }
+        }
+        if parts.is_empty() {
+            return Ok(Expr { kind: ExprKind::Literal(JsonType::JsonString(text)), position })
+        }
+        if !text.is_empty() {
+            parts.push(StringPart::Text(text));
+        }
+        return Ok(Expr { kind: ExprKind::String(parts), position })
+    }
+}
+
+/// Variables bound while evaluating, innermost first.
+struct Env<'a> {
+    name: &'a str,
+    value: JsonType,
+    parent: Option<&'a Env<'a>>
+}
+
+fn lookup<'a>(env: Option<&'a Env<'a>>, name: &str) -> Option<&'a JsonType> {
+    let mut current = env;
+    while let Some(e) = current {
+        if e.name == name {
+            return Some(&e.value)
+        }
+        current = e.parent;
+    }
+    return None
+}
+
+fn truthy(value: &JsonType) -> bool {
+    return !matches!(value, JsonType::JsonNull | JsonType::JsonBool(false))
+}
+
+fn sorted_keys(map: &HashMap<String, JsonType>) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    return keys
+}
+
+/// The jq ordering: null < false < true < numbers < strings < arrays < objects.
+fn compare(a: &JsonType, b: &JsonType) -> Ordering {
+    let rank = |v: &JsonType| match v {
+        JsonType::JsonNull => 0,
+        JsonType::JsonBool(false) => 1,
+        JsonType::JsonBool(true) => 2,
+        JsonType::JsonNumber(_) => 3,
+        JsonType::JsonString(_) => 4,
+        JsonType::JsonArray(_) => 5,
+        JsonType::JsonObject(_) => 6
+    };
+    match (a, b) {
+        (JsonType::JsonNumber(a), JsonType::JsonNumber(b)) => return a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        (JsonType::JsonString(a), JsonType::JsonString(b)) => return a.cmp(b),
+        (JsonType::JsonArray(a), JsonType::JsonArray(b)) => {
+            for (x, y) in a.iter().zip(b.iter()) {
+                let order = compare(x, y);
+                if order != Ordering::Equal {
+                    return order
+                }
+            }
+            return a.len().cmp(&b.len())
+        },
+        (JsonType::JsonObject(a), JsonType::JsonObject(b)) => {
+            // First by the sorted key sets, then value by value
+            let (keys_a, keys_b) = (sorted_keys(a), sorted_keys(b));
+            let order = keys_a.cmp(&keys_b);
+            if order != Ordering::Equal {
+                return order
+            }
+            for key in keys_a {
+                let order = compare(&a[key], &b[key]);
+                if order != Ordering::Equal {
+                    return order
+                }
+            }
+            return Ordering::Equal
+        },
+        _ => return rank(a).cmp(&rank(b))
+    }
+}
+
+/// Text for string interpolation and `tostring`: strings as they are,
+/// anything else as JSON.
+fn to_text(value: &JsonType) -> String {
+    match value {
+        JsonType::JsonString(s) => return s.clone(),
+        value => return to_string(value)
+    }
+}
+
+fn deep_merge(a: &mut HashMap<String, JsonType>, b: &HashMap<String, JsonType>) {
+    for (key, value) in b {
+        match (a.get_mut(key), value) {
+            (Some(JsonType::JsonObject(inner)), JsonType::JsonObject(other)) => deep_merge(inner, other),
+            _ => {a.insert(key.clone(), value.clone());}
+        }
+    }
+}
+
+impl Expr {
+    fn error<T>(&self, message: String) -> Result<T, JqError> {
+        return Err(JqError::Runtime { position: self.position, message })
+    }
+
+    fn binary(&self, op: BinaryOp, a: &JsonType, b: &JsonType) -> Result<JsonType, JqError> {
+        use JsonType::*;
+        let result = match (op, a, b) {
+            (BinaryOp::Equal, a, b) => JsonBool(compare(a, b) == Ordering::Equal),
+            (BinaryOp::NotEqual, a, b) => JsonBool(compare(a, b) != Ordering::Equal),
+            (BinaryOp::Less, a, b) => JsonBool(compare(a, b) == Ordering::Less),
+            (BinaryOp::LessEqual, a, b) => JsonBool(compare(a, b) != Ordering::Greater),
+            (BinaryOp::Greater, a, b) => JsonBool(compare(a, b) == Ordering::Greater),
+            (BinaryOp::GreaterEqual, a, b) => JsonBool(compare(a, b) != Ordering::Less),
+            (BinaryOp::Add, JsonNull, b) => b.clone(),
+            (BinaryOp::Add, a, JsonNull) => a.clone(),
+            (BinaryOp::Add, JsonNumber(a), JsonNumber(b)) => JsonNumber(a + b),
+            (BinaryOp::Add, JsonString(a), JsonString(b)) => JsonString(format!("{a}{b}")),
+            (BinaryOp::Add, JsonArray(a), JsonArray(b)) => JsonArray(a.iter().chain(b.iter()).cloned().collect()),
+            (BinaryOp::Add, JsonObject(a), JsonObject(b)) => {
+                let mut output = a.clone();
+                output.extend(b.iter().map(|(k, v)| (k.clone(), v.clone())));
+                JsonObject(output)
+            },
+            (BinaryOp::Subtract, JsonNumber(a), JsonNumber(b)) => JsonNumber(a - b),
+            (BinaryOp::Subtract, JsonArray(a), JsonArray(b)) => JsonArray(a.iter().filter(|x| !b.contains(x)).cloned().collect()),
+            (BinaryOp::Multiply, JsonNumber(a), JsonNumber(b)) => JsonNumber(a * b),
+            (BinaryOp::Multiply, JsonString(s), JsonNumber(n)) | (BinaryOp::Multiply, JsonNumber(n), JsonString(s)) => {
+                if *n <= 0.0 {
+                    JsonNull
+                } else if n.ceil() * s.len() as f64 > MAX_STRING_LENGTH as f64 {
+                    return self.error(format!("Repeat string result too long ({} bytes)", format_number(n.ceil() * s.len() as f64)))
+                } else {
+                    JsonString(s.repeat(n.ceil() as usize))
+                }
+            },
+            (BinaryOp::Multiply, JsonObject(a), JsonObject(b)) => {
+                let mut output = a.clone();
+                deep_merge(&mut output, b);
+                JsonObject(output)
+            },
+            (BinaryOp::Divide, JsonNumber(a), JsonNumber(b)) if *b == 0.0 => {
+                return self.error(format!("{} and {} cannot be divided because the divisor is zero", format_number(*a), format_number(*b)))
+            },
+            (BinaryOp::Divide, JsonNumber(a), JsonNumber(b)) => JsonNumber(a / b),
+            (BinaryOp::Divide, JsonString(a), JsonString(b)) => {
+                JsonArray(a.split(b.as_str()).map(|s| JsonString(s.to_string())).collect())
+            },
+            (BinaryOp::Modulo, JsonNumber(a), JsonNumber(b)) => {
+                let (a, b) = (a.trunc() as i64, b.trunc() as i64);
+                if b == 0 {
+                    return self.error(format!("{a} and {b} cannot be divided because the divisor is zero"))
+                }
+                let Some(divisor) = b.checked_abs() else {
+                    return self.error(format!("{a} and {b} cannot be divided because the divisor is out of range"))
+                };
+                JsonNumber((a % divisor) as f64)
+            },
+            (op, a, b) => {
+                let verb = match op {
+                    BinaryOp::Add => "added",
+                    BinaryOp::Subtract => "subtracted",
+                    BinaryOp::Multiply => "multiplied",
+                    _ => "divided"
+                };
+                return self.error(format!("{} ({}) and {} ({}) cannot be {verb}", a.type_name(), to_string(a), b.type_name(), to_string(b)))
+            }
+        };
+        return Ok(result)
+    }
+
+    fn index(&self, target: &JsonType, key: &JsonType) -> Result<JsonType, JqError> {
+        match (target, key) {
+            (JsonType::JsonNull, JsonType::JsonString(_) | JsonType::JsonNumber(_)) => return Ok(JsonType::JsonNull),
+            (JsonType::JsonObject(map), JsonType::JsonString(key)) => return Ok(map.get(key).cloned().unwrap_or(JsonType::JsonNull)),
+            (JsonType::JsonArray(values), JsonType::JsonNumber(n)) => {
+                let index = n.floor() as i64;
+                let index = if index < 0 { values.len() as i64 + index } else { index };
+                return Ok(usize::try_from(index).ok().and_then(|i| values.get(i)).cloned().unwrap_or(JsonType::JsonNull))
+            },
+            (target, JsonType::JsonString(key)) => return self.error(format!("Cannot index {} with \"{key}\"", target.type_name())),
+            (target, key) => return self.error(format!("Cannot index {} with {}", target.type_name(), key.type_name()))
+        }
+    }
+
+    fn slice(&self, target: &JsonType, from: &JsonType, to: &JsonType) -> Result<JsonType, JqError> {
+        let len = match target {
+            JsonType::JsonNull => return Ok(JsonType::JsonNull),
+            JsonType::JsonArray(values) => values.len(),
+            JsonType::JsonString(s) => s.chars().count(),
+            target => return self.error(format!("Cannot index {} with object", target.type_name()))
+        };
+        let bound = |value: &JsonType, default: usize| -> Result<usize, JqError> {
+            match value {
+                JsonType::JsonNull => return Ok(default),
+                JsonType::JsonNumber(n) => {
+                    let n = n.floor() as i64;
+                    let n = if n < 0 { len as i64 + n } else { n };
+                    return Ok(n.clamp(0, len as i64) as usize)
+                },
+                _ => return self.error("Start and end indices of an array slice must be numbers".to_string())
+            }
+        };
+        let (from, to) = (bound(from, 0)?, bound(to, len)?);
+        let to = to.max(from);
+        match target {
+            JsonType::JsonArray(values) => return Ok(JsonType::JsonArray(values[from..to].to_vec())),
+            JsonType::JsonString(s) => return Ok(JsonType::JsonString(s.chars().skip(from).take(to - from).collect())),
+            _ => unreachable!()
+        }
+    }
+
+    fn iterate(&self, value: &JsonType) -> Result<Vec<JsonType>, JqError> {
+        match value {
+            JsonType::JsonArray(values) => return Ok(values.clone()),
+            JsonType::JsonObject(map) => return Ok(sorted_keys(map).into_iter().map(|k| map[k].clone()).collect()),
+            value => return self.error(format!("Cannot iterate over {}", value.type_name()))
+        }
+    }
+
+    /// Every value of `a` combined with every value of `b`, `b` in the outer loop like jq does.
+    fn product(&self, a: &Expr, b: &Expr, input: &JsonType, env: Option<&Env>, mut f: impl FnMut(&JsonType, &JsonType) -> Result<Vec<JsonType>, JqError>) -> Result<Vec<JsonType>, JqError> {
+        let mut output = Vec::new();
+        let rights = b.eval(input, env)?;
+        let lefts = a.eval(input, env)?;
+        for right in &rights {
+            for left in &lefts {
+                output.extend(f(left, right)?);
+            }
+        }
+        return Ok(output)
+    }
+
+    fn eval(&self, input: &JsonType, env: Option<&Env>) -> Result<Vec<JsonType>, JqError> {
+        match &self.kind {
+            ExprKind::Identity => return Ok(vec![input.clone()]),
+            ExprKind::RecurseAll => {
+                let mut output = Vec::new();
+                let mut stack = vec![input.clone()];
+                while let Some(value) = stack.pop() {
+                    if let Ok(mut children) = self.iterate(&value) {
+                        children.reverse();
+                        stack.extend(children);
+                    }
+                    output.push(value);
+                }
+                return Ok(output)
+            },
+            ExprKind::Literal(value) => return Ok(vec![value.clone()]),
+            ExprKind::String(parts) => {
+                let mut output = vec![String::new()];
+                for part in parts {
+                    match part {
+                        StringPart::Text(text) => output.iter_mut().for_each(|s| s.push_str(text)),
+                        StringPart::Interpolation(expr) => {
+                            let values = expr.eval(input, env)?;
+                            output = output.iter().flat_map(|s| values.iter().map(move |v| format!("{s}{}", to_text(v)))).collect();
+                        }
+                    }
+                }
+                return Ok(output.into_iter().map(JsonType::JsonString).collect())
+            },
+            ExprKind::Variable(name) => return Ok(vec![lookup(env, name).unwrap().clone()]),
+            ExprKind::Index(target, key) => {
+                return self.product(target, key, input, env, |t, k| Ok(vec![self.index(t, k)?]))
+            },
+            ExprKind::Slice(target, from, to) => {
+                let null = Expr { kind: ExprKind::Literal(JsonType::JsonNull), position: self.position };
+                let from = from.as_deref().unwrap_or(&null).eval(input, env)?;
+                let to = to.as_deref().unwrap_or(&null).eval(input, env)?;
+                let mut output = Vec::new();
+                for t in target.eval(input, env)? {
+                    for f in &from {
+                        for e in &to {
+                            output.push(self.slice(&t, f, e)?);
+                        }
+                    }
+                }
+                return Ok(output)
+            },
+            ExprKind::Iterate(target) => {
+                let mut output = Vec::new();
+                for value in target.eval(input, env)? {
+                    output.extend(self.iterate(&value)?);
+                }
+                return Ok(output)
+            },
+            ExprKind::Try(expr) => return Ok(expr.eval(input, env).unwrap_or_default()),
+            ExprKind::Array(None) => return Ok(vec![JsonType::JsonArray(Vec::new())]),
+            ExprKind::Array(Some(expr)) => return Ok(vec![JsonType::JsonArray(expr.eval(input, env)?)]),
+            ExprKind::Object(entries) => {
+                let mut output = vec![HashMap::new()];
+                for (key, value) in entries {
+                    let keys = match key {
+                        ObjectKey::Name(name) => vec![JsonType::JsonString(name.clone())],
+                        ObjectKey::Computed(expr) => expr.eval(input, env)?
+                    };
+                    let values = value.eval(input, env)?;
+                    let mut next = Vec::new();
+                    for object in &output {
+                        for key in &keys {
+                            let JsonType::JsonString(key) = key else {
+                                return self.error(format!("Object keys must be strings, not {}", key.type_name()))
+                            };
+                            for value in &values {
+                                let mut object = object.clone();
+                                object.insert(key.clone(), value.clone());
+                                next.push(object);
+                            }
+                        }
+                    }
+                    output = next;
+                }
+                return Ok(output.into_iter().map(JsonType::JsonObject).collect())
+            },
+            ExprKind::Negate(expr) => {
+                let mut output = Vec::new();
+                for value in expr.eval(input, env)? {
+                    match value {
+                        JsonType::JsonNumber(n) => output.push(JsonType::JsonNumber(-n)),
+                        value => return self.error(format!("{} ({}) cannot be negated", value.type_name(), to_string(&value)))
+                    }
+                }
+                return Ok(output)
+            },
+            ExprKind::Pipe(left, right) => {
+                let mut output = Vec::new();
+                for value in left.eval(input, env)? {
+                    output.extend(right.eval(&value, env)?);
+                }
+                return Ok(output)
+            },
+            ExprKind::Comma(left, right) => {
+                let mut output = left.eval(input, env)?;
+                output.extend(right.eval(input, env)?);
+                return Ok(output)
+            },
+            ExprKind::Binary(op, left, right) => {
+                return self.product(left, right, input, env, |a, b| Ok(vec![self.binary(*op, a, b)?]))
+            },
+            ExprKind::And(left, right) | ExprKind::Or(left, right) => {
+                let is_and = matches!(self.kind, ExprKind::And(..));
+                let mut output = Vec::new();
+                for value in left.eval(input, env)? {
+                    // Short-circuits without evaluating the right side
+                    if truthy(&value) != is_and {
+                        output.push(JsonType::JsonBool(!is_and));
+                        continue;
+                    }
+                    output.extend(right.eval(input, env)?.iter().map(|v| JsonType::JsonBool(truthy(v))));
+                }
+                return Ok(output)
+            },
+            ExprKind::Alternative(left, right) => {
+                let values: Vec<JsonType> = left.eval(input, env).unwrap_or_default().into_iter().filter(truthy).collect();
+                if values.is_empty() {
+                    return right.eval(input, env)
+                }
+                return Ok(values)
+            },
+            ExprKind::If(condition, then, otherwise) => {
+                let mut output = Vec::new();
+                for value in condition.eval(input, env)? {
+                    if truthy(&value) {
+                        output.extend(then.eval(input, env)?);
+                    } else if let Some(otherwise) = otherwise {
+                        output.extend(otherwise.eval(input, env)?);
+                    } else {
+                        output.push(input.clone());
+                    }
+                }
+                return Ok(output)
+            },
+            ExprKind::Bind(source, name, body) => {
+                let mut output = Vec::new();
+                for value in source.eval(input, env)? {
+                    let inner = Env { name, value, parent: env };
+                    output.extend(body.eval(input, Some(&inner))?);
+                }
+                return Ok(output)
+            },
+            ExprKind::Reduce(source, name, init, update) => {
+                let items = source.eval(input, env)?;
+                let mut output = Vec::new();
+                for mut accumulator in init.eval(input, env)? {
+                    for item in &items {
+                        let inner = Env { name, value: item.clone(), parent: env };
+                        accumulator = update.eval(&accumulator, Some(&inner))?.pop().unwrap_or(JsonType::JsonNull);
+                    }
+                    output.push(accumulator);
+                }
+                return Ok(output)
+            },
+            ExprKind::Call(name, args) => return self.call(name, args, input, env)
+        }
+    }
+
+    fn call(&self, name: &str, args: &[Expr], input: &JsonType, env: Option<&Env>) -> Result<Vec<JsonType>, JqError> {
+        let one = |value: JsonType| Ok(vec![value]);
+        match (name, input) {
+            ("empty", _) => return Ok(Vec::new()),
+            ("not", value) => return one(JsonType::JsonBool(!truthy(value))),
+            ("length", JsonType::JsonNull) => return one(JsonType::JsonNumber(0.0)),
+            ("length", JsonType::JsonNumber(n)) => return one(JsonType::JsonNumber(n.abs())),
+            ("length", JsonType::JsonString(s)) => return one(JsonType::JsonNumber(s.chars().count() as f64)),
+            ("length", JsonType::JsonArray(values)) => return one(JsonType::JsonNumber(values.len() as f64)),
+            ("length", JsonType::JsonObject(map)) => return one(JsonType::JsonNumber(map.len() as f64)),
+            ("keys", JsonType::JsonObject(map)) => {
+                return one(JsonType::JsonArray(sorted_keys(map).into_iter().map(|k| JsonType::JsonString(k.clone())).collect()))
+            },
+            ("keys", JsonType::JsonArray(values)) => {
+                return one(JsonType::JsonArray((0..values.len()).map(|i| JsonType::JsonNumber(i as f64)).collect()))
+            },
+            // `select(. != null)`
+            ("values", JsonType::JsonNull) => return Ok(Vec::new()),
+            ("values", value) => return one(value.clone()),
+            ("has", value) => {
+                let mut output = Vec::new();
+                for key in args[0].eval(input, env)? {
+                    let has = match (value, &key) {
+                        (JsonType::JsonObject(map), JsonType::JsonString(key)) => map.contains_key(key),
+                        (JsonType::JsonArray(values), JsonType::JsonNumber(n)) => *n >= 0.0 && (*n as usize) < values.len(),
+                        (value, key) => return self.error(format!("Cannot check whether {} has a {} key", value.type_name(), key.type_name()))
+                    };
+                    output.push(JsonType::JsonBool(has));
+                }
+                return Ok(output)
+            },
+            ("add", value) => {
+                let mut sum = JsonType::JsonNull;
+                for item in self.iterate(value)? {
+                    sum = self.binary(BinaryOp::Add, &sum, &item)?;
+                }
+                return one(sum)
+            },
+            ("type", value) => return one(JsonType::JsonString(value.type_name().to_string())),
+            ("tostring", value) => return one(JsonType::JsonString(to_text(value))),
+            ("tonumber", JsonType::JsonNumber(n)) => return one(JsonType::JsonNumber(*n)),
+            ("tonumber", JsonType::JsonString(s)) => {
+                match s.trim().parse::<f64>() {
+                    Ok(n) => return one(JsonType::JsonNumber(n)),
+                    Err(_) => return self.error(format!("Cannot parse '{s}' as a number"))
+                }
+            },
+            ("to_entries", JsonType::JsonObject(map)) => {
+                let entries = sorted_keys(map).into_iter().map(|k| {
+                    let mut entry = HashMap::new();
+                    entry.insert("key".to_string(), JsonType::JsonString(k.clone()));
+                    entry.insert("value".to_string(), map[k].clone());
+                    JsonType::JsonObject(entry)
+                });
+                return one(JsonType::JsonArray(entries.collect()))
+            },
+            ("from_entries", JsonType::JsonArray(entries)) => {
+                let mut output = HashMap::new();
+                for entry in entries {
+                    let JsonType::JsonObject(entry) = entry else {
+                        return self.error(format!("Cannot use {} as an entry", entry.type_name()))
+                    };
+                    let field = |names: &[&str]| names.iter().find_map(|n| entry.get(*n).filter(|v| truthy(v)).cloned());
+                    let key = match field(&["key", "k", "name", "Name", "Key", "K"]) {
+                        Some(JsonType::JsonString(key)) => key,
+                        Some(key @ (JsonType::JsonNumber(_) | JsonType::JsonBool(_))) => to_string(&key),
+                        _ => return self.error("Cannot use an entry without a string key".to_string())
+                    };
+                    output.insert(key, field(&["value", "v", "Value", "V"]).unwrap_or(JsonType::JsonNull));
+                }
+                return one(JsonType::JsonObject(output))
+            },
+            ("with_entries", _) => {
+                let entries = self.call("to_entries", &[], input, env)?.pop().unwrap();
+                let mapped = self.call("map", args, &entries, env)?.pop().unwrap();
+                return self.call("from_entries", &[], &mapped, env)
+            },
+            ("map", value) => {
+                let mut output = Vec::new();
+                for item in self.iterate(value)? {
+                    output.extend(args[0].eval(&item, env)?);
+                }
+                return one(JsonType::JsonArray(output))
+            },
+            ("select", value) => {
+                let matches = args[0].eval(input, env)?.iter().filter(|v| truthy(v)).count();
+                return Ok(vec![value.clone(); matches])
+            },
+            ("sort", JsonType::JsonArray(values)) => {
+                let mut values = values.clone();
+                values.sort_by(compare);
+                return one(JsonType::JsonArray(values))
+            },
+            ("sort_by", JsonType::JsonArray(values)) => {
+                let mut keyed = Vec::new();
+                for value in values {
+                    keyed.push((JsonType::JsonArray(args[0].eval(value, env)?), value.clone()));
+                }
+                keyed.sort_by(|a, b| compare(&a.0, &b.0));
+                return one(JsonType::JsonArray(keyed.into_iter().map(|(_, v)| v).collect()))
+            },
+            ("unique", JsonType::JsonArray(values)) => {
+                let mut values = values.clone();
+                values.sort_by(compare);
+                values.dedup_by(|a, b| compare(a, b) == Ordering::Equal);
+                return one(JsonType::JsonArray(values))
+            },
+            ("reverse", JsonType::JsonArray(values)) => return one(JsonType::JsonArray(values.iter().rev().cloned().collect())),
+            ("reverse", JsonType::JsonString(s)) => return one(JsonType::JsonString(s.chars().rev().collect())),
+            ("reverse", JsonType::JsonNull) => return one(JsonType::JsonArray(Vec::new())),
+            ("min", JsonType::JsonArray(values)) => return one(values.iter().min_by(|a, b| compare(a, b)).cloned().unwrap_or(JsonType::JsonNull)),
+            ("max", JsonType::JsonArray(values)) => return one(values.iter().max_by(|a, b| compare(a, b)).cloned().unwrap_or(JsonType::JsonNull)),
+            ("floor", JsonType::JsonNumber(n)) => return one(JsonType::JsonNumber(n.floor())),
+            ("join", JsonType::JsonArray(values)) => {
+                let mut output = Vec::new();
+                for separator in args[0].eval(input, env)? {
+                    let JsonType::JsonString(separator) = separator else {
+                        return self.error("join needs a string separator".to_string())
+                    };
+                    let mut parts = Vec::new();
+                    for value in values {
+                        match value {
+                            JsonType::JsonNull => parts.push(String::new()),
+                            JsonType::JsonObject(_) | JsonType::JsonArray(_) => {
+                                return self.error(format!("Cannot join with {}", value.type_name()))
+                            },
+                            value => parts.push(to_text(value))
+                        }
+                    }
+                    output.push(JsonType::JsonString(parts.join(&separator)));
+                }
+                return Ok(output)
+            },
+            ("range", _) => {
+                let (from, to) = match args {
+                    [to] => (vec![JsonType::JsonNumber(0.0)], to.eval(input, env)?),
+                    [from, to] => (from.eval(input, env)?, to.eval(input, env)?),
+                    _ => unreachable!()
+                };
+                let mut output = Vec::new();
+                for f in &from {
+                    for t in &to {
+                        let (JsonType::JsonNumber(n), JsonType::JsonNumber(t)) = (f, t) else {
+                            return self.error("Range bounds must be numeric".to_string())
+                        };
+                        let count = (t - n).ceil();
+                        if n < t && (!count.is_finite() || output.len() as f64 + count > MAX_RANGE_LENGTH as f64) {
+                            return self.error(format!("Range from {} to {} is too long", format_number(*n), format_number(*t)))
+                        }
+                        let mut n = *n;
+                        // Past 2^53 adding 1 no longer changes the number
+                        while n < *t && n + 1.0 != n {
+                            output.push(JsonType::JsonNumber(n));
+                            n += 1.0;
+                        }
+                    }
+                }
+                return Ok(output)
+            },
+            ("error", _) => {
+                let message = args[0].eval(input, env)?.first().map(to_text).unwrap_or_else(|| "null".to_string());
+                return self.error(message)
+            },
+            (name, value) => return self.error(format!("{} ({}) has no {name}", value.type_name(), to_string(value)))
+        }
+    }
+}
+
+impl Filter {
+    pub fn parse(filter: &str) -> Result<Filter, JqError> {
+        let mut parser = Parser { source: filter, pos: 0, scope: Vec::new(), term: None };
+        let expr = parser.pipe()?;
+        parser.skip();
+        if parser.peek().is_some() {
+            return Err(parser.unexpected())
+        }
+        return Ok(Filter { expr })
+    }
+
+    /// Runs the filter on `input` and collects every output.
+    pub fn run(&self, input: &JsonType) -> Result<Vec<JsonType>, JqError> {
+        return self.expr.eval(input, None)
+    }
+}
+
+/// Parses `filter` and runs it on `input`.
+pub fn run(filter: &str, input: &JsonType) -> Result<Vec<JsonType>, JqError> {
+    return Filter::parse(filter)?.run(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_document;
+
+    fn json(data: &str) -> JsonType {
+        return parse_document(data).unwrap()
+    }
+
+    fn check(filter: &str, input: &str, expected: &[&str]) {
+        let output = run(filter, &json(input)).unwrap();
+        let expected: Vec<JsonType> = expected.iter().map(|e| json(e)).collect();
+        assert_eq!(output, expected, "{filter}");
+    }
+
+    const USERS: &str = r#"{"users": [
+        {"name": "Alice", "age": 31, "tags": ["admin", "dev"]},
+        {"name": "Bob", "age": 25, "tags": []},
+        {"name": "Carol", "age": 42, "tags": ["dev"]}
+    ]}"#;
+
+    #[test]
+    fn paths_and_pipes() {
+        check(".", "[1]", &["[1]"]);
+        check(".users[0].name", USERS, &["\"Alice\""]);
+        check(".users[-1] | .name", USERS, &["\"Carol\""]);
+        check(".users[].name", USERS, &["\"Alice\"", "\"Bob\"", "\"Carol\""]);
+        check(".[\"a b\"], .\"a b\"", r#"{"a b": 1}"#, &["1", "1"]);
+        check(".missing.deeper", "{}", &["null"]);
+        check(".[1:3], .[-2:], .[:1]", "[0, 1, 2, 3]", &["[1, 2]", "[2, 3]", "[0]"]);
+        check(".[2:4]", "\"abcdef\"", &["\"cd\""]);
+        check(".[]", r#"{"b": 2, "a": 1}"#, &["1", "2"]);
+        check("[..]", "[[1]]", &["[[[1]], [1], 1]"]);
+        check(".a?, 1", "[]", &["1"]);
+        check("[.[] | .x?]", r#"[1, {"x": 2}]"#, &["[2]"]);
+    }
+
+    #[test]
+    fn construction() {
+        check("[.users[] | {name, old: (.age > 30)}]", USERS, &[r#"[{"name": "Alice", "old": true}, {"name": "Bob", "old": false}, {"name": "Carol", "old": true}]"#]);
+        check("{(.k): .v, \"lit\": 1}", r#"{"k": "key", "v": 2}"#, &[r#"{"key": 2, "lit": 1}"#]);
+        check("{a: (1, 2)}", "null", &[r#"{"a": 1}"#, r#"{"a": 2}"#]);
+        check(". as $x | {$x}", "3", &[r#"{"x": 3}"#]);
+        check("[]", "null", &["[]"]);
+        check("\"\\(.name) is \\(.age)\"", r#"{"name": "Alice", "age": 31}"#, &["\"Alice is 31\""]);
+        check("\"v: \\([1, \"a\"])\"", "null", &["\"v: [1,\\\"a\\\"]\""]);
+    }
+
+    #[test]
+    fn arithmetic_and_logic() {
+        check("1 + 2 * 3 - 4 / 2 % 3", "null", &["5"]);
+        check("[(1, 2) + (10, 20)]", "null", &["[11, 12, 21, 22]"]);
+        check(".a + .b", r#"{"a": [1], "b": [2]}"#, &["[1, 2]"]);
+        check(". + {b: 2}", r#"{"a": 1}"#, &[r#"{"a": 1, "b": 2}"#]);
+        check("\"ab\" + \"cd\", null + 1, [1, 2, 1] - [1]", "null", &["\"abcd\"", "1", "[2]"]);
+        check("{a: {b: 1}} * {a: {c: 2}}", "null", &[r#"{"a": {"b": 1, "c": 2}}"#]);
+        check("\"a,b\" / \",\"", "null", &[r#"["a", "b"]"#]);
+        check("-.x", r#"{"x": 3}"#, &["-3"]);
+        check("1 < 2, \"a\" > \"b\", [1] == [1], null < false, {} > []", "null", &["true", "false", "true", "true", "true"]);
+        check("true and (false, true), false or false, (1 | not)", "null", &["false", "true", "false", "false"]);
+        check(".a // \"default\", (false, 1, null, 2) // 3", "{}", &["\"default\"", "1", "2"]);
+        check("if . > 2 then \"big\" elif . > 0 then \"small\" else \"none\" end", "1", &["\"small\""]);
+        check("if . then 1 end", "false", &["false"]);
+    }
+
+    #[test]
+    fn builtins() {
+        check("[.users[] | select(.age > 30) | .name]", USERS, &[r#"["Alice", "Carol"]"#]);
+        check(".users | map(.age) | add", USERS, &["98"]);
+        check(".users | map(.tags | length)", USERS, &["[2, 0, 1]"]);
+        check("keys, length", r#"{"b": 1, "a": 2}"#, &[r#"["a", "b"]"#, "2"]);
+        check("to_entries", r#"{"b": 1, "a": 2}"#, &[r#"[{"key": "a", "value": 2}, {"key": "b", "value": 1}]"#]);
+        check("from_entries", r#"[{"key": "a", "value": 1}, {"k": "b", "v": 2}, {"name": 3}]"#, &[r#"{"a": 1, "b": 2, "3": null}"#]);
+        check("with_entries({key, value: (.value + 1)})", r#"{"a": 1}"#, &[r#"{"a": 2}"#]);
+        check("[.[] | type]", r#"[null, true, 1, "s", [], {}]"#, &[r#"["null", "boolean", "number", "string", "array", "object"]"#]);
+        check("sort, sort_by(-.), unique, min, max, reverse", "[3, 1, 3, 2]", &["[1, 2, 3, 3]", "[3, 3, 2, 1]", "[1, 2, 3]", "1", "3", "[2, 3, 1, 3]"]);
+        check("[range(3)], [range(1; 3)], has(\"a\"), (\"12\" | tonumber), (1 | tostring)", r#"{"a": null}"#, &["[0, 1, 2]", "[1, 2]", "true", "12", "\"1\""]);
+        check("join(\"-\")", r#"["a", 1, null, true]"#, &["\"a-1--true\""]);
+        check("[.[] | empty]", "[1, 2]", &["[]"]);
+        check("[.[] | values]", r#"[1, null, false, []]"#, &["[1, false, []]"]);
+    }
+
+    #[test]
+    fn variables_and_reduce() {
+        check("reduce .[] as $x (0; . + $x)", "[1, 2, 3, 4]", &["10"]);
+        check("reduce .users[] as $u ({}; . + {($u.name): $u.age})", USERS, &[r#"{"Alice": 31, "Bob": 25, "Carol": 42}"#]);
+        check(".users[] as $u | $u.tags[] | \"\\($u.name):\\(.)\"", USERS, &["\"Alice:admin\"", "\"Alice:dev\"", "\"Carol:dev\""]);
+        check(". as $a | [1, 2] | map(. * $a)", "10", &["[10, 20]"]);
+        check("# comment\n.a # trailing\n", r#"{"a": 1}"#, &["1"]);
+        check("(.a as $x | $x) as $y | [$y, -$y]", r#"{"a": 1}"#, &["[1, -1]"]);
+    }
+
+    #[test]
+    fn deep_nesting() {
+        // Each level used to be parsed twice
+        let filter = format!("{}.a{}", "(".repeat(40), ")".repeat(40));
+        check(&filter, r#"{"a": 1}"#, &["1"]);
+        let filter = format!("{}1{}", "[".repeat(40), "]".repeat(40));
+        assert!(run(&filter, &JsonType::JsonNull).is_ok());
+    }
+
+    #[test]
+    fn errors_carry_positions() {
+        let input = json(r#"{"a": 1, "s": "x"}"#);
+        assert_eq!(run(".a | .b", &input), Err(JqError::Runtime { position: 5, message: "Cannot index number with \"b\"".to_string() }));
+        assert_eq!(run(".a[]", &input), Err(JqError::Runtime { position: 2, message: "Cannot iterate over number".to_string() }));
+        assert_eq!(run(".s + .a", &input), Err(JqError::Runtime { position: 3, message: "string (\"x\") and number (1) cannot be added".to_string() }));
+        assert_eq!(run("1 / 0", &input).unwrap_err(), JqError::Runtime { position: 2, message: "1 and 0 cannot be divided because the divisor is zero".to_string() });
+        assert_eq!(run("error(\"boom\")", &input), Err(JqError::Runtime { position: 0, message: "boom".to_string() }));
+        assert_eq!(run(".a | map(.)", &input), Err(JqError::Runtime { position: 5, message: "Cannot iterate over number".to_string() }));
+        assert_eq!(Filter::parse(".a | frob").unwrap_err(), JqError::UnknownFunction { position: 5, name: "frob/0".to_string() });
+        assert_eq!(Filter::parse("map").unwrap_err(), JqError::UnknownFunction { position: 0, name: "map/0".to_string() });
+        assert_eq!(Filter::parse(". | $nope").unwrap_err(), JqError::UndefinedVariable { position: 4, name: "nope".to_string() });
+        assert_eq!(Filter::parse("[1, 2").unwrap_err(), JqError::UnexpectedEnd);
+        assert_eq!(Filter::parse(".a )").unwrap_err(), JqError::UnexpectedChar { position: 3, found: ')' });
+        assert_eq!(Filter::parse("if . then 1").unwrap_err(), JqError::UnexpectedEnd);
+        assert_eq!(run("\"abc\" * 1e9", &input), Err(JqError::Runtime { position: 6, message: "Repeat string result too long (3000000000 bytes)".to_string() }));
+        assert_eq!(run("range(1e12)", &input), Err(JqError::Runtime { position: 0, message: "Range from 0 to 1000000000000 is too long".to_string() }));
+        assert!(matches!(run("range(0; 1e400)", &input), Err(JqError::Runtime { .. })));
+        assert_eq!(run("[range(9007199254740992; 9007199254740994)]", &input).map(|v| v.len()), Ok(1));
+        assert_eq!(run("1 % -9223372036854775808", &input), Err(JqError::Runtime { position: 2, message: "1 and -9223372036854775808 cannot be divided because the divisor is out of range".to_string() }));
+    }
+}
@@ -0,0 +1,1121 @@
+//! JSONPath queries (RFC 9535).
+//!
+//! A query such as `$.store.book[?@.price < 10].title` selects a list of nodes
+//! from a value. Every node comes with its normalized path, e.g.
+//! `$['store']['book'][0]['title']`. Members of an object are visited in
+//! sorted key order, since `JsonObject` does not keep the parsed order.
+
+use std::borrow::Cow;
+
+use crate::parser::JsonType;
+use crate::regex::Regex;
+
+/// Why a query could not be parsed. Positions are byte offsets into the query.
+#[derive(PartialEq, Debug, Clone)]
+pub enum PathError {
+    UnexpectedChar { position: usize, found: char },
+    UnexpectedEnd,
+    /// The number or string literal at this position is malformed or out of range.
+    InvalidLiteral(usize),
+    /// The function called at this position does not exist.
+    UnknownFunction(usize),
+    /// The function at this position gets arguments of the wrong type or
+    /// count, or its result is used where that type is not allowed.
+    InvalidArguments(usize),
+    /// The query at this position is compared but can select more than one node.
+    NotSingular(usize)
+}
+
+/// One selected value together with its normalized path.
+#[derive(PartialEq, Debug, Clone)]
+pub struct PathNode<'a> {
+    pub location: String,
+    pub value: &'a JsonType
+}
+
+/// A parsed query, see `JsonPath::parse`.
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    segments: Vec<Segment>
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>)
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice { start: Option<i64>, end: Option<i64>, step: Option<i64> },
+    Filter(Expr)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    /// A query that is true when it selects anything
+    Exists(Query),
+    /// A function returning a logical value
+    Test(Function),
+    Compare(Comparable, CompareOp, Comparable)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum CompareOp {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
+}
+
+/// A query inside a filter, starting at `@` or at `$`.
+#[derive(Debug, Clone)]
+struct Query {
+    relative: bool,
+    segments: Vec<Segment>
+}
+
+#[derive(Debug, Clone)]
+enum Comparable {
+    Literal(JsonType),
+    /// A singular query
+    Query(Query),
+    /// A function returning a value
+    Function(Function)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionName {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value
+}
+
+#[derive(Debug, Clone)]
+enum Argument {
+    Value(Comparable),
+    Nodes(Query)
+}
+
+#[derive(Debug, Clone)]
+struct Function {
+    name: FunctionName,
+    args: Vec<Argument>
+}
+
+/// The types of RFC 9535 section 2.4.1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Value,
+    Logical,
+    Nodes
+}
+
+impl FunctionName {
+    fn from_name(name: &str) -> Option<FunctionName> {
+        match name {
+            "length" => return Some(FunctionName::Length),
+            "count" => return Some(FunctionName::Count),
+            "match" => return Some(FunctionName::Match),
+            "search" => return Some(FunctionName::Search),
+            "value" => return Some(FunctionName::Value),
+            _ => return None
+        }
+    }
+
+    fn parameters(self) -> &'static [Type] {
+        match self {
+            FunctionName::Length => return &[Type::Value],
+            FunctionName::Count | FunctionName::Value => return &[Type::Nodes],
+            FunctionName::Match | FunctionName::Search => return &[Type::Value, Type::Value]
+        }
+    }
+
+    fn result(self) -> Type {
+        match self {
+            FunctionName::Match | FunctionName::Search => return Type::Logical,
+            _ => return Type::Value
+        }
+    }
+}
+
+impl Query {
+    /// Whether the query can select at most one node: only names and indices.
+    fn is_singular(&self) -> bool {
+        return self.segments.iter().all(|segment| match segment {
+            Segment::Child(selectors) => selectors.len() == 1 && matches!(selectors[0], Selector::Name(_) | Selector::Index(_)),
+            Segment::Descendant(_) => false
+        })
+    }
+}
+
+// Integers must stay within the range I-JSON can represent exactly
+const MAX_INT: i64 = (1 << 53) - 1;
+
+fn is_blank(c: char) -> bool {
+    return matches!(c, ' ' | '\t' | '\n' | '\r')
+}
+
+fn is_name_first(c: char) -> bool {
+    return c.is_ascii_alphabetic() || c == '_' || c as u32 >= 0x80
+}
+
+fn is_name_char(c: char) -> bool {
+    return is_name_first(c) || c.is_ascii_digit()
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        return self.source.get(self.pos..).unwrap()
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        return Some(c)
+    }
+
+    fn unexpected(&self) -> PathError {
+        match self.peek() {
+            Some(found) => return PathError::UnexpectedChar { position: self.pos, found },
+            None => return PathError::UnexpectedEnd
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), PathError> {
+        if self.peek() != Some(c) {
+            return Err(self.unexpected())
+        }
+        self.pos += c.len_utf8();
+        return Ok(())
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            return true
+        }
+        return false
+    }
+
+    fn skip_blank(&mut self) {
+        while self.peek().is_some_and(is_blank) {
+            self.pos += 1;
+        }
+    }
+
+    /// Segments up to the first thing that is not one. Blank space is only
+    /// consumed when a segment follows it.
+    fn segments(&mut self) -> Result<Vec<Segment>, PathError> {
+        let mut output = Vec::new();
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+            if self.eat("..") {
+                let selectors = match self.peek() {
+                    Some('[') => self.bracketed()?,
+                    Some('*') => {self.pos += 1; vec![Selector::Wildcard]},
+                    Some(c) if is_name_first(c) => vec![Selector::Name(self.name())],
+                    _ => return Err(self.unexpected())
+                };
+                output.push(Segment::Descendant(selectors));
+            } else if self.eat(".") {
+                let selector = match self.peek() {
+                    Some('*') => {self.pos += 1; Selector::Wildcard},
+                    Some(c) if is_name_first(c) => Selector::Name(self.name()),
+                    _ => return Err(self.unexpected())
+                };
+                output.push(Segment::Child(vec![selector]));
+            } else if self.peek() == Some('[') {
+                output.push(Segment::Child(self.bracketed()?));
+            } else {
+                self.pos = start;
+                return Ok(output)
+            }
+        }
+    }
+
+    fn name(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(is_name_char) {
+            self.bump();
+        }
+        return self.source.get(start..self.pos).unwrap().to_string()
+    }
+
+    fn bracketed(&mut self) -> Result<Vec<Selector>, PathError> {
+        self.expect('[')?;
+        let mut output = Vec::new();
+        loop {
+            self.skip_blank();
+            output.push(self.selector()?);
+            self.skip_blank();
+            match self.peek() {
+                Some(',') => {self.pos += 1;},
+                Some(']') => {self.pos += 1; return Ok(output)},
+                _ => return Err(self.unexpected())
+            }
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, PathError> {
+        match self.peek() {
+            Some('\'') | Some('"') => return Ok(Selector::Name(self.string()?)),
+            Some('*') => {self.pos += 1; return Ok(Selector::Wildcard)},
+            Some('?') => {
+                self.pos += 1;
+                self.skip_blank();
+                return Ok(Selector::Filter(self.logical_or()?))
+            },
+            _ => {}
+        }
+
+        // An index or a slice
+        let start = self.integer()?;
+        self.skip_blank();
+        if !self.eat(":") {
+            return match start {
+                Some(index) => Ok(Selector::Index(index)),
+                None => Err(self.unexpected())
+            }
+        }
+        self.skip_blank();
+        let end = self.integer()?;
+        self.skip_blank();
+        let mut step = None;
+        if self.eat(":") {
+            self.skip_blank();
+            step = self.integer()?;
+        }
+        return Ok(Selector::Slice { start, end, step })
+    }
+
+    /// An optional integer without leading zeros and without `-0`.
+    fn integer(&mut self) -> Result<Option<i64>, PathError> {
+        let start = self.pos;
+        self.eat("-");
+        let digits = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let text = self.source.get(start..self.pos).unwrap();
+        let digit_text = self.source.get(digits..self.pos).unwrap();
+        if digit_text.is_empty() {
+            if digits != start {
+                return Err(self.unexpected())
+            }
+            return Ok(None)
+        }
+        if (digit_text.len() > 1 && digit_text.starts_with('0')) || text == "-0" {
+            return Err(PathError::InvalidLiteral(start))
+        }
+        match text.parse::<i64>() {
+            Ok(n) if n.abs() <= MAX_INT => return Ok(Some(n)),
+            _ => return Err(PathError::InvalidLiteral(start))
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, PathError> {
+        let start = self.pos;
+        let digits = self.rest().get(..4).ok_or(PathError::UnexpectedEnd)?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(PathError::InvalidLiteral(start))
+        }
+        self.pos += 4;
+        return Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    /// A single- or double-quoted string literal.
+    fn string(&mut self) -> Result<String, PathError> {
+        let start = self.pos;
+        let quote = self.bump().ok_or(PathError::UnexpectedEnd)?;
+        let mut output = String::new();
+        loop {
+            let c = self.bump().ok_or(PathError::UnexpectedEnd)?;
+            match c {
+                c if c == quote => return Ok(output),
+                '\\' => {
+                    let escape = self.bump().ok_or(PathError::UnexpectedEnd)?;
+                    match escape {
+                        'b' => output.push('\u{8}'),
+                        'f' => output.push('\u{c}'),
+                        'n' => output.push('\n'),
+                        'r' => output.push('\r'),
+                        't' => output.push('\t'),
+                        '/' | '\\' => output.push(escape),
+                        c if c == quote => output.push(c),
+                        'u' => {
+                            let high = self.hex4()?;
+                            let code = match high {
+                                0xD800..=0xDBFF => {
+                                    if !self.eat("\\u") {
+                                        return Err(PathError::InvalidLiteral(start))
+                                    }
+                                    let low = self.hex4()?;
+                                    if !(0xDC00..=0xDFFF).contains(&low) {
+                                        return Err(PathError::InvalidLiteral(start))
+                                    }
+                                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                                },
+                                0xDC00..=0xDFFF => return Err(PathError::InvalidLiteral(start)),
+                                code => code
+                            };
+                            output.push(char::from_u32(code).unwrap());
+                        },
+                        _ => return Err(PathError::InvalidLiteral(start))
+                    }
+                },
+                c if c < ' ' => return Err(PathError::InvalidLiteral(start)),
+                c => output.push(c)
+            }
+        }
+    }
+
+    /// A JSON number, where `-0` is allowed.
+    fn number(&mut self) -> Result<f64, PathError> {
+        let start = self.pos;
+        self.eat("-");
+        let digits = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let integer = self.source.get(digits..self.pos).unwrap();
+        if integer.is_empty() || (integer.len() > 1 && integer.starts_with('0')) {
+            return Err(PathError::InvalidLiteral(start))
+        }
+        if self.eat(".") {
+            let fraction = self.pos;
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+            if fraction == self.pos {
+                return Err(PathError::InvalidLiteral(start))
+            }
+        }
+        if self.eat("e") || self.eat("E") {
+            if !self.eat("+") {
+                self.eat("-");
+            }
+            let exponent = self.pos;
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1;
+            }
+            if exponent == self.pos {
+                return Err(PathError::InvalidLiteral(start))
+            }
+        }
+        return self.source.get(start..self.pos).unwrap().parse::<f64>().map_err(|_| PathError::InvalidLiteral(start))
+    }
+
+    fn logical_or(&mut self) -> Result<Expr, PathError> {
+        let mut output = vec![self.logical_and()?];
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+            if !self.eat("||") {
+                self.pos = start;
+                break;
+            }
+            self.skip_blank();
+            output.push(self.logical_and()?);
+        }
+        if output.len() == 1 {
+            return Ok(output.pop().unwrap())
+        }
+        return Ok(Expr::Or(output))
+    }
+
+    fn logical_and(&mut self) -> Result<Expr, PathError> {
+        let mut output = vec![self.basic()?];
+        loop {
+            let start = self.pos;
+            self.skip_blank();
+            if !self.eat("&&") {
+                self.pos = start;
+                break;
+            }
+            self.skip_blank();
+            output.push(self.basic()?);
+        }
+        if output.len() == 1 {
+            return Ok(output.pop().unwrap())
+        }
+        return Ok(Expr::And(output))
+    }
+
+    fn parenthesized(&mut self) -> Result<Expr, PathError> {
+        self.expect('(')?;
+        self.skip_blank();
+        let expr = self.logical_or()?;
+        self.skip_blank();
+        self.expect(')')?;
+        return Ok(expr)
+    }
+
+    fn compare_op(&mut self) -> Option<CompareOp> {
+        let start = self.pos;
+        self.skip_blank();
+        let ops = [
+            ("==", CompareOp::Equal),
+            ("!=", CompareOp::NotEqual),
+            ("<=", CompareOp::LessEqual),
+            (">=", CompareOp::GreaterEqual),
+            ("<", CompareOp::Less),
+            (">", CompareOp::Greater)
+        ];
+        for (text, op) in ops {
+            if self.eat(text) {
+                self.skip_blank();
+                return Some(op)
+            }
+        }
+        self.pos = start;
+        return None
+    }
+
+    fn basic(&mut self) -> Result<Expr, PathError> {
+        if self.eat("!") {
+            self.skip_blank();
+            if self.peek() == Some('(') {
+                return Ok(Expr::Not(Box::new(self.parenthesized()?)))
+            }
+            // A negated test can not be the left side of a comparison
+            let start = self.pos;
+            let operand = self.operand()?;
+            return Ok(Expr::Not(Box::new(to_test(operand, start)?)))
+        }
+        if self.peek() == Some('(') {
+            return self.parenthesized()
+        }
+
+        let start = self.pos;
+        let left = self.operand()?;
+        let Some(op) = self.compare_op() else {
+            return to_test(left, start)
+        };
+        let left = to_comparable(left, start)?;
+        let right_start = self.pos;
+        let right = self.operand()?;
+        let right = to_comparable(right, right_start)?;
+        return Ok(Expr::Compare(left, op, right))
+    }
+
+    fn operand(&mut self) -> Result<Parsed, PathError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('@') | Some('$') => {
+                let relative = self.bump() == Some('@');
+                return Ok(Parsed::Query(Query { relative, segments: self.segments()? }))
+            },
+            Some('\'') | Some('"') => return Ok(Parsed::Literal(JsonType::JsonString(self.string()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => return Ok(Parsed::Literal(JsonType::JsonNumber(self.number()?))),
+            Some(c) if c.is_ascii_lowercase() => {
+                let name = self.name();
+                if self.peek() != Some('(') {
+                    let literal = match name.as_str() {
+                        "true" => JsonType::JsonBool(true),
+                        "false" => JsonType::JsonBool(false),
+                        "null" => JsonType::JsonNull,
+                        _ => {
+                            self.pos = start;
+                            return Err(self.unexpected())
+                        }
+                    };
+                    return Ok(Parsed::Literal(literal))
+                }
+                let name = FunctionName::from_name(&name).ok_or(PathError::UnknownFunction(start))?;
+                return Ok(Parsed::Function(self.function(name, start)?))
+            },
+            _ => return Err(self.unexpected())
+        }
+    }
+
+    fn function(&mut self, name: FunctionName, start: usize) -> Result<Function, PathError> {
+        self.expect('(')?;
+        let mut args = Vec::new();
+        self.skip_blank();
+        if self.peek() != Some(')') {
+            loop {
+                let arg_start = self.pos;
+                let arg = self.operand()?;
+                let parameter = name.parameters().get(args.len()).ok_or(PathError::InvalidArguments(start))?;
+                let arg = match parameter {
+                    Type::Value => Argument::Value(to_comparable(arg, arg_start).map_err(|_| PathError::InvalidArguments(start))?),
+                    Type::Nodes => {
+                        match arg {
+                            Parsed::Query(query) => Argument::Nodes(query),
+                            _ => return Err(PathError::InvalidArguments(start))
+                        }
+                    },
+                    Type::Logical => return Err(PathError::InvalidArguments(start))
+                };
+                args.push(arg);
+                self.skip_blank();
+                if !self.eat(",") {
+                    break;
+                }
+                self.skip_blank();
+            }
+        }
+        self.expect(')')?;
+        if args.len() != name.parameters().len() {
+            return Err(PathError::InvalidArguments(start))
+        }
+        return Ok(Function { name, args })
+    }
+}
+
+/// What an operand of a filter parsed as, before its use is known.
+enum Parsed {
+    Literal(JsonType),
+    Query(Query),
+    Function(Function)
+}
+
+/// Checks that an operand can be compared: a literal, a singular query or a
+/// function returning a value.
+fn to_comparable(parsed: Parsed, start: usize) -> Result<Comparable, PathError> {
+    match parsed {
+        Parsed::Literal(value) => return Ok(Comparable::Literal(value)),
+        Parsed::Query(query) if query.is_singular() => return Ok(Comparable::Query(query)),
+        Parsed::Query(_) => return Err(PathError::NotSingular(start)),
+        Parsed::Function(function) if function.name.result() == Type::Value => return Ok(Comparable::Function(function)),
+        Parsed::Function(_) => return Err(PathError::InvalidArguments(start))
+    }
+}
+
+/// Checks that an operand can stand alone as a test: a query or a function
+/// returning a logical value.
+fn to_test(parsed: Parsed, start: usize) -> Result<Expr, PathError> {
+    match parsed {
+        Parsed::Query(query) => return Ok(Expr::Exists(query)),
+        Parsed::Function(function) if function.name.result() == Type::Logical => return Ok(Expr::Test(function)),
+        _ => return Err(PathError::InvalidArguments(start))
+    }
+}
+
+/// Writes a member name the way normalized paths quote it.
+fn normalized_name(name: &str) -> String {
+    let mut output = String::from("['");
+    for c in name.chars() {
+        match c {
+            '\'' => output.push_str("\\'"),
+            '\\' => output.push_str("\\\\"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c < ' ' => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c)
+        }
+    }
+    output.push_str("']");
+    return output
+}
+
+/// The children of a value: array elements in order, object members by key.
+fn children(node: &PathNode<'_>) -> Vec<(String, Option<usize>)> {
+    match node.value {
+        JsonType::JsonArray(values) => return (0..values.len()).map(|i| (String::new(), Some(i))).collect(),
+        JsonType::JsonObject(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            return keys.into_iter().map(|k| (k.clone(), None)).collect()
+        },
+        _ => return Vec::new()
+    }
+}
+
+fn child<'a>(node: &PathNode<'a>, key: &str, index: Option<usize>) -> PathNode<'a> {
+    match (node.value, index) {
+        (JsonType::JsonArray(values), Some(i)) => {
+            return PathNode { location: format!("{}[{i}]", node.location), value: &values[i] }
+        },
+        (JsonType::JsonObject(map), _) => {
+            return PathNode { location: format!("{}{}", node.location, normalized_name(key)), value: &map[key] }
+        },
+        _ => unreachable!()
+    }
+}
+
+fn all_children<'a>(node: &PathNode<'a>) -> Vec<PathNode<'a>> {
+    return children(node).into_iter().map(|(key, index)| child(node, &key, index)).collect()
+}
+
+/// Resolves a possibly negative index against an array of `len` elements.
+fn normalize(index: i64, len: i64) -> i64 {
+    if index >= 0 {
+        return index
+    }
+    return len + index
+}
+
+fn slice_indices(start: Option<i64>, end: Option<i64>, step: Option<i64>, len: usize) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let mut output = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0), len).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len), len).clamp(0, len);
+        let mut i = lower;
+        while i < upper {
+            output.push(i as usize);
+            i += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1), len).clamp(-1, len - 1);
+        let lower = match end {
+            Some(end) => normalize(end, len).clamp(-1, len - 1),
+            None => -1
+        };
+        let mut i = upper;
+        while lower < i {
+            output.push(i as usize);
+            i += step;
+        }
+    }
+    return output
+}
+
+fn select<'a>(selector: &Selector, root: &'a JsonType, node: &PathNode<'a>, output: &mut Vec<PathNode<'a>>) {
+    match (selector, node.value) {
+        (Selector::Name(name), JsonType::JsonObject(map)) if map.contains_key(name) => {
+            output.push(child(node, name, None));
+        },
+        (Selector::Wildcard, _) => output.extend(all_children(node)),
+        (Selector::Index(index), JsonType::JsonArray(values)) => {
+            let index = normalize(*index, values.len() as i64);
+            if 0 <= index && index < values.len() as i64 {
+                output.push(child(node, "", Some(index as usize)));
+            }
+        },
+        (Selector::Slice { start, end, step }, JsonType::JsonArray(values)) => {
+            for i in slice_indices(*start, *end, *step, values.len()) {
+                output.push(child(node, "", Some(i)));
+            }
+        },
+        (Selector::Filter(expr), _) => {
+            for candidate in all_children(node) {
+                if evaluate(expr, root, candidate.value) {
+                    output.push(candidate);
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+/// The node itself followed by all its descendants, depth first.
+fn descendants<'a>(node: PathNode<'a>, output: &mut Vec<PathNode<'a>>) {
+    let children = all_children(&node);
+    output.push(node);
+    for child in children {
+        descendants(child, output);
+    }
+}
+
+fn run<'a>(segments: &[Segment], root: &'a JsonType, start: PathNode<'a>) -> Vec<PathNode<'a>> {
+    let mut nodes = vec![start];
+    for segment in segments {
+        let mut output = Vec::new();
+        match segment {
+            Segment::Child(selectors) => {
+                for node in &nodes {
+                    for selector in selectors {
+                        select(selector, root, node, &mut output);
+                    }
+                }
+            },
+            Segment::Descendant(selectors) => {
+                for node in nodes {
+                    let mut visited = Vec::new();
+                    descendants(node, &mut visited);
+                    for descendant in &visited {
+                        for selector in selectors {
+                            select(selector, root, descendant, &mut output);
+                        }
+                    }
+                }
+            }
+        }
+        nodes = output;
+    }
+    return nodes
+}
+
+fn run_query<'a>(query: &Query, root: &'a JsonType, current: &'a JsonType) -> Vec<PathNode<'a>> {
+    let start = if query.relative { current } else { root };
+    return run(&query.segments, root, PathNode { location: "$".to_string(), value: start })
+}
+
+/// A comparable evaluates to a value or to nothing.
+fn comparable<'a>(c: &'a Comparable, root: &'a JsonType, current: &'a JsonType) -> Option<Cow<'a, JsonType>> {
+    match c {
+        Comparable::Literal(value) => return Some(Cow::Borrowed(value)),
+        Comparable::Query(query) => {
+            let mut nodes = run_query(query, root, current);
+            if nodes.len() == 1 {
+                return Some(Cow::Borrowed(nodes.pop().unwrap().value))
+            }
+            return None
+        },
+        Comparable::Function(function) => {
+            match call(function, root, current) {
+                Called::Value(value) => return value,
+                Called::Logical(_) => return None
+            }
+        }
+    }
+}
+
+enum Called<'a> {
+    Value(Option<Cow<'a, JsonType>>),
+    Logical(bool)
+}
+
+fn string_argument<'a>(arg: &'a Argument, root: &'a JsonType, current: &'a JsonType) -> Option<String> {
+    let Argument::Value(c) = arg else {
+        return None
+    };
+    match comparable(c, root, current).as_deref() {
+        Some(JsonType::JsonString(s)) => return Some(s.clone()),
+        _ => return None
+    }
+}
+
+fn nodes_argument<'a>(arg: &'a Argument, root: &'a JsonType, current: &'a JsonType) -> Vec<PathNode<'a>> {
+    match arg {
+        Argument::Nodes(query) => return run_query(query, root, current),
+        Argument::Value(_) => return Vec::new()
+    }
+}
+
+fn call<'a>(function: &'a Function, root: &'a JsonType, current: &'a JsonType) -> Called<'a> {
+    let args = &function.args;
+    match function.name {
+        FunctionName::Length => {
+            let Argument::Value(c) = &args[0] else {
+                return Called::Value(None)
+            };
+            let length = match comparable(c, root, current).as_deref() {
+                Some(JsonType::JsonString(s)) => s.chars().count(),
+                Some(JsonType::JsonArray(values)) => values.len(),
+                Some(JsonType::JsonObject(map)) => map.len(),
+                _ => return Called::Value(None)
+            };
+            return Called::Value(Some(Cow::Owned(JsonType::JsonNumber(length as f64))))
+        },
+        FunctionName::Count => {
+            let count = nodes_argument(&args[0], root, current).len();
+            return Called::Value(Some(Cow::Owned(JsonType::JsonNumber(count as f64))))
+        },
+        FunctionName::Value => {
+            let mut nodes = nodes_argument(&args[0], root, current);
+            if nodes.len() == 1 {
+                return Called::Value(Some(Cow::Borrowed(nodes.pop().unwrap().value)))
+            }
+            return Called::Value(None)
+        },
+        FunctionName::Match | FunctionName::Search => {
+            let text = string_argument(&args[0], root, current);
+            let pattern = string_argument(&args[1], root, current);
+            let (Some(text), Some(pattern)) = (text, pattern) else {
+                return Called::Logical(false)
+            };
+            // An invalid pattern matches nothing
+            let Ok(regex) = Regex::new(&pattern) else {
+                return Called::Logical(false)
+            };
+            if function.name == FunctionName::Match {
+                return Called::Logical(regex.is_full_match(&text))
+            }
+            return Called::Logical(regex.is_match(&text))
+        }
+    }
+}
+
+fn equal(a: &Option<Cow<'_, JsonType>>, b: &Option<Cow<'_, JsonType>>) -> bool {
+    match (a, b) {
+        (None, None) => return true,
+        (Some(a), Some(b)) => return a == b,
+        _ => return false
+    }
+}
+
+fn less(a: &Option<Cow<'_, JsonType>>, b: &Option<Cow<'_, JsonType>>) -> bool {
+    match (a.as_deref(), b.as_deref()) {
+        (Some(JsonType::JsonNumber(a)), Some(JsonType::JsonNumber(b))) => return a < b,
+        (Some(JsonType::JsonString(a)), Some(JsonType::JsonString(b))) => return a < b,
+        _ => return false
+    }
+}
+
+fn evaluate<'a>(expr: &'a Expr, root: &'a JsonType, current: &'a JsonType) -> bool {
+    match expr {
+        Expr::Or(exprs) => return exprs.iter().any(|e| evaluate(e, root, current)),
+        Expr::And(exprs) => return exprs.iter().all(|e| evaluate(e, root, current)),
+        Expr::Not(expr) => return !evaluate(expr, root, current),
+        Expr::Exists(query) => return !run_query(query, root, current).is_empty(),
+        Expr::Test(function) => {
+            match call(function, root, current) {
+                Called::Logical(result) => return result,
+                Called::Value(_) => return false
+            }
+        },
+        Expr::Compare(left, op, right) => {
+            let a = comparable(left, root, current);
+            let b = comparable(right, root, current);
+            match op {
+                CompareOp::Equal => return equal(&a, &b),
+                CompareOp::NotEqual => return !equal(&a, &b),
+                CompareOp::Less => return less(&a, &b),
+                CompareOp::LessEqual => return less(&a, &b) || equal(&a, &b),
+                CompareOp::Greater => return less(&b, &a),
+                CompareOp::GreaterEqual => return less(&b, &a) || equal(&a, &b)
+            }
+        }
+    }
+}
+
+impl JsonPath {
+    /// Parses a query, which must start with `$`.
+    pub fn parse(query: &str) -> Result<JsonPath, PathError> {
+        let mut parser = Parser { source: query, pos: 0 };
+        parser.expect('$')?;
+        let segments = parser.segments()?;
+        if parser.peek().is_some() {
+            return Err(parser.unexpected())
+        }
+        return Ok(JsonPath { segments })
+    }
+
+    /// Selects the matching nodes of `value`, in the order the RFC describes.
+    pub fn query<'a>(&self, value: &'a JsonType) -> Vec<PathNode<'a>> {
+        return run(&self.segments, value, PathNode { location: "$".to_string(), value })
+    }
+}
+
+/// Parses `query` and runs it against `value`.
+pub fn query<'a>(query: &str, value: &'a JsonType) -> Result<Vec<PathNode<'a>>, PathError> {
+    return Ok(JsonPath::parse(query)?.query(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_document;
+
+    const BOOKSTORE: &str = r#"{ "store": {
+        "book": [
+          { "category": "reference", "author": "Nigel Rees", "title": "Sayings of the Century", "price": 8.95 },
+          { "category": "fiction", "author": "Evelyn Waugh", "title": "Sword of Honour", "price": 12.99 },
+          { "category": "fiction", "author": "Herman Melville", "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+          { "category": "fiction", "author": "J. R. R. Tolkien", "title": "The Lord of the Rings", "isbn": "0-395-19395-8", "price": 22.99 }
+        ],
+        "bicycle": { "color": "red", "price": 399 }
+      }
+    }"#;
+
+    fn json(data: &str) -> JsonType {
+        return parse_document(data).unwrap()
+    }
+
+    /// Runs `path` and compares the selected values and locations.
+    fn check(doc: &JsonType, path: &str, expected: &[(&str, &str)]) {
+        let nodes = query(path, doc).unwrap();
+        let actual: Vec<(String, JsonType)> = nodes.into_iter().map(|n| (n.location, n.value.clone())).collect();
+        let expected: Vec<(String, JsonType)> = expected.iter().map(|(l, v)| (l.to_string(), json(v))).collect();
+        assert_eq!(actual, expected, "{path}");
+    }
+
+    fn values(doc: &JsonType, path: &str) -> Vec<JsonType> {
+        return query(path, doc).unwrap().into_iter().map(|n| n.value.clone()).collect()
+    }
+
+    #[test]
+    fn rfc_overview_examples() {
+        let doc = json(BOOKSTORE);
+        let authors = ["\"Nigel Rees\"", "\"Evelyn Waugh\"", "\"Herman Melville\"", "\"J. R. R. Tolkien\""];
+        assert_eq!(values(&doc, "$.store.book[*].author"), authors.map(json));
+        assert_eq!(values(&doc, "$..author"), authors.map(json));
+        assert_eq!(query("$.store.*", &doc).unwrap().len(), 2);
+        assert_eq!(values(&doc, "$.store..price"), ["399", "8.95", "12.99", "8.99", "22.99"].map(json));
+        check(&doc, "$..book[2].author", &[("$['store']['book'][2]['author']", "\"Herman Melville\"")]);
+        check(&doc, "$..book[2].publisher", &[]);
+        check(&doc, "$..book[-1].title", &[("$['store']['book'][3]['title']", "\"The Lord of the Rings\"")]);
+        assert_eq!(query("$..book[0,1]", &doc).unwrap().len(), 2);
+        assert_eq!(query("$..book[:2]", &doc).unwrap().len(), 2);
+        assert_eq!(values(&doc, "$..book[?@.isbn].title"), ["\"Moby Dick\"", "\"The Lord of the Rings\""].map(json));
+        assert_eq!(values(&doc, "$..book[?@.price<10].title"), ["\"Sayings of the Century\"", "\"Moby Dick\""].map(json));
+        assert_eq!(query("$..*", &doc).unwrap().len(), 27);
+    }
+
+    #[test]
+    fn selectors() {
+        let doc = json(r#"{"o": {"j j": {"k.k": 3}}, "'": {"@": 2}}"#);
+        check(&doc, "$.o['j j']", &[("$['o']['j j']", r#"{"k.k": 3}"#)]);
+        check(&doc, "$.o['j j']['k.k']", &[("$['o']['j j']['k.k']", "3")]);
+        check(&doc, r#"$.o["j j"]["k.k"]"#, &[("$['o']['j j']['k.k']", "3")]);
+        check(&doc, r#"$["'"]["@"]"#, &[(r"$['\'']['@']", "2")]);
+
+        let doc = json(r#"["a", "b", "c", "d", "e", "f", "g"]"#);
+        check(&doc, "$[1]", &[("$[1]", "\"b\"")]);
+        check(&doc, "$[-2]", &[("$[5]", "\"f\"")]);
+        check(&doc, "$[7]", &[]);
+        assert_eq!(values(&doc, "$[1:3]"), ["\"b\"", "\"c\""].map(json));
+        assert_eq!(values(&doc, "$[5:]"), ["\"f\"", "\"g\""].map(json));
+        assert_eq!(values(&doc, "$[1:5:2]"), ["\"b\"", "\"d\""].map(json));
+        assert_eq!(values(&doc, "$[5:1:-2]"), ["\"f\"", "\"d\""].map(json));
+        assert_eq!(values(&doc, "$[::-1]"), ["\"g\"", "\"f\"", "\"e\"", "\"d\"", "\"c\"", "\"b\"", "\"a\""].map(json));
+        assert_eq!(values(&doc, "$[0:5:0]"), vec![]);
+        assert_eq!(values(&doc, "$[-10:2]"), ["\"a\"", "\"b\""].map(json));
+        assert_eq!(values(&doc, "$[ 0 , 0 ]"), ["\"a\"", "\"a\""].map(json));
+
+        let doc = json(r#"{"a": "\u000b", "\u000b": 1}"#);
+        check(&doc, "$['\\u000b']", &[("$['\\u000b']", "1")]);
+    }
+
+    #[test]
+    fn descendants() {
+        let doc = json(r#"{"o": {"j": 1, "k": 2}, "a": [5, 3, [{"j": 4}, {"k": 6}]]}"#);
+        check(&doc, "$..j", &[("$['a'][2][0]['j']", "4"), ("$['o']['j']", "1")]);
+        check(&doc, "$..[0]", &[("$['a'][0]", "5"), ("$['a'][2][0]", r#"{"j": 4}"#)]);
+        check(&doc, "$.o..[*, *]", &[("$['o']['j']", "1"), ("$['o']['k']", "2"), ("$['o']['j']", "1"), ("$['o']['k']", "2")]);
+        assert_eq!(values(&doc, "$.a..[0, 1]"), ["5", "3", r#"{"j": 4}"#, r#"{"k": 6}"#].map(json));
+    }
+
+    #[test]
+    fn filters() {
+        let doc = json(r#"{"a": [3, 5, 1, 2, 4, 6, {"b": "j"}, {"b": "k"}, {"b": {}}, {"b": "kilo"}], "o": {"p": 1, "q": 2, "r": 3, "s": 5, "t": {"u": 6}}, "e": "f"}"#);
+        check(&doc, "$.a[?@.b == 'kilo']", &[("$['a'][9]", r#"{"b": "kilo"}"#)]);
+        check(&doc, "$.a[?(@.b == 'kilo')]", &[("$['a'][9]", r#"{"b": "kilo"}"#)]);
+        assert_eq!(values(&doc, "$.a[?@>3.5]"), ["5", "4", "6"].map(json));
+        assert_eq!(query("$.a[?@.b]", &doc).unwrap().len(), 4);
+        assert_eq!(query("$[?@.*]", &doc).unwrap().iter().map(|n| n.location.as_str()).collect::<Vec<_>>(), ["$['a']", "$['o']"]);
+        assert_eq!(query("$[?@[?@.b]]", &doc).unwrap().iter().map(|n| n.location.as_str()).collect::<Vec<_>>(), ["$['a']"]);
+        assert_eq!(values(&doc, "$.o[?@<3, ?@<3]"), ["1", "2", "1", "2"].map(json));
+        assert_eq!(values(&doc, r#"$.a[?@<2 || @.b == "k"]"#), ["1", r#"{"b": "k"}"#].map(json));
+        assert_eq!(values(&doc, r#"$.a[?match(@.b, "[jk]")]"#), [r#"{"b": "j"}"#, r#"{"b": "k"}"#].map(json));
+        assert_eq!(values(&doc, r#"$.a[?search(@.b, "[jk]")]"#), [r#"{"b": "j"}"#, r#"{"b": "k"}"#, r#"{"b": "kilo"}"#].map(json));
+        assert_eq!(values(&doc, "$.o[?@>1 && @<4]"), ["2", "3"].map(json));
+        assert_eq!(values(&doc, "$.o[?@.u || @.x]"), [r#"{"u": 6}"#].map(json));
+        assert_eq!(values(&doc, "$.a[?@.b == $.x]"), ["3", "5", "1", "2", "4", "6"].map(json));
+        assert_eq!(query("$.a[?@ == @]", &doc).unwrap().len(), 10);
+        assert_eq!(values(&doc, "$.a[?!(@ < 5) && !@.b]"), ["5", "6"].map(json));
+    }
+
+    #[test]
+    fn comparisons() {
+        // From table 11 of the RFC, evaluated against a single candidate
+        let holds = |expr: &str| !query(&format!("$[?{expr}]"), &json("[0]")).unwrap().is_empty();
+        let cases = [
+            ("$.absent1 == $.absent2", true),
+            ("$.absent1 <= $.absent2", true),
+            ("$.absent == 'g'", false),
+            ("$.absent1 != $.absent2", false),
+            ("$.absent != 'g'", true),
+            ("1 <= 2", true),
+            ("1 > 2", false),
+            ("13 == '13'", false),
+            ("'a' <= 'b'", true),
+            ("'a' > 'b'", false),
+            ("true != false", true),
+            ("null == null", true),
+            ("1 == 1.0", true),
+            ("-0 == 0", true)
+        ];
+        for (expr, expected) in cases {
+            assert_eq!(holds(expr), expected, "{expr}");
+        }
+        let doc = json(r#"[{"obj": {"x": "y"}, "arr": [2, 3]}]"#);
+        let on_doc = |expr: &str| query(&format!("$[?{expr}]"), &doc).unwrap().len() == 1;
+        assert!(on_doc("@.obj == @.obj"));
+        assert!(!on_doc("@.obj == @.arr"));
+        assert!(!on_doc("@.obj < @.obj"));
+        assert!(on_doc("@.obj <= @.obj"));
+        assert!(on_doc("@.arr != @.obj"));
+        assert!(on_doc("@.arr[1] > @.arr[0]"));
+    }
+
+    #[test]
+    fn functions() {
+        let doc = json(r#"[{"authors": ["a", "b"], "date": "1974-05-01", "color": "red", "name": "Bob"}, {"authors": "abcdef", "date": "1974-06-01", "name": "Rob Roy"}]"#);
+        assert_eq!(query("$[?length(@.authors) >= 5]", &doc).unwrap().len(), 1);
+        assert_eq!(query("$[?count(@.*) == 4]", &doc).unwrap().len(), 1);
+        assert_eq!(query(r#"$[?match(@.date, "1974-05-..")]"#, &doc).unwrap().len(), 1);
+        assert_eq!(query(r#"$[?search(@.name, "[BR]ob")]"#, &doc).unwrap().len(), 2);
+        assert_eq!(query(r#"$[?match(@.name, "[BR]ob")]"#, &doc).unwrap().len(), 1);
+        assert_eq!(query(r#"$[?value(@..color) == "red"]"#, &doc).unwrap().len(), 1);
+        assert_eq!(query(r#"$[?match(@.name, "(")]"#, &doc).unwrap().len(), 0);
+        assert_eq!(query("$[?length('héllo') == 5]", &doc).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn null_semantics() {
+        let doc = json(r#"{"a": null, "b": [null], "c": [{}], "null": 1}"#);
+        check(&doc, "$.a", &[("$['a']", "null")]);
+        check(&doc, "$.a[0]", &[]);
+        check(&doc, "$.a.d", &[]);
+        check(&doc, "$.b[0]", &[("$['b'][0]", "null")]);
+        check(&doc, "$.b[*]", &[("$['b'][0]", "null")]);
+        check(&doc, "$.b[?@]", &[("$['b'][0]", "null")]);
+        check(&doc, "$.b[?@==null]", &[("$['b'][0]", "null")]);
+        check(&doc, "$.c[?@.d==null]", &[]);
+        check(&doc, "$.null", &[("$['null']", "1")]);
+    }
+
+    #[test]
+    fn well_typedness_and_syntax_errors() {
+        let valid = [
+            "$[?length(@) < 3]",
+            "$[?count(@.*) == 1]",
+            "$[?match(@.timezone, 'Europe/.*')]",
+            "$[?value(@..color) == \"red\"]",
+            "$[?!match(@.a, 'x')]",
+            "$.a.b.c",
+            "$",
+            "$ .a [0]",
+            "$[?@.a==1]",
+            "$['\\ud83d\\ude00']"
+        ];
+        for path in valid {
+            assert!(JsonPath::parse(path).is_ok(), "{path}");
+        }
+        assert_eq!(JsonPath::parse("$[?length(@.*) < 3]").unwrap_err(), PathError::InvalidArguments(3));
+        assert_eq!(JsonPath::parse("$[?count(1) == 1]").unwrap_err(), PathError::InvalidArguments(3));
+        assert_eq!(JsonPath::parse("$[?foo(@.*) == 1]").unwrap_err(), PathError::UnknownFunction(3));
+        assert_eq!(JsonPath::parse("$[?match(@.a, 'x') == true]").unwrap_err(), PathError::InvalidArguments(3));
+        assert_eq!(JsonPath::parse("$[?value(@..color)]").unwrap_err(), PathError::InvalidArguments(3));
+        assert_eq!(JsonPath::parse("$[?@.* == 1]").unwrap_err(), PathError::NotSingular(3));
+        assert_eq!(JsonPath::parse("$[?1]").unwrap_err(), PathError::InvalidArguments(3));
+        assert_eq!(JsonPath::parse("$[01]").unwrap_err(), PathError::InvalidLiteral(2));
+        assert_eq!(JsonPath::parse("$[-0]").unwrap_err(), PathError::InvalidLiteral(2));
+        assert_eq!(JsonPath::parse("$[9007199254740992]").unwrap_err(), PathError::InvalidLiteral(2));
+        assert_eq!(JsonPath::parse("$.a ").unwrap_err(), PathError::UnexpectedChar { position: 3, found: ' ' });
+        assert_eq!(JsonPath::parse(" $").unwrap_err(), PathError::UnexpectedChar { position: 0, found: ' ' });
+        assert_eq!(JsonPath::parse("$['a'").unwrap_err(), PathError::UnexpectedEnd);
+        assert_eq!(JsonPath::parse("$.1").unwrap_err(), PathError::UnexpectedChar { position: 2, found: '1' });
+        assert_eq!(JsonPath::parse("$['\\x']").unwrap_err(), PathError::InvalidLiteral(2));
+        assert_eq!(JsonPath::parse("$[?@.a == 01]").unwrap_err(), PathError::InvalidLiteral(10));
+    }
+}
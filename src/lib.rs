@@ -1,11 +1,37 @@
 //pub mod parser;
+// Lets the derive macros name `::JSON_parser` from inside this crate too
+extern crate self as JSON_parser;
+
 pub mod parser;
+#[macro_use]
+mod macros;
+pub mod value;
+pub mod convert;
+pub mod recover;
+pub mod cst;
+pub mod spans;
+pub mod pointer;
+pub mod patch;
+pub mod merge;
+pub mod regex;
+pub mod jsonpath;
+pub mod jq;
+pub mod diff;
+pub mod schema;
+pub mod infer;
+pub mod codegen;
+pub mod stats;
+pub mod serializer;
+pub mod edit;
+#[cfg(feature = "serde")]
+pub mod serde;
 
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use std::collections::HashMap;
 
@@ -18,10 +44,30 @@ mod tests {
         parse_number,
         parse_object,
         parse_value,
+        parse_array_with,
+        parse_number_with,
+        parse_object_with,
+        parse_string_with,
+        parse_value_with,
+        parse_bool_with,
+        parse_null_with,
+        parse_document,
+        parse_document_with,
+        parse_string_raw,
+        parse_string_raw_with,
+        Dialect,
+        ParseError,
+        ParserOptions,
     };
+    use crate::recover::{parse_recovering, parse_recovering_with};
 
     use super::*;
 
+    #[test]
+    fn it_works() {
+        assert_eq!(add(2, 2), 4);
+    }
+
     #[test]
     fn parse_string_test() {
         assert_eq!(
@@ -156,14 +202,7 @@ mod tests {
     fn parse_dict_test() {
         assert_eq!(
             parse_object("{\"1\": 123, \"2\": 234, \"3\": 345}"),
-            Ok((
-                JsonType::JsonObject(HashMap::from([
-                    ("1".to_string(), JsonType::JsonNumber(123.0)),
-                    ("2".to_string(), JsonType::JsonNumber(234.0)),
-                    ("3".to_string(), JsonType::JsonNumber(345.0)),
-                ])),
-                ""
-            ))
+            Ok((json!({"1": 123, "2": 234, "3": 345}), ""))
         )
     }
 
@@ -171,41 +210,7 @@ mod tests {
     fn parse_deeply_nested_arrays() {
         assert_eq!(
             parse_array("[[[[1, 2], [3, 4]], [[5, 6], [7, 8]]], [[[9, 10]]]]"),
-            Ok((
-                JsonType::JsonArray(vec![
-                    JsonType::JsonArray(vec![
-                        JsonType::JsonArray(vec![
-                            JsonType::JsonArray(vec![
-                                JsonType::JsonNumber(1.0),
-                                JsonType::JsonNumber(2.0)
-                            ]),
-                            JsonType::JsonArray(vec![
-                                JsonType::JsonNumber(3.0),
-                                JsonType::JsonNumber(4.0)
-                            ])
-                        ]),
-                        JsonType::JsonArray(vec![
-                            JsonType::JsonArray(vec![
-                                JsonType::JsonNumber(5.0),
-                                JsonType::JsonNumber(6.0)
-                            ]),
-                            JsonType::JsonArray(vec![
-                                JsonType::JsonNumber(7.0),
-                                JsonType::JsonNumber(8.0)
-                            ])
-                        ])
-                    ]),
-                    JsonType::JsonArray(vec![
-                        JsonType::JsonArray(vec![
-                            JsonType::JsonArray(vec![
-                                JsonType::JsonNumber(9.0),
-                                JsonType::JsonNumber(10.0)
-                            ])
-                        ])
-                    ])
-                ]),
-                ""
-            ))
+            Ok((json!([[[[1, 2], [3, 4]], [[5, 6], [7, 8]]], [[[9, 10]]]]), ""))
         )
     }
 
@@ -214,24 +219,10 @@ mod tests {
         assert_eq!(
             parse_object("{\"matrix\": [[1, 2, 3], [4, 5, 6]], \"names\": [\"alice\", \"bob\"]}"),
             Ok((
-                JsonType::JsonObject(HashMap::from([
-                    ("matrix".to_string(), JsonType::JsonArray(vec![
-                        JsonType::JsonArray(vec![
-                            JsonType::JsonNumber(1.0),
-                            JsonType::JsonNumber(2.0),
-                            JsonType::JsonNumber(3.0)
-                        ]),
-                        JsonType::JsonArray(vec![
-                            JsonType::JsonNumber(4.0),
-                            JsonType::JsonNumber(5.0),
-                            JsonType::JsonNumber(6.0)
-                        ])
-                    ])),
-                    ("names".to_string(), JsonType::JsonArray(vec![
-                        JsonType::JsonString("alice".to_string()),
-                        JsonType::JsonString("bob".to_string())
-                    ]))
-                ])),
+                json!({
+                    "matrix": [[1, 2, 3], [4, 5, 6]],
+                    "names": ["alice", "bob"]
+                }),
                 ""
             ))
         )
@@ -242,15 +233,9 @@ mod tests {
         assert_eq!(
             parse_array("[{\"id\": 1, \"name\": \"Alice\"}, {\"id\": 2, \"name\": \"Bob\"}]"),
             Ok((
-                JsonType::JsonArray(vec![
-                    JsonType::JsonObject(HashMap::from([
-                        ("id".to_string(), JsonType::JsonNumber(1.0)),
-                        ("name".to_string(), JsonType::JsonString("Alice".to_string()))
-                    ])),
-                    JsonType::JsonObject(HashMap::from([
-                        ("id".to_string(), JsonType::JsonNumber(2.0)),
-                        ("name".to_string(), JsonType::JsonString("Bob".to_string()))
-                    ]))
+                json!([
+                    {"id": 1, "name": "Alice"},
+                    {"id": 2, "name": "Bob"}
                 ]),
                 ""
             ))
@@ -262,23 +247,14 @@ mod tests {
         assert_eq!(
             parse_object("{\"users\": [{\"name\": \"Alice\", \"scores\": [10, 20, 30], \"meta\": {\"active\": true, \"level\": 5}}], \"count\": 1}"),
             Ok((
-                JsonType::JsonObject(HashMap::from([
-                    ("users".to_string(), JsonType::JsonArray(vec![
-                        JsonType::JsonObject(HashMap::from([
-                            ("name".to_string(), JsonType::JsonString("Alice".to_string())),
-                            ("scores".to_string(), JsonType::JsonArray(vec![
-                                JsonType::JsonNumber(10.0),
-                                JsonType::JsonNumber(20.0),
-                                JsonType::JsonNumber(30.0)
-                            ])),
-                            ("meta".to_string(), JsonType::JsonObject(HashMap::from([
-                                ("active".to_string(), JsonType::JsonBool(true)),
-                                ("level".to_string(), JsonType::JsonNumber(5.0))
-                            ])))
-                        ]))
-                    ])),
-                    ("count".to_string(), JsonType::JsonNumber(1.0))
-                ])),
+                json!({
+                    "users": [{
+                        "name": "Alice",
+                        "scores": [10, 20, 30],
+                        "meta": {"active": true, "level": 5}
+                    }],
+                    "count": 1
+                }),
                 ""
             ))
         )
@@ -569,4 +545,487 @@ mod tests {
     fn test_plus_sign() {
         assert!(parse_number(r#"+123"#).is_err());
     }
+
+    // ============================================================================
+    // JSON5 DIALECT TESTS
+    // ============================================================================
+
+    #[test]
+    fn json5_single_quotes() {
+        assert_eq!(
+            parse_string_with(r#"'hello'"#, Dialect::Json5.into()),
+            Ok((JsonType::JsonString("hello".to_string()), ""))
+        );
+        assert_eq!(
+            parse_object_with(r#"{'name': 'Alice'}"#, Dialect::Json5.into()),
+            Ok((
+                JsonType::JsonObject(HashMap::from([
+                    ("name".to_string(), JsonType::JsonString("Alice".to_string()))
+                ])),
+                ""
+            ))
+        );
+        assert_eq!(
+            parse_string_with(r#"'it\'s "quoted"'"#, Dialect::Json5.into()),
+            Ok((JsonType::JsonString("it's \"quoted\"".to_string()), ""))
+        );
+    }
+
+    #[test]
+    fn json5_trailing_commas() {
+        assert_eq!(
+            parse_array_with(r#"[1, 2, 3,]"#, Dialect::Json5.into()),
+            Ok((
+                JsonType::JsonArray(vec![
+                    JsonType::JsonNumber(1.0),
+                    JsonType::JsonNumber(2.0),
+                    JsonType::JsonNumber(3.0)
+                ]),
+                ""
+            ))
+        );
+        assert_eq!(
+            parse_object_with(r#"{"a": 1, "b": 2,}"#, Dialect::Json5.into()),
+            Ok((
+                JsonType::JsonObject(HashMap::from([
+                    ("a".to_string(), JsonType::JsonNumber(1.0)),
+                    ("b".to_string(), JsonType::JsonNumber(2.0))
+                ])),
+                ""
+            ))
+        );
+        assert!(parse_array_with(r#"[1,, 2]"#, Dialect::Json5.into()).is_err());
+        assert!(parse_array_with(r#"[,]"#, Dialect::Json5.into()).is_err());
+    }
+
+    #[test]
+    fn json5_unquoted_keys() {
+        assert_eq!(
+            parse_object_with(r#"{name: "Alice", $id: 1, _private: true}"#, Dialect::Json5.into()),
+            Ok((
+                JsonType::JsonObject(HashMap::from([
+                    ("name".to_string(), JsonType::JsonString("Alice".to_string())),
+                    ("$id".to_string(), JsonType::JsonNumber(1.0)),
+                    ("_private".to_string(), JsonType::JsonBool(true))
+                ])),
+                ""
+            ))
+        );
+        assert!(parse_object_with(r#"{123: "value"}"#, Dialect::Json5.into()).is_err());
+    }
+
+    #[test]
+    fn json5_numbers() {
+        assert_eq!(parse_number_with("+123", Dialect::Json5.into()), Ok((JsonType::JsonNumber(123.0), "")));
+        assert_eq!(parse_number_with("0x1F", Dialect::Json5.into()), Ok((JsonType::JsonNumber(31.0), "")));
+        assert_eq!(parse_number_with("-0xff", Dialect::Json5.into()), Ok((JsonType::JsonNumber(-255.0), "")));
+        assert_eq!(parse_number_with(".5", Dialect::Json5.into()), Ok((JsonType::JsonNumber(0.5), "")));
+        assert_eq!(parse_number_with("5.", Dialect::Json5.into()), Ok((JsonType::JsonNumber(5.0), "")));
+        assert_eq!(parse_number_with("Infinity", Dialect::Json5.into()), Ok((JsonType::JsonNumber(f64::INFINITY), "")));
+        assert_eq!(parse_number_with("-Infinity", Dialect::Json5.into()), Ok((JsonType::JsonNumber(f64::NEG_INFINITY), "")));
+        match parse_value_with("NaN", Dialect::Json5.into()) {
+            Ok((JsonType::JsonNumber(n), "")) => assert!(n.is_nan()),
+            r => panic!("expected NaN, got {r:?}")
+        }
+        assert!(parse_number_with(".", Dialect::Json5.into()).is_err());
+        assert!(parse_number_with("0x", Dialect::Json5.into()).is_err());
+        assert!(parse_number_with("0123", Dialect::Json5.into()).is_err());
+        assert!(parse_value(r#"Infinity"#).is_err());
+        assert!(parse_value(r#"NaN"#).is_err());
+        assert!(parse_number(r#"0x1F"#).is_err());
+    }
+
+    #[test]
+    fn json5_comments() {
+        let data = "// leading comment\n[1, /* inline */ 2 // trailing\n, 3]";
+        assert_eq!(
+            parse_value_with(data, Dialect::Json5.into()),
+            Ok((
+                JsonType::JsonArray(vec![
+                    JsonType::JsonNumber(1.0),
+                    JsonType::JsonNumber(2.0),
+                    JsonType::JsonNumber(3.0)
+                ]),
+                ""
+            ))
+        );
+        assert!(parse_value(data).is_err());
+        assert!(parse_value_with("[1 /* unclosed", Dialect::Json5.into()).is_err());
+    }
+
+    #[test]
+    fn json5_multiline_strings() {
+        assert_eq!(
+            parse_string_with("'line one \\\nline two'", Dialect::Json5.into()),
+            Ok((JsonType::JsonString("line one line two".to_string()), ""))
+        );
+        assert!(parse_string_with("'line one\nline two'", Dialect::Json5.into()).is_err());
+        assert!(parse_string("\"line one \\\nline two\"").is_err());
+    }
+
+    #[test]
+    fn json5_document() {
+        let data = r#"{
+            // comments
+            unquoted: 'and you can quote me on that',
+            singleQuotes: 'I can use "double quotes" here',
+            hexadecimal: 0xdecaf,
+            leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+            positiveSign: +1,
+            trailingComma: 'in objects', andIn: ['arrays',],
+            "backwardsCompatible": "with JSON",
+        }"#;
+        assert_eq!(
+            parse_value_with(data, Dialect::Json5.into()),
+            Ok((
+                JsonType::JsonObject(HashMap::from([
+                    ("unquoted".to_string(), JsonType::JsonString("and you can quote me on that".to_string())),
+                    ("singleQuotes".to_string(), JsonType::JsonString("I can use \"double quotes\" here".to_string())),
+                    ("hexadecimal".to_string(), JsonType::JsonNumber(912559.0)),
+                    ("leadingDecimalPoint".to_string(), JsonType::JsonNumber(0.8675309)),
+                    ("andTrailing".to_string(), JsonType::JsonNumber(8675309.0)),
+                    ("positiveSign".to_string(), JsonType::JsonNumber(1.0)),
+                    ("trailingComma".to_string(), JsonType::JsonString("in objects".to_string())),
+                    ("andIn".to_string(), JsonType::JsonArray(vec![JsonType::JsonString("arrays".to_string())])),
+                    ("backwardsCompatible".to_string(), JsonType::JsonString("with JSON".to_string()))
+                ])),
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_escaped_strings() {
+        assert_eq!(
+            parse_string(r#""say \"hi\"\n\t\\ \u00e9 \ud83d\ude00""#),
+            Ok((JsonType::JsonString("say \"hi\"\n\t\\ é 😀".to_string()), ""))
+        );
+        assert!(parse_string(r#""bad \q escape""#).is_err());
+        assert!(parse_string(r#""lone \ud83d surrogate""#).is_err());
+    }
+
+    #[test]
+    fn parse_exponent_numbers() {
+        assert_eq!(parse_number("1e3 "), Ok((JsonType::JsonNumber(1000.0), " ")));
+        assert_eq!(parse_number("-2.5E-2,"), Ok((JsonType::JsonNumber(-0.025), ",")));
+        assert_eq!(parse_value("42"), Ok((JsonType::JsonNumber(42.0), "")));
+        assert!(parse_number("1e").is_err());
+        assert!(parse_number("1e+").is_err());
+    }
+
+    // ============================================================================
+    // LENIENCY FLAG TESTS
+    // Each of these flips one of the malformed JSON tests above.
+    // ============================================================================
+
+    #[test]
+    fn allow_trailing_commas() {
+        let options = ParserOptions { allow_trailing_commas: true, ..ParserOptions::default() };
+        assert_eq!(
+            parse_array_with(r#"[1, 2, 3,]"#, options),
+            Ok((
+                JsonType::JsonArray(vec![
+                    JsonType::JsonNumber(1.0),
+                    JsonType::JsonNumber(2.0),
+                    JsonType::JsonNumber(3.0)
+                ]),
+                ""
+            ))
+        );
+        assert_eq!(
+            parse_object_with(r#"{"a": 1, "b": 2,}"#, options),
+            Ok((
+                JsonType::JsonObject(HashMap::from([
+                    ("a".to_string(), JsonType::JsonNumber(1.0)),
+                    ("b".to_string(), JsonType::JsonNumber(2.0))
+                ])),
+                ""
+            ))
+        );
+        // Only a single trailing comma is accepted
+        assert!(parse_array_with(r#"[1,, 2]"#, options).is_err());
+        assert!(parse_array_with(r#"[1, 2,,]"#, options).is_err());
+    }
+
+    #[test]
+    fn allow_single_quotes() {
+        let options = ParserOptions { allow_single_quotes: true, ..ParserOptions::default() };
+        assert_eq!(
+            parse_string_with(r#"'hello'"#, options),
+            Ok((JsonType::JsonString("hello".to_string()), ""))
+        );
+        assert_eq!(
+            parse_object_with(r#"{'name': 'Alice'}"#, options),
+            Ok((
+                JsonType::JsonObject(HashMap::from([
+                    ("name".to_string(), JsonType::JsonString("Alice".to_string()))
+                ])),
+                ""
+            ))
+        );
+    }
+
+    #[test]
+    fn allow_unquoted_keys() {
+        let options = ParserOptions { allow_unquoted_keys: true, ..ParserOptions::default() };
+        assert_eq!(
+            parse_object_with(r#"{name: "Alice"}"#, options),
+            Ok((
+                JsonType::JsonObject(HashMap::from([
+                    ("name".to_string(), JsonType::JsonString("Alice".to_string()))
+                ])),
+                ""
+            ))
+        );
+        assert!(parse_object_with(r#"{123: "value"}"#, options).is_err());
+    }
+
+    #[test]
+    fn allow_leading_zeros() {
+        let options = ParserOptions { allow_leading_zeros: true, ..ParserOptions::default() };
+        assert_eq!(parse_number_with(r#"0123"#, options), Ok((JsonType::JsonNumber(123.0), "")));
+        assert_eq!(parse_number_with(r#"00"#, options), Ok((JsonType::JsonNumber(0.0), "")));
+    }
+
+    #[test]
+    fn allow_nan_infinity() {
+        let options = ParserOptions { allow_nan_infinity: true, ..ParserOptions::default() };
+        assert!(parse_value(r#"[NaN, Infinity, -Infinity]"#).is_err());
+        match parse_value_with(r#"[NaN, Infinity, -Infinity]"#, options) {
+            Ok((JsonType::JsonArray(values), "")) => {
+                assert!(matches!(values[0], JsonType::JsonNumber(n) if n.is_nan()));
+                assert_eq!(values[1], JsonType::JsonNumber(f64::INFINITY));
+                assert_eq!(values[2], JsonType::JsonNumber(f64::NEG_INFINITY));
+            },
+            r => panic!("unexpected result {r:?}")
+        }
+        // Still not a null
+        assert_eq!(parse_value_with("null", options), Ok((JsonType::JsonNull, "")));
+    }
+
+    #[test]
+    fn allow_control_characters() {
+        let options = ParserOptions { allow_control_characters: true, ..ParserOptions::default() };
+        assert!(parse_string("\"tab\there\"").is_err());
+        assert!(parse_string("\"line\nbreak\"").is_err());
+        assert_eq!(
+            parse_string_with("\"tab\there\"", options),
+            Ok((JsonType::JsonString("tab\there".to_string()), ""))
+        );
+    }
+
+    #[test]
+    fn case_insensitive_literals() {
+        let options = ParserOptions { case_insensitive_literals: true, ..ParserOptions::default() };
+        assert_eq!(parse_null_with(r#"NULL"#, options), Ok((JsonType::JsonNull, "")));
+        assert_eq!(parse_null_with(r#"Null"#, options), Ok((JsonType::JsonNull, "")));
+        assert_eq!(parse_bool_with(r#"TRUE"#, options), Ok((JsonType::JsonBool(true), "")));
+        assert_eq!(parse_bool_with(r#"FALSE"#, options), Ok((JsonType::JsonBool(false), "")));
+        assert_eq!(
+            parse_value_with(r#"[True, NULL]"#, options),
+            Ok((JsonType::JsonArray(vec![JsonType::JsonBool(true), JsonType::JsonNull]), ""))
+        );
+    }
+
+    #[test]
+    fn allow_plus_sign() {
+        let options = ParserOptions { allow_plus_sign: true, ..ParserOptions::default() };
+        assert_eq!(parse_number_with(r#"+123"#, options), Ok((JsonType::JsonNumber(123.0), "")));
+        assert!(parse_number_with(r#"+-123"#, options).is_err());
+    }
+
+    #[test]
+    fn allow_leading_and_trailing_decimal_point() {
+        let leading = ParserOptions { allow_leading_decimal_point: true, ..ParserOptions::default() };
+        let trailing = ParserOptions { allow_trailing_decimal_point: true, ..ParserOptions::default() };
+        assert_eq!(parse_number_with(r#".123"#, leading), Ok((JsonType::JsonNumber(0.123), "")));
+        assert!(parse_number_with(r#"123."#, leading).is_err());
+        assert_eq!(parse_number_with(r#"123."#, trailing), Ok((JsonType::JsonNumber(123.0), "")));
+        assert!(parse_number_with(r#".123"#, trailing).is_err());
+    }
+
+    #[test]
+    fn dialect_presets() {
+        assert_eq!(ParserOptions::from(Dialect::Json), ParserOptions::default());
+        let json5 = ParserOptions::from(Dialect::Json5);
+        assert!(json5.allow_comments && json5.allow_trailing_commas && json5.allow_unquoted_keys);
+        assert!(!json5.allow_leading_zeros && !json5.case_insensitive_literals);
+    }
+
+    // ============================================================================
+    // STRICT MODE TESTS
+    // ============================================================================
+
+    #[test]
+    fn strict_capital_literals() {
+        let strict = ParserOptions { strict: true, ..ParserOptions::default() };
+        assert_eq!(parse_value("True"), Err(ParseError::MalformedBool));
+        assert!(matches!(parse_value_with("True", strict), Err(ParseError::UnexpectedChar { found: 'T', .. })));
+        assert!(matches!(parse_value_with("False", strict), Err(ParseError::UnexpectedChar { found: 'F', .. })));
+    }
+
+    #[test]
+    fn strict_ascii_digits() {
+        let strict = ParserOptions { strict: true, ..ParserOptions::default() };
+        // Arabic-Indic digits one, two, three
+        assert!(parse_number_with("\u{661}\u{662}\u{663}", strict).is_err());
+        assert!(matches!(parse_value_with("\u{661}", strict), Err(ParseError::UnexpectedChar { .. })));
+        assert_eq!(parse_value_with("123", strict), Ok((JsonType::JsonNumber(123.0), "")));
+    }
+
+    #[test]
+    fn strict_string_start() {
+        let strict = ParserOptions { strict: true, ..ParserOptions::default() };
+        assert_eq!(parse_string_raw(r#"junk"key""#), Ok(("key".to_string(), "")));
+        assert!(parse_string_raw_with(r#"junk"key""#, strict).is_err());
+        assert_eq!(parse_string_raw_with(r#"  "key""#, strict), Ok(("key".to_string(), "")));
+        assert!(parse_object_with(r#"{"a": 1,,"b": 2}"#, strict).is_err());
+    }
+
+    #[test]
+    fn strict_whitespace() {
+        let strict = ParserOptions { strict: true, ..ParserOptions::default() };
+        assert!(parse_value("[\u{c}1]").is_ok());
+        assert!(parse_value_with("[\u{c}1]", strict).is_err());
+        assert!(parse_value_with("[\u{a0}1]", strict).is_err());
+        assert!(parse_value_with(" \t\r\n[1]", strict).is_ok());
+    }
+
+    #[test]
+    fn parse_document_rejects_trailing_data() {
+        assert_eq!(parse_document(" [1] \n"), Ok(JsonType::JsonArray(vec![JsonType::JsonNumber(1.0)])));
+        assert!(parse_document("[1]]").is_err());
+        assert!(parse_document(r#"{"a": 1} "x""#).is_err());
+    }
+
+    #[test]
+    fn max_depth() {
+        let options = ParserOptions { max_depth: Some(2), ..ParserOptions::default() };
+        assert!(parse_document_with("[[1]]", options).is_ok());
+        assert_eq!(parse_document_with("[[[1]]]", options), Err(ParseError::MaxDepthExceeded));
+        assert_eq!(parse_document_with(r#"{"a": {"b": []}}"#, options), Err(ParseError::MaxDepthExceeded));
+
+        // Limited by default, deep input is an error instead of a stack overflow
+        assert_eq!(parse_document(&"[".repeat(100_000)), Err(ParseError::MaxDepthExceeded));
+        let deep = "[".repeat(200) + &"]".repeat(200);
+        assert_eq!(parse_document(&deep), Err(ParseError::MaxDepthExceeded));
+        let unlimited = ParserOptions { max_depth: None, ..ParserOptions::default() };
+        assert!(parse_document_with(&deep, unlimited).is_ok());
+    }
+
+    // ============================================================================
+    // ERROR RECOVERY TESTS
+    // ============================================================================
+
+    #[test]
+    fn recovering_valid_input() {
+        let (value, errors) = parse_recovering(r#"{"a": [1, 2], "b": null}"#);
+        assert_eq!(errors, vec![]);
+        assert_eq!(value, parse_document(r#"{"a": [1, 2], "b": null}"#).unwrap());
+    }
+
+    #[test]
+    fn recovering_reports_every_error() {
+        let (value, errors) = parse_recovering(r#"{"a": tru, "b": 2, "c": [1, @, 3], "d": 4x}"#);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0], ParseError::MalformedBool);
+        assert_eq!(
+            value,
+            JsonType::JsonObject(HashMap::from([
+                ("a".to_string(), JsonType::JsonNull),
+                ("b".to_string(), JsonType::JsonNumber(2.0)),
+                ("c".to_string(), JsonType::JsonArray(vec![
+                    JsonType::JsonNumber(1.0),
+                    JsonType::JsonNull,
+                    JsonType::JsonNumber(3.0)
+                ])),
+                ("d".to_string(), JsonType::JsonNull)
+            ]))
+        );
+    }
+
+    #[test]
+    fn recovering_missing_separators() {
+        let (value, errors) = parse_recovering(r#"[1 2, 3]"#);
+        assert_eq!(errors, vec![ParseError::UnexpectedChar { expected: ",,]".to_string(), found: '2' }]);
+        assert_eq!(
+            value,
+            JsonType::JsonArray(vec![
+                JsonType::JsonNumber(1.0),
+                JsonType::JsonNumber(2.0),
+                JsonType::JsonNumber(3.0)
+            ])
+        );
+
+        let (value, errors) = parse_recovering(r#"{"a" 1 "b": 2}"#);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            value,
+            JsonType::JsonObject(HashMap::from([
+                ("a".to_string(), JsonType::JsonNumber(1.0)),
+                ("b".to_string(), JsonType::JsonNumber(2.0))
+            ]))
+        );
+    }
+
+    #[test]
+    fn recovering_commas_and_keys() {
+        let (value, errors) = parse_recovering(r#"{"a": 1,, name: 2, "c": 3,}"#);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            value,
+            JsonType::JsonObject(HashMap::from([
+                ("a".to_string(), JsonType::JsonNumber(1.0)),
+                ("name".to_string(), JsonType::JsonNumber(2.0)),
+                ("c".to_string(), JsonType::JsonNumber(3.0))
+            ]))
+        );
+
+        let (value, errors) = parse_recovering(r#"[1,, 2,]"#);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            value,
+            JsonType::JsonArray(vec![
+                JsonType::JsonNumber(1.0),
+                JsonType::JsonNull,
+                JsonType::JsonNumber(2.0)
+            ])
+        );
+    }
+
+    #[test]
+    fn recovering_unclosed_and_mismatched() {
+        let (value, errors) = parse_recovering(r#"{"a": [1, 2}"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            value,
+            JsonType::JsonObject(HashMap::from([
+                ("a".to_string(), JsonType::JsonArray(vec![JsonType::JsonNumber(1.0), JsonType::JsonNumber(2.0)]))
+            ]))
+        );
+
+        let (value, errors) = parse_recovering(r#"[1, [2, 3"#);
+        assert_eq!(errors, vec![ParseError::UnexpectedEof, ParseError::UnexpectedEof]);
+        assert_eq!(
+            value,
+            JsonType::JsonArray(vec![
+                JsonType::JsonNumber(1.0),
+                JsonType::JsonArray(vec![JsonType::JsonNumber(2.0), JsonType::JsonNumber(3.0)])
+            ])
+        );
+
+        let (value, errors) = parse_recovering("");
+        assert_eq!((value, errors), (JsonType::JsonNull, vec![ParseError::UnexpectedEof]));
+    }
+
+    #[test]
+    fn recovering_respects_options() {
+        let json5 = Dialect::Json5.into();
+        let (_, errors) = parse_recovering_with("{a: 1, b: [2,],}", json5);
+        assert_eq!(errors, vec![]);
+        let (_, errors) = parse_recovering("{a: 1, b: [2,],}");
+        assert_eq!(errors.len(), 4);
+        let (_, errors) = parse_recovering(r#"[1] x"#);
+        assert_eq!(errors, vec![ParseError::UnexpectedChar { expected: "end of input".to_string(), found: 'x' }]);
+    }
 }
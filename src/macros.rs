@@ -0,0 +1,161 @@
+//! The `json!` macro.
+
+/// Builds a `JsonType` from JSON-like syntax.
+///
+/// `null`, `true`, `false`, arrays and objects are written as in JSON. Any
+/// other value is a Rust expression converted with `JsonType::from`, and
+/// object keys are expressions converted into a `String`, so both literals and
+/// variables work:
+///
+/// ```
+/// use JSON_parser::json;
+///
+/// let name = "Alice";
+/// let x = 3;
+/// let value = json!({"name": name, "scores": [1, 2, x], "extra": null});
+/// assert_eq!(value.to_string(), r#"{"extra":null,"name":"Alice","scores":[1,2,3]}"#);
+/// ```
+#[macro_export]
+macro_rules! json {
+    (null) => {
+        $crate::parser::JsonType::JsonNull
+    };
+    (true) => {
+        $crate::parser::JsonType::JsonBool(true)
+    };
+    (false) => {
+        $crate::parser::JsonType::JsonBool(false)
+    };
+    ([]) => {
+        $crate::parser::JsonType::JsonArray(::std::vec::Vec::new())
+    };
+    ([ $($tt:tt)+ ]) => {
+        $crate::parser::JsonType::JsonArray($crate::json_internal!(@array [] $($tt)+))
+    };
+    ({}) => {
+        $crate::parser::JsonType::JsonObject(::std::collections::HashMap::new())
+    };
+    ({ $($tt:tt)+ }) => {{
+        let mut object: ::std::collections::HashMap<::std::string::String, $crate::parser::JsonType> = ::std::collections::HashMap::new();
+        $crate::json_internal!(@object object () ($($tt)+) ($($tt)+));
+        $crate::parser::JsonType::JsonObject(object)
+    }};
+    ($other:expr) => {
+        $crate::parser::JsonType::from($other)
+    };
+}
+
+/// Token muncher behind `json!`. Array elements and object values are
+/// collected one at a time, since an element can only be recognised as an
+/// expression once the `,` after it is found.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! json_internal {
+    // Arrays: the elements parsed so far are in the brackets
+
+    (@array [$($elements:expr,)*]) => {
+        ::std::vec![$($elements,)*]
+    };
+    (@array [$($elements:expr),*]) => {
+        ::std::vec![$($elements),*]
+    };
+    (@array [$($elements:expr,)*] null $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elements,)* $crate::json!(null)] $($rest)*)
+    };
+    (@array [$($elements:expr,)*] true $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elements,)* $crate::json!(true)] $($rest)*)
+    };
+    (@array [$($elements:expr,)*] false $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elements,)* $crate::json!(false)] $($rest)*)
+    };
+    (@array [$($elements:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elements,)* $crate::json!([$($array)*])] $($rest)*)
+    };
+    (@array [$($elements:expr,)*] {$($object:tt)*} $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elements,)* $crate::json!({$($object)*})] $($rest)*)
+    };
+    (@array [$($elements:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elements,)* $crate::json!($next),] $($rest)*)
+    };
+    (@array [$($elements:expr,)*] $last:expr) => {
+        $crate::json_internal!(@array [$($elements,)* $crate::json!($last)])
+    };
+    (@array [$($elements:expr),*] , $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($elements,)*] $($rest)*)
+    };
+
+    // Objects: `(key tokens) (remaining tokens) (copy of the remaining tokens)`,
+    // or `[key] (value)` once a value is parsed
+
+    (@object $object:ident () () ()) => {};
+    (@object $object:ident [$($key:tt)+] ($value:expr) , $($rest:tt)*) => {
+        let _ = $object.insert(($($key)+).into(), $value);
+        $crate::json_internal!(@object $object () ($($rest)*) ($($rest)*));
+    };
+    (@object $object:ident [$($key:tt)+] ($value:expr)) => {
+        let _ = $object.insert(($($key)+).into(), $value);
+    };
+    (@object $object:ident ($($key:tt)+) (: null $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json!(null)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: true $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json!(true)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: false $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json!(false)) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: [$($array:tt)*] $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json!([$($array)*])) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: {$($map:tt)*} $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json!({$($map)*})) $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr , $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json!($value)) , $($rest)*);
+    };
+    (@object $object:ident ($($key:tt)+) (: $value:expr) $copy:tt) => {
+        $crate::json_internal!(@object $object [$($key)+] ($crate::json!($value)));
+    };
+    (@object $object:ident () (($key:expr) : $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object ($key) (: $($rest)*) (: $($rest)*));
+    };
+    (@object $object:ident ($($key:tt)*) ($tt:tt $($rest:tt)*) $copy:tt) => {
+        $crate::json_internal!(@object $object ($($key)* $tt) ($($rest)*) ($($rest)*));
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::parser::{parse_document, JsonType};
+
+    #[test]
+    fn literals_and_nesting() {
+        assert_eq!(json!(null), JsonType::JsonNull);
+        assert_eq!(json!([]), JsonType::JsonArray(vec![]));
+        assert_eq!(json!({}), JsonType::JsonObject(HashMap::new()));
+        assert_eq!(json!([1, -2.5, "s", true, null, [false], {"a": {}}]), parse_document(r#"[1, -2.5, "s", true, null, [false], {"a": {}}]"#).unwrap());
+        assert_eq!(
+            json!({"a": null, "b": [1, [2, {"c": true}]], "d": {"e": "f",}, "g": -1,}),
+            parse_document(r#"{"a": null, "b": [1, [2, {"c": true}]], "d": {"e": "f"}, "g": -1}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn interpolation() {
+        let name = "Alice";
+        let scores = [10, 20];
+        let key = String::from("dynamic");
+        let nested = json!({"inner": 1});
+        let value = json!({
+            "name": name,
+            "scores": [scores[0], scores[1], scores[0] + scores[1]],
+            "ratio": 1.0 / 4.0,
+            key.clone(): nested.clone(),
+            (format!("{name}!")): name.len() > 3
+        });
+        assert_eq!(value, parse_document(r#"{"name": "Alice", "scores": [10, 20, 30], "ratio": 0.25, "dynamic": {"inner": 1}, "Alice!": true}"#).unwrap());
+        assert_eq!(json!([nested, String::from("x"), 7u8]), parse_document(r#"[{"inner": 1}, "x", 7]"#).unwrap());
+    }
+}
@@ -0,0 +1,108 @@
+//! JSON Merge Patch (RFC 7396).
+//!
+//! A merge patch looks like the document it changes: objects are merged key by
+//! key, a `null` removes a key and any other value replaces what was there.
+
+use std::collections::HashMap;
+
+use crate::parser::JsonType;
+
+/// Applies the merge patch `patch` to `target`.
+pub fn merge_patch(target: &mut JsonType, patch: &JsonType) {
+    let JsonType::JsonObject(patch) = patch else {
+        *target = patch.clone();
+        return
+    };
+    if !matches!(target, JsonType::JsonObject(_)) {
+        *target = JsonType::JsonObject(HashMap::new());
+    }
+    let JsonType::JsonObject(map) = target else {
+        unreachable!()
+    };
+    for (key, value) in patch {
+        match value {
+            JsonType::JsonNull => {map.remove(key);},
+            value => merge_patch(map.entry(key.clone()).or_insert(JsonType::JsonNull), value)
+        }
+    }
+}
+
+/// Produces a merge patch that turns `old` into `new`.
+///
+/// Merge patches cannot set a value to `null`, since that removes it, so a
+/// `null` inside an object of `new` is dropped unless it is already in `old`.
+/// Arrays are always replaced as a whole.
+pub fn create_merge_patch(old: &JsonType, new: &JsonType) -> JsonType {
+    let (JsonType::JsonObject(old), JsonType::JsonObject(new)) = (old, new) else {
+        return new.clone()
+    };
+    let mut output = HashMap::new();
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            output.insert(key.clone(), JsonType::JsonNull);
+        }
+    }
+    for (key, value) in new {
+        match old.get(key) {
+            Some(previous) if previous == value => {},
+            Some(previous) => {output.insert(key.clone(), create_merge_patch(previous, value));},
+            None if *value == JsonType::JsonNull => {},
+            None => {output.insert(key.clone(), create_merge_patch(&JsonType::JsonNull, value));}
+        }
+    }
+    return JsonType::JsonObject(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_document;
+
+    fn json(data: &str) -> JsonType {
+        return parse_document(data).unwrap()
+    }
+
+    // Appendix A of RFC 7396: original, patch, result
+    const APPENDIX_A: [(&str, &str, &str); 15] = [
+        (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+        (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+        (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+        (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+        (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+        (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+        (r#"{"a":{"b":"c"}}"#, r#"{"a":{"b":"d","c":null}}"#, r#"{"a":{"b":"d"}}"#),
+        (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+        (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+        (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+        (r#"{"a":"foo"}"#, "null", "null"),
+        (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+        (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+        (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+        (r#"{}"#, r#"{"a":{"bb":{"ccc":null}}}"#, r#"{"a":{"bb":{}}}"#)
+    ];
+
+    #[test]
+    fn rfc_appendix_a() {
+        for (original, patch, result) in APPENDIX_A {
+            let mut target = json(original);
+            merge_patch(&mut target, &json(patch));
+            assert_eq!(target, json(result), "{original} + {patch}");
+        }
+    }
+
+    #[test]
+    fn create_inverts_merge() {
+        for (original, _, result) in APPENDIX_A {
+            let (old, new) = (json(original), json(result));
+            let mut target = old.clone();
+            merge_patch(&mut target, &create_merge_patch(&old, &new));
+            assert_eq!(target, new, "{original} -> {result}");
+        }
+
+        let old = json(r#"{"title": "Goodbye!", "author": {"givenName": "John", "familyName": "Doe"}, "tags": ["example", "sample"], "content": "x"}"#);
+        let new = json(r#"{"title": "Hello!", "author": {"givenName": "John"}, "tags": ["example"], "content": "x", "phoneNumber": "+01-123-456-7890"}"#);
+        let patch = create_merge_patch(&old, &new);
+        assert_eq!(patch, json(r#"{"title": "Hello!", "author": {"familyName": null}, "tags": ["example"], "phoneNumber": "+01-123-456-7890"}"#));
+        assert_eq!(create_merge_patch(&new, &new), json("{}"));
+    }
+}
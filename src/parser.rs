@@ -1,6 +1,6 @@
 use std::{collections::HashMap};
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum ParseError {
     UnexpectedChar { expected: String, found: char },
     UnexpectedEof,
@@ -8,10 +8,11 @@ pub enum ParseError {
     MalformedNull,
     MalformedBool,
     MalformedString,
-    MalformedArray
+    MalformedArray,
+    MaxDepthExceeded
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum JsonType {
     JsonObject(HashMap<String, JsonType>),
     JsonArray(Vec<JsonType>),
@@ -21,18 +22,182 @@ pub enum JsonType {
     JsonNull
 }
 
+/// Preset flavours of JSON, see `ParserOptions` for the individual switches.
+///
+/// `Json` is the default and keeps the strict behaviour of the plain `parse_*`
+/// functions. `Json5` additionally accepts comments, trailing commas, unquoted
+/// identifier keys, single-quoted strings, escaped newlines inside strings,
+/// hexadecimal numbers, leading/trailing decimal points, explicit `+` signs and
+/// `Infinity`/`NaN`.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub enum Dialect {
+    #[default]
+    Json,
+    Json5
+}
+
+/// Individual leniency switches consulted by the `parse_*_with` functions.
+///
+/// Every flag defaults to `false`, which is plain JSON, and nesting is limited
+/// to `DEFAULT_MAX_DEPTH`. A `Dialect` converts into the matching set of flags,
+/// so `ParserOptions::from(Dialect::Json5)` can be used as a starting point and
+/// adjusted field by field.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ParserOptions {
+    /// `//` line comments and `/* */` block comments count as whitespace.
+    pub allow_comments: bool,
+    /// A single `,` may follow the last element of an array or object.
+    pub allow_trailing_commas: bool,
+    /// Strings may be delimited by `'` as well as `"`.
+    pub allow_single_quotes: bool,
+    /// Object keys may be bare identifiers such as `{name: 1}`.
+    pub allow_unquoted_keys: bool,
+    /// Integers may start with extra zeros, `007` is read as `7`.
+    pub allow_leading_zeros: bool,
+    /// `NaN`, `Infinity` and `-Infinity` are accepted as numbers.
+    pub allow_nan_infinity: bool,
+    /// Raw control characters (below U+0020) may appear inside strings.
+    pub allow_control_characters: bool,
+    /// `true`, `false` and `null` are matched ignoring ASCII case.
+    pub case_insensitive_literals: bool,
+    /// Hexadecimal integers such as `0x1F`.
+    pub allow_hex_numbers: bool,
+    /// Numbers may carry an explicit `+` sign.
+    pub allow_plus_sign: bool,
+    /// Numbers may start with a decimal point, `.5`.
+    pub allow_leading_decimal_point: bool,
+    /// Numbers may end with a decimal point, `5.`.
+    pub allow_trailing_decimal_point: bool,
+    /// A backslash followed by a line break continues the string on the next line.
+    pub allow_escaped_newlines: bool,
+    /// The JSON5 escapes `\'`, `\v`, `\0`, `\xHH`, and any other escaped character as itself.
+    pub allow_extended_escapes: bool,
+    /// Fixes the legacy quirks of the default parser that RFC 8259 forbids: only
+    /// space, tab, CR and LF count as whitespace, only ASCII digits form numbers,
+    /// `T`/`F` are not treated as the start of a literal and strings must start
+    /// exactly at their opening quote instead of skipping any junk before it.
+    pub strict: bool,
+    /// Maximum nesting depth of arrays and objects. `None` removes the limit,
+    /// deeply nested input then overflows the stack and aborts the process.
+    pub max_depth: Option<usize>,
+}
+
+/// The nesting depth `ParserOptions::default()` allows. Real documents stay
+/// far below it, and the recursive parsers need little stack to reach it.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        return ParserOptions {
+            allow_comments: false,
+            allow_trailing_commas: false,
+            allow_single_quotes: false,
+            allow_unquoted_keys: false,
+            allow_leading_zeros: false,
+            allow_nan_infinity: false,
+            allow_control_characters: false,
+            case_insensitive_literals: false,
+            allow_hex_numbers: false,
+            allow_plus_sign: false,
+            allow_leading_decimal_point: false,
+            allow_trailing_decimal_point: false,
+            allow_escaped_newlines: false,
+            allow_extended_escapes: false,
+            strict: false,
+            max_depth: Some(DEFAULT_MAX_DEPTH)
+        }
+    }
+}
+
+impl From<Dialect> for ParserOptions {
+    fn from(dialect: Dialect) -> Self {
+        match dialect {
+            Dialect::Json => ParserOptions::default(),
+            Dialect::Json5 => ParserOptions {
+                allow_comments: true,
+                allow_trailing_commas: true,
+                allow_single_quotes: true,
+                allow_unquoted_keys: true,
+                allow_nan_infinity: true,
+                allow_hex_numbers: true,
+                allow_plus_sign: true,
+                allow_leading_decimal_point: true,
+                allow_trailing_decimal_point: true,
+                allow_escaped_newlines: true,
+                allow_extended_escapes: true,
+                ..ParserOptions::default()
+            }
+        }
+    }
+}
+
+impl ParserOptions {
+    /// Options for the next nesting level, failing once `max_depth` is used up.
+    pub(crate) fn nested(self) -> Result<ParserOptions, ParseError> {
+        match self.max_depth {
+            Some(0) => Err(ParseError::MaxDepthExceeded),
+            Some(depth) => Ok(ParserOptions { max_depth: Some(depth - 1), ..self }),
+            None => Ok(self)
+        }
+    }
+}
+
+fn is_whitespace(c: char, options: ParserOptions) -> bool {
+    if options.strict {
+        return matches!(c, ' ' | '\t' | '\n' | '\r')
+    }
+    return c.is_whitespace()
+}
+
+fn is_digit(c: char, options: ParserOptions) -> bool {
+    if options.strict {
+        return c.is_ascii_digit()
+    }
+    return c.is_numeric()
+}
+
+fn trim(data: &str, options: ParserOptions) -> &str {
+    return data.trim_start_matches(|c| is_whitespace(c, options))
+}
+
+/// Skips whitespace and, if enabled, `//` line comments and `/* */` block comments.
+pub(crate) fn skip_whitespace(data: &str, options: ParserOptions) -> Result<&str, ParseError> {
+    let mut rest = trim(data, options);
+    if !options.allow_comments {
+        return Ok(rest)
+    }
+    loop {
+        if let Some(comment) = rest.strip_prefix("//") {
+            match comment.find(['\n', '\r']) {
+                Some(i) => {rest = trim(comment.get(i..).unwrap(), options)},
+                None => {return Ok("")}
+            }
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            match comment.find("*/") {
+                Some(i) => {rest = trim(comment.get((i+2)..).unwrap(), options)},
+                None => {return Err(ParseError::UnexpectedEof)}
+            }
+        } else {
+            return Ok(rest)
+        }
+    }
+}
+
 pub fn parse_array(data: &str) -> Result<(JsonType, &str), ParseError> {
+    return parse_array_with(data, ParserOptions::default())
+}
 
-    let mut rest = data.trim_start();
-    let mut chars = rest.chars();
-    match chars.next() {
+pub fn parse_array_with(data: &str, options: ParserOptions) -> Result<(JsonType, &str), ParseError> {
+
+    let mut rest = skip_whitespace(data, options)?;
+    match rest.chars().next() {
         Some('[') => {},
         None => {return Err(ParseError::UnexpectedEof)},
         _ => {return Err(ParseError::MalformedArray)}
     }
-    rest = rest.get(1..).unwrap();
+    rest = skip_whitespace(rest.get(1..).unwrap(), options)?;
     // Handle empty array
-    match chars.next() {
+    match rest.chars().next() {
         Some(']') => return Ok((JsonType::JsonArray(Vec::new()), rest.get(1..).unwrap())),
         None => {return Err(ParseError::UnexpectedEof)},
         _ => {}
@@ -41,14 +206,14 @@ pub fn parse_array(data: &str) -> Result<(JsonType, &str), ParseError> {
     let mut values = Vec::<JsonType>::new();
     loop {
         let value;
-        match parse_value(rest) {
+        match parse_value_with(rest, options) {
             Ok(r) => {(value, rest) = r},
             Err(err) => return Err(err)
         }
         values.push(value);
 
         // Check if there is a new entry or if array has ended
-        rest = rest.trim_start();
+        rest = skip_whitespace(rest, options)?;
         match rest.chars().next() {
             Some(']') => {return Ok((JsonType::JsonArray(values), rest.get(1..).unwrap()))},
             Some(',') => {},
@@ -56,11 +221,23 @@ pub fn parse_array(data: &str) -> Result<(JsonType, &str), ParseError> {
             _ => {return Err(ParseError::MalformedArray)}
         }
         rest = rest.get(1..).unwrap();
+
+        // A single trailing comma may close the array
+        if options.allow_trailing_commas {
+            rest = skip_whitespace(rest, options)?;
+            if let Some(r) = rest.strip_prefix(']') {
+                return Ok((JsonType::JsonArray(values), r))
+            }
+        }
     }
 }
 
 pub fn parse_string(data: &str) -> Result<(JsonType, &str), ParseError> {
-    match parse_string_raw(data) {
+    return parse_string_with(data, ParserOptions::default())
+}
+
+pub fn parse_string_with(data: &str, options: ParserOptions) -> Result<(JsonType, &str), ParseError> {
+    match parse_string_raw_with(data, options) {
         Ok(r) => {
             let j_str = JsonType::JsonString(r.0);
             return Ok((j_str, r.1))
@@ -70,27 +247,125 @@ pub fn parse_string(data: &str) -> Result<(JsonType, &str), ParseError> {
 }
 
 pub fn parse_string_raw(data: &str) -> Result<(String, &str), ParseError> {
-    let start ;
-    match data.find('"') {
-        Some(i) => {start = i+1;},
-        None => {return Err(ParseError::MalformedString)}
-    }
-    let rest = data.get((start)..).unwrap();
-    match rest.find('"') {
+    return parse_string_raw_with(data, ParserOptions::default())
+}
+
+pub fn parse_string_raw_with(data: &str, options: ParserOptions) -> Result<(String, &str), ParseError> {
+    let start;
+    let quote;
+    let found = match (options.strict, options.allow_single_quotes) {
+        (true, single_quotes) => {
+            let trimmed = skip_whitespace(data, options)?;
+            match trimmed.chars().next() {
+                Some('"') => Some(data.len() - trimmed.len()),
+                Some('\'') if single_quotes => Some(data.len() - trimmed.len()),
+                _ => None
+            }
+        },
+        (false, false) => data.find('"'),
+        (false, true) => data.find(['"', '\''])
+    };
+    match found {
         Some(i) => {
-            return Ok((
-                data.get(start..(start+i)).unwrap().to_string(),
-                data.get((start+i+1)..).unwrap()
-            ))
+            quote = data.get(i..).unwrap().chars().next().unwrap();
+            start = i+1;
         },
         None => {return Err(ParseError::MalformedString)}
     }
+
+    let mut output = String::new();
+    let mut chars = data.get(start..).unwrap().char_indices();
+    loop {
+        match chars.next() {
+            Some((i, c)) if c == quote => {
+                return Ok((output, data.get((start+i+1)..).unwrap()))
+            },
+            Some((_, '\\')) => {
+                match chars.next() {
+                    Some((_, '"')) => output.push('"'),
+                    Some((_, '\\')) => output.push('\\'),
+                    Some((_, '/')) => output.push('/'),
+                    Some((_, 'b')) => output.push('\u{8}'),
+                    Some((_, 'f')) => output.push('\u{c}'),
+                    Some((_, 'n')) => output.push('\n'),
+                    Some((_, 'r')) => output.push('\r'),
+                    Some((_, 't')) => output.push('\t'),
+                    Some((_, 'u')) => output.push(parse_unicode_escape(&mut chars)?),
+                    // Escaped line terminators continue the string on the next line
+                    Some((_, '\n' | '\u{2028}' | '\u{2029}')) if options.allow_escaped_newlines => {},
+                    Some((_, '\r')) if options.allow_escaped_newlines => {
+                        if chars.clone().next().map(|(_, c)| c) == Some('\n') {
+                            chars.next();
+                        }
+                    },
+                    Some((_, c)) if options.allow_extended_escapes => {
+                        match c {
+                            'v' => output.push('\u{b}'),
+                            '0' => output.push('\0'),
+                            'x' => {
+                                let code = parse_hex_digits(&mut chars, 2)?;
+                                output.push(char::from_u32(code).unwrap());
+                            },
+                            c => output.push(c)
+                        }
+                    },
+                    Some(_) => {return Err(ParseError::MalformedString)},
+                    None => {return Err(ParseError::MalformedString)}
+                }
+            },
+            Some((_, c)) if c < ' ' && !options.allow_control_characters => {
+                return Err(ParseError::MalformedString)
+            },
+            Some((_, c)) => output.push(c),
+            None => {return Err(ParseError::MalformedString)}
+        }
+    }
+}
+
+fn parse_hex_digits(chars: &mut std::str::CharIndices, count: usize) -> Result<u32, ParseError> {
+    let mut code = 0;
+    for _ in 0..count {
+        match chars.next().and_then(|(_, c)| c.to_digit(16)) {
+            Some(d) => {code = code * 16 + d},
+            None => {return Err(ParseError::MalformedString)}
+        }
+    }
+    return Ok(code)
+}
+
+fn parse_unicode_escape(chars: &mut std::str::CharIndices) -> Result<char, ParseError> {
+    let high = parse_hex_digits(chars, 4)?;
+    if !(0xD800..0xDC00).contains(&high) {
+        return char::from_u32(high).ok_or(ParseError::MalformedString)
+    }
+    // High surrogate, must be followed by an escaped low surrogate
+    if chars.next().map(|(_, c)| c) != Some('\\') || chars.next().map(|(_, c)| c) != Some('u') {
+        return Err(ParseError::MalformedString)
+    }
+    let low = parse_hex_digits(chars, 4)?;
+    if !(0xDC00..0xE000).contains(&low) {
+        return Err(ParseError::MalformedString)
+    }
+    return char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).ok_or(ParseError::MalformedString)
 }
 
 pub fn parse_bool(data: &str) -> Result<(JsonType, &str), ParseError> {
-    if let Some(v) = data.get(..4) && v == "true" {
+    return parse_bool_with(data, ParserOptions::default())
+}
+
+/// Compares the start of `data` against a literal, ignoring ASCII case if enabled.
+fn starts_with_literal(data: &str, literal: &str, options: ParserOptions) -> bool {
+    match data.get(..literal.len()) {
+        Some(v) if options.case_insensitive_literals => v.eq_ignore_ascii_case(literal),
+        Some(v) => v == literal,
+        None => false
+    }
+}
+
+pub fn parse_bool_with(data: &str, options: ParserOptions) -> Result<(JsonType, &str), ParseError> {
+    if starts_with_literal(data, "true", options) {
         return Ok((JsonType::JsonBool(true), data.get(4..).unwrap()))
-    } else if let Some(v) = data.get(..5) && v == "false" {
+    } else if starts_with_literal(data, "false", options) {
         return Ok((JsonType::JsonBool(false), data.get(5..).unwrap()))
     } else {
         return Err(ParseError::MalformedBool)
@@ -99,75 +374,183 @@ pub fn parse_bool(data: &str) -> Result<(JsonType, &str), ParseError> {
 
 #[derive(PartialEq, Debug)]
 enum NumberState {
-    Parsing,
-    ParsingAfterDecimal
+    Sign,
+    Integer,
+    AfterDecimal,
+    Exponent
 }
 
 pub fn parse_number(data: &str) -> Result<(JsonType, &str), ParseError> {
-    let rest = data.trim_start();
-    let mut state = NumberState::Parsing;
-    let mut chars = rest.chars();
+    return parse_number_with(data, ParserOptions::default())
+}
+
+pub fn parse_number_with(data: &str, options: ParserOptions) -> Result<(JsonType, &str), ParseError> {
+    let rest = skip_whitespace(data, options)?;
+
+    // Named numbers and hexadecimal integers
+    if options.allow_nan_infinity || options.allow_hex_numbers {
+        let (sign, unsigned) = match rest.chars().next() {
+            Some('-') => (-1.0, rest.get(1..).unwrap()),
+            Some('+') if options.allow_plus_sign => (1.0, rest.get(1..).unwrap()),
+            _ => (1.0, rest)
+        };
+        if options.allow_nan_infinity {
+            if let Some(r) = unsigned.strip_prefix("Infinity") {
+                return end_number(sign * f64::INFINITY, r, options)
+            }
+            if let Some(r) = unsigned.strip_prefix("NaN") {
+                return end_number(f64::NAN, r, options)
+            }
+        }
+        if options.allow_hex_numbers && let Some(hex) = unsigned.strip_prefix("0x").or(unsigned.strip_prefix("0X")) {
+            let digits = hex.find(|c: char| !c.is_ascii_hexdigit()).unwrap_or(hex.len());
+            if digits == 0 {
+                return Err(ParseError::MalformedNumber)
+            }
+            let mut value = 0.0;
+            for c in hex.get(..digits).unwrap().chars() {
+                value = value * 16.0 + c.to_digit(16).unwrap() as f64;
+            }
+            return end_number(sign * value, hex.get(digits..).unwrap(), options)
+        }
+    }
+
+    let mut state = NumberState::Sign;
+    let mut chars = rest.chars().peekable();
     let mut i = 0;
+    let mut integer_digits = 0;
+    let mut fraction_digits = 0;
+    let mut exponent_digits = 0;
 
-    loop {
-        if let Some(c) = chars.next() {
-            match c {
-                '.' => {
-                    match state {
-                        NumberState::Parsing => {state = NumberState::ParsingAfterDecimal; i+=1}
-                        NumberState::ParsingAfterDecimal => {return Err(ParseError::MalformedNumber)}
-                    }
+    while let Some(&c) = chars.peek() {
+        match c {
+            '-' | '+' if state == NumberState::Sign => {
+                if c == '+' && !options.allow_plus_sign {
+                    return Err(ParseError::MalformedNumber)
                 }
-                '-' => {
-                    if i != 0 {
-                        return Err(ParseError::MalformedNumber)
-                    }
+                state = NumberState::Integer;
+            },
+            '.' if state == NumberState::Sign || state == NumberState::Integer => {
+                state = NumberState::AfterDecimal;
+            },
+            'e' | 'E' if state != NumberState::Exponent => {
+                state = NumberState::Exponent;
+                chars.next();
+                i += 1;
+                if let Some(&s) = chars.peek() && (s == '-' || s == '+') {
+                    chars.next();
                     i += 1;
                 }
-                'e' | 'E' => {panic!("Haven't implemented scientific notation")}
-                c if c.is_numeric() => {i += 1;},
-                ',' | ']' | '}' => {break},
-                c if c.is_whitespace() => {break},
-                _ => return Err(ParseError::MalformedNumber)
-            }
-        } else {
-            return Err(ParseError::UnexpectedEof)
+                continue;
+            },
+            c if is_digit(c, options) => {
+                match state {
+                    NumberState::Sign | NumberState::Integer => {
+                        // Leading zeros are not allowed
+                        if integer_digits == 1 && !options.allow_leading_zeros && rest.get(..i).unwrap().ends_with('0') {
+                            return Err(ParseError::MalformedNumber)
+                        }
+                        state = NumberState::Integer;
+                        integer_digits += 1;
+                    },
+                    NumberState::AfterDecimal => {fraction_digits += 1},
+                    NumberState::Exponent => {exponent_digits += 1}
+                }
+            },
+            _ => {break}
         }
+        chars.next();
+        i += c.len_utf8();
+    }
+
+    if state == NumberState::Exponent && exponent_digits == 0 {
+        return Err(ParseError::MalformedNumber)
+    }
+    let has_decimal = rest.get(..i).unwrap().contains('.');
+    if integer_digits == 0 && !(has_decimal && options.allow_leading_decimal_point) {
+        return Err(ParseError::MalformedNumber)
+    }
+    if has_decimal && fraction_digits == 0 && !(integer_digits > 0 && options.allow_trailing_decimal_point) {
+        return Err(ParseError::MalformedNumber)
+    }
+
+    let text = rest.get(..i).unwrap();
+    let value = match text.strip_suffix('.').unwrap_or(text).parse::<f64>() {
+        Ok(v) => v,
+        Err(_) => {return Err(ParseError::MalformedNumber)}
+    };
+    return end_number(value, rest.get(i..).unwrap(), options)
+}
+
+/// A number must be followed by a delimiter, whitespace or the end of input.
+fn end_number(value: f64, rest: &str, options: ParserOptions) -> Result<(JsonType, &str), ParseError> {
+    match rest.chars().next() {
+        None | Some(',' | ']' | '}') => {},
+        Some('/') if options.allow_comments => {},
+        Some(c) if is_whitespace(c, options) => {},
+        _ => {return Err(ParseError::MalformedNumber)}
     }
-    let ex = rest.get(..i);
-    let value = rest.get(..i).unwrap().parse::<f64>().unwrap();
-    return Ok((JsonType::JsonNumber(value), rest.get(i..).unwrap()))
+    return Ok((JsonType::JsonNumber(value), rest))
 }
 
 pub fn parse_null(data: &str) -> Result<(JsonType, &str), ParseError> {
-    if let Some(v) = data.get(..4) && v == "null" {
+    return parse_null_with(data, ParserOptions::default())
+}
+
+pub fn parse_null_with(data: &str, options: ParserOptions) -> Result<(JsonType, &str), ParseError> {
+    if starts_with_literal(data, "null", options) {
         return Ok((JsonType::JsonNull, data.get(4..).unwrap()))
     } else {
         return Err(ParseError::MalformedNull)
     }
 }
 
-pub fn parse_value(mut data: &str) -> Result<(JsonType, &str), ParseError> {
-    data = data.trim_start();
+pub fn parse_value(data: &str) -> Result<(JsonType, &str), ParseError> {
+    return parse_value_with(data, ParserOptions::default())
+}
+
+pub fn parse_value_with(mut data: &str, options: ParserOptions) -> Result<(JsonType, &str), ParseError> {
+    data = skip_whitespace(data, options)?;
     if let Some(c) = data.chars().next() {
         match c {
             '"' => {
-                return parse_string(data)
+                return parse_string_with(data, options)
+            },
+            '\'' if options.allow_single_quotes => {
+                return parse_string_with(data, options)
             },
             '{' => {
-                return parse_object(data);
+                return parse_object_with(data, options.nested()?);
             },
             '[' => {
-                return parse_array(data);
+                return parse_array_with(data, options.nested()?);
+            },
+            't' | 'f' => {
+                return parse_bool_with(data, options);
+            },
+            'T' | 'F' if !options.strict || options.case_insensitive_literals => {
+                return parse_bool_with(data, options);
             },
-            'T' | 't' | 'F' | 'f' => {
-                return parse_bool(data);
+            'N' if options.allow_nan_infinity && data.starts_with("NaN") => {
+                return parse_number_with(data, options);
             },
             'n' => {
-                return parse_null(data);
+                return parse_null_with(data, options);
             },
-            c if c.is_numeric() || c == '-' => {
-                return parse_number(data);
+            'N' if options.case_insensitive_literals => {
+                return parse_null_with(data, options);
+            },
+            c if is_digit(c, options) || c == '-' => {
+                return parse_number_with(data, options);
+            },
+            '+' if options.allow_plus_sign => {
+                return parse_number_with(data, options);
+            },
+            '.' if options.allow_leading_decimal_point => {
+                return parse_number_with(data, options);
+            },
+            'I' if options.allow_nan_infinity => {
+                return parse_number_with(data, options);
             },
             _ => {
                 return Err(ParseError::UnexpectedChar { expected: "\",{,[,T,t,F,f,n,is_numeric".to_string(), found: c });
@@ -178,48 +561,73 @@ pub fn parse_value(mut data: &str) -> Result<(JsonType, &str), ParseError> {
     }
 }
 
+pub fn parse_document(data: &str) -> Result<JsonType, ParseError> {
+    return parse_document_with(data, ParserOptions::default())
+}
+
+/// Parses a complete JSON text: a single value with nothing but whitespace around it.
+pub fn parse_document_with(data: &str, options: ParserOptions) -> Result<JsonType, ParseError> {
+    let (value, rest) = parse_value_with(data, options)?;
+    match skip_whitespace(rest, options)?.chars().next() {
+        None => {return Ok(value)},
+        Some(c) => {
+            return Err(ParseError::UnexpectedChar { expected: "end of input".to_string(), found: c });
+        }
+    }
+}
+
 //const JSON_TOKENS : str = "[{}[],:\"]";
 
-#[derive(PartialEq, Debug)]
-enum ObjectState {
-    WaitingObject, // Whitespace until {
-    StartedObject, // Whitespace until "
-    CollectingKey, // Hold chars until "
-    EndKey, // Whitespace until :
-    WaitingValue, // Whitespace until ""
-    EndValue, // Immediately need a , or a }
-    Finished, // Done parsing
+/// Parses an unquoted key, which follows the ECMAScript identifier rules.
+pub(crate) fn parse_identifier(data: &str) -> Option<(String, &str)> {
+    let mut chars = data.char_indices();
+    match chars.next() {
+        Some((_, c)) if c.is_alphabetic() || c == '$' || c == '_' => {},
+        _ => {return None}
+    }
+    let end = chars
+        .find(|(_, c)| !(c.is_alphanumeric() || *c == '$' || *c == '_'))
+        .map(|(i, _)| i)
+        .unwrap_or(data.len());
+    return Some((data.get(..end).unwrap().to_string(), data.get(end..).unwrap()))
 }
 
 pub fn parse_object(data: &str) -> Result<(JsonType, &str), ParseError> {
+    return parse_object_with(data, ParserOptions::default())
+}
+
+pub fn parse_object_with(data: &str, options: ParserOptions) -> Result<(JsonType, &str), ParseError> {
     let mut output: HashMap<String, JsonType> = HashMap::new();
-    let mut rest = data.trim_start();
-    let mut chars = rest.chars();
-    match chars.next() {
+    let mut rest = skip_whitespace(data, options)?;
+    match rest.chars().next() {
         Some('{') => {},
         None => {return Err(ParseError::UnexpectedEof)},
         c => {
             return Err(ParseError::UnexpectedChar { expected: "{".to_string(), found: c.unwrap() });
         }
     }
-    match chars.next() {
-        Some('}') => {return Ok((JsonType::JsonObject(HashMap::new()), rest.get(2..).unwrap()))},
+    rest = skip_whitespace(rest.get(1..).unwrap(), options)?;
+    match rest.chars().next() {
+        Some('}') => {return Ok((JsonType::JsonObject(HashMap::new()), rest.get(1..).unwrap()))},
         None => {return Err(ParseError::UnexpectedEof)},
         _ => {}
     }
-    rest = rest.get(1..).unwrap();
-    rest = rest.trim_start();
 
     loop {
         // Get key
         let key;
-        match parse_string_raw(rest) {
-            Ok(r) => {(key, rest) = r;},
-            Err(e) => {return Err(e)}
+        match parse_identifier(rest) {
+            Some(r) if options.allow_unquoted_keys => {(key, rest) = r;},
+            _ => {
+                match parse_string_raw_with(rest, options) {
+                    Ok(r) => {(key, rest) = r;},
+                    Err(e) => {return Err(e)}
+                }
+            }
         }
 
         // Get :
-        rest = rest.trim_start();
+        rest = skip_whitespace(rest, options)?;
         match rest.chars().next() {
             Some(':') => {},
             Some(c) => {
@@ -231,14 +639,14 @@ pub fn parse_object(data: &str) -> Result<(JsonType, &str), ParseError> {
 
         // Get value
         let value;
-        match parse_value(rest) {
+        match parse_value_with(rest, options) {
             Ok(r) => {(value, rest) = r;},
             Err(e) => {return Err(e)}
         }
 
         output.insert(key, value);
 
-        rest = rest.trim_start();
+        rest = skip_whitespace(rest, options)?;
         // Check for end of array
         match rest.chars().next() {
             Some('}') => {break},
@@ -246,7 +654,13 @@ pub fn parse_object(data: &str) -> Result<(JsonType, &str), ParseError> {
             Some(c) => return Err(ParseError::UnexpectedChar { expected: "},,".to_string(), found: c}),
             None => return Err(ParseError::UnexpectedEof)
         }
+        rest = skip_whitespace(rest.get(1..).unwrap(), options)?;
+
+        // A single trailing comma may close the object
+        if options.allow_trailing_commas && rest.starts_with('}') {
+            break
+        }
     }
 
     return Ok((JsonType::JsonObject(output), rest.get(1..).unwrap()));
-}
\ No newline at end of file
+}
@@ -0,0 +1,407 @@
+//! JSON Patch (RFC 6902).
+//!
+//! A patch is an array of operations such as
+//! `{"op": "replace", "path": "/port", "value": 8080}`. `apply_patch` runs
+//! them in order and `diff` produces a patch that turns one value into another.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::cst::{self, NodeKind};
+use crate::parser::{JsonType, ParseError, ParserOptions};
+use crate::pointer::{PointerError, escape_token, parse_pointer};
+
+/// Why a patch could not be applied. Positions count operations from 0.
+#[derive(PartialEq, Debug, Clone)]
+pub enum PatchError {
+    /// The patch is not an array.
+    NotAnArray,
+    /// The operation at this position is not an object with a known `op` and
+    /// the members that operation needs.
+    InvalidOperation(usize),
+    /// A pointer of the operation at this position could not be followed.
+    Pointer(usize, PointerError),
+    /// The `test` operation at this position did not match.
+    TestFailed(usize),
+    /// The `move` operation at this position tries to move a value into itself.
+    MoveIntoChild(usize),
+    /// The patch text given to `apply_patch_text` is not JSON, with the byte
+    /// offset of the error.
+    Parse { error: ParseError, offset: usize }
+}
+
+fn member<'a>(operation: &'a HashMap<String, JsonType>, name: &str, index: usize) -> Result<&'a JsonType, PatchError> {
+    return operation.get(name).ok_or(PatchError::InvalidOperation(index))
+}
+
+fn pointer_member<'a>(operation: &'a HashMap<String, JsonType>, name: &str, index: usize) -> Result<&'a str, PatchError> {
+    match member(operation, name, index)? {
+        JsonType::JsonString(pointer) => return Ok(pointer),
+        _ => return Err(PatchError::InvalidOperation(index))
+    }
+}
+
+fn apply_operation(target: &mut JsonType, operation: &JsonType, index: usize) -> Result<(), PatchError> {
+    let JsonType::JsonObject(operation) = operation else {
+        return Err(PatchError::InvalidOperation(index))
+    };
+    let JsonType::JsonString(op) = member(operation, "op", index)? else {
+        return Err(PatchError::InvalidOperation(index))
+    };
+    let path = pointer_member(operation, "path", index)?;
+    let pointer_error = |e| PatchError::Pointer(index, e);
+
+    match op.as_str() {
+        "add" => {
+            let value = member(operation, "value", index)?.clone();
+            target.pointer_insert(path, value).map_err(pointer_error)?;
+        },
+        "remove" => {
+            target.pointer_remove(path).map_err(pointer_error)?;
+        },
+        "replace" => {
+            let value = member(operation, "value", index)?.clone();
+            *target.try_pointer_mut(path).map_err(pointer_error)? = value;
+        },
+        "move" => {
+            let from = pointer_member(operation, "from", index)?;
+            let from_tokens = parse_pointer(from).map_err(pointer_error)?;
+            let path_tokens = parse_pointer(path).map_err(pointer_error)?;
+            if path_tokens.len() > from_tokens.len() && path_tokens.starts_with(&from_tokens) {
+                return Err(PatchError::MoveIntoChild(index))
+            }
+            if from_tokens == path_tokens {
+                // Still has to exist
+                target.try_pointer(from).map_err(pointer_error)?;
+                return Ok(())
+            }
+            let value = target.pointer_remove(from).map_err(pointer_error)?;
+            target.pointer_insert(path, value).map_err(pointer_error)?;
+        },
+        "copy" => {
+            let from = pointer_member(operation, "from", index)?;
+            let value = target.try_pointer(from).map_err(pointer_error)?.clone();
+            target.pointer_insert(path, value).map_err(pointer_error)?;
+        },
+        "test" => {
+            let value = member(operation, "value", index)?;
+            if target.try_pointer(path).map_err(pointer_error)? != value {
+                return Err(PatchError::TestFailed(index))
+            }
+        },
+        _ => {return Err(PatchError::InvalidOperation(index))}
+    }
+    return Ok(())
+}
+
+/// Applies every operation of `patch` to `target`.
+///
+/// Either the whole patch succeeds or `target` is left exactly as it was.
+pub fn apply_patch(target: &mut JsonType, patch: &JsonType) -> Result<(), PatchError> {
+    let JsonType::JsonArray(operations) = patch else {
+        return Err(PatchError::NotAnArray)
+    };
+    // Work on a copy, so a failing operation cannot leave half a patch behind
+    let mut output = target.clone();
+    for (index, operation) in operations.iter().enumerate() {
+        apply_operation(&mut output, operation, index)?;
+    }
+    *target = output;
+    return Ok(())
+}
+
+/// `apply_patch` for a patch that is still text. Unlike a parsed `JsonType`
+/// the text shows members given twice, and an operation with one, such as the
+/// two `op`s of RFC 6902 A.13, is an `InvalidOperation` instead of silently
+/// using the last.
+pub fn apply_patch_text(target: &mut JsonType, patch: &str) -> Result<(), PatchError> {
+    return apply_patch_text_with(target, patch, ParserOptions::default())
+}
+
+pub fn apply_patch_text_with(target: &mut JsonType, patch: &str, options: ParserOptions) -> Result<(), PatchError> {
+    let root = cst::parse_with(patch, options).map_err(|(error, offset)| PatchError::Parse { error, offset })?;
+    if let NodeKind::Array { elements, .. } = &root.kind {
+        for (index, element) in elements.iter().enumerate() {
+            if let NodeKind::Object { members, .. } = &element.kind {
+                let mut names = HashSet::new();
+                if !members.iter().all(|member| names.insert(member.name())) {
+                    return Err(PatchError::InvalidOperation(index))
+                }
+            }
+        }
+    }
+    return apply_patch(target, &root.to_json())
+}
+
+fn operation(op: &str, path: &str, value: Option<&JsonType>) -> JsonType {
+    let mut output = HashMap::new();
+    output.insert("op".to_string(), JsonType::JsonString(op.to_string()));
+    output.insert("path".to_string(), JsonType::JsonString(path.to_string()));
+    if let Some(value) = value {
+        output.insert("value".to_string(), value.clone());
+    }
+    return JsonType::JsonObject(output)
+}
+
+/// One step of turning one array into another.
+pub(crate) enum Edit {
+    Keep,
+    Replace(usize),
+    Delete,
+    Insert(usize)
+}
+
+/// Largest number of element pairs, after the common start and end are cut
+/// off, for which `edit_script` searches the shortest script. Its table needs
+/// this many words of memory.
+const MAX_EDIT_CELLS: usize = 1 << 20;
+
+/// A sequence of insertions, deletions and replacements turning `a` into `b`.
+///
+/// Elements equal at the start and end are kept. What lies between gets the
+/// shortest script, i.e. the edit distance, unless both parts are so long that
+/// the search would be too expensive. The elements are then simply paired by
+/// position.
+pub(crate) fn edit_script(a: &[JsonType], b: &[JsonType]) -> Vec<Edit> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..].iter().rev().zip(b[prefix..].iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a_middle, b_middle) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut output: Vec<Edit> = (0..prefix).map(|_| Edit::Keep).collect();
+    if a_middle.len().saturating_mul(b_middle.len()) <= MAX_EDIT_CELLS {
+        shortest_script(a_middle, b_middle, prefix, &mut output);
+    } else {
+        let paired = a_middle.len().min(b_middle.len());
+        output.extend((0..paired).map(|j| Edit::Replace(prefix + j)));
+        output.extend((paired..a_middle.len()).map(|_| Edit::Delete));
+        output.extend((paired..b_middle.len()).map(|j| Edit::Insert(prefix + j)));
+    }
+    output.extend((0..suffix).map(|_| Edit::Keep));
+    return output
+}
+
+/// The shortest script for `a` and `b`, which start at `offset` in their arrays.
+fn shortest_script(a: &[JsonType], b: &[JsonType], offset: usize, output: &mut Vec<Edit>) {
+    // costs[i][j] is the number of edits needed to turn a[i..] into b[j..]
+    let mut costs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..=a.len()).rev() {
+        for j in (0..=b.len()).rev() {
+            costs[i][j] = if i == a.len() {
+                b.len() - j
+            } else if j == b.len() {
+                a.len() - i
+            } else if a[i] == b[j] {
+                costs[i + 1][j + 1]
+            } else {
+                1 + costs[i + 1][j + 1].min(costs[i + 1][j]).min(costs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] && costs[i][j] == costs[i + 1][j + 1] {
+            output.push(Edit::Keep);
+            i += 1;
+            j += 1;
+        } else if i < a.len() && j < b.len() && costs[i][j] == 1 + costs[i + 1][j + 1] {
+            output.push(Edit::Replace(offset + j));
+            i += 1;
+            j += 1;
+        } else if i < a.len() && costs[i][j] == 1 + costs[i + 1][j] {
+            output.push(Edit::Delete);
+            i += 1;
+        } else {
+            output.push(Edit::Insert(offset + j));
+            j += 1;
+        }
+    }
+}
+
+fn diff_arrays(a: &[JsonType], b: &[JsonType], path: &str, output: &mut Vec<JsonType>) {
+    // Position in the array as it looks after the operations emitted so far
+    let mut position = 0;
+    let mut old = 0;
+    for edit in edit_script(a, b) {
+        match edit {
+            Edit::Keep => {
+                position += 1;
+                old += 1;
+            },
+            Edit::Replace(j) => {
+                diff_into(&a[old], &b[j], &format!("{path}/{position}"), output);
+                position += 1;
+                old += 1;
+            },
+            Edit::Delete => {
+                output.push(operation("remove", &format!("{path}/{position}"), None));
+                old += 1;
+            },
+            Edit::Insert(j) => {
+                output.push(operation("add", &format!("{path}/{position}"), Some(&b[j])));
+                position += 1;
+            }
+        }
+    }
+}
+
+fn diff_into(a: &JsonType, b: &JsonType, path: &str, output: &mut Vec<JsonType>) {
+    if a == b {
+        return
+    }
+    match (a, b) {
+        (JsonType::JsonObject(a), JsonType::JsonObject(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))).collect();
+            keys.sort();
+            for key in keys {
+                let child = format!("{path}/{}", escape_token(key));
+                match (a.get(key), b.get(key)) {
+                    (Some(old), Some(new)) => diff_into(old, new, &child, output),
+                    (Some(_), None) => output.push(operation("remove", &child, None)),
+                    (None, Some(new)) => output.push(operation("add", &child, Some(new))),
+                    (None, None) => {}
+                }
+            }
+        },
+        (JsonType::JsonArray(a), JsonType::JsonArray(b)) => diff_arrays(a, b, path, output),
+        _ => output.push(operation("replace", path, Some(b)))
+    }
+}
+
+/// Produces a patch that turns `a` into `b`.
+///
+/// Equal parts are left alone, objects are compared key by key and arrays
+/// are aligned with the fewest insertions, deletions and replacements, so the
+/// patch only touches what changed. It uses `add`, `remove` and `replace`.
+pub fn diff(a: &JsonType, b: &JsonType) -> JsonType {
+    let mut output = Vec::new();
+    diff_into(a, b, "", &mut output);
+    return JsonType::JsonArray(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_document;
+
+    fn json(data: &str) -> JsonType {
+        return parse_document(data).unwrap()
+    }
+
+    fn check(doc: &str, patch: &str, expected: &str) {
+        let mut target = json(doc);
+        apply_patch(&mut target, &json(patch)).unwrap();
+        assert_eq!(target, json(expected), "{patch}");
+    }
+
+    fn check_error(doc: &str, patch: &str, error: PatchError) {
+        let mut target = json(doc);
+        assert_eq!(apply_patch(&mut target, &json(patch)), Err(error), "{patch}");
+        assert_eq!(target, json(doc));
+    }
+
+    #[test]
+    fn rfc_appendix_a() {
+        // A.1 to A.16 of RFC 6902
+        check(r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz", "value": "qux"}]"#, r#"{"baz": "qux", "foo": "bar"}"#);
+        check(r#"{"foo": ["bar", "baz"]}"#, r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#, r#"{"foo": ["bar", "qux", "baz"]}"#);
+        check(r#"{"baz": "qux", "foo": "bar"}"#, r#"[{"op": "remove", "path": "/baz"}]"#, r#"{"foo": "bar"}"#);
+        check(r#"{"foo": ["bar", "qux", "baz"]}"#, r#"[{"op": "remove", "path": "/foo/1"}]"#, r#"{"foo": ["bar", "baz"]}"#);
+        check(r#"{"baz": "qux", "foo": "bar"}"#, r#"[{"op": "replace", "path": "/baz", "value": "boo"}]"#, r#"{"baz": "boo", "foo": "bar"}"#);
+        check(
+            r#"{"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}"#,
+            r#"[{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]"#,
+            r#"{"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}"#
+        );
+        check(r#"{"foo": ["all", "grass", "cows", "eat"]}"#, r#"[{"op": "move", "from": "/foo/1", "path": "/foo/3"}]"#, r#"{"foo": ["all", "cows", "eat", "grass"]}"#);
+        check(
+            r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+            r#"[{"op": "test", "path": "/baz", "value": "qux"}, {"op": "test", "path": "/foo/1", "value": 2}]"#,
+            r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#
+        );
+        check_error(r#"{"baz": "qux"}"#, r#"[{"op": "test", "path": "/baz", "value": "bar"}]"#, PatchError::TestFailed(0));
+        check(r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/child", "value": {"grandchild": {}}}]"#, r#"{"foo": "bar", "child": {"grandchild": {}}}"#);
+        check(r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz", "value": "qux", "xyz": 123}]"#, r#"{"foo": "bar", "baz": "qux"}"#);
+        check_error(r#"{"foo": "bar"}"#, r#"[{"op": "add", "path": "/baz/bat", "value": "qux"}]"#, PatchError::Pointer(0, PointerError::NotFound(0)));
+        // A.13, duplicate members are only visible in the text
+        let mut target = json(r#"{"foo": "bar"}"#);
+        let patch = r#"[{"op": "add", "path": "/baz", "value": "qux", "op": "remove"}]"#;
+        assert_eq!(apply_patch_text(&mut target, patch), Err(PatchError::InvalidOperation(0)));
+        assert_eq!(target, json(r#"{"foo": "bar"}"#));
+        check(r#"{"/": 9, "~1": 10}"#, r#"[{"op": "test", "path": "/~01", "value": 10}]"#, r#"{"/": 9, "~1": 10}"#);
+        check_error(r#"{"/": 9, "~1": 10}"#, r#"[{"op": "test", "path": "/~01", "value": "10"}]"#, PatchError::TestFailed(0));
+        check(r#"{"foo": ["bar"]}"#, r#"[{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]"#, r#"{"foo": ["bar", ["abc", "def"]]}"#);
+    }
+
+    #[test]
+    fn errors_roll_back() {
+        let doc = r#"{"a": [1, 2], "b": {"c": 1}}"#;
+        check_error(doc, r#"[{"op": "remove", "path": "/a/0"}, {"op": "remove", "path": "/x"}]"#, PatchError::Pointer(1, PointerError::NotFound(0)));
+        check_error(doc, r#"[{"op": "copy", "from": "/a", "path": "/z"}, {"op": "frobnicate", "path": "/a"}]"#, PatchError::InvalidOperation(1));
+        check_error(doc, r#"[{"op": "add", "path": "/z"}]"#, PatchError::InvalidOperation(0));
+        check_error(doc, r#"[{"op": "replace", "path": "/z", "value": 1}]"#, PatchError::Pointer(0, PointerError::NotFound(0)));
+        check_error(doc, r#"[{"op": "move", "from": "/b", "path": "/b/c/d"}]"#, PatchError::MoveIntoChild(0));
+        check_error(doc, r#"[{"op": "add", "path": "/a/01", "value": 0}]"#, PatchError::Pointer(0, PointerError::InvalidIndex(1)));
+        check_error(doc, r#"{"op": "remove", "path": "/a"}"#, PatchError::NotAnArray);
+        check(doc, r#"[{"op": "copy", "from": "/b", "path": "/a/-"}, {"op": "move", "from": "/b", "path": "/b"}]"#, r#"{"a": [1, 2, {"c": 1}], "b": {"c": 1}}"#);
+        check(doc, r#"[{"op": "replace", "path": "", "value": 5}]"#, "5");
+    }
+
+    #[test]
+    fn patch_text() {
+        let mut target = json(r#"{"a": 1}"#);
+        apply_patch_text(&mut target, r#"[{"op": "add", "path": "/b", "value": {"c": 1, "c": 2}}]"#).unwrap();
+        assert_eq!(target, json(r#"{"a": 1, "b": {"c": 2}}"#));
+        assert_eq!(apply_patch_text(&mut target, "[{]"), Err(PatchError::Parse { error: ParseError::UnexpectedChar { expected: "\"".to_string(), found: ']' }, offset: 2 }));
+        let json5 = crate::parser::Dialect::Json5.into();
+        apply_patch_text_with(&mut target, "[{op: 'remove', path: '/b'},]", json5).unwrap();
+        assert_eq!(target, json(r#"{"a": 1}"#));
+    }
+
+    fn round_trip(a: &str, b: &str) -> usize {
+        let (a, b) = (json(a), json(b));
+        let patch = diff(&a, &b);
+        let mut target = a.clone();
+        apply_patch(&mut target, &patch).unwrap();
+        assert_eq!(target, b, "{patch}");
+        match patch {
+            JsonType::JsonArray(operations) => return operations.len(),
+            _ => unreachable!()
+        }
+    }
+
+    #[test]
+    fn diff_produces_small_patches() {
+        assert_eq!(round_trip(r#"{"a": 1}"#, r#"{"a": 1}"#), 0);
+        assert_eq!(round_trip(r#"{"a": 1, "b": {"c": [1, 2]}}"#, r#"{"a": 1, "b": {"c": [1, 3]}, "d": null}"#), 2);
+        assert_eq!(round_trip("[1, 2, 3, 4, 5]", "[1, 3, 4, 5]"), 1);
+        assert_eq!(round_trip("[1, 2, 3]", "[0, 1, 2, 3, 4]"), 2);
+        assert_eq!(round_trip("[1, 2, 3]", "[3, 2, 1]"), 2);
+        assert_eq!(round_trip(r#"[{"id": 1, "x": 1}, {"id": 2}]"#, r#"[{"id": 1, "x": 2}, {"id": 2}]"#), 1);
+        assert_eq!(round_trip("[1, 2, 3, 4]", "[]"), 4);
+        assert_eq!(round_trip(r#"{"a~/b": [true]}"#, r#"{"a~/b": "x"}"#), 1);
+        assert_eq!(round_trip("[1]", "{}"), 1);
+
+        let patch = diff(&json(r#"{"a": 1, "b": [1, 2]}"#), &json(r#"{"b": [2]}"#));
+        assert_eq!(patch, json(r#"[{"op": "remove", "path": "/a"}, {"op": "remove", "path": "/b/0"}]"#));
+    }
+
+    #[test]
+    fn large_arrays() {
+        // Too many pairs for the shortest script, the middle is paired by position
+        let a: Vec<JsonType> = (0..20_000).map(|i| JsonType::JsonNumber(i as f64)).collect();
+        let mut b: Vec<JsonType> = a.iter().rev().cloned().collect();
+        b.push(JsonType::JsonNull);
+        let (a, b) = (JsonType::JsonArray(a), JsonType::JsonArray(b));
+        let mut target = a.clone();
+        apply_patch(&mut target, &diff(&a, &b)).unwrap();
+        assert_eq!(target, b);
+
+        // Common ends are cut off first, so one change in a long array stays small
+        let long: Vec<JsonType> = (0..50_000).map(|i| JsonType::JsonNumber(i as f64)).collect();
+        let mut changed = long.clone();
+        changed[25_000] = JsonType::JsonNull;
+        changed.insert(30_000, JsonType::JsonBool(true));
+        let patch = diff(&JsonType::JsonArray(long), &JsonType::JsonArray(changed));
+        assert_eq!(patch, json(r#"[{"op": "replace", "path": "/25000", "value": null}, {"op": "add", "path": "/30000", "value": true}]"#));
+    }
+}
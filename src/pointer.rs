@@ -0,0 +1,253 @@
+//! JSON Pointer (RFC 6901) lookups and edits on `JsonType`.
+//!
+//! A pointer is either empty, meaning the whole document, or a sequence of
+//! `/`-prefixed reference tokens such as `/users/0/name`. Inside a token `~1`
+//! stands for `/` and `~0` for `~`.
+
+use crate::parser::JsonType;
+
+/// Why a pointer could not be used. Segment positions count from 0.
+#[derive(PartialEq, Debug, Clone)]
+pub enum PointerError {
+    /// The pointer is not empty and does not start with `/`.
+    MissingSlash,
+    /// The segment at this position has a `~` that is not followed by `0` or `1`.
+    InvalidEscape(usize),
+    /// The segment at this position names a key that does not exist.
+    NotFound(usize),
+    /// The segment at this position tries to look inside a string, number, bool or null.
+    NotAContainer(usize),
+    /// The segment at this position is not a valid index for its array: not a
+    /// number, a number with leading zeros, out of bounds, or `-` where an
+    /// existing element is needed.
+    InvalidIndex(usize),
+    /// The operation needs a parent value, but the pointer is empty.
+    EmptyPointer
+}
+
+/// Splits a pointer into its unescaped reference tokens.
+pub fn parse_pointer(pointer: &str) -> Result<Vec<String>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new())
+    }
+    let Some(pointer) = pointer.strip_prefix('/') else {
+        return Err(PointerError::MissingSlash)
+    };
+    let mut tokens = Vec::new();
+    for (segment, raw) in pointer.split('/').enumerate() {
+        let mut token = String::with_capacity(raw.len());
+        let mut chars = raw.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '~' => {
+                    match chars.next() {
+                        Some('0') => token.push('~'),
+                        Some('1') => token.push('/'),
+                        _ => {return Err(PointerError::InvalidEscape(segment))}
+                    }
+                },
+                c => token.push(c)
+            }
+        }
+        tokens.push(token);
+    }
+    return Ok(tokens)
+}
+
+/// Escapes a key for use as a reference token.
+pub fn escape_token(key: &str) -> String {
+    return key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Builds a pointer from unescaped tokens, the inverse of `parse_pointer`.
+pub fn to_pointer(tokens: &[&str]) -> String {
+    let mut output = String::new();
+    for token in tokens {
+        output.push('/');
+        output.push_str(&escape_token(token));
+    }
+    return output
+}
+
+/// Reads an array index, which must be `0` or a number without leading zeros.
+fn parse_index(token: &str, segment: usize) -> Result<usize, PointerError> {
+    if token.is_empty() || (token.len() > 1 && token.starts_with('0')) || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(PointerError::InvalidIndex(segment))
+    }
+    return token.parse::<usize>().map_err(|_| PointerError::InvalidIndex(segment))
+}
+
+fn step<'a>(value: &'a JsonType, token: &str, segment: usize) -> Result<&'a JsonType, PointerError> {
+    match value {
+        JsonType::JsonObject(map) => return map.get(token).ok_or(PointerError::NotFound(segment)),
+        JsonType::JsonArray(values) => {
+            let index = parse_index(token, segment)?;
+            return values.get(index).ok_or(PointerError::InvalidIndex(segment))
+        },
+        _ => return Err(PointerError::NotAContainer(segment))
+    }
+}
+
+fn step_mut<'a>(value: &'a mut JsonType, token: &str, segment: usize) -> Result<&'a mut JsonType, PointerError> {
+    match value {
+        JsonType::JsonObject(map) => return map.get_mut(token).ok_or(PointerError::NotFound(segment)),
+        JsonType::JsonArray(values) => {
+            let index = parse_index(token, segment)?;
+            return values.get_mut(index).ok_or(PointerError::InvalidIndex(segment))
+        },
+        _ => return Err(PointerError::NotAContainer(segment))
+    }
+}
+
+fn resolve_mut<'a>(value: &'a mut JsonType, tokens: &[String]) -> Result<&'a mut JsonType, PointerError> {
+    let mut current = value;
+    for (segment, token) in tokens.iter().enumerate() {
+        current = step_mut(current, token, segment)?;
+    }
+    return Ok(current)
+}
+
+impl JsonType {
+    /// Looks up the value at `pointer`, e.g. `"/users/0/name"`.
+    pub fn pointer(&self, pointer: &str) -> Option<&JsonType> {
+        return self.try_pointer(pointer).ok()
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut JsonType> {
+        return self.try_pointer_mut(pointer).ok()
+    }
+
+    /// Like `pointer`, but says which segment could not be followed.
+    pub fn try_pointer(&self, pointer: &str) -> Result<&JsonType, PointerError> {
+        let mut current = self;
+        for (segment, token) in parse_pointer(pointer)?.iter().enumerate() {
+            current = step(current, token, segment)?;
+        }
+        return Ok(current)
+    }
+
+    pub fn try_pointer_mut(&mut self, pointer: &str) -> Result<&mut JsonType, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        return resolve_mut(self, &tokens)
+    }
+
+    /// Adds `value` at `pointer` and returns the value it replaced, if any.
+    ///
+    /// The parent must exist. In an object the last token is set as a key, in
+    /// an array the value is inserted before the given index, which may be
+    /// the length of the array, and `-` appends. The empty pointer replaces
+    /// the whole value.
+    pub fn pointer_insert(&mut self, pointer: &str, value: JsonType) -> Result<Option<JsonType>, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)))
+        };
+        let segment = parents.len();
+        match resolve_mut(self, parents)? {
+            JsonType::JsonObject(map) => return Ok(map.insert(last.clone(), value)),
+            JsonType::JsonArray(values) => {
+                let index = match last.as_str() {
+                    "-" => values.len(),
+                    token => parse_index(token, segment)?
+                };
+                if index > values.len() {
+                    return Err(PointerError::InvalidIndex(segment))
+                }
+                values.insert(index, value);
+                return Ok(None)
+            },
+            _ => return Err(PointerError::NotAContainer(segment))
+        }
+    }
+
+    /// Removes and returns the value at `pointer`. Later array elements shift down.
+    pub fn pointer_remove(&mut self, pointer: &str) -> Result<JsonType, PointerError> {
+        let tokens = parse_pointer(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Err(PointerError::EmptyPointer)
+        };
+        let segment = parents.len();
+        match resolve_mut(self, parents)? {
+            JsonType::JsonObject(map) => return map.remove(last).ok_or(PointerError::NotFound(segment)),
+            JsonType::JsonArray(values) => {
+                let index = parse_index(last, segment)?;
+                if index >= values.len() {
+                    return Err(PointerError::InvalidIndex(segment))
+                }
+                return Ok(values.remove(index))
+            },
+            _ => return Err(PointerError::NotAContainer(segment))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_document;
+
+    // The example document from section 5 of RFC 6901
+    const RFC_EXAMPLE: &str = r#"{
+        "foo": ["bar", "baz"],
+        "": 0,
+        "a/b": 1,
+        "c%d": 2,
+        "e^f": 3,
+        "g|h": 4,
+        "i\\j": 5,
+        "k\"l": 6,
+        " ": 7,
+        "m~n": 8
+    }"#;
+
+    #[test]
+    fn rfc_examples() {
+        let doc = parse_document(RFC_EXAMPLE).unwrap();
+        assert_eq!(doc.pointer(""), Some(&doc));
+        assert_eq!(doc.pointer("/foo"), Some(&parse_document(r#"["bar", "baz"]"#).unwrap()));
+        assert_eq!(doc.pointer("/foo/0"), Some(&JsonType::JsonString("bar".to_string())));
+        let expected = [("/", 0.0), ("/a~1b", 1.0), ("/c%d", 2.0), ("/e^f", 3.0), ("/g|h", 4.0), ("/i\\j", 5.0), ("/k\"l", 6.0), ("/ ", 7.0), ("/m~0n", 8.0)];
+        for (pointer, number) in expected {
+            assert_eq!(doc.pointer(pointer), Some(&JsonType::JsonNumber(number)), "{pointer}");
+        }
+    }
+
+    #[test]
+    fn reports_failing_segment() {
+        let doc = parse_document(r#"{"users": [{"name": "Alice"}], "n": 1}"#).unwrap();
+        assert_eq!(doc.try_pointer("users"), Err(PointerError::MissingSlash));
+        assert_eq!(doc.try_pointer("/users/0/name/x"), Err(PointerError::NotAContainer(3)));
+        assert_eq!(doc.try_pointer("/users/0/age"), Err(PointerError::NotFound(2)));
+        assert_eq!(doc.try_pointer("/users/01"), Err(PointerError::InvalidIndex(1)));
+        assert_eq!(doc.try_pointer("/users/1"), Err(PointerError::InvalidIndex(1)));
+        assert_eq!(doc.try_pointer("/users/-"), Err(PointerError::InvalidIndex(1)));
+        assert_eq!(doc.try_pointer("/users/+0"), Err(PointerError::InvalidIndex(1)));
+        assert_eq!(doc.try_pointer("/n~2"), Err(PointerError::InvalidEscape(0)));
+        assert_eq!(doc.try_pointer("/n~"), Err(PointerError::InvalidEscape(0)));
+        assert_eq!(doc.pointer("/users/0/name"), Some(&JsonType::JsonString("Alice".to_string())));
+    }
+
+    #[test]
+    fn mutation() {
+        let mut doc = parse_document(r#"{"list": [1, 2], "obj": {}}"#).unwrap();
+        *doc.pointer_mut("/list/0").unwrap() = JsonType::JsonBool(true);
+        assert_eq!(doc.pointer_insert("/list/-", JsonType::JsonNumber(3.0)), Ok(None));
+        assert_eq!(doc.pointer_insert("/list/0", JsonType::JsonNull), Ok(None));
+        assert_eq!(doc.pointer_insert("/list/5", JsonType::JsonNull), Err(PointerError::InvalidIndex(1)));
+        assert_eq!(doc.pointer_insert("/obj/a~1b", JsonType::JsonNumber(1.0)), Ok(None));
+        assert_eq!(doc.pointer_insert("/obj/a~1b", JsonType::JsonNumber(2.0)), Ok(Some(JsonType::JsonNumber(1.0))));
+        assert_eq!(doc.pointer_insert("/missing/a", JsonType::JsonNull), Err(PointerError::NotFound(0)));
+        assert_eq!(doc, parse_document(r#"{"list": [null, true, 2, 3], "obj": {"a/b": 2}}"#).unwrap());
+
+        assert_eq!(doc.pointer_remove("/list/1"), Ok(JsonType::JsonBool(true)));
+        assert_eq!(doc.pointer_remove("/list/-"), Err(PointerError::InvalidIndex(1)));
+        assert_eq!(doc.pointer_remove("/obj/x"), Err(PointerError::NotFound(1)));
+        assert_eq!(doc.pointer_remove(""), Err(PointerError::EmptyPointer));
+        assert_eq!(doc.pointer_remove("/obj"), Ok(parse_document(r#"{"a/b": 2}"#).unwrap()));
+        assert_eq!(doc, parse_document(r#"{"list": [null, 2, 3]}"#).unwrap());
+
+        assert!(doc.pointer_insert("", JsonType::JsonNull).unwrap().is_some());
+        assert_eq!(doc, JsonType::JsonNull);
+        assert_eq!(to_pointer(&["a/b", "m~n", "0"]), "/a~1b/m~0n/0");
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use crate::parser::{
+    JsonType,
+    ParseError,
+    ParserOptions,
+    parse_identifier,
+    parse_string_raw_with,
+    parse_value_with,
+    skip_whitespace,
+};
+
+/// Parses `data` without stopping at the first error.
+///
+/// Every error is recorded, the parser then skips ahead to the next `,`, `]` or
+/// `}` and carries on. Values that could not be parsed are replaced by a
+/// `JsonNull` placeholder, so the returned value is a best-effort picture of the
+/// document. An empty error list means the input was valid.
+pub fn parse_recovering(data: &str) -> (JsonType, Vec<ParseError>) {
+    return parse_recovering_with(data, ParserOptions::default())
+}
+
+pub fn parse_recovering_with(data: &str, options: ParserOptions) -> (JsonType, Vec<ParseError>) {
+    let mut errors = Vec::new();
+    let (value, rest) = recover_value(data, options, &mut errors);
+    match skip_whitespace(rest, options) {
+        Ok(rest) => {
+            if let Some(c) = rest.chars().next() {
+                errors.push(ParseError::UnexpectedChar { expected: "end of input".to_string(), found: c });
+            }
+        },
+        Err(e) => errors.push(e)
+    }
+    return (value, errors)
+}
+
+/// Skips to the next `,`, `]` or `}`, or to the end of the input.
+fn synchronize(data: &str) -> &str {
+    match data.find([',', ']', '}']) {
+        Some(i) => return data.get(i..).unwrap(),
+        None => return ""
+    }
+}
+
+/// Whether a value could start at `c`, used to tell a missing separator from junk.
+fn starts_value(c: char) -> bool {
+    return matches!(c, '"' | '\'' | '{' | '[' | '-' | '+' | '.') || c.is_alphanumeric()
+}
+
+fn skip<'a>(data: &'a str, options: ParserOptions, errors: &mut Vec<ParseError>) -> &'a str {
+    match skip_whitespace(data, options) {
+        Ok(rest) => return rest,
+        Err(e) => {
+            // Only an unterminated block comment fails here, it runs to the end
+            errors.push(e);
+            return ""
+        }
+    }
+}
+
+fn recover_value<'a>(data: &'a str, options: ParserOptions, errors: &mut Vec<ParseError>) -> (JsonType, &'a str) {
+    let rest = skip(data, options, errors);
+    match rest.chars().next() {
+        Some('[') | Some('{') => {
+            let nested = match options.nested() {
+                Ok(nested) => nested,
+                Err(e) => {
+                    errors.push(e);
+                    return (JsonType::JsonNull, synchronize(rest.get(1..).unwrap()))
+                }
+            };
+            if rest.starts_with('[') {
+                return recover_array(rest, nested, errors)
+            }
+            return recover_object(rest, nested, errors)
+        },
+        None => {
+            errors.push(ParseError::UnexpectedEof);
+            return (JsonType::JsonNull, rest)
+        },
+        Some(_) => {
+            // Scalars never nest, so the regular parser can handle them
+            match parse_value_with(rest, options) {
+                Ok(r) => return r,
+                Err(e) => {
+                    errors.push(e);
+                    return (JsonType::JsonNull, synchronize(rest))
+                }
+            }
+        }
+    }
+}
+
+fn recover_array<'a>(data: &'a str, options: ParserOptions, errors: &mut Vec<ParseError>) -> (JsonType, &'a str) {
+    let mut values = Vec::new();
+    let mut rest = skip(data.get(1..).unwrap(), options, errors);
+    if let Some(r) = rest.strip_prefix(']') {
+        return (JsonType::JsonArray(values), r)
+    }
+
+    loop {
+        let value;
+        (value, rest) = recover_value(rest, options, errors);
+        values.push(value);
+
+        // Check if there is a new entry or if array has ended
+        rest = skip(rest, options, errors);
+        match rest.chars().next() {
+            Some(']') => {return (JsonType::JsonArray(values), rest.get(1..).unwrap())},
+            Some(',') => {rest = rest.get(1..).unwrap()},
+            None => {
+                errors.push(ParseError::UnexpectedEof);
+                return (JsonType::JsonArray(values), rest)
+            },
+            Some('}') => {
+                // Mismatched brace, leave it for the enclosing object
+                errors.push(ParseError::UnexpectedChar { expected: ",,]".to_string(), found: '}' });
+                return (JsonType::JsonArray(values), rest)
+            },
+            Some(c) if starts_value(c) => {
+                // Missing comma, carry on with the next value
+                errors.push(ParseError::UnexpectedChar { expected: ",,]".to_string(), found: c });
+                continue;
+            },
+            Some(c) => {
+                errors.push(ParseError::UnexpectedChar { expected: ",,]".to_string(), found: c });
+                rest = synchronize(rest.get(c.len_utf8()..).unwrap());
+                match rest.chars().next() {
+                    Some(',') => {rest = rest.get(1..).unwrap()},
+                    Some(']') => {return (JsonType::JsonArray(values), rest.get(1..).unwrap())},
+                    _ => {return (JsonType::JsonArray(values), rest)}
+                }
+            }
+        }
+
+        rest = skip(rest, options, errors);
+        if let Some(r) = rest.strip_prefix(']') {
+            if !options.allow_trailing_commas {
+                errors.push(ParseError::MalformedArray);
+            }
+            return (JsonType::JsonArray(values), r)
+        }
+    }
+}
+
+fn recover_object<'a>(data: &'a str, options: ParserOptions, errors: &mut Vec<ParseError>) -> (JsonType, &'a str) {
+    let mut output: HashMap<String, JsonType> = HashMap::new();
+    let mut rest = skip(data.get(1..).unwrap(), options, errors);
+    if let Some(r) = rest.strip_prefix('}') {
+        return (JsonType::JsonObject(output), r)
+    }
+
+    loop {
+        // Get key, or skip the whole entry if there is none
+        let key = match rest.chars().next() {
+            Some('"') => Some(parse_string_raw_with(rest, options)),
+            Some('\'') if options.allow_single_quotes => Some(parse_string_raw_with(rest, options)),
+            Some(c) => {
+                match parse_identifier(rest) {
+                    Some(r) => {
+                        // Keep a bare key even when it is not allowed
+                        if !options.allow_unquoted_keys {
+                            errors.push(ParseError::UnexpectedChar { expected: "\"".to_string(), found: c });
+                        }
+                        Some(Ok(r))
+                    },
+                    None => {
+                        errors.push(ParseError::UnexpectedChar { expected: "\"".to_string(), found: c });
+                        None
+                    }
+                }
+            },
+            None => None
+        };
+        let key = match key {
+            Some(Ok((key, r))) => {rest = r; Some(key)},
+            Some(Err(e)) => {errors.push(e); None},
+            None => None
+        };
+
+        if let Some(key) = key {
+            // Get :
+            rest = skip(rest, options, errors);
+            match rest.chars().next() {
+                Some(':') => {rest = rest.get(1..).unwrap()},
+                Some(c) => {
+                    errors.push(ParseError::UnexpectedChar { expected: ":".to_string(), found: c });
+                },
+                None => {}
+            }
+
+            // Get value
+            let value;
+            (value, rest) = recover_value(rest, options, errors);
+            output.insert(key, value);
+        } else {
+            rest = synchronize(rest);
+        }
+
+        rest = skip(rest, options, errors);
+        // Check for end of object
+        match rest.chars().next() {
+            Some('}') => {return (JsonType::JsonObject(output), rest.get(1..).unwrap())},
+            Some(',') => {rest = rest.get(1..).unwrap()},
+            None => {
+                errors.push(ParseError::UnexpectedEof);
+                return (JsonType::JsonObject(output), rest)
+            },
+            Some(']') => {
+                // Mismatched bracket, leave it for the enclosing array
+                errors.push(ParseError::UnexpectedChar { expected: "},,".to_string(), found: ']' });
+                return (JsonType::JsonObject(output), rest)
+            },
+            Some(c) if c == '"' => {
+                // Missing comma, carry on with the next entry
+                errors.push(ParseError::UnexpectedChar { expected: "},,".to_string(), found: c });
+                continue;
+            },
+            Some(c) => {
+                errors.push(ParseError::UnexpectedChar { expected: "},,".to_string(), found: c });
+                rest = synchronize(rest.get(c.len_utf8()..).unwrap());
+                match rest.chars().next() {
+                    Some(',') => {rest = rest.get(1..).unwrap()},
+                    Some('}') => {return (JsonType::JsonObject(output), rest.get(1..).unwrap())},
+                    _ => {return (JsonType::JsonObject(output), rest)}
+                }
+            }
+        }
+
+        rest = skip(rest, options, errors);
+        if let Some(r) = rest.strip_prefix('}') {
+            if !options.allow_trailing_commas {
+                errors.push(ParseError::UnexpectedChar { expected: "\"".to_string(), found: '}' });
+            }
+            return (JsonType::JsonObject(output), r)
+        }
+    }
+}
@@ -0,0 +1,552 @@
+//! A small regular expression engine.
+//!
+//! It covers I-Regexp (RFC 9485), the interoperable subset used by JSONPath,
+//! and the common parts of ECMAScript syntax used by JSON Schema patterns:
+//! literals, `.`, character classes with ranges, `\d` `\w` `\s` and their
+//! negations, `\p{..}` for the general categories `L`, `Lu`, `Ll`, `N` and
+//! `Nd` (approximated with the `char` predicates of the standard library),
+//! groups, alternation, greedy and lazy quantifiers and the `^`/`$` anchors.
+//! Backreferences and lookaround are not supported.
+//!
+//! Patterns are compiled to a program for a Thompson NFA, which is run on all
+//! its states at once. Matching never backtracks or recurses, so it takes time
+//! linear in the length of the text, whatever the pattern. Parsing and
+//! compiling recurse into groups, so groups may only be nested
+//! `MAX_GROUP_DEPTH` deep and a pattern from untrusted data cannot overflow
+//! the stack either.
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum RegexError {
+    /// The character at this position cannot appear there.
+    UnexpectedChar(usize),
+    /// The pattern ended inside a group, class or escape.
+    UnexpectedEnd,
+    /// The quantifier at this position is malformed or has `min > max`.
+    InvalidRepeat(usize),
+    /// The escape at this position is not supported.
+    UnsupportedEscape(usize),
+    /// The compiled pattern would be too large, typically because of nested
+    /// counted repetition such as `(a{1000}){1000}`, or its groups are nested
+    /// too deep.
+    TooLarge
+}
+
+/// Most instructions a compiled pattern may have.
+const MAX_PROGRAM: usize = 100_000;
+
+/// Deepest nesting of groups in a pattern.
+pub const MAX_GROUP_DEPTH: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+enum Property {
+    Letter,
+    Uppercase,
+    Lowercase,
+    Number,
+    Digit
+}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+    Property(Property, bool)
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    /// `.`, anything but a line break
+    Any,
+    Class { items: Vec<ClassItem>, negated: bool },
+    Start,
+    End,
+    /// Alternatives, each a sequence of nodes
+    Group(Vec<Vec<Node>>),
+    Repeat { node: Box<Node>, min: usize, max: Option<usize> }
+}
+
+/// An instruction of a compiled pattern. Jumps hold the index of their target.
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class { items: Vec<ClassItem>, negated: bool },
+    Start,
+    End,
+    /// Continue at both targets
+    Split(usize, usize),
+    Jump(usize),
+    Match
+}
+
+/// A compiled pattern.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>
+}
+
+fn class_item_matches(item: &ClassItem, c: char) -> bool {
+    match item {
+        ClassItem::Range(from, to) => return *from <= c && c <= *to,
+        ClassItem::Digit(negated) => return c.is_ascii_digit() != *negated,
+        ClassItem::Word(negated) => return (c.is_ascii_alphanumeric() || c == '_') != *negated,
+        ClassItem::Space(negated) => return c.is_whitespace() != *negated,
+        ClassItem::Property(property, negated) => {
+            let matches = match property {
+                Property::Letter => c.is_alphabetic(),
+                Property::Uppercase => c.is_uppercase(),
+                Property::Lowercase => c.is_lowercase(),
+                // Without the Unicode tables, decimal digits are all numerics
+                Property::Number | Property::Digit => c.is_numeric()
+            };
+            return matches != *negated
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    // Groups open at the current position
+    depth: usize
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        return c
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, RegexError> {
+        let mut output = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            output.push(self.sequence()?);
+        }
+        return Ok(output)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, RegexError> {
+        let mut output = Vec::new();
+        loop {
+            let start = self.pos;
+            let atom = match self.peek() {
+                None | Some('|') | Some(')') => return Ok(output),
+                Some('(') => {
+                    self.pos += 1;
+                    if self.chars.get(self.pos..self.pos + 2) == Some(&['?', ':']) {
+                        self.pos += 2;
+                    }
+                    if self.depth == MAX_GROUP_DEPTH {
+                        return Err(RegexError::TooLarge)
+                    }
+                    self.depth += 1;
+                    let group = self.alternatives()?;
+                    self.depth -= 1;
+                    match self.bump() {
+                        Some(')') => Node::Group(group),
+                        Some(_) => return Err(RegexError::UnexpectedChar(self.pos - 1)),
+                        None => return Err(RegexError::UnexpectedEnd)
+                    }
+                },
+                Some('[') => {
+                    self.pos += 1;
+                    self.class()?
+                },
+                Some('.') => {self.pos += 1; Node::Any},
+                Some('^') => {self.pos += 1; Node::Start},
+                Some('$') => {self.pos += 1; Node::End},
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.escape()? {
+                        ClassItem::Range(c, _) => Node::Char(c),
+                        item => Node::Class { items: vec![item], negated: false }
+                    }
+                },
+                Some('*') | Some('+') | Some('?') => return Err(RegexError::InvalidRepeat(start)),
+                Some(c) => {self.pos += 1; Node::Char(c)}
+            };
+            output.push(self.quantifier(atom)?);
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        return self.chars[start..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    /// Reads `{n}`, `{n,}` or `{n,m}`, or returns `None` and leaves the
+    /// position alone if the brace does not start one.
+    fn braces(&mut self) -> Option<(usize, Option<usize>)> {
+        let start = self.pos;
+        self.pos += 1;
+        let bounds = match self.number() {
+            Some(min) => {
+                match self.bump() {
+                    Some('}') => Some((min, Some(min))),
+                    Some(',') if self.peek() == Some('}') => {self.pos += 1; Some((min, None))},
+                    Some(',') => {
+                        match (self.number(), self.bump()) {
+                            (Some(max), Some('}')) => Some((min, Some(max))),
+                            _ => None
+                        }
+                    },
+                    _ => None
+                }
+            },
+            None => None
+        };
+        if bounds.is_none() {
+            self.pos = start;
+        }
+        return bounds
+    }
+
+    fn quantifier(&mut self, atom: Node) -> Result<Node, RegexError> {
+        let start = self.pos;
+        let (min, max) = match self.peek() {
+            Some('*') => {self.pos += 1; (0, None)},
+            Some('+') => {self.pos += 1; (1, None)},
+            Some('?') => {self.pos += 1; (0, Some(1))},
+            Some('{') => {
+                match self.braces() {
+                    Some(bounds) => bounds,
+                    None => return Ok(atom)
+                }
+            },
+            _ => return Ok(atom)
+        };
+        if max.is_some_and(|max| max < min) || matches!(atom, Node::Start | Node::End) {
+            return Err(RegexError::InvalidRepeat(start))
+        }
+        // Lazy quantifiers match the same texts, only which match is found
+        // first differs, and that is never reported
+        if self.peek() == Some('?') {
+            self.pos += 1;
+        }
+        return Ok(Node::Repeat { node: Box::new(atom), min, max })
+    }
+
+    /// Reads the escape after a `\`. A plain character comes back as a
+    /// one-character range.
+    fn escape(&mut self) -> Result<ClassItem, RegexError> {
+        let start = self.pos - 1;
+        let c = self.bump().ok_or(RegexError::UnexpectedEnd)?;
+        let single = |c: char| ClassItem::Range(c, c);
+        match c {
+            'd' | 'D' => return Ok(ClassItem::Digit(c == 'D')),
+            'w' | 'W' => return Ok(ClassItem::Word(c == 'W')),
+            's' | 'S' => return Ok(ClassItem::Space(c == 'S')),
+            'n' => return Ok(single('\n')),
+            'r' => return Ok(single('\r')),
+            't' => return Ok(single('\t')),
+            'f' => return Ok(single('\u{c}')),
+            'v' => return Ok(single('\u{b}')),
+            'p' | 'P' => {
+                if self.bump() != Some('{') {
+                    return Err(RegexError::UnsupportedEscape(start))
+                }
+                let name_start = self.pos;
+                while self.peek().is_some_and(|c| c != '}') {
+                    self.pos += 1;
+                }
+                let name: String = self.chars[name_start..self.pos].iter().collect();
+                if self.bump() != Some('}') {
+                    return Err(RegexError::UnexpectedEnd)
+                }
+                let property = match name.as_str() {
+                    "L" => Property::Letter,
+                    "Lu" => Property::Uppercase,
+                    "Ll" => Property::Lowercase,
+                    "N" => Property::Number,
+                    "Nd" => Property::Digit,
+                    _ => return Err(RegexError::UnsupportedEscape(start))
+                };
+                return Ok(ClassItem::Property(property, c == 'P'))
+            },
+            c if c.is_ascii_alphanumeric() => return Err(RegexError::UnsupportedEscape(start)),
+            c => return Ok(single(c))
+        }
+    }
+
+    fn class(&mut self) -> Result<Node, RegexError> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        loop {
+            let item = match self.bump() {
+                None => return Err(RegexError::UnexpectedEnd),
+                Some(']') => return Ok(Node::Class { items, negated }),
+                Some('\\') if self.peek() == Some('b') => {self.pos += 1; ClassItem::Range('\u{8}', '\u{8}')},
+                Some('\\') => self.escape()?,
+                Some(c) => ClassItem::Range(c, c)
+            };
+            // A `-` between two characters makes a range, anywhere else it is literal
+            let ClassItem::Range(from, _) = item else {
+                items.push(item);
+                continue;
+            };
+            if self.peek() != Some('-') || self.chars.get(self.pos + 1).is_none_or(|c| *c == ']') {
+                items.push(item);
+                continue;
+            }
+            let dash = self.pos;
+            self.pos += 1;
+            let to = match self.bump() {
+                Some('\\') => self.escape()?,
+                Some(c) => ClassItem::Range(c, c),
+                None => return Err(RegexError::UnexpectedEnd)
+            };
+            match to {
+                ClassItem::Range(to, _) if from <= to => items.push(ClassItem::Range(from, to)),
+                _ => return Err(RegexError::UnexpectedChar(dash))
+            }
+        }
+    }
+}
+
+fn emit(program: &mut Vec<Inst>, inst: Inst) -> Result<usize, RegexError> {
+    if program.len() >= MAX_PROGRAM {
+        return Err(RegexError::TooLarge)
+    }
+    program.push(inst);
+    return Ok(program.len() - 1)
+}
+
+fn compile_sequence(program: &mut Vec<Inst>, nodes: &[Node]) -> Result<(), RegexError> {
+    for node in nodes {
+        compile(program, node)?;
+    }
+    return Ok(())
+}
+
+fn compile(program: &mut Vec<Inst>, node: &Node) -> Result<(), RegexError> {
+    match node {
+        Node::Char(c) => {emit(program, Inst::Char(*c))?;},
+        Node::Any => {emit(program, Inst::Any)?;},
+        Node::Class { items, negated } => {emit(program, Inst::Class { items: items.clone(), negated: *negated })?;},
+        Node::Start => {emit(program, Inst::Start)?;},
+        Node::End => {emit(program, Inst::End)?;},
+        Node::Group(alternatives) => {
+            // split L1, next; L1: first; jump end; next: split L2, ...; last; end:
+            let mut jumps = Vec::new();
+            for (i, alternative) in alternatives.iter().enumerate() {
+                if i + 1 == alternatives.len() {
+                    compile_sequence(program, alternative)?;
+                    break
+                }
+                let split = emit(program, Inst::Split(0, 0))?;
+                compile_sequence(program, alternative)?;
+                jumps.push(emit(program, Inst::Jump(0))?);
+                program[split] = Inst::Split(split + 1, program.len());
+            }
+            let end = program.len();
+            for jump in jumps {
+                program[jump] = Inst::Jump(end);
+            }
+        },
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(program, node)?;
+            }
+            match max {
+                // loop: split body, end; body; jump loop; end:
+                None => {
+                    let split = emit(program, Inst::Split(0, 0))?;
+                    compile(program, node)?;
+                    emit(program, Inst::Jump(split))?;
+                    program[split] = Inst::Split(split + 1, program.len());
+                },
+                Some(max) => {
+                    for _ in *min..*max {
+                        let split = emit(program, Inst::Split(0, 0))?;
+                        compile(program, node)?;
+                        program[split] = Inst::Split(split + 1, program.len());
+                    }
+                }
+            }
+        }
+    }
+    return Ok(())
+}
+
+/// The states of a run: instructions waiting for the next character.
+struct States {
+    list: Vec<usize>,
+    /// For each instruction, the last generation it was added in
+    seen: Vec<usize>,
+    generation: usize,
+    stack: Vec<usize>
+}
+
+impl States {
+    /// Empties the list and starts a new generation.
+    fn clear(&mut self) {
+        self.list.clear();
+        self.generation += 1;
+    }
+
+    /// Adds `pc` and everything reachable from it without reading a character.
+    fn add(&mut self, program: &[Inst], pc: usize, pos: usize, len: usize) {
+        self.stack.push(pc);
+        while let Some(pc) = self.stack.pop() {
+            if self.seen[pc] == self.generation {
+                continue
+            }
+            self.seen[pc] = self.generation;
+            match &program[pc] {
+                Inst::Jump(target) => self.stack.push(*target),
+                Inst::Split(first, second) => {
+                    self.stack.push(*second);
+                    self.stack.push(*first);
+                },
+                Inst::Start if pos == 0 => self.stack.push(pc + 1),
+                Inst::End if pos == len => self.stack.push(pc + 1),
+                Inst::Start | Inst::End => {},
+                _ => self.list.push(pc)
+            }
+        }
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex, RegexError> {
+        let mut parser = Parser { chars: pattern.chars().collect(), pos: 0, depth: 0 };
+        let alternatives = parser.alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err(RegexError::UnexpectedChar(parser.pos))
+        }
+        let mut program = Vec::new();
+        compile(&mut program, &Node::Group(alternatives))?;
+        emit(&mut program, Inst::Match)?;
+        return Ok(Regex { program })
+    }
+
+    /// Runs all states in lockstep over `text`. Unanchored runs start a new
+    /// state at every position and succeed on any match.
+    fn run(&self, text: &str, anchored: bool) -> bool {
+        let input: Vec<char> = text.chars().collect();
+        let len = input.len();
+        let mut current = States { list: Vec::new(), seen: vec![0; self.program.len()], generation: 0, stack: Vec::new() };
+        let mut next = States { list: Vec::new(), seen: vec![0; self.program.len()], generation: 0, stack: Vec::new() };
+        current.clear();
+        current.add(&self.program, 0, 0, len);
+        for (pos, &c) in input.iter().enumerate() {
+            if !anchored && current.list.iter().any(|pc| matches!(self.program[*pc], Inst::Match)) {
+                return true
+            }
+            if anchored && current.list.is_empty() {
+                return false
+            }
+            next.clear();
+            for &pc in &current.list {
+                let advances = match &self.program[pc] {
+                    Inst::Char(expected) => *expected == c,
+                    Inst::Any => c != '\n' && c != '\r',
+                    Inst::Class { items, negated } => items.iter().any(|item| class_item_matches(item, c)) != *negated,
+                    _ => false
+                };
+                if advances {
+                    next.add(&self.program, pc + 1, pos + 1, len);
+                }
+            }
+            if !anchored {
+                next.add(&self.program, 0, pos + 1, len);
+            }
+            std::mem::swap(&mut current, &mut next);
+        }
+        return current.list.iter().any(|pc| matches!(self.program[*pc], Inst::Match))
+    }
+
+    /// Whether the pattern matches the whole of `text`.
+    pub fn is_full_match(&self, text: &str) -> bool {
+        return self.run(text, true)
+    }
+
+    /// Whether the pattern matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        return self.run(text, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full(pattern: &str, text: &str) -> bool {
+        return Regex::new(pattern).unwrap().is_full_match(text)
+    }
+
+    #[test]
+    fn matching() {
+        assert!(full("abc", "abc"));
+        assert!(!full("abc", "abcd"));
+        assert!(full("a.c", "aéc"));
+        assert!(!full("a.c", "a\nc"));
+        assert!(full("[a-c]+x?", "abcab"));
+        assert!(full("[^0-9]*", "abc"));
+        assert!(!full("[^0-9]*", "a1"));
+        assert!(full("(ab|cd){2,3}", "abcdab"));
+        assert!(!full("(ab|cd){2,3}", "ab"));
+        assert!(full("a{2}b{1,}c{0,1}", "aabbb"));
+        assert!(full("\\d+\\.\\d*", "12.5"));
+        assert!(full("\\p{Lu}\\p{Ll}+", "Émile"));
+        assert!(full("(a*)*b", "aaab"));
+        assert!(full("[-a]+", "-a-"));
+        assert!(full("a{,2}", "a{,2}"));
+        assert!(full("(?:x|y)z", "yz"));
+
+        let search = Regex::new("^[BR]ob").unwrap();
+        assert!(search.is_match("Bob Dylan"));
+        assert!(!search.is_match("A Bob"));
+        assert!(Regex::new("o+?").unwrap().is_match("foo"));
+        assert!(Regex::new("").unwrap().is_match("anything"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Regex::new("(ab").unwrap_err(), RegexError::UnexpectedEnd);
+        assert_eq!(Regex::new("ab)").unwrap_err(), RegexError::UnexpectedChar(2));
+        assert_eq!(Regex::new("*a").unwrap_err(), RegexError::InvalidRepeat(0));
+        assert_eq!(Regex::new("a{3,1}").unwrap_err(), RegexError::InvalidRepeat(1));
+        assert_eq!(Regex::new("[z-a]").unwrap_err(), RegexError::UnexpectedChar(2));
+        assert_eq!(Regex::new("\\1").unwrap_err(), RegexError::UnsupportedEscape(0));
+        assert_eq!(Regex::new("\\p{Greek}").unwrap_err(), RegexError::UnsupportedEscape(0));
+        assert_eq!(Regex::new("[ab").unwrap_err(), RegexError::UnexpectedEnd);
+        assert_eq!(Regex::new("(a{1000}){1000}").unwrap_err(), RegexError::TooLarge);
+        let nested = |depth: usize| format!("{}a{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(full(&nested(MAX_GROUP_DEPTH), "a"));
+        assert_eq!(Regex::new(&nested(MAX_GROUP_DEPTH + 1)).unwrap_err(), RegexError::TooLarge);
+        assert_eq!(Regex::new(&nested(200_000)).unwrap_err(), RegexError::TooLarge);
+    }
+
+    #[test]
+    fn linear_time() {
+        // Used to recurse once per character and overflow the stack
+        let long = "x".repeat(200_000);
+        assert!(full(".*", &long));
+        assert!(full("^[a-z]+$", &long));
+        assert!(!full("[a-z]+", &(long.clone() + "1")));
+        assert!(Regex::new("x$").unwrap().is_match(&long));
+
+        // Used to backtrack exponentially
+        let text = "a".repeat(28) + "c";
+        assert!(!full("(a+)+b", &text));
+        assert!(!Regex::new("(a+)+b").unwrap().is_match(&text));
+        assert!(!full("(a|aa)*b", &"a".repeat(10_000)));
+        assert!(full("(a|aa)*b", &("a".repeat(10_000) + "b")));
+    }
+}
@@ -0,0 +1,953 @@
+//! JSON Schema (draft 2020-12) validation.
+//!
+//! `Schema::new` checks a schema once, compiling its `pattern`s and resolving
+//! its `$ref`s, and `Schema::validate` then checks any number of instances.
+//! Errors follow the "basic" output format of the specification: a flat list
+//! where each error names the keyword that failed (`keywordLocation`, and
+//! `absoluteKeywordLocation` once a `$ref` was followed) and the value it
+//! failed on (`instanceLocation`), both as JSON Pointers. `Output` converts
+//! into that JSON shape with `ToJson`.
+//!
+//! References are resolved locally: `#`, `#/json/pointer`, `#anchor` for
+//! `$anchor`, and the `$id` of the root or of a subschema. Known `format`s are
+//! asserted, unknown ones are ignored. Keywords the validator does not know,
+//! such as `unevaluatedProperties`, are ignored as well.
+
+use std::collections::HashMap;
+
+use crate::convert::ToJson;
+use crate::parser::JsonType;
+use crate::pointer::{escape_token, parse_pointer};
+use crate::regex::{Regex, RegexError};
+use crate::serializer::{format_number, to_string};
+
+/// Why a schema cannot be used. `path` is the JSON Pointer of the offending
+/// value inside the schema.
+#[derive(PartialEq, Debug, Clone)]
+pub enum SchemaError {
+    /// A subschema is neither an object nor a boolean.
+    NotASchema { path: String },
+    /// A `pattern` or `patternProperties` key is not a supported regular expression.
+    InvalidPattern { path: String, error: RegexError },
+    /// A `$ref` does not point to a schema inside the document.
+    UnresolvedRef { path: String, reference: String }
+}
+
+/// One failed keyword, an output unit of the "basic" format.
+#[derive(PartialEq, Debug, Clone)]
+pub struct ValidationError {
+    /// Path through the schema keywords that were evaluated, including any `$ref`.
+    pub keyword_location: String,
+    /// Where the keyword really is, `<$id>#<pointer>`. Only set when the
+    /// evaluation went through a `$ref`, otherwise it equals `keyword_location`.
+    pub absolute_keyword_location: Option<String>,
+    /// JSON Pointer of the value that failed.
+    pub instance_location: String,
+    pub error: String
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Output {
+    pub errors: Vec<ValidationError>
+}
+
+impl Output {
+    pub fn is_valid(&self) -> bool {
+        return self.errors.is_empty()
+    }
+}
+
+impl ToJson for ValidationError {
+    fn to_json(&self) -> JsonType {
+        let mut unit = json!({
+            "keywordLocation": self.keyword_location.as_str(),
+            "instanceLocation": self.instance_location.as_str(),
+            "error": self.error.as_str()
+        });
+        if let Some(absolute) = &self.absolute_keyword_location {
+            unit.as_object_mut().unwrap().insert("absoluteKeywordLocation".to_string(), json!(absolute));
+        }
+        return unit
+    }
+}
+
+/// `{"valid": true}`, or `{"valid": false, "errors": [...]}`.
+impl ToJson for Output {
+    fn to_json(&self) -> JsonType {
+        if self.is_valid() {
+            return json!({"valid": true})
+        }
+        return json!({"valid": false, "errors": self.errors.iter().map(ToJson::to_json).collect::<Vec<_>>()})
+    }
+}
+
+/// A checked schema, ready to validate instances.
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: JsonType,
+    /// The root `$id`, used as the base of `absoluteKeywordLocation`
+    base: String,
+    patterns: HashMap<String, Regex>,
+    /// `$anchor` names and `$id`s to the pointer of their subschema
+    anchors: HashMap<String, String>,
+    ids: HashMap<String, String>
+}
+
+// Keywords whose value is a single subschema, a map of subschemas or a list of subschemas
+const SCHEMA_KEYWORDS: [&str; 10] = [
+    "additionalProperties", "items", "contains", "not", "if", "then", "else", "propertyNames",
+    "unevaluatedItems", "unevaluatedProperties"
+];
+const SCHEMA_MAP_KEYWORDS: [&str; 5] = ["properties", "patternProperties", "$defs", "definitions", "dependentSchemas"];
+const SCHEMA_LIST_KEYWORDS: [&str; 4] = ["allOf", "anyOf", "oneOf", "prefixItems"];
+
+
+impl Schema {
+    pub fn new(schema: &JsonType) -> Result<Schema, SchemaError> {
+        let base = match schema.get("$id").and_then(JsonType::as_str) {
+            Some(id) => id.trim_end_matches('#').to_string(),
+            None => String::new()
+        };
+        let mut compiled = Schema { root: schema.clone(), base, patterns: HashMap::new(), anchors: HashMap::new(), ids: HashMap::new() };
+        let mut refs = Vec::new();
+        compiled.collect(schema, String::new(), &mut refs)?;
+
+        // A reference may point below a keyword that is not walked, such as
+        // `{"$ref": "#/components/a"}`, so its target is collected as well
+        let mut visited = Vec::new();
+        while let Some((path, reference)) = refs.pop() {
+            let Some(pointer) = compiled.resolve(&reference) else {
+                return Err(SchemaError::UnresolvedRef { path, reference })
+            };
+            if !visited.contains(&pointer) {
+                let target = compiled.root.pointer(&pointer).unwrap().clone();
+                compiled.collect(&target, pointer.clone(), &mut refs)?;
+                visited.push(pointer);
+            }
+        }
+        return Ok(compiled)
+    }
+
+    /// Walks every subschema, compiling patterns and recording anchors, ids and references.
+    fn collect(&mut self, schema: &JsonType, path: String, refs: &mut Vec<(String, String)>) -> Result<(), SchemaError> {
+        let map = match schema {
+            JsonType::JsonBool(_) => return Ok(()),
+            JsonType::JsonObject(map) => map,
+            _ => return Err(SchemaError::NotASchema { path })
+        };
+        if let Some(JsonType::JsonString(pattern)) = map.get("pattern") {
+            self.compile(pattern, format!("{path}/pattern"))?;
+        }
+        if let Some(JsonType::JsonObject(patterns)) = map.get("patternProperties") {
+            for pattern in patterns.keys() {
+                self.compile(pattern, format!("{path}/patternProperties/{}", escape_token(pattern)))?;
+            }
+        }
+        if let Some(JsonType::JsonString(anchor)) = map.get("$anchor") {
+            self.anchors.insert(anchor.clone(), path.clone());
+        }
+        if let Some(JsonType::JsonString(id)) = map.get("$id") && !path.is_empty() {
+            self.ids.insert(id.trim_end_matches('#').to_string(), path.clone());
+        }
+        if let Some(JsonType::JsonString(reference)) = map.get("$ref") {
+            refs.push((format!("{path}/$ref"), reference.clone()));
+        }
+
+        for keyword in SCHEMA_KEYWORDS {
+            if let Some(subschema) = map.get(keyword) {
+                self.collect(subschema, format!("{path}/{keyword}"), refs)?;
+            }
+        }
+        for keyword in SCHEMA_MAP_KEYWORDS {
+            if let Some(JsonType::JsonObject(subschemas)) = map.get(keyword) {
+                for (key, subschema) in subschemas {
+                    self.collect(subschema, format!("{path}/{keyword}/{}", escape_token(key)), refs)?;
+                }
+            }
+        }
+        for keyword in SCHEMA_LIST_KEYWORDS {
+            if let Some(JsonType::JsonArray(subschemas)) = map.get(keyword) {
+                for (i, subschema) in subschemas.iter().enumerate() {
+                    self.collect(subschema, format!("{path}/{keyword}/{i}"), refs)?;
+                }
+            }
+        }
+        return Ok(())
+    }
+
+    fn compile(&mut self, pattern: &str, path: String) -> Result<(), SchemaError> {
+        if self.patterns.contains_key(pattern) {
+            return Ok(())
+        }
+        match Regex::new(pattern) {
+            Ok(regex) => {self.patterns.insert(pattern.to_string(), regex);},
+            Err(error) => {return Err(SchemaError::InvalidPattern { path, error })}
+        }
+        return Ok(())
+    }
+
+    /// The pointer of the subschema a `$ref` names, if it exists.
+    fn resolve(&self, reference: &str) -> Option<String> {
+        let (resource, fragment) = match reference.split_once('#') {
+            Some((resource, fragment)) => (resource, fragment),
+            None => (reference, "")
+        };
+        let base = match resource {
+            "" => String::new(),
+            resource if resource == self.base => String::new(),
+            resource => self.ids.get(resource)?.clone()
+        };
+        let pointer = match fragment {
+            "" => base,
+            fragment if fragment.starts_with('/') => format!("{base}{}", percent_decode(fragment)?),
+            anchor => self.anchors.get(anchor)?.clone()
+        };
+        match self.root.pointer(&pointer) {
+            Some(JsonType::JsonObject(_) | JsonType::JsonBool(_)) => return Some(pointer),
+            _ => return None
+        }
+    }
+
+    pub fn validate(&self, instance: &JsonType) -> Output {
+        let location = Location { keyword: String::new(), absolute: String::new(), via_ref: false };
+        return Output { errors: self.check(&self.root, instance, &location, "", None) }
+    }
+
+    pub fn is_valid(&self, instance: &JsonType) -> bool {
+        return self.validate(instance).is_valid()
+    }
+}
+
+/// Checks `instance` against `schema` in one go.
+pub fn validate(schema: &JsonType, instance: &JsonType) -> Result<Output, SchemaError> {
+    return Ok(Schema::new(schema)?.validate(instance))
+}
+
+/// `%XX` escapes in a URI fragment.
+fn percent_decode(fragment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(fragment.len());
+    let mut input = fragment.bytes();
+    while let Some(b) = input.next() {
+        if b == b'%' {
+            let high = (input.next()? as char).to_digit(16)?;
+            let low = (input.next()? as char).to_digit(16)?;
+            bytes.push((high * 16 + low) as u8);
+        } else {
+            bytes.push(b);
+        }
+    }
+    return String::from_utf8(bytes).ok()
+}
+
+/// Where the schema being evaluated is: the keyword path taken to reach it and
+/// its pointer inside the root schema.
+struct Location {
+    keyword: String,
+    absolute: String,
+    via_ref: bool
+}
+
+impl Location {
+    fn child(&self, tokens: &[&str]) -> Location {
+        let mut keyword = self.keyword.clone();
+        let mut absolute = self.absolute.clone();
+        for token in tokens {
+            keyword.push('/');
+            keyword.push_str(&escape_token(token));
+            absolute.push('/');
+            absolute.push_str(&escape_token(token));
+        }
+        return Location { keyword, absolute, via_ref: self.via_ref }
+    }
+}
+
+/// A numeric bound keyword, whether a number satisfies it, and how a failure reads.
+type Bound = (&'static str, fn(f64, f64) -> bool, &'static str);
+
+fn json_type_matches(name: &str, instance: &JsonType) -> bool {
+    match (name, instance) {
+        ("integer", JsonType::JsonNumber(n)) => return n.fract() == 0.0,
+        (name, instance) => return name == instance.type_name()
+    }
+}
+
+fn is_multiple_of(value: f64, divisor: f64) -> bool {
+    let quotient = value / divisor;
+    if !quotient.is_finite() {
+        return false
+    }
+    return (quotient - quotient.round()).abs() <= f64::EPSILON * quotient.abs().max(1.0)
+}
+
+fn describe(value: &JsonType) -> String {
+    let text = to_string(value);
+    if text.chars().count() > 40 {
+        return format!("{}...", text.chars().take(40).collect::<String>())
+    }
+    return text
+}
+
+/// A `$ref` being followed, with the ones it is nested in. Following the same
+/// reference again for the same instance location would never end, while
+/// recursion into the instance, as in a linked list, is bounded by the data.
+struct Active<'a> {
+    pointer: &'a str,
+    instance_path: &'a str,
+    parent: Option<&'a Active<'a>>
+}
+
+impl Active<'_> {
+    fn contains(mut active: Option<&Active>, pointer: &str, instance_path: &str) -> bool {
+        while let Some(current) = active {
+            if current.pointer == pointer && current.instance_path == instance_path {
+                return true
+            }
+            active = current.parent;
+        }
+        return false
+    }
+}
+
+impl Schema {
+    fn error(&self, location: &Location, keyword: &str, instance_path: &str, error: String) -> ValidationError {
+        let at = location.child(&[keyword]);
+        let absolute = match location.via_ref {
+            true => Some(format!("{}#{}", self.base, at.absolute)),
+            false => None
+        };
+        return ValidationError { keyword_location: at.keyword, absolute_keyword_location: absolute, instance_location: instance_path.to_string(), error }
+    }
+
+    /// All errors of `instance` against `schema`, empty if it is valid.
+    fn check(&self, schema: &JsonType, instance: &JsonType, location: &Location, instance_path: &str, active: Option<&Active>) -> Vec<ValidationError> {
+        let map = match schema {
+            JsonType::JsonBool(true) => return Vec::new(),
+            JsonType::JsonObject(map) => map,
+            _ => {
+                let absolute = location.via_ref.then(|| format!("{}#{}", self.base, location.absolute));
+                return vec![ValidationError {
+                    keyword_location: location.keyword.clone(),
+                    absolute_keyword_location: absolute,
+                    instance_location: instance_path.to_string(),
+                    error: "no value is allowed here".to_string()
+                }]
+            }
+        };
+        let mut errors = Vec::new();
+
+        if let Some(JsonType::JsonString(reference)) = map.get("$ref") && let Some(pointer) = self.resolve(reference) {
+            if Active::contains(active, &pointer, instance_path) {
+                errors.push(self.error(location, "$ref", instance_path, "circular $ref".to_string()));
+            } else {
+                let target = Location { keyword: location.child(&["$ref"]).keyword, absolute: pointer.clone(), via_ref: true };
+                let inner = Active { pointer: &pointer, instance_path, parent: active };
+                errors.extend(self.check(self.root.pointer(&pointer).unwrap(), instance, &target, instance_path, Some(&inner)));
+            }
+        }
+
+        self.check_generic(map, instance, location, instance_path, &mut errors);
+        match instance {
+            JsonType::JsonNumber(n) => self.check_number(map, *n, location, instance_path, &mut errors),
+            JsonType::JsonString(s) => self.check_string(map, s, location, instance_path, &mut errors),
+            JsonType::JsonArray(values) => self.check_array(map, values, location, instance_path, active, &mut errors),
+            JsonType::JsonObject(members) => self.check_object(map, members, location, instance_path, active, &mut errors),
+            _ => {}
+        }
+        self.check_applicators(map, instance, location, instance_path, active, &mut errors);
+        return errors
+    }
+
+    /// `type`, `enum` and `const`.
+    fn check_generic(&self, map: &HashMap<String, JsonType>, instance: &JsonType, location: &Location, instance_path: &str, errors: &mut Vec<ValidationError>) {
+        match map.get("type") {
+            Some(JsonType::JsonString(name)) if !json_type_matches(name, instance) => {
+                errors.push(self.error(location, "type", instance_path, format!("expected {name}, found {}", instance.type_name())));
+            },
+            Some(JsonType::JsonArray(names)) if !names.iter().any(|name| name.as_str().is_some_and(|name| json_type_matches(name, instance))) => {
+                let names: Vec<&str> = names.iter().filter_map(JsonType::as_str).collect();
+                errors.push(self.error(location, "type", instance_path, format!("expected {}, found {}", names.join(" or "), instance.type_name())));
+            },
+            _ => {}
+        }
+        if let Some(JsonType::JsonArray(values)) = map.get("enum") && !values.contains(instance) {
+            errors.push(self.error(location, "enum", instance_path, format!("{} is not one of {}", describe(instance), describe(&map["enum"]))));
+        }
+        if let Some(value) = map.get("const") && value != instance {
+            errors.push(self.error(location, "const", instance_path, format!("expected {}, found {}", describe(value), describe(instance))));
+        }
+    }
+
+    fn check_number(&self, map: &HashMap<String, JsonType>, n: f64, location: &Location, instance_path: &str, errors: &mut Vec<ValidationError>) {
+        let bounds: [Bound; 4] = [
+            ("minimum", |n, bound| n >= bound, "less than the minimum of"),
+            ("maximum", |n, bound| n <= bound, "greater than the maximum of"),
+            ("exclusiveMinimum", |n, bound| n > bound, "not greater than"),
+            ("exclusiveMaximum", |n, bound| n < bound, "not less than")
+        ];
+        for (keyword, holds, message) in bounds {
+            if let Some(bound) = map.get(keyword).and_then(JsonType::as_f64) && !holds(n, bound) {
+                errors.push(self.error(location, keyword, instance_path, format!("{} is {message} {}", format_number(n), format_number(bound))));
+            }
+        }
+        if let Some(divisor) = map.get("multipleOf").and_then(JsonType::as_f64) && divisor > 0.0 && !is_multiple_of(n, divisor) {
+            errors.push(self.error(location, "multipleOf", instance_path, format!("{} is not a multiple of {}", format_number(n), format_number(divisor))));
+        }
+    }
+
+    fn check_string(&self, map: &HashMap<String, JsonType>, s: &str, location: &Location, instance_path: &str, errors: &mut Vec<ValidationError>) {
+        // Lengths count code points, not bytes
+        let length = s.chars().count() as f64;
+        if let Some(min) = map.get("minLength").and_then(JsonType::as_f64) && length < min {
+            errors.push(self.error(location, "minLength", instance_path, format!("{} is shorter than {} characters", describe(&json!(s)), format_number(min))));
+        }
+        if let Some(max) = map.get("maxLength").and_then(JsonType::as_f64) && length > max {
+            errors.push(self.error(location, "maxLength", instance_path, format!("{} is longer than {} characters", describe(&json!(s)), format_number(max))));
+        }
+        if let Some(JsonType::JsonString(pattern)) = map.get("pattern") && !self.patterns[pattern].is_match(s) {
+            errors.push(self.error(location, "pattern", instance_path, format!("{} does not match {}", describe(&json!(s)), describe(&json!(pattern)))));
+        }
+        if let Some(JsonType::JsonString(format)) = map.get("format") && format_matches(format, s) == Some(false) {
+            errors.push(self.error(location, "format", instance_path, format!("{} is not a valid {format}", describe(&json!(s)))));
+        }
+    }
+
+    fn check_array(
+        &self,
+        map: &HashMap<String, JsonType>,
+        values: &[JsonType],
+        location: &Location,
+        instance_path: &str,
+        active: Option<&Active>,
+        errors: &mut Vec<ValidationError>
+    ) {
+        let count = values.len() as f64;
+        if let Some(min) = map.get("minItems").and_then(JsonType::as_f64) && count < min {
+            errors.push(self.error(location, "minItems", instance_path, format!("expected at least {} items, found {}", format_number(min), values.len())));
+        }
+        if let Some(max) = map.get("maxItems").and_then(JsonType::as_f64) && count > max {
+            errors.push(self.error(location, "maxItems", instance_path, format!("expected at most {} items, found {}", format_number(max), values.len())));
+        }
+        if map.get("uniqueItems") == Some(&JsonType::JsonBool(true)) {
+            for (i, value) in values.iter().enumerate() {
+                if let Some(j) = values.get(..i).unwrap().iter().position(|other| other == value) {
+                    errors.push(self.error(location, "uniqueItems", instance_path, format!("items {j} and {i} are equal")));
+                    break
+                }
+            }
+        }
+
+        let element_path = |i: usize| format!("{instance_path}/{i}");
+        let mut prefix = 0;
+        if let Some(JsonType::JsonArray(schemas)) = map.get("prefixItems") {
+            for (i, (schema, value)) in schemas.iter().zip(values).enumerate() {
+                errors.extend(self.check(schema, value, &location.child(&["prefixItems", &i.to_string()]), &element_path(i), active));
+            }
+            prefix = schemas.len();
+        }
+        if let Some(schema) = map.get("items") {
+            for (i, value) in values.iter().enumerate().skip(prefix) {
+                errors.extend(self.check(schema, value, &location.child(&["items"]), &element_path(i), active));
+            }
+        }
+
+        if let Some(schema) = map.get("contains") {
+            let at = location.child(&["contains"]);
+            let matches = values.iter().enumerate().filter(|(i, value)| self.check(schema, value, &at, &element_path(*i), active).is_empty()).count();
+            let min = map.get("minContains").and_then(JsonType::as_f64);
+            let max = map.get("maxContains").and_then(JsonType::as_f64);
+            match min {
+                Some(min) if (matches as f64) < min => {
+                    errors.push(self.error(location, "minContains", instance_path, format!("expected at least {} matching items, found {matches}", format_number(min))));
+                },
+                None if matches == 0 => {
+                    errors.push(self.error(location, "contains", instance_path, "no item matches".to_string()));
+                },
+                _ => {}
+            }
+            if let Some(max) = max && matches as f64 > max {
+                errors.push(self.error(location, "maxContains", instance_path, format!("expected at most {} matching items, found {matches}", format_number(max))));
+            }
+        }
+    }
+
+    fn check_object(
+        &self,
+        map: &HashMap<String, JsonType>,
+        members: &HashMap<String, JsonType>,
+        location: &Location,
+        instance_path: &str,
+        active: Option<&Active>,
+        errors: &mut Vec<ValidationError>
+    ) {
+        let count = members.len() as f64;
+        if let Some(min) = map.get("minProperties").and_then(JsonType::as_f64) && count < min {
+            errors.push(self.error(location, "minProperties", instance_path, format!("expected at least {} properties, found {}", format_number(min), members.len())));
+        }
+        if let Some(max) = map.get("maxProperties").and_then(JsonType::as_f64) && count > max {
+            errors.push(self.error(location, "maxProperties", instance_path, format!("expected at most {} properties, found {}", format_number(max), members.len())));
+        }
+        if let Some(JsonType::JsonArray(required)) = map.get("required") {
+            for name in required.iter().filter_map(JsonType::as_str) {
+                if !members.contains_key(name) {
+                    errors.push(self.error(location, "required", instance_path, format!("missing required property {}", describe(&json!(name)))));
+                }
+            }
+        }
+        if let Some(JsonType::JsonObject(dependencies)) = map.get("dependentRequired") {
+            let mut triggers: Vec<_> = dependencies.iter().filter(|(name, _)| members.contains_key(*name)).collect();
+            triggers.sort_by(|a, b| a.0.cmp(b.0));
+            for (trigger, required) in triggers {
+                for name in required.as_array().into_iter().flatten().filter_map(JsonType::as_str) {
+                    if !members.contains_key(name) {
+                        errors.push(self.error(location, "dependentRequired", instance_path, format!("property {} requires {}", describe(&json!(trigger)), describe(&json!(name)))));
+                    }
+                }
+            }
+        }
+
+        let mut keys: Vec<&String> = members.keys().collect();
+        keys.sort();
+        let member_path = |key: &str| format!("{instance_path}/{}", escape_token(key));
+        let properties = map.get("properties").and_then(JsonType::as_object);
+        let patterns = map.get("patternProperties").and_then(JsonType::as_object);
+        for key in keys {
+            let value = &members[key];
+            let mut evaluated = false;
+            if let Some(schema) = properties.and_then(|properties| properties.get(key)) {
+                errors.extend(self.check(schema, value, &location.child(&["properties", key]), &member_path(key), active));
+                evaluated = true;
+            }
+            if let Some(patterns) = patterns {
+                let mut matching: Vec<_> = patterns.iter().filter(|(pattern, _)| self.patterns[pattern.as_str()].is_match(key)).collect();
+                matching.sort_by(|a, b| a.0.cmp(b.0));
+                for (pattern, schema) in matching {
+                    errors.extend(self.check(schema, value, &location.child(&["patternProperties", pattern]), &member_path(key), active));
+                    evaluated = true;
+                }
+            }
+            if !evaluated && let Some(schema) = map.get("additionalProperties") {
+                errors.extend(self.check(schema, value, &location.child(&["additionalProperties"]), &member_path(key), active));
+            }
+            if let Some(schema) = map.get("propertyNames") {
+                errors.extend(self.check(schema, &json!(key), &location.child(&["propertyNames"]), &member_path(key), active));
+            }
+        }
+
+        if let Some(JsonType::JsonObject(dependencies)) = map.get("dependentSchemas") {
+            let mut triggers: Vec<_> = dependencies.iter().filter(|(name, _)| members.contains_key(*name)).collect();
+            triggers.sort_by(|a, b| a.0.cmp(b.0));
+            for (trigger, schema) in triggers {
+                let object = JsonType::JsonObject(members.clone());
+                errors.extend(self.check(schema, &object, &location.child(&["dependentSchemas", trigger]), instance_path, active));
+            }
+        }
+    }
+
+    /// `allOf`, `anyOf`, `oneOf`, `not` and `if`/`then`/`else`.
+    fn check_applicators(
+        &self,
+        map: &HashMap<String, JsonType>,
+        instance: &JsonType,
+        location: &Location,
+        instance_path: &str,
+        active: Option<&Active>,
+        errors: &mut Vec<ValidationError>
+    ) {
+        let each = |keyword: &str| -> Vec<Vec<ValidationError>> {
+            let Some(JsonType::JsonArray(schemas)) = map.get(keyword) else {
+                return Vec::new()
+            };
+            return schemas.iter().enumerate()
+                .map(|(i, schema)| self.check(schema, instance, &location.child(&[keyword, &i.to_string()]), instance_path, active))
+                .collect()
+        };
+
+        for results in each("allOf") {
+            errors.extend(results);
+        }
+
+        let any = each("anyOf");
+        if !any.is_empty() && !any.iter().any(Vec::is_empty) {
+            errors.push(self.error(location, "anyOf", instance_path, format!("does not match any of the {} subschemas", any.len())));
+            errors.extend(any.into_iter().flatten());
+        }
+
+        let one = each("oneOf");
+        let matching: Vec<usize> = one.iter().enumerate().filter(|(_, results)| results.is_empty()).map(|(i, _)| i).collect();
+        if !one.is_empty() && matching.is_empty() {
+            errors.push(self.error(location, "oneOf", instance_path, format!("does not match any of the {} subschemas", one.len())));
+            errors.extend(one.into_iter().flatten());
+        } else if matching.len() > 1 {
+            let matching: Vec<String> = matching.iter().map(usize::to_string).collect();
+            errors.push(self.error(location, "oneOf", instance_path, format!("matches subschemas {}, but must match exactly one", matching.join(", "))));
+        }
+
+        if let Some(schema) = map.get("not") && self.check(schema, instance, &location.child(&["not"]), instance_path, active).is_empty() {
+            errors.push(self.error(location, "not", instance_path, "must not match the subschema".to_string()));
+        }
+
+        if let Some(condition) = map.get("if") {
+            let branch = match self.check(condition, instance, &location.child(&["if"]), instance_path, active).is_empty() {
+                true => "then",
+                false => "else"
+            };
+            if let Some(schema) = map.get(branch) {
+                errors.extend(self.check(schema, instance, &location.child(&[branch]), instance_path, active));
+            }
+        }
+    }
+}
+
+/// Whether `text` is valid for `format`, `None` for formats that are not checked.
+pub(crate) fn format_matches(format: &str, text: &str) -> Option<bool> {
+    let valid = match format {
+        "date-time" => {
+            match text.split_once(['T', 't']) {
+                Some((date, time)) => is_date(date) && is_time(time),
+                None => false
+            }
+        },
+        "date" => is_date(text),
+        "time" => is_time(text),
+        "email" => {
+            match text.rsplit_once('@') {
+                Some((local, domain)) => {
+                    !local.is_empty() && local.len() <= 64 && !local.contains(|c: char| c.is_whitespace() || c == '@' || c.is_control())
+                        && is_hostname(domain)
+                },
+                None => false
+            }
+        },
+        "hostname" => is_hostname(text),
+        "ipv4" => text.parse::<std::net::Ipv4Addr>().is_ok(),
+        "ipv6" => text.parse::<std::net::Ipv6Addr>().is_ok(),
+        "uri" => {
+            match text.split_once(':') {
+                Some((scheme, _)) => is_scheme(scheme) && is_uri_reference(text),
+                None => false
+            }
+        },
+        "uri-reference" => is_uri_reference(text),
+        "uuid" => {
+            let groups: Vec<&str> = text.split('-').collect();
+            groups.len() == 5
+                && groups.iter().zip([8, 4, 4, 4, 12]).all(|(group, length)| group.len() == length && group.bytes().all(|b| b.is_ascii_hexdigit()))
+        },
+        "regex" => Regex::new(text).is_ok(),
+        "json-pointer" => parse_pointer(text).is_ok(),
+        _ => return None
+    };
+    return Some(valid)
+}
+
+/// `len` ASCII digits, as a number.
+fn digits(text: &str, len: usize) -> Option<u32> {
+    if text.len() != len || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None
+    }
+    return text.parse().ok()
+}
+
+/// RFC 3339 `full-date`, `YYYY-MM-DD` with the real number of days per month.
+fn is_date(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return false
+    };
+    let (Some(year), Some(month), Some(day)) = (digits(year, 4), digits(month, 2), digits(day, 2)) else {
+        return false
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false
+    };
+    return (1..=days).contains(&day)
+}
+
+/// RFC 3339 `full-time`, `HH:MM:SS[.fraction]` followed by `Z` or `+HH:MM`.
+fn is_time(text: &str) -> bool {
+    let (time, offset) = match text.find(['Z', 'z', '+', '-']) {
+        Some(i) => (text.get(..i).unwrap(), text.get(i..).unwrap()),
+        None => return false
+    };
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None)
+    };
+    if fraction.is_some_and(|f| f.is_empty() || !f.bytes().all(|b| b.is_ascii_digit())) {
+        return false
+    }
+    let parts: Vec<&str> = time.split(':').collect();
+    let [hour, minute, second] = parts.as_slice() else {
+        return false
+    };
+    let (Some(hour), Some(minute), Some(second)) = (digits(hour, 2), digits(minute, 2), digits(second, 2)) else {
+        return false
+    };
+    // Leap seconds are accepted without checking that they fall on a real one
+    if hour > 23 || minute > 59 || second > 60 {
+        return false
+    }
+    if offset.eq_ignore_ascii_case("z") {
+        return true
+    }
+    let Some((hours, minutes)) = offset.get(1..).and_then(|o| o.split_once(':')) else {
+        return false
+    };
+    return digits(hours, 2).is_some_and(|h| h <= 23) && digits(minutes, 2).is_some_and(|m| m <= 59)
+}
+
+/// RFC 1123 host names: dot-separated labels of letters, digits and inner hyphens.
+fn is_hostname(text: &str) -> bool {
+    let text = text.strip_suffix('.').unwrap_or(text);
+    if text.is_empty() || text.len() > 253 {
+        return false
+    }
+    return text.split('.').all(|label| {
+        !label.is_empty() && label.len() <= 63 && !label.starts_with('-') && !label.ends_with('-')
+            && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+    })
+}
+
+fn is_scheme(scheme: &str) -> bool {
+    let mut chars = scheme.chars();
+    return chars.next().is_some_and(|c| c.is_ascii_alphabetic()) && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
+
+/// Only characters RFC 3986 allows in a URI, and well-formed `%` escapes.
+fn is_uri_reference(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                if !bytes.get((i + 1)..(i + 3)).is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) {
+                    return false
+                }
+                i += 2;
+            },
+            b if b.is_ascii_alphanumeric() || b"-._~:/?#[]@!$&'()*+,;=".contains(&b) => {},
+            _ => return false
+        }
+        i += 1;
+    }
+    return true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(schema: JsonType, instance: JsonType) -> Vec<(String, String)> {
+        let output = Schema::new(&schema).unwrap().validate(&instance);
+        return output.errors.into_iter().map(|e| (e.keyword_location, e.instance_location)).collect()
+    }
+
+    fn locations(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        return pairs.iter().map(|(k, i)| (k.to_string(), i.to_string())).collect()
+    }
+
+    #[test]
+    fn assertions() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name", "age"],
+            "properties": {
+                "name": {"type": "string", "minLength": 2, "maxLength": 5, "pattern": "^[A-Z]"},
+                "age": {"type": "integer", "minimum": 0, "exclusiveMaximum": 150},
+                "ratio": {"multipleOf": 0.1},
+                "role": {"enum": ["admin", "user"]},
+                "version": {"const": 2},
+                "tags": {"type": "array", "uniqueItems": true, "maxItems": 3, "items": {"type": ["string", "null"]}}
+            },
+            "additionalProperties": false
+        });
+        assert!(Schema::new(&schema).unwrap().is_valid(&json!({"name": "Ann", "age": 30, "ratio": 0.3, "role": "user", "version": 2, "tags": ["a", null]})));
+        assert_eq!(
+            errors(schema.clone(), json!({"name": "bob", "age": 1.5, "extra": 1})),
+            locations(&[
+                ("/properties/age/type", "/age"),
+                ("/additionalProperties", "/extra"),
+                ("/properties/name/pattern", "/name")
+            ])
+        );
+        assert_eq!(
+            errors(schema, json!({"name": "Brunhilde", "ratio": 0.25, "role": "root", "version": 2.5, "tags": ["a", "a", 1, "b"]})),
+            locations(&[
+                ("/required", ""),
+                ("/properties/name/maxLength", "/name"),
+                ("/properties/ratio/multipleOf", "/ratio"),
+                ("/properties/role/enum", "/role"),
+                ("/properties/tags/maxItems", "/tags"),
+                ("/properties/tags/uniqueItems", "/tags"),
+                ("/properties/tags/items/type", "/tags/2"),
+                ("/properties/version/const", "/version")
+            ])
+        );
+        assert_eq!(errors(json!(false), json!(1)), locations(&[("", "")]));
+        assert_eq!(errors(json!({"type": "number"}), json!("1")), locations(&[("/type", "")]));
+
+        // Long strings used to overflow the stack of the pattern matcher
+        let long = "a".repeat(200_000);
+        assert!(errors(json!({"pattern": "^[a-z]+$"}), json!(long.clone())).is_empty());
+        assert_eq!(errors(json!({"pattern": "^[a-z]+$"}), json!(long + "1")), locations(&[("/pattern", "")]));
+    }
+
+    #[test]
+    fn arrays_and_objects() {
+        let schema = json!({
+            "prefixItems": [{"type": "string"}, {"type": "number"}],
+            "items": {"type": "boolean"},
+            "contains": {"const": true},
+            "maxContains": 1
+        });
+        assert!(Schema::new(&schema).unwrap().is_valid(&json!(["a", 1, true, false])));
+        assert_eq!(errors(schema.clone(), json!([1, "a", false])), locations(&[("/prefixItems/0/type", "/0"), ("/prefixItems/1/type", "/1"), ("/contains", "")]));
+        assert_eq!(errors(schema, json!(["a", 1, true, true])), locations(&[("/maxContains", "")]));
+        assert_eq!(errors(json!({"contains": {"type": "null"}, "minContains": 2}), json!([null])), locations(&[("/minContains", "")]));
+
+        let schema = json!({
+            "patternProperties": {"^x-": {"type": "string"}},
+            "additionalProperties": {"type": "number"},
+            "propertyNames": {"maxLength": 4},
+            "dependentRequired": {"a": ["b"]},
+            "minProperties": 1
+        });
+        assert!(Schema::new(&schema).unwrap().is_valid(&json!({"x-a": "s", "n": 1})));
+        assert_eq!(
+            errors(schema.clone(), json!({"x-a": 1, "a": "s", "x-long": "s"})),
+            locations(&[
+                ("/dependentRequired", ""),
+                ("/additionalProperties/type", "/a"),
+                ("/patternProperties/^x-/type", "/x-a"),
+                ("/propertyNames/maxLength", "/x-long")
+            ])
+        );
+        assert_eq!(errors(schema, json!({})), locations(&[("/minProperties", "")]));
+    }
+
+    #[test]
+    fn applicators() {
+        let schema = json!({
+            "anyOf": [{"type": "string"}, {"type": "number"}],
+            "oneOf": [{"minimum": 0}, {"maximum": 10}],
+            "not": {"const": 5}
+        });
+        let schema = Schema::new(&schema).unwrap();
+        assert!(schema.is_valid(&json!(-1)));
+        assert!(schema.is_valid(&json!(11)));
+        assert_eq!(
+            schema.validate(&json!(null)).errors.iter().map(|e| e.keyword_location.as_str()).collect::<Vec<_>>(),
+            vec!["/anyOf", "/anyOf/0/type", "/anyOf/1/type", "/oneOf"]
+        );
+        assert_eq!(schema.validate(&json!(3)).errors[0].error, "matches subschemas 0, 1, but must match exactly one");
+        assert_eq!(
+            schema.validate(&json!(5)).errors.iter().map(|e| e.keyword_location.as_str()).collect::<Vec<_>>(),
+            vec!["/oneOf", "/not"]
+        );
+
+        let schema = json!({
+            "allOf": [{"required": ["kind"]}],
+            "if": {"properties": {"kind": {"const": "a"}}},
+            "then": {"required": ["a"]},
+            "else": {"required": ["b"]}
+        });
+        assert_eq!(errors(schema.clone(), json!({"kind": "a", "b": 1})), locations(&[("/then/required", "")]));
+        assert_eq!(errors(schema.clone(), json!({"kind": "x", "b": 1})), vec![]);
+        assert_eq!(errors(schema, json!({})), locations(&[("/allOf/0/required", ""), ("/then/required", "")]));
+    }
+
+    #[test]
+    fn references() {
+        let schema = json!({
+            "$id": "https://example.com/tree",
+            "$defs": {
+                "node": {
+                    "$anchor": "node",
+                    "type": "object",
+                    "properties": {
+                        "value": {"$ref": "#/$defs/positive"},
+                        "children": {"type": "array", "items": {"$ref": "#node"}}
+                    }
+                },
+                "positive": {"type": "number", "exclusiveMinimum": 0},
+                "a~b/c%": {"$ref": "https://example.com/tree#/$defs/positive"}
+            },
+            "properties": {"root": {"$ref": "#/$defs/node"}, "weird": {"$ref": "#/$defs/a~0b~1c%25"}}
+        });
+        let schema = Schema::new(&schema).unwrap();
+        assert!(schema.is_valid(&json!({"root": {"value": 1, "children": [{"value": 2, "children": []}]}, "weird": 1})));
+        let output = schema.validate(&json!({"root": {"children": [{"value": 0}]}}));
+        assert_eq!(output.errors, vec![ValidationError {
+            keyword_location: "/properties/root/$ref/properties/children/items/$ref/properties/value/$ref/exclusiveMinimum".to_string(),
+            absolute_keyword_location: Some("https://example.com/tree#/$defs/positive/exclusiveMinimum".to_string()),
+            instance_location: "/root/children/0/value".to_string(),
+            error: "0 is not greater than 0".to_string()
+        }]);
+        assert_eq!(
+            output.to_json(),
+            json!({"valid": false, "errors": [{
+                "keywordLocation": "/properties/root/$ref/properties/children/items/$ref/properties/value/$ref/exclusiveMinimum",
+                "absoluteKeywordLocation": "https://example.com/tree#/$defs/positive/exclusiveMinimum",
+                "instanceLocation": "/root/children/0/value",
+                "error": "0 is not greater than 0"
+            }]})
+        );
+        assert_eq!(schema.validate(&json!({"weird": -1})).errors[0].keyword_location, "/properties/weird/$ref/$ref/exclusiveMinimum");
+        assert_eq!(schema.validate(&json!({})).to_json(), json!({"valid": true}));
+
+        // A cycle that never reaches an assertion
+        assert_eq!(errors(json!({"$ref": "#"}), json!(1)), locations(&[("/$ref/$ref", "")]));
+        assert_eq!(errors(json!({"$defs": {"a": {"$ref": "#/$defs/b"}, "b": {"$ref": "#/$defs/a"}}, "$ref": "#/$defs/a"}), json!(1)).len(), 1);
+        // Recursion into the instance is not a cycle, however deep
+        let list = json!({"$defs": {"node": {"type": "object", "properties": {"next": {"$ref": "#/$defs/node"}}}}, "$ref": "#/$defs/node"});
+        let mut deep = json!({});
+        for _ in 0..200 {
+            deep = json!({"next": deep});
+        }
+        assert!(Schema::new(&list).unwrap().is_valid(&deep));
+        // Targets outside the known keywords are compiled too
+        assert_eq!(errors(json!({"$ref": "#/components/id", "components": {"id": {"pattern": "^a"}}}), json!("b")), locations(&[("/$ref/pattern", "")]));
+    }
+
+    #[test]
+    fn schema_errors() {
+        assert_eq!(Schema::new(&json!({"items": 1})).unwrap_err(), SchemaError::NotASchema { path: "/items".to_string() });
+        assert!(matches!(
+            Schema::new(&json!({"properties": {"a/b": {"pattern": "("}}})),
+            Err(SchemaError::InvalidPattern { path, .. }) if path == "/properties/a~1b/pattern"
+        ));
+        assert_eq!(
+            validate(&json!({"allOf": [{"$ref": "#/$defs/missing"}]}), &json!(1)),
+            Err(SchemaError::UnresolvedRef { path: "/allOf/0/$ref".to_string(), reference: "#/$defs/missing".to_string() })
+        );
+    }
+
+    #[test]
+    fn formats() {
+        let valid = [
+            ("date-time", "2024-02-29T23:59:60.5+01:00"), ("date-time", "1990-12-31t15:59:59z"), ("date", "2000-02-29"),
+            ("time", "08:30:00Z"), ("email", "a.b+c@example.com"), ("hostname", "sub.example-1.com"), ("ipv4", "192.168.0.1"),
+            ("ipv6", "::1"), ("uri", "https://example.com/a?b=c#d"), ("uri-reference", "../a%20b"),
+            ("uuid", "123e4567-e89b-12d3-a456-426614174000"), ("regex", "^a+$"), ("json-pointer", "/a~1b"), ("unknown", "anything")
+        ];
+        for (format, text) in valid {
+            assert!(format_matches(format, text).unwrap_or(true), "{format}: {text}");
+        }
+        let invalid = [
+            ("date-time", "2024-02-29 23:59:59Z"), ("date", "2023-02-29"), ("date", "2023-1-01"), ("time", "24:00:00Z"),
+            ("time", "08:30:00"), ("email", "no-at-sign"), ("hostname", "-bad.com"), ("ipv4", "256.1.1.1"), ("ipv4", "01.1.1.1"),
+            ("ipv6", "1:::2"), ("uri", "no scheme"), ("uri", "/relative"), ("uuid", "123e4567e89b12d3a456426614174000"),
+            ("regex", "(unclosed"), ("json-pointer", "no-slash")
+        ];
+        for (format, text) in invalid {
+            assert_eq!(format_matches(format, text), Some(false), "{format}: {text}");
+        }
+        assert_eq!(errors(json!({"format": "email"}), json!("nope")), locations(&[("/format", "")]));
+        assert_eq!(errors(json!({"format": "email"}), json!(1)), vec![]);
+    }
+}
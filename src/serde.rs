@@ -0,0 +1,962 @@
+//! serde support, behind the `serde` feature.
+//!
+//! `Deserializer` reads JSON text straight into the target type, walking the
+//! input with the same `parse_*_with` functions as the parser instead of
+//! building a `JsonType` first, so `ParserOptions` apply here too. Strings
+//! without escapes are borrowed from the input. `Serializer` writes compact
+//! JSON in the order the fields are serialized.
+//!
+//! ```
+//! use JSON_parser::serde::{from_str, to_string};
+//! use JSON_parser::parser::JsonType;
+//!
+//! let value: Vec<(String, u8)> = from_str(r#"[["a", 1], ["b", 2]]"#).unwrap();
+//! assert_eq!(value, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+//! assert_eq!(to_string(&value).unwrap(), r#"[["a",1],["b",2]]"#);
+//!
+//! let json: JsonType = from_str(r#"{"k": [true, null]}"#).unwrap();
+//! assert_eq!(to_string(&json).unwrap(), r#"{"k":[true,null]}"#);
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::ser::{self, Serialize};
+
+use crate::parser::{
+    parse_identifier, parse_null_with, parse_string_raw_with, parse_value_with, skip_whitespace,
+    JsonType, ParseError, ParserOptions
+};
+use crate::serializer::{escape_string, format_number};
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Error {
+    /// The input is not valid JSON, `position` is the byte offset of the
+    /// value or token that failed.
+    Parse { position: usize, error: ParseError },
+    /// Reported by a `Serialize` or `Deserialize` implementation, for example
+    /// a missing field or a value of the wrong type.
+    Custom(String)
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse { position, error } => return write!(f, "{error:?} at byte {position}"),
+            Error::Custom(message) => return f.write_str(message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Error {
+        return Error::Custom(message.to_string())
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(message: T) -> Error {
+        return Error::Custom(message.to_string())
+    }
+}
+
+pub fn from_str<'de, T: de::Deserialize<'de>>(data: &'de str) -> Result<T, Error> {
+    return from_str_with(data, ParserOptions::default())
+}
+
+/// Deserializes a complete JSON text: a single value with nothing but
+/// whitespace around it.
+pub fn from_str_with<'de, T: de::Deserialize<'de>>(data: &'de str, options: ParserOptions) -> Result<T, Error> {
+    let mut deserializer = Deserializer::new_with(data, options);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    return Ok(value)
+}
+
+/// Serializes without any whitespace.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    return Ok(serializer.into_string())
+}
+
+pub struct Deserializer<'de> {
+    input: &'de str,
+    rest: &'de str,
+    options: ParserOptions
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(data: &'de str) -> Deserializer<'de> {
+        return Deserializer::new_with(data, ParserOptions::default())
+    }
+
+    pub fn new_with(data: &'de str, options: ParserOptions) -> Deserializer<'de> {
+        return Deserializer { input: data, rest: data, options }
+    }
+
+    /// Checks that only whitespace is left after the value.
+    pub fn end(&mut self) -> Result<(), Error> {
+        match self.peek()? {
+            None => return Ok(()),
+            Some(c) => return Err(self.error(ParseError::UnexpectedChar { expected: "end of input".to_string(), found: c }))
+        }
+    }
+
+    fn error(&self, error: ParseError) -> Error {
+        return Error::Parse { position: self.input.len() - self.rest.len(), error }
+    }
+
+    /// Skips whitespace and returns the next character without consuming it.
+    fn peek(&mut self) -> Result<Option<char>, Error> {
+        match skip_whitespace(self.rest, self.options) {
+            Ok(rest) => {self.rest = rest},
+            Err(e) => {return Err(self.error(e))}
+        }
+        return Ok(self.rest.chars().next())
+    }
+
+    /// Consumes `expected` as the next character, `error` builds the error
+    /// for any other character.
+    fn expect(&mut self, expected: char, error: fn(char) -> ParseError) -> Result<(), Error> {
+        match self.peek()? {
+            Some(c) if c == expected => {
+                self.rest = self.rest.get(c.len_utf8()..).unwrap();
+                return Ok(())
+            },
+            Some(c) => return Err(self.error(error(c))),
+            None => return Err(self.error(ParseError::UnexpectedEof))
+        }
+    }
+
+    /// Runs `f` one nesting level deeper, failing once `max_depth` is used up.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Deserializer<'de>) -> Result<T, Error>) -> Result<T, Error> {
+        let outer = self.options;
+        match outer.nested() {
+            Ok(options) => {self.options = options},
+            Err(e) => {return Err(self.error(e))}
+        }
+        let result = f(self);
+        self.options = outer;
+        return result
+    }
+
+    fn visit_string<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value, Error> {
+        match self.parse_str()? {
+            Cow::Borrowed(content) => return visitor.visit_borrowed_str(content),
+            Cow::Owned(output) => return visitor.visit_string(output)
+        }
+    }
+
+    /// Reads a string, borrowed from the input when it has no escapes.
+    fn parse_str(&mut self) -> Result<Cow<'de, str>, Error> {
+        let data = self.rest;
+        let (output, rest) = match parse_string_raw_with(data, self.options) {
+            Ok(r) => r,
+            Err(e) => {return Err(self.error(e))}
+        };
+        self.rest = rest;
+
+        // Without escapes the decoded string is exactly the text between the quotes
+        let raw = data.get(..(data.len() - rest.len())).unwrap();
+        let quote = raw.chars().last().unwrap();
+        let content = raw.get((raw.find(quote).unwrap() + 1)..(raw.len() - 1)).unwrap();
+        if !content.contains('\\') {
+            return Ok(Cow::Borrowed(content))
+        }
+        return Ok(Cow::Owned(output))
+    }
+
+    /// Reads an object key, a string or, if enabled, a bare identifier.
+    fn parse_key(&mut self) -> Result<Cow<'de, str>, Error> {
+        if self.options.allow_unquoted_keys && let Some((key, rest)) = parse_identifier(self.rest) {
+            let key = self.rest.get(..key.len()).unwrap();
+            self.rest = rest;
+            return Ok(Cow::Borrowed(key))
+        }
+        return self.parse_str()
+    }
+}
+
+/// Integers are visited as `u64` or `i64` when their text allows it, so they
+/// deserialize into integer types, everything else as `f64`.
+fn visit_number<'de, V: Visitor<'de>>(text: &str, value: f64, visitor: V) -> Result<V::Value, Error> {
+    if let Ok(n) = text.parse::<u64>() {
+        return visitor.visit_u64(n)
+    }
+    if let Ok(n) = text.parse::<i64>() {
+        return visitor.visit_i64(n)
+    }
+    let unsigned = text.trim_start_matches(['-', '+']);
+    let hex = unsigned.starts_with("0x") || unsigned.starts_with("0X");
+    if hex && value.abs() < 9007199254740992.0 {
+        if value < 0.0 {
+            return visitor.visit_i64(value as i64)
+        }
+        return visitor.visit_u64(value as u64)
+    }
+    return visitor.visit_f64(value)
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.peek()? {
+            Some('{') => {
+                let value = self.nested(|de| {
+                    de.rest = de.rest.get(1..).unwrap();
+                    return visitor.visit_map(Access { de, first: true })
+                })?;
+                self.expect('}', |found| ParseError::UnexpectedChar { expected: "},,".to_string(), found })?;
+                return Ok(value)
+            },
+            Some('[') => {
+                let value = self.nested(|de| {
+                    de.rest = de.rest.get(1..).unwrap();
+                    return visitor.visit_seq(Access { de, first: true })
+                })?;
+                self.expect(']', |_| ParseError::MalformedArray)?;
+                return Ok(value)
+            },
+            Some('"') => return self.visit_string(visitor),
+            Some('\'') if self.options.allow_single_quotes => return self.visit_string(visitor),
+            Some(_) => {},
+            None => return Err(self.error(ParseError::UnexpectedEof))
+        }
+
+        // Scalars other than strings are read with the parser itself
+        let data = self.rest;
+        let (value, rest) = match parse_value_with(data, self.options) {
+            Ok(r) => r,
+            Err(e) => {return Err(self.error(e))}
+        };
+        self.rest = rest;
+        match value {
+            JsonType::JsonNumber(n) => return visit_number(data.get(..(data.len() - rest.len())).unwrap(), n, visitor),
+            JsonType::JsonBool(b) => return visitor.visit_bool(b),
+            JsonType::JsonNull => return visitor.visit_unit(),
+            _ => unreachable!("containers and strings are handled above")
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.peek()?;
+        match parse_null_with(self.rest, self.options) {
+            Ok((_, rest)) => {
+                self.rest = rest;
+                return visitor.visit_none()
+            },
+            Err(_) => return visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        return visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are strings, all others an object with the variant name
+    /// as its only key.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V
+    ) -> Result<V::Value, Error> {
+        match self.peek()? {
+            Some('{') => {
+                let value = self.nested(|de| {
+                    de.rest = de.rest.get(1..).unwrap();
+                    return visitor.visit_enum(Variant { de })
+                })?;
+                self.expect('}', |found| ParseError::UnexpectedChar { expected: "},,".to_string(), found })?;
+                return Ok(value)
+            },
+            Some('"') | Some('\'') => {
+                let name = self.parse_key()?;
+                return visitor.visit_enum(name.into_deserializer())
+            },
+            _ => return self.deserialize_any(visitor)
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+/// Elements of an array or members of an object. The closing bracket is left
+/// for the caller, which also reports elements the visitor did not read.
+struct Access<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool
+}
+
+impl Access<'_, '_> {
+    /// Steps over the `,` before the next entry, `false` at the closing bracket.
+    fn has_next(&mut self, close: char) -> Result<bool, Error> {
+        if self.de.peek()? == Some(close) {
+            return Ok(false)
+        }
+        if !self.first {
+            match close {
+                ']' => self.de.expect(',', |_| ParseError::MalformedArray)?,
+                _ => self.de.expect(',', |found| ParseError::UnexpectedChar { expected: "},,".to_string(), found })?
+            }
+            // A single trailing comma may close the container
+            if self.de.options.allow_trailing_commas && self.de.peek()? == Some(close) {
+                return Ok(false)
+            }
+        }
+        self.first = false;
+        return Ok(true)
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Access<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        if !self.has_next(']')? {
+            return Ok(None)
+        }
+        return seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Access<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        if !self.has_next('}')? {
+            return Ok(None)
+        }
+        let key = self.de.parse_key()?;
+        return seed.deserialize(Key(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        self.de.expect(':', |found| ParseError::UnexpectedChar { expected: ":".to_string(), found })?;
+        return seed.deserialize(&mut *self.de)
+    }
+}
+
+/// An object key. Keys are always strings, but maps with number or boolean
+/// keys read them from the string, as in `{"1": ...}`. Keys without escapes
+/// are borrowed from the input.
+struct Key<'de>(Cow<'de, str>);
+
+impl<'de> Key<'de> {
+    fn visit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Cow::Borrowed(key) => return visitor.visit_borrowed_str(key),
+            Cow::Owned(key) => return visitor.visit_string(key)
+        }
+    }
+}
+
+macro_rules! deserialize_key_from_str {
+    ($($method:ident => $visit:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse() {
+                    Ok(value) => return visitor.$visit(value),
+                    Err(_) => return self.visit(visitor)
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Key<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        return self.visit(visitor)
+    }
+
+    deserialize_key_from_str! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8, deserialize_i16 => visit_i16, deserialize_i32 => visit_i32, deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8, deserialize_u16 => visit_u16, deserialize_u32 => visit_u32, deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32, deserialize_f64 => visit_f64
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        return visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        return visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V
+    ) -> Result<V::Value, Error> {
+        return visitor.visit_enum(self.0.into_deserializer())
+    }
+
+    serde::forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// The `"name": content` inside an externally tagged enum object.
+struct Variant<'a, 'de> {
+    de: &'a mut Deserializer<'de>
+}
+
+impl<'de> de::EnumAccess<'de> for Variant<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        self.de.peek()?;
+        let name = self.de.parse_key()?;
+        let variant = seed.deserialize(name.into_deserializer())?;
+        self.de.expect(':', |found| ParseError::UnexpectedChar { expected: ":".to_string(), found })?;
+        return Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Variant<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        return de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        return seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        return de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        return de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+#[derive(Default)]
+pub struct Serializer {
+    output: String
+}
+
+impl Serializer {
+    pub fn new() -> Serializer {
+        return Serializer { output: String::new() }
+    }
+
+    pub fn into_string(self) -> String {
+        return self.output
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Compound<'a>;
+    type SerializeTuple = Compound<'a>;
+    type SerializeTupleStruct = Compound<'a>;
+    type SerializeTupleVariant = Compound<'a>;
+    type SerializeMap = Compound<'a>;
+    type SerializeStruct = Compound<'a>;
+    type SerializeStructVariant = Compound<'a>;
+
+    fn serialize_bool(self, value: bool) -> Result<(), Error> {
+        self.output.push_str(if value {"true"} else {"false"});
+        return Ok(())
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<(), Error> {
+        return self.serialize_i64(value as i64)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<(), Error> {
+        return self.serialize_i64(value as i64)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<(), Error> {
+        return self.serialize_i64(value as i64)
+    }
+
+    // Integers are written exactly, even where `JsonType` would round them
+    fn serialize_i64(self, value: i64) -> Result<(), Error> {
+        self.output.push_str(&value.to_string());
+        return Ok(())
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<(), Error> {
+        self.output.push_str(&value.to_string());
+        return Ok(())
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<(), Error> {
+        return self.serialize_u64(value as u64)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<(), Error> {
+        return self.serialize_u64(value as u64)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<(), Error> {
+        return self.serialize_u64(value as u64)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<(), Error> {
+        self.output.push_str(&value.to_string());
+        return Ok(())
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<(), Error> {
+        self.output.push_str(&value.to_string());
+        return Ok(())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<(), Error> {
+        return self.serialize_f64(value as f64)
+    }
+
+    /// `NaN` and infinities are written as `null`, see `format_number`.
+    fn serialize_f64(self, value: f64) -> Result<(), Error> {
+        self.output.push_str(&format_number(value));
+        return Ok(())
+    }
+
+    fn serialize_char(self, value: char) -> Result<(), Error> {
+        self.output.push_str(&escape_string(&value.to_string()));
+        return Ok(())
+    }
+
+    fn serialize_str(self, value: &str) -> Result<(), Error> {
+        self.output.push_str(&escape_string(value));
+        return Ok(())
+    }
+
+    /// Bytes become an array of numbers.
+    fn serialize_bytes(self, value: &[u8]) -> Result<(), Error> {
+        return value.serialize(self)
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        return self.serialize_unit()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+        return value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.output.push_str("null");
+        return Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        return self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<(), Error> {
+        return self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<(), Error> {
+        return value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T
+    ) -> Result<(), Error> {
+        self.output.push('{');
+        self.output.push_str(&escape_string(variant));
+        self.output.push(':');
+        value.serialize(&mut *self)?;
+        self.output.push('}');
+        return Ok(())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.output.push('[');
+        return Ok(Compound { serializer: self, first: true, close: "]" })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a>, Error> {
+        return self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>, Error> {
+        return self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize
+    ) -> Result<Compound<'a>, Error> {
+        self.output.push('{');
+        self.output.push_str(&escape_string(variant));
+        self.output.push_str(":[");
+        return Ok(Compound { serializer: self, first: true, close: "]}" })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a>, Error> {
+        self.output.push('{');
+        return Ok(Compound { serializer: self, first: true, close: "}" })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a>, Error> {
+        return self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize
+    ) -> Result<Compound<'a>, Error> {
+        self.output.push('{');
+        self.output.push_str(&escape_string(variant));
+        self.output.push_str(":{");
+        return Ok(Compound { serializer: self, first: true, close: "}}" })
+    }
+}
+
+/// An array or object being written, `close` ends it.
+pub struct Compound<'a> {
+    serializer: &'a mut Serializer,
+    first: bool,
+    close: &'static str
+}
+
+impl Compound<'_> {
+    fn separator(&mut self) {
+        if !self.first {
+            self.serializer.output.push(',');
+        }
+        self.first = false;
+    }
+
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.separator();
+        return value.serialize(&mut *self.serializer)
+    }
+
+    /// Object keys must be strings. Numbers and booleans are quoted, as in
+    /// `{"1": ...}` for a map with integer keys.
+    fn key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.separator();
+        let mut serializer = Serializer::new();
+        key.serialize(&mut serializer)?;
+        let text = serializer.into_string();
+        match text.chars().next() {
+            Some('"') => self.serializer.output.push_str(&text),
+            Some('-' | '0'..='9' | 't' | 'f') => self.serializer.output.push_str(&escape_string(&text)),
+            _ => {return Err(Error::Custom(format!("object keys must be strings, found {text}")))}
+        }
+        self.serializer.output.push(':');
+        return Ok(())
+    }
+
+    fn end(self) -> Result<(), Error> {
+        self.serializer.output.push_str(self.close);
+        return Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        return self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        return Compound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        return self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        return Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        return self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        return Compound::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        return self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        return Compound::end(self)
+    }
+}
+
+impl ser::SerializeMap for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        return self.key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        return value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        return Compound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.key(key)?;
+        return value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        return Compound::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Compound<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.key(key)?;
+        return value.serialize(&mut *self.serializer)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        return Compound::end(self)
+    }
+}
+
+/// Objects are serialized with their keys sorted, like `serializer::to_string`.
+/// Integral numbers within the exact range of `f64` are serialized as `i64`,
+/// so formats with a separate integer type keep them as integers.
+impl Serialize for JsonType {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            JsonType::JsonObject(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                let mut object = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    ser::SerializeMap::serialize_entry(&mut object, key, value)?;
+                }
+                return ser::SerializeMap::end(object)
+            },
+            JsonType::JsonArray(values) => return values.serialize(serializer),
+            JsonType::JsonString(s) => return serializer.serialize_str(s),
+            JsonType::JsonNumber(n) if n.fract() == 0.0 && n.abs() < 9007199254740992.0 => return serializer.serialize_i64(*n as i64),
+            JsonType::JsonNumber(n) => return serializer.serialize_f64(*n),
+            JsonType::JsonBool(b) => return serializer.serialize_bool(*b),
+            JsonType::JsonNull => return serializer.serialize_unit()
+        }
+    }
+}
+
+struct JsonTypeVisitor;
+
+impl<'de> Visitor<'de> for JsonTypeVisitor {
+    type Value = JsonType;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str("any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<JsonType, E> {
+        return Ok(JsonType::JsonBool(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<JsonType, E> {
+        return Ok(JsonType::JsonNumber(value as f64))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<JsonType, E> {
+        return Ok(JsonType::JsonNumber(value as f64))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<JsonType, E> {
+        return Ok(JsonType::JsonNumber(value))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<JsonType, E> {
+        return Ok(JsonType::JsonString(value.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<JsonType, E> {
+        return Ok(JsonType::JsonString(value))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<JsonType, E> {
+        return Ok(JsonType::JsonNull)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<JsonType, E> {
+        return Ok(JsonType::JsonNull)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<JsonType, D::Error> {
+        return de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<JsonType, A::Error> {
+        let mut values = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        return Ok(JsonType::JsonArray(values))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<JsonType, A::Error> {
+        let mut output = HashMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            output.insert(key, value);
+        }
+        return Ok(JsonType::JsonObject(output))
+    }
+}
+
+impl<'de> de::Deserialize<'de> for JsonType {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<JsonType, D::Error> {
+        return deserializer.deserialize_any(JsonTypeVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::parser::{parse_document, Dialect};
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct User<'a> {
+        name: String,
+        #[serde(borrow)]
+        nickname: Cow<'a, str>,
+        age: u8,
+        email: Option<String>,
+        scores: Vec<f64>,
+        role: Role
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Role {
+        Guest,
+        Member(u32),
+        Admin { level: i8 },
+        Pair(bool, bool)
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = r#"{"name":"Alice","nickname":"al","age":31,"email":null,"scores":[1.5,-2,3e-7],"role":{"Admin":{"level":-1}}}"#;
+        let user: User = from_str(text).unwrap();
+        assert!(matches!(user.nickname, Cow::Borrowed("al")));
+        assert_eq!(user.scores, vec![1.5, -2.0, 3e-7]);
+        assert_eq!(to_string(&user).unwrap(), text);
+
+        for (role, text) in [
+            (Role::Guest, r#""Guest""#),
+            (Role::Member(7), r#"{"Member":7}"#),
+            (Role::Pair(true, false), r#"{"Pair":[true,false]}"#)
+        ] {
+            assert_eq!(to_string(&role).unwrap(), text);
+            assert_eq!(from_str::<Role>(text), Ok(role));
+        }
+
+        let map: BTreeMap<u32, Option<char>> = from_str(r#" { "1" : "x", "20": null } "#).unwrap();
+        assert_eq!(to_string(&map).unwrap(), r#"{"1":"x","20":null}"#);
+        let map: HashMap<&str, u8> = from_str(r#"{"a": 1, "b": 2}"#).unwrap();
+        assert_eq!(map, HashMap::from([("a", 1), ("b", 2)]));
+        assert!(from_str::<HashMap<&str, u8>>(r#"{"\u0061": 1}"#).is_err());
+        assert_eq!(from_str::<String>(r#""a\né""#), Ok("a\né".to_string()));
+        assert_eq!(to_string(&(f64::NAN, u64::MAX, "\"")).unwrap(), r#"[null,18446744073709551615,"\""]"#);
+        assert!(to_string(&BTreeMap::from([((), 1)])).is_err());
+    }
+
+    #[test]
+    fn json_type() {
+        let text = r#"{"a": [1, 2.5, "s", true, null, {}], "b": {"c": -3}}"#;
+        let value: JsonType = from_str(text).unwrap();
+        assert_eq!(value, parse_document(text).unwrap());
+        assert_eq!(to_string(&value).unwrap(), value.to_string());
+    }
+
+    #[test]
+    fn options() {
+        let options = ParserOptions::from(Dialect::Json5);
+        let value: BTreeMap<String, Vec<i32>> = from_str_with("{a: [0x10, +2,], // c\n 'b': []}", options).unwrap();
+        assert_eq!(value, BTreeMap::from([("a".to_string(), vec![16, 2]), ("b".to_string(), vec![])]));
+
+        let shallow = ParserOptions { max_depth: Some(1), ..ParserOptions::default() };
+        assert_eq!(from_str_with::<JsonType>("[[1]]", shallow), Err(Error::Parse { position: 1, error: ParseError::MaxDepthExceeded }));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            from_str::<Vec<u8>>("[1, 2"),
+            Err(Error::Parse { position: 5, error: ParseError::UnexpectedEof })
+        );
+        assert_eq!(
+            from_str::<JsonType>("[1] x"),
+            Err(Error::Parse { position: 4, error: ParseError::UnexpectedChar { expected: "end of input".to_string(), found: 'x' } })
+        );
+        assert_eq!(from_str::<(u8, u8)>("[1, 2, 3]"), Err(Error::Parse { position: 5, error: ParseError::MalformedArray }));
+        assert_eq!(from_str::<u8>("300"), Err(Error::Custom("invalid value: integer `300`, expected u8".to_string())));
+        assert_eq!(from_str::<u8>("1.5"), Err(Error::Custom("invalid type: floating point `1.5`, expected u8".to_string())));
+        assert!(matches!(from_str::<User>(r#"{"name": "x"}"#), Err(Error::Custom(m)) if m == "missing field `nickname`"));
+    }
+}
@@ -0,0 +1,274 @@
+//! Turns a `JsonType` back into JSON text.
+//!
+//! Object keys are written in sorted order so the output is deterministic,
+//! `JsonObject` does not remember the order it was parsed in. To reformat a
+//! document without reordering it, serialize its concrete syntax tree with
+//! `node_to_string` or `node_to_string_indented` instead.
+
+use std::fmt;
+
+use crate::cst::{Node, NodeKind};
+use crate::parser::{parse_number_with, parse_string_raw_with, JsonType, ParserOptions};
+
+/// Quotes and escapes a string the way JSON requires.
+pub fn escape_string(data: &str) -> String {
+    let mut output = String::with_capacity(data.len() + 2);
+    output.push('"');
+    for c in data.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            c if c < ' ' => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c)
+        }
+    }
+    output.push('"');
+    return output
+}
+
+/// Formats a number like JavaScript does, integers without a fraction and very
+/// large or small magnitudes with an exponent. `NaN` and infinities have no
+/// JSON representation and are written as `null`.
+pub fn format_number(value: f64) -> String {
+    if !value.is_finite() {
+        return "null".to_string()
+    }
+    let magnitude = value.abs();
+    if magnitude >= 1e21 || (magnitude != 0.0 && magnitude < 1e-6) {
+        return format!("{value:e}")
+    }
+    return format!("{value}")
+}
+
+fn sorted_entries(map: &std::collections::HashMap<String, JsonType>) -> Vec<(&String, &JsonType)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    return entries
+}
+
+fn write_compact(output: &mut String, value: &JsonType) {
+    match value {
+        JsonType::JsonObject(map) => {
+            output.push('{');
+            for (i, (key, value)) in sorted_entries(map).into_iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                output.push_str(&escape_string(key));
+                output.push(':');
+                write_compact(output, value);
+            }
+            output.push('}');
+        },
+        JsonType::JsonArray(values) => {
+            output.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                write_compact(output, value);
+            }
+            output.push(']');
+        },
+        JsonType::JsonString(s) => output.push_str(&escape_string(s)),
+        JsonType::JsonNumber(n) => output.push_str(&format_number(*n)),
+        JsonType::JsonBool(b) => output.push_str(if *b {"true"} else {"false"}),
+        JsonType::JsonNull => output.push_str("null")
+    }
+}
+
+fn write_pretty(output: &mut String, value: &JsonType, indent: &str, depth: usize) {
+    let newline = |output: &mut String, depth: usize| {
+        output.push('\n');
+        for _ in 0..depth {
+            output.push_str(indent);
+        }
+    };
+    match value {
+        JsonType::JsonObject(map) if !map.is_empty() => {
+            output.push('{');
+            for (i, (key, value)) in sorted_entries(map).into_iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                newline(output, depth + 1);
+                output.push_str(&escape_string(key));
+                output.push_str(": ");
+                write_pretty(output, value, indent, depth + 1);
+            }
+            newline(output, depth);
+            output.push('}');
+        },
+        JsonType::JsonArray(values) if !values.is_empty() => {
+            output.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                newline(output, depth + 1);
+                write_pretty(output, value, indent, depth + 1);
+            }
+            newline(output, depth);
+            output.push(']');
+        },
+        value => write_compact(output, value)
+    }
+}
+
+/// Serializes without any whitespace.
+pub fn to_string(value: &JsonType) -> String {
+    let mut output = String::new();
+    write_compact(&mut output, value);
+    return output
+}
+
+/// Serializes with one entry per line, indented by `indent` spaces per level.
+pub fn to_string_pretty(value: &JsonType, indent: usize) -> String {
+    return to_string_indented(value, &" ".repeat(indent))
+}
+
+/// Like `to_string_pretty`, with an arbitrary indentation unit such as `"\t"`.
+pub fn to_string_indented(value: &JsonType, indent: &str) -> String {
+    let mut output = String::new();
+    write_pretty(&mut output, value, indent, 0);
+    return output
+}
+
+/// The JSON text of a scalar node. Numbers and double-quoted strings keep
+/// their source text, so `1.50` or `"\u00e9"` are written as they were and
+/// integers beyond the precision of `f64` survive. JSON5-only spellings such
+/// as `'single'`, `"\x41"` or `0x1F` are rewritten from the parsed value.
+fn scalar_text(text: &str, value: &JsonType) -> String {
+    let strict = ParserOptions { strict: true, ..ParserOptions::default() };
+    match value {
+        JsonType::JsonString(s) => {
+            match parse_string_raw_with(text, strict) {
+                Ok((_, "")) => return text.to_string(),
+                _ => return escape_string(s)
+            }
+        },
+        JsonType::JsonNumber(n) => {
+            match parse_number_with(text, strict) {
+                Ok((_, "")) => return text.to_string(),
+                _ => return format_number(*n)
+            }
+        },
+        value => return to_string(value)
+    }
+}
+
+fn write_node(output: &mut String, node: &Node, indent: Option<&str>, depth: usize) {
+    let newline = |output: &mut String, depth: usize| {
+        if let Some(indent) = indent {
+            output.push('\n');
+            for _ in 0..depth {
+                output.push_str(indent);
+            }
+        }
+    };
+    match &node.kind {
+        NodeKind::Scalar { text, value } => output.push_str(&scalar_text(text, value)),
+        NodeKind::Array { elements, .. } if elements.is_empty() => output.push_str("[]"),
+        NodeKind::Object { members, .. } if members.is_empty() => output.push_str("{}"),
+        NodeKind::Array { elements, .. } => {
+            output.push('[');
+            for (i, element) in elements.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                newline(output, depth + 1);
+                write_node(output, element, indent, depth + 1);
+            }
+            newline(output, depth);
+            output.push(']');
+        },
+        NodeKind::Object { members, .. } => {
+            output.push('{');
+            for (i, member) in members.iter().enumerate() {
+                if i > 0 {
+                    output.push(',');
+                }
+                newline(output, depth + 1);
+                output.push_str(&escape_string(member.name()));
+                output.push_str(if indent.is_some() {": "} else {":"});
+                write_node(output, &member.value, indent, depth + 1);
+            }
+            newline(output, depth);
+            output.push('}');
+        }
+    }
+}
+
+/// Serializes a syntax tree without any whitespace, keeping the order of
+/// object members. Comments are dropped.
+pub fn node_to_string(node: &Node) -> String {
+    let mut output = String::new();
+    write_node(&mut output, node, None, 0);
+    return output
+}
+
+/// Like `node_to_string`, with one entry per line indented by `indent` per level.
+pub fn node_to_string_indented(node: &Node, indent: &str) -> String {
+    let mut output = String::new();
+    write_node(&mut output, node, Some(indent), 0);
+    return output
+}
+
+impl fmt::Display for JsonType {
+    /// Writes compact JSON, see `serializer::to_string`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return f.write_str(&to_string(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::parser::parse_document;
+
+    #[test]
+    fn compact() {
+        let value = parse_document(r#"{"b": [1, 2.5, -3e-9], "a": {"x": null, "y": true}, "s": "q\"\n\u0001"}"#).unwrap();
+        assert_eq!(to_string(&value), r#"{"a":{"x":null,"y":true},"b":[1,2.5,-3e-9],"s":"q\"\n\u0001"}"#);
+        assert_eq!(value.to_string(), to_string(&value));
+        assert_eq!(parse_document(&to_string(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn pretty() {
+        let value = parse_document(r#"{"b": [1, []], "a": {}}"#).unwrap();
+        assert_eq!(to_string_pretty(&value, 2), "{\n  \"a\": {},\n  \"b\": [\n    1,\n    []\n  ]\n}");
+        assert_eq!(to_string_indented(&JsonType::JsonArray(vec![JsonType::JsonNull]), "\t"), "[\n\tnull\n]");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(format_number(3.0), "3");
+        assert_eq!(format_number(-0.5), "-0.5");
+        assert_eq!(format_number(1e21), "1e21");
+        assert_eq!(format_number(1e20), "100000000000000000000");
+        assert_eq!(format_number(f64::NAN), "null");
+        assert_eq!(to_string(&JsonType::JsonObject(HashMap::new())), "{}");
+    }
+
+    #[test]
+    fn nodes() {
+        let node = crate::cst::parse(r#" {"b": [1.50, 12345678901234567890, "\u00e9"], "a": {}, "c": [[]]} "#).unwrap();
+        assert_eq!(node_to_string(&node), r#"{"b":[1.50,12345678901234567890,"\u00e9"],"a":{},"c":[[]]}"#);
+        assert_eq!(node_to_string_indented(&node, "  "), "{\n  \"b\": [\n    1.50,\n    12345678901234567890,\n    \"\\u00e9\"\n  ],\n  \"a\": {},\n  \"c\": [\n    []\n  ]\n}");
+
+        let json5 = crate::cst::parse_with("// c\n{a: 'x\"', b: [0x1F, +1, .5, Infinity, true,],}", crate::parser::Dialect::Json5.into()).unwrap();
+        assert_eq!(node_to_string(&json5), r#"{"a":"x\"","b":[31,1,0.5,null,true]}"#);
+
+        let escapes = crate::cst::parse_with(r#"{"a": "\x41\v", "b": "line\
+break", "c": "\u0041"}"#, crate::parser::Dialect::Json5.into()).unwrap();
+        assert_eq!(node_to_string(&escapes), r#"{"a":"A\u000b","b":"linebreak","c":"\u0041"}"#);
+    }
+}
@@ -0,0 +1,202 @@
+//! Maps parsed values back to where they came from in the input.
+//!
+//! `parse_with_spans` returns the usual `JsonType` together with a `SpanMap`
+//! keyed by JSON Pointer (`""` for the root, `/users/0/name` for a nested
+//! value), so an error found on the parsed value can be reported against the
+//! original text.
+
+use std::collections::HashMap;
+
+use crate::cst::{self, Node, NodeKind, Span};
+use crate::parser::{JsonType, ParseError, ParserOptions};
+use crate::pointer::escape_token;
+
+/// A 1-based line and column. Columns count characters, not bytes.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize
+}
+
+/// Where a value sits in the input, as bytes and as line/column.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Location {
+    pub span: Span,
+    pub start: Position,
+    pub end: Position
+}
+
+/// Locations of every value of a document, keyed by JSON Pointer.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct SpanMap {
+    locations: HashMap<String, Location>
+}
+
+impl SpanMap {
+    /// Looks up the value at `pointer`, e.g. `"/servers/1/port"`.
+    pub fn get(&self, pointer: &str) -> Option<&Location> {
+        return self.locations.get(pointer)
+    }
+
+    pub fn len(&self) -> usize {
+        return self.locations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.locations.is_empty()
+    }
+
+    /// All pointers and their locations, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Location)> {
+        return self.locations.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+/// Turns byte offsets into line/column positions.
+pub struct LineIndex {
+    // Byte offset at which each line starts
+    starts: Vec<usize>
+}
+
+impl LineIndex {
+    /// `\n`, `\r\n` and a lone `\r` all end a line.
+    pub fn new(data: &str) -> LineIndex {
+        let mut starts = vec![0];
+        let bytes = data.as_bytes();
+        for (i, b) in bytes.iter().enumerate() {
+            match b {
+                b'\n' => starts.push(i + 1),
+                b'\r' if bytes.get(i + 1) != Some(&b'\n') => starts.push(i + 1),
+                _ => {}
+            }
+        }
+        return LineIndex { starts }
+    }
+
+    /// Position of the byte `offset` in `data`, which must be the text the
+    /// index was built from.
+    pub fn position(&self, data: &str, offset: usize) -> Position {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1
+        };
+        let column = data.get(self.starts[line]..offset).map(|s| s.chars().count()).unwrap_or(0) + 1;
+        return Position { line: line + 1, column }
+    }
+}
+
+/// Line and column of a byte offset, handy for the offsets returned with errors.
+pub fn position(data: &str, offset: usize) -> Position {
+    return LineIndex::new(data).position(data, offset)
+}
+
+fn collect(node: &Node, pointer: String, data: &str, index: &LineIndex, map: &mut SpanMap) {
+    match &node.kind {
+        NodeKind::Scalar { .. } => {},
+        NodeKind::Array { elements, .. } => {
+            for (i, element) in elements.iter().enumerate() {
+                collect(element, format!("{pointer}/{i}"), data, index, map);
+            }
+        },
+        NodeKind::Object { members, .. } => {
+            // With duplicate keys the last one wins, as in the parsed value, so
+            // nothing below an overwritten member may end up in the map
+            let last: HashMap<&str, usize> = members.iter().enumerate().map(|(i, member)| (member.name(), i)).collect();
+            for (i, member) in members.iter().enumerate() {
+                if last[member.name()] != i {
+                    continue
+                }
+                let child = format!("{pointer}/{}", escape_token(member.name()));
+                collect(&member.value, child, data, index, map);
+            }
+        }
+    }
+    let location = Location {
+        span: node.span,
+        start: index.position(data, node.span.start),
+        end: index.position(data, node.span.end)
+    };
+    map.locations.insert(pointer, location);
+}
+
+/// Parses a complete document and records the location of every value in it.
+///
+/// On failure the error is returned with its byte offset, see `position` to
+/// turn it into a line and column.
+pub fn parse_with_spans(data: &str) -> Result<(JsonType, SpanMap), (ParseError, usize)> {
+    return parse_with_spans_with(data, ParserOptions::default())
+}
+
+pub fn parse_with_spans_with(data: &str, options: ParserOptions) -> Result<(JsonType, SpanMap), (ParseError, usize)> {
+    let root = cst::parse_with(data, options)?;
+    let index = LineIndex::new(data);
+    let mut map = SpanMap::default();
+    collect(&root, String::new(), data, &index, &mut map);
+    return Ok((root.to_json(), map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Dialect, parse_document};
+
+    const CONFIG: &str = "{\n  \"name\": \"api\",\n  \"servers\": [\n    {\"host\": \"a\", \"port\": 80},\n    {\"host\": \"é\", \"port\": \"x\"}\n  ],\n  \"a/b~c\": null\n}";
+
+    fn text<'a>(data: &'a str, map: &SpanMap, pointer: &str) -> &'a str {
+        let span = map.get(pointer).unwrap().span;
+        return &data[span.start..span.end]
+    }
+
+    #[test]
+    fn maps_pointers_to_spans() {
+        let (value, map) = parse_with_spans(CONFIG).unwrap();
+        assert_eq!(value, parse_document(CONFIG).unwrap());
+        assert_eq!(map.len(), 10);
+        assert_eq!(text(CONFIG, &map, ""), CONFIG);
+        assert_eq!(text(CONFIG, &map, "/name"), "\"api\"");
+        assert_eq!(text(CONFIG, &map, "/servers/0"), "{\"host\": \"a\", \"port\": 80}");
+        assert_eq!(text(CONFIG, &map, "/servers/1/port"), "\"x\"");
+        assert_eq!(text(CONFIG, &map, "/a~1b~0c"), "null");
+        assert_eq!(map.get("/servers/2"), None);
+    }
+
+    #[test]
+    fn reports_lines_and_columns() {
+        let (_, map) = parse_with_spans(CONFIG).unwrap();
+        let port = map.get("/servers/1/port").unwrap();
+        // The é before it is two bytes but one column
+        assert_eq!(port.start, Position { line: 5, column: 27 });
+        assert_eq!(port.end, Position { line: 5, column: 30 });
+        assert_eq!(map.get("").unwrap().start, Position { line: 1, column: 1 });
+        assert_eq!(map.get("").unwrap().end, Position { line: 8, column: 2 });
+
+        let data = "[1,\r\n2,\r3]";
+        let (_, map) = parse_with_spans(data).unwrap();
+        assert_eq!(map.get("/1").unwrap().start, Position { line: 2, column: 1 });
+        assert_eq!(map.get("/2").unwrap().start, Position { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn with_options_and_errors() {
+        let data = "// servers\n{ports: [80, 443,],}";
+        let (_, map) = parse_with_spans_with(data, Dialect::Json5.into()).unwrap();
+        assert_eq!(map.get("/ports/1").unwrap().start, Position { line: 2, column: 14 });
+
+        let data = "{\n  \"a\": [1,\n  2,]\n}";
+        let (error, offset) = parse_with_spans(data).unwrap_err();
+        assert_eq!(error, ParseError::MalformedArray);
+        assert_eq!(position(data, offset), Position { line: 3, column: 5 });
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        let data = r#"{"a": {"old": [1]}, "b": 2, "a": {"new": 3}}"#;
+        let (value, map) = parse_with_spans(data).unwrap();
+        assert_eq!(text(data, &map, "/a"), r#"{"new": 3}"#);
+        assert_eq!(text(data, &map, "/a/new"), "3");
+        assert_eq!(map.get("/a/old"), None);
+        assert_eq!(map.get("/a/old/0"), None);
+        assert!(map.iter().all(|(pointer, _)| value.pointer(pointer).is_some()));
+        assert_eq!(map.len(), 4);
+    }
+}
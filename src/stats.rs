@@ -0,0 +1,543 @@
+//! Structural summaries of documents, for getting to know unfamiliar data.
+//!
+//! `Stats` counts the values of each type, the deepest nesting, the longest
+//! arrays, how often each key occurs, how long the strings are and the range
+//! of the numbers at each path. `analyze` summarizes one parsed value.
+//!
+//! `analyze_reader` works on a stream instead. It reads whitespace separated
+//! documents, such as NDJSON, and walks into every array and object as it
+//! reads them, so only the path to the current value is held in memory and a
+//! file of any size and nesting can be analyzed. Since it sees the text, it
+//! also finds keys that occur twice in one object, which a parsed `JsonType`
+//! cannot hold. The values of such keys are all counted, where a parsed
+//! document only has the last.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, ErrorKind};
+
+use crate::convert::ToJson;
+use crate::cst;
+use crate::parser::{parse_string_raw_with, JsonType, ParseError, ParserOptions};
+use crate::pointer::escape_token;
+
+/// How many of the longest arrays `Stats` keeps.
+pub const LARGEST_ARRAYS: usize = 10;
+
+/// Why a stream could not be analyzed.
+#[derive(PartialEq, Debug, Clone)]
+pub enum AnalyzeError {
+    /// The input is not JSON. `offset` counts bytes from the start of the stream.
+    Parse { error: ParseError, offset: usize },
+    /// Reading failed, with the message of the I/O error.
+    Io(String)
+}
+
+/// The numbers found at one path.
+#[derive(PartialEq, Debug, Clone, Default, ToJson)]
+pub struct NumberStats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64
+}
+
+impl NumberStats {
+    fn add(&mut self, n: f64) {
+        if self.count == 0 || n < self.min {
+            self.min = n;
+        }
+        if self.count == 0 || n > self.max {
+            self.max = n;
+        }
+        self.count += 1;
+        self.sum += n;
+    }
+
+    pub fn mean(&self) -> f64 {
+        return self.sum / self.count as f64
+    }
+}
+
+/// A non-empty array, `document` counts the documents of the input from 0.
+#[derive(PartialEq, Debug, Clone, ToJson)]
+pub struct LargeArray {
+    pub document: usize,
+    pub pointer: String,
+    pub length: usize
+}
+
+/// A key that already occurred in its object. `pointer` names the later value.
+#[derive(PartialEq, Debug, Clone, ToJson)]
+pub struct DuplicateKey {
+    pub document: usize,
+    pub pointer: String
+}
+
+#[derive(PartialEq, Debug, Clone, Default, ToJson)]
+pub struct Stats {
+    pub documents: usize,
+    /// Number of values of each type, keyed by `JsonType::type_name`.
+    pub types: BTreeMap<String, usize>,
+    /// Deepest nesting of arrays and objects, `0` when there are only scalars.
+    pub max_depth: usize,
+    /// Up to `LARGEST_ARRAYS` arrays, longest first. Of equally long arrays
+    /// the first one finished comes first, which is the innermost one when
+    /// reading a stream.
+    pub largest_arrays: Vec<LargeArray>,
+    /// How many objects each key occurs in.
+    pub keys: BTreeMap<String, usize>,
+    /// String lengths in characters, bucketed by powers of two: `[0]` counts
+    /// empty strings and `[i]` those from `2^(i-1)` to `2^i - 1` characters.
+    pub string_lengths: Vec<usize>,
+    pub longest_string: usize,
+    /// Keyed by pointer with every array index replaced by `*`, so the
+    /// elements of an array share one entry: `/users/*/age`.
+    pub numbers: BTreeMap<String, NumberStats>,
+    pub duplicate_keys: Vec<DuplicateKey>
+}
+
+/// Reads a stream a byte at a time and keeps count of the offset.
+struct Stream<R> {
+    reader: R,
+    offset: usize
+}
+
+impl<R: BufRead> Stream<R> {
+    fn peek(&mut self) -> Result<Option<u8>, AnalyzeError> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buffer) => return Ok(buffer.first().copied()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                Err(e) => return Err(AnalyzeError::Io(e.to_string()))
+            }
+        }
+    }
+
+    fn bump(&mut self) {
+        self.reader.consume(1);
+        self.offset += 1;
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), AnalyzeError> {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek()? {
+            self.bump();
+        }
+        return Ok(())
+    }
+
+    fn error(&self, error: ParseError) -> AnalyzeError {
+        return AnalyzeError::Parse { error, offset: self.offset }
+    }
+
+    /// The error for anything but `expected` at the current offset.
+    fn unexpected(&mut self, expected: &str) -> Result<AnalyzeError, AnalyzeError> {
+        self.peek()?;
+        let buffer = self.reader.fill_buf().unwrap_or_default();
+        let text = match std::str::from_utf8(&buffer[..buffer.len().min(4)]) {
+            Ok(text) => text,
+            Err(e) => std::str::from_utf8(&buffer[..e.valid_up_to()]).unwrap()
+        };
+        match text.chars().next() {
+            Some(found) => return Ok(self.error(ParseError::UnexpectedChar { expected: expected.to_string(), found })),
+            None if buffer.is_empty() => return Ok(self.error(ParseError::UnexpectedEof)),
+            None => return Ok(self.error(ParseError::MalformedString))
+        }
+    }
+
+    /// The text of the next scalar or key and its offset, up to the next
+    /// whitespace or delimiter. A bracket or stray delimiter is returned on
+    /// its own for the parser to report.
+    fn value_text(&mut self) -> Result<(String, usize), AnalyzeError> {
+        let start = self.offset;
+        let mut bytes = Vec::new();
+        let mut in_string = false;
+        let mut escaped = false;
+        while let Some(b) = self.peek()? {
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if b == b'\\' {
+                    escaped = true;
+                } else if b == b'"' {
+                    in_string = false;
+                }
+            } else {
+                match b {
+                    b'"' => in_string = true,
+                    b' ' | b'\t' | b'\n' | b'\r' | b',' | b':' | b'[' | b']' | b'{' | b'}' if !bytes.is_empty() => break,
+                    _ => {}
+                }
+            }
+            bytes.push(b);
+            self.bump();
+            if !in_string && matches!(bytes[0], b'[' | b'{' | b'"' | b',' | b':' | b']' | b'}') {
+                break
+            }
+        }
+        match String::from_utf8(bytes) {
+            Ok(text) => return Ok((text, start)),
+            Err(e) => {
+                let offset = start + e.utf8_error().valid_up_to();
+                return Err(AnalyzeError::Parse { error: ParseError::MalformedString, offset })
+            }
+        }
+    }
+}
+
+/// An open array or object in `Stats::add_document`, with the lengths of the
+/// pointer and template at the container itself. `length` counts the elements
+/// so far, `keys` holds the keys so far.
+enum Frame {
+    Array { pointer_length: usize, template_length: usize, length: usize },
+    Object { pointer_length: usize, template_length: usize, keys: HashSet<String> }
+}
+
+impl Stats {
+    fn count_type(&mut self, name: &str) {
+        *self.types.entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    fn add_array(&mut self, pointer: &str, length: usize) {
+        if length == 0 {
+            return
+        }
+        // Checked first, the pointers of deeply nested arrays are long
+        if self.largest_arrays.len() == LARGEST_ARRAYS && self.largest_arrays.last().is_some_and(|last| last.length >= length) {
+            return
+        }
+        let document = self.documents;
+        self.largest_arrays.push(LargeArray { document, pointer: pointer.to_string(), length });
+        // Stable, so the first of equally long arrays stays in front
+        self.largest_arrays.sort_by_key(|array| Reverse(array.length));
+        self.largest_arrays.truncate(LARGEST_ARRAYS);
+    }
+
+    /// Counts a value that is not an array or object, found at `template`.
+    fn add_scalar(&mut self, value: &JsonType, template: &str) {
+        self.count_type(value.type_name());
+        match value {
+            JsonType::JsonString(s) => {
+                let length = s.chars().count();
+                let bucket = (usize::BITS - length.leading_zeros()) as usize;
+                if self.string_lengths.len() <= bucket {
+                    self.string_lengths.resize(bucket + 1, 0);
+                }
+                self.string_lengths[bucket] += 1;
+                self.longest_string = self.longest_string.max(length);
+            },
+            JsonType::JsonNumber(n) => self.numbers.entry(template.to_string()).or_default().add(*n),
+            _ => {}
+        }
+    }
+
+    /// Counts `value` and everything in it. The values still to visit are
+    /// kept on a stack, so deep documents cannot overflow the call stack.
+    fn walk(&mut self, value: &JsonType) {
+        // The pointer of the value visited last, and its template with the
+        // array indices replaced by `*`. The parent of the next value to visit
+        // is always on that path.
+        let mut pointer = String::new();
+        let mut template = String::new();
+        // Each value with its depth, the lengths of its parent's pointer and
+        // template, its own token and whether it is an array element
+        let mut stack = vec![(value, 0, 0, 0, String::new(), false)];
+        while let Some((value, depth, pointer_length, template_length, token, element)) = stack.pop() {
+            pointer.truncate(pointer_length);
+            template.truncate(template_length);
+            if depth > 0 {
+                pointer.push('/');
+                pointer.push_str(&token);
+                template.push_str(if element {"/*"} else {"/"});
+                if !element {
+                    template.push_str(&token);
+                }
+            }
+            match value {
+                JsonType::JsonObject(map) => {
+                    self.count_type("object");
+                    self.max_depth = self.max_depth.max(depth + 1);
+                    for (key, child) in map {
+                        *self.keys.entry(key.clone()).or_insert(0) += 1;
+                        stack.push((child, depth + 1, pointer.len(), template.len(), escape_token(key), false));
+                    }
+                },
+                JsonType::JsonArray(values) => {
+                    self.count_type("array");
+                    self.max_depth = self.max_depth.max(depth + 1);
+                    self.add_array(&pointer, values.len());
+                    for (i, child) in values.iter().enumerate().rev() {
+                        stack.push((child, depth + 1, pointer.len(), template.len(), i.to_string(), true));
+                    }
+                },
+                value => self.add_scalar(value, &template)
+            }
+        }
+    }
+
+    /// Reads the key of an object member up to its `:` and moves `pointer`
+    /// and `template` to the member. `keys` are those the object already has.
+    fn read_key<R: BufRead>(
+        &mut self,
+        stream: &mut Stream<R>,
+        keys: &mut HashSet<String>,
+        pointer: &mut String,
+        template: &mut String
+    ) -> Result<(), AnalyzeError> {
+        stream.skip_whitespace()?;
+        let (text, start) = stream.value_text()?;
+        let key = match parse_string_raw_with(&text, ParserOptions::default()) {
+            Ok((key, "")) => key,
+            Ok(_) => return Err(AnalyzeError::Parse { error: ParseError::MalformedString, offset: start }),
+            Err(error) => return Err(AnalyzeError::Parse { error, offset: start })
+        };
+        stream.skip_whitespace()?;
+        if stream.peek()? != Some(b':') {
+            return Err(stream.unexpected(":")?)
+        }
+        stream.bump();
+
+        let token = escape_token(&key);
+        pointer.push('/');
+        pointer.push_str(&token);
+        template.push('/');
+        template.push_str(&token);
+        if keys.contains(&key) {
+            self.duplicate_keys.push(DuplicateKey { document: self.documents, pointer: pointer.clone() });
+        } else {
+            *self.keys.entry(key.clone()).or_insert(0) += 1;
+            keys.insert(key);
+        }
+        return Ok(())
+    }
+
+    /// Reads and counts one document of a stream. Arrays and objects are
+    /// walked as they are read, keeping a stack of the open ones.
+    fn add_document<R: BufRead>(&mut self, stream: &mut Stream<R>) -> Result<(), AnalyzeError> {
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut pointer = String::new();
+        let mut template = String::new();
+        loop {
+            // At the start of a value
+            stream.skip_whitespace()?;
+            match stream.peek()? {
+                Some(b'[') => {
+                    stream.bump();
+                    self.count_type("array");
+                    self.max_depth = self.max_depth.max(stack.len() + 1);
+                    stream.skip_whitespace()?;
+                    if stream.peek()? == Some(b']') {
+                        stream.bump();
+                    } else {
+                        stack.push(Frame::Array { pointer_length: pointer.len(), template_length: template.len(), length: 1 });
+                        pointer.push_str("/0");
+                        template.push_str("/*");
+                        continue
+                    }
+                },
+                Some(b'{') => {
+                    stream.bump();
+                    self.count_type("object");
+                    self.max_depth = self.max_depth.max(stack.len() + 1);
+                    stream.skip_whitespace()?;
+                    if stream.peek()? == Some(b'}') {
+                        stream.bump();
+                    } else {
+                        let (pointer_length, template_length) = (pointer.len(), template.len());
+                        let mut keys = HashSet::new();
+                        self.read_key(stream, &mut keys, &mut pointer, &mut template)?;
+                        stack.push(Frame::Object { pointer_length, template_length, keys });
+                        continue
+                    }
+                },
+                _ => {
+                    let (text, start) = stream.value_text()?;
+                    match cst::parse(&text) {
+                        Ok(node) => self.add_scalar(&node.to_json(), &template),
+                        Err((error, offset)) => return Err(AnalyzeError::Parse { error, offset: start + offset })
+                    }
+                }
+            }
+
+            // After a value: close the containers it ends, until one has a next value
+            loop {
+                let Some(frame) = stack.last_mut() else {
+                    self.documents += 1;
+                    return Ok(())
+                };
+                stream.skip_whitespace()?;
+                let next = stream.peek()?;
+                match frame {
+                    Frame::Array { pointer_length, length, .. } if next == Some(b',') => {
+                        stream.bump();
+                        pointer.truncate(*pointer_length);
+                        pointer.push_str(&format!("/{length}"));
+                        *length += 1;
+                        break
+                    },
+                    Frame::Object { pointer_length, template_length, keys } if next == Some(b',') => {
+                        stream.bump();
+                        pointer.truncate(*pointer_length);
+                        template.truncate(*template_length);
+                        self.read_key(stream, keys, &mut pointer, &mut template)?;
+                        break
+                    },
+                    Frame::Array { .. } if next == Some(b']') => {},
+                    Frame::Object { .. } if next == Some(b'}') => {},
+                    _ if next.is_none() => return Err(stream.error(ParseError::UnexpectedEof)),
+                    Frame::Array { .. } => return Err(stream.error(ParseError::MalformedArray)),
+                    Frame::Object { .. } => return Err(stream.unexpected("},,")?)
+                }
+                stream.bump();
+                match stack.pop().unwrap() {
+                    Frame::Array { pointer_length, template_length, length } => {
+                        pointer.truncate(pointer_length);
+                        template.truncate(template_length);
+                        self.add_array(&pointer, length);
+                    },
+                    Frame::Object { pointer_length, template_length, .. } => {
+                        pointer.truncate(pointer_length);
+                        template.truncate(template_length);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds one more document.
+    pub fn add(&mut self, value: &JsonType) {
+        self.walk(value);
+        self.documents += 1;
+    }
+
+    /// Adds every document of a stream, see the module documentation. On an
+    /// error the documents read so far are already counted.
+    pub fn add_reader<R: BufRead>(&mut self, reader: R) -> Result<(), AnalyzeError> {
+        let mut stream = Stream { reader, offset: 0 };
+        loop {
+            stream.skip_whitespace()?;
+            if stream.peek()?.is_none() {
+                return Ok(())
+            }
+            self.add_document(&mut stream)?;
+        }
+    }
+
+    /// Number of values of all types.
+    pub fn nodes(&self) -> usize {
+        return self.types.values().sum()
+    }
+}
+
+/// Summarizes a single document.
+pub fn analyze(value: &JsonType) -> Stats {
+    let mut stats = Stats::default();
+    stats.add(value);
+    return stats
+}
+
+/// Summarizes every document of a stream without holding it all in memory.
+pub fn analyze_reader<R: BufRead>(reader: R) -> Result<Stats, AnalyzeError> {
+    let mut stats = Stats::default();
+    stats.add_reader(reader)?;
+    return Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types(counts: &[(&str, usize)]) -> BTreeMap<String, usize> {
+        return counts.iter().map(|(name, count)| (name.to_string(), *count)).collect()
+    }
+
+    #[test]
+    fn summary() {
+        let value = json!({
+            "users": [{"name": "Al", "age": 40, "tags": []}, {"name": "Bo", "age": 20.5, "tags": ["x", "yyyy"]}],
+            "total": 2,
+            "next": null
+        });
+        let stats = analyze(&value);
+        assert_eq!(stats.documents, 1);
+        assert_eq!(stats.types, types(&[("array", 3), ("null", 1), ("number", 3), ("object", 3), ("string", 4)]));
+        assert_eq!(stats.nodes(), 14);
+        assert_eq!(stats.max_depth, 4);
+        assert_eq!(stats.largest_arrays, vec![
+            LargeArray { document: 0, pointer: "/users".to_string(), length: 2 },
+            LargeArray { document: 0, pointer: "/users/1/tags".to_string(), length: 2 }
+        ]);
+        assert_eq!(stats.keys["name"], 2);
+        assert_eq!(stats.keys["total"], 1);
+        // "x" in bucket 1, "Al" and "Bo" in bucket 2 (2-3), "yyyy" in bucket 3 (4-7)
+        assert_eq!(stats.string_lengths, vec![0, 1, 2, 1]);
+        assert_eq!(stats.longest_string, 4);
+        let ages = &stats.numbers["/users/*/age"];
+        assert_eq!((ages.count, ages.min, ages.max, ages.mean()), (2, 20.5, 40.0, 30.25));
+        assert_eq!(stats.numbers["/total"].count, 1);
+        assert!(stats.duplicate_keys.is_empty());
+
+        assert_eq!(analyze(&json!(1)).max_depth, 0);
+        assert_eq!(analyze(&json!("")).string_lengths, vec![1]);
+        let json = stats.to_json();
+        assert_eq!(json["numbers"]["/total"], json!({"count": 1, "min": 2, "max": 2, "sum": 2}));
+        assert_eq!(json["largest_arrays"][0], json!({"document": 0, "pointer": "/users", "length": 2}));
+    }
+
+    #[test]
+    fn streams() {
+        let text = r#"[{"a": 1, "b": [1, 2, 3]}, {"a": 2, "c": {"d": [[], {}], "e~": "y"}}, "x", [], {}]"#;
+        let streamed = analyze_reader(text.as_bytes()).unwrap();
+        assert_eq!(streamed, analyze(&crate::parser::parse_document(text).unwrap()));
+        let wrapped = format!(r#"{{"data": {text}}}"#);
+        assert_eq!(analyze_reader(wrapped.as_bytes()).unwrap(), analyze(&crate::parser::parse_document(&wrapped).unwrap()));
+
+        // Every value of a repeated key is counted, but the key only once
+        let stats = analyze_reader(r#"[{"a": 2, "b": {"a": 1}, "a": 3}]"#.as_bytes()).unwrap();
+        assert_eq!(stats.duplicate_keys, vec![DuplicateKey { document: 0, pointer: "/0/a".to_string() }]);
+        assert_eq!((stats.keys["a"], stats.numbers["/*/a"].count), (2, 2));
+
+        let lines = "{\"id\": 1}\n{\"id\": 2, \"l\": [true]}\n\n7\n";
+        let stats = analyze_reader(lines.as_bytes()).unwrap();
+        assert_eq!(stats.documents, 3);
+        assert_eq!(stats.numbers["/id"].max, 2.0);
+        assert_eq!(stats.numbers[""].count, 1);
+        assert_eq!(stats.largest_arrays, vec![LargeArray { document: 1, pointer: "/l".to_string(), length: 1 }]);
+
+        assert_eq!(analyze_reader(&b""[..]).unwrap(), Stats::default());
+        let error = |text: &str| analyze_reader(text.as_bytes()).unwrap_err();
+        assert_eq!(error("[1, 2"), AnalyzeError::Parse { error: ParseError::UnexpectedEof, offset: 5 });
+        assert_eq!(error("[1 2]"), AnalyzeError::Parse { error: ParseError::MalformedArray, offset: 3 });
+        assert_eq!(error("[1, ], 2"), AnalyzeError::Parse { error: ParseError::UnexpectedChar { expected: "\",{,[,T,t,F,f,n,is_numeric".to_string(), found: ']' }, offset: 4 });
+        assert_eq!(error("{} {\"a\": }"), AnalyzeError::Parse { error: ParseError::UnexpectedChar { expected: "\",{,[,T,t,F,f,n,is_numeric".to_string(), found: '}' }, offset: 9 });
+        assert_eq!(analyze_reader(&b"[\"a\", \"\xff\"]"[..]), Err(AnalyzeError::Parse { error: ParseError::MalformedString, offset: 7 }));
+        assert_eq!(error("{\"a\" 1}"), AnalyzeError::Parse { error: ParseError::UnexpectedChar { expected: ":".to_string(), found: '1' }, offset: 5 });
+        assert_eq!(error("{\"a\": 1 é}"), AnalyzeError::Parse { error: ParseError::UnexpectedChar { expected: "},,".to_string(), found: 'é' }, offset: 8 });
+        assert_eq!(error("{1: 2}"), AnalyzeError::Parse { error: ParseError::MalformedString, offset: 1 });
+    }
+
+    #[test]
+    fn deep_documents() {
+        // Neither the stream nor a parsed value is walked by recursion
+        let depth = 200_000;
+        let text = format!("{}1{}", "[".repeat(depth), "]".repeat(depth));
+        let stats = analyze_reader(text.as_bytes()).unwrap();
+        assert_eq!((stats.max_depth, stats.types["array"], stats.largest_arrays.len()), (depth, depth, LARGEST_ARRAYS));
+        assert_eq!(stats.largest_arrays[0].pointer, "/0".repeat(depth - 1));
+        assert_eq!(stats.numbers["/*".repeat(depth).as_str()].count, 1);
+
+        let text = format!("{}{}", "{\"a\":".repeat(depth), "}".repeat(depth));
+        let error = analyze_reader(text.as_bytes()).unwrap_err();
+        assert_eq!(error, AnalyzeError::Parse { error: ParseError::UnexpectedChar { expected: "\",{,[,T,t,F,f,n,is_numeric".to_string(), found: '}' }, offset: 5 * depth });
+
+        let mut value = json!(1);
+        for _ in 0..depth {
+            value = JsonType::JsonArray(vec![value]);
+        }
+        assert_eq!(analyze(&value).max_depth, depth);
+        // Dropping is recursive, so the value is taken apart first
+        while let JsonType::JsonArray(mut values) = value {
+            value = values.pop().unwrap();
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Accessors, indexing and conversions into `JsonType`.
+//!
+//! The `as_*` methods return `None` when the value has another type, so
+//! nested lookups read as a chain instead of a `match` per level. Indexing
+//! with `[]` never panics: a missing key or index, or indexing a value that
+//! is not a container, gives `null`.
+
+use std::collections::HashMap;
+use std::ops::Index;
+
+use crate::parser::JsonType;
+
+// Returned by reference when indexing misses
+static NULL: JsonType = JsonType::JsonNull;
+
+/// A key or position that can look up a member of a `JsonType`: `&str` and
+/// `String` for objects, `usize` for arrays.
+pub trait JsonIndex {
+    fn index_into<'a>(&self, value: &'a JsonType) -> Option<&'a JsonType>;
+    fn index_into_mut<'a>(&self, value: &'a mut JsonType) -> Option<&'a mut JsonType>;
+}
+
+impl JsonIndex for usize {
+    fn index_into<'a>(&self, value: &'a JsonType) -> Option<&'a JsonType> {
+        match value {
+            JsonType::JsonArray(values) => return values.get(*self),
+            _ => return None
+        }
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut JsonType) -> Option<&'a mut JsonType> {
+        match value {
+            JsonType::JsonArray(values) => return values.get_mut(*self),
+            _ => return None
+        }
+    }
+}
+
+impl JsonIndex for str {
+    fn index_into<'a>(&self, value: &'a JsonType) -> Option<&'a JsonType> {
+        match value {
+            JsonType::JsonObject(map) => return map.get(self),
+            _ => return None
+        }
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut JsonType) -> Option<&'a mut JsonType> {
+        match value {
+            JsonType::JsonObject(map) => return map.get_mut(self),
+            _ => return None
+        }
+    }
+}
+
+impl JsonIndex for String {
+    fn index_into<'a>(&self, value: &'a JsonType) -> Option<&'a JsonType> {
+        return self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut JsonType) -> Option<&'a mut JsonType> {
+        return self.as_str().index_into_mut(value)
+    }
+}
+
+impl<T: JsonIndex + ?Sized> JsonIndex for &T {
+    fn index_into<'a>(&self, value: &'a JsonType) -> Option<&'a JsonType> {
+        return (**self).index_into(value)
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut JsonType) -> Option<&'a mut JsonType> {
+        return (**self).index_into_mut(value)
+    }
+}
+
+impl JsonType {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonType::JsonString(s) => return Some(s),
+            _ => return None
+        }
+    }
+
+    pub fn as_str_mut(&mut self) -> Option<&mut String> {
+        match self {
+            JsonType::JsonString(s) => return Some(s),
+            _ => return None
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonType::JsonNumber(n) => return Some(*n),
+            _ => return None
+        }
+    }
+
+    pub fn as_f64_mut(&mut self) -> Option<&mut f64> {
+        match self {
+            JsonType::JsonNumber(n) => return Some(n),
+            _ => return None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonType::JsonBool(b) => return Some(*b),
+            _ => return None
+        }
+    }
+
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self {
+            JsonType::JsonBool(b) => return Some(b),
+            _ => return None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonType>> {
+        match self {
+            JsonType::JsonArray(values) => return Some(values),
+            _ => return None
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<JsonType>> {
+        match self {
+            JsonType::JsonArray(values) => return Some(values),
+            _ => return None
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&HashMap<String, JsonType>> {
+        match self {
+            JsonType::JsonObject(map) => return Some(map),
+            _ => return None
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut HashMap<String, JsonType>> {
+        match self {
+            JsonType::JsonObject(map) => return Some(map),
+            _ => return None
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        return *self == JsonType::JsonNull
+    }
+
+    /// `"object"`, `"array"`, `"string"`, `"number"`, `"boolean"` or `"null"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            JsonType::JsonObject(_) => return "object",
+            JsonType::JsonArray(_) => return "array",
+            JsonType::JsonString(_) => return "string",
+            JsonType::JsonNumber(_) => return "number",
+            JsonType::JsonBool(_) => return "boolean",
+            JsonType::JsonNull => return "null"
+        }
+    }
+
+    /// The member at `index`, a key for objects or a position for arrays.
+    pub fn get<I: JsonIndex>(&self, index: I) -> Option<&JsonType> {
+        return index.index_into(self)
+    }
+
+    pub fn get_mut<I: JsonIndex>(&mut self, index: I) -> Option<&mut JsonType> {
+        return index.index_into_mut(self)
+    }
+
+    /// Moves the value out, leaving `null` in its place.
+    pub fn take(&mut self) -> JsonType {
+        return std::mem::replace(self, JsonType::JsonNull)
+    }
+}
+
+impl<I: JsonIndex> Index<I> for JsonType {
+    type Output = JsonType;
+
+    /// Like `get`, but gives `null` instead of `None`.
+    fn index(&self, index: I) -> &JsonType {
+        return index.index_into(self).unwrap_or(&NULL)
+    }
+}
+
+impl From<bool> for JsonType {
+    fn from(value: bool) -> JsonType {
+        return JsonType::JsonBool(value)
+    }
+}
+
+impl From<&str> for JsonType {
+    fn from(value: &str) -> JsonType {
+        return JsonType::JsonString(value.to_string())
+    }
+}
+
+impl From<String> for JsonType {
+    fn from(value: String) -> JsonType {
+        return JsonType::JsonString(value)
+    }
+}
+
+impl From<&String> for JsonType {
+    fn from(value: &String) -> JsonType {
+        return JsonType::JsonString(value.clone())
+    }
+}
+
+impl From<char> for JsonType {
+    fn from(value: char) -> JsonType {
+        return JsonType::JsonString(value.to_string())
+    }
+}
+
+// All numbers are stored as f64, so large 64-bit integers lose precision
+macro_rules! from_number {
+    ($($number:ty),*) => {
+        $(
+            impl From<$number> for JsonType {
+                fn from(value: $number) -> JsonType {
+                    return JsonType::JsonNumber(value as f64)
+                }
+            }
+        )*
+    };
+}
+
+from_number!(f64, f32, i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl<T: Into<JsonType>> From<Vec<T>> for JsonType {
+    fn from(values: Vec<T>) -> JsonType {
+        return JsonType::JsonArray(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<JsonType>> From<HashMap<String, T>> for JsonType {
+    fn from(map: HashMap<String, T>) -> JsonType {
+        return JsonType::JsonObject(map.into_iter().map(|(k, v)| (k, v.into())).collect())
+    }
+}
+
+/// `None` becomes `null`.
+impl<T: Into<JsonType>> From<Option<T>> for JsonType {
+    fn from(value: Option<T>) -> JsonType {
+        match value {
+            Some(value) => return value.into(),
+            None => return JsonType::JsonNull
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accessors() {
+        let mut value = json!({"name": "Alice", "age": 31, "admin": false, "tags": ["a"], "meta": {}, "none": null});
+        assert_eq!(value["name"].as_str(), Some("Alice"));
+        assert_eq!(value["age"].as_f64(), Some(31.0));
+        assert_eq!(value["admin"].as_bool(), Some(false));
+        assert_eq!(value["tags"].as_array().map(Vec::len), Some(1));
+        assert!(value["meta"].as_object().is_some_and(HashMap::is_empty));
+        assert!(value["none"].is_null());
+        assert_eq!(value["name"].as_f64(), None);
+        assert_eq!(value.as_array(), None);
+
+        *value.get_mut("age").and_then(JsonType::as_f64_mut).unwrap() += 1.0;
+        value.get_mut("tags").and_then(JsonType::as_array_mut).unwrap().push(json!("b"));
+        value.get_mut("meta").and_then(JsonType::as_object_mut).unwrap().insert("k".to_string(), json!(1));
+        *value.get_mut("admin").and_then(JsonType::as_bool_mut).unwrap() = true;
+        value.get_mut("name").and_then(JsonType::as_str_mut).unwrap().push('!');
+        assert_eq!(value, json!({"name": "Alice!", "age": 32, "admin": true, "tags": ["a", "b"], "meta": {"k": 1}, "none": null}));
+    }
+
+    #[test]
+    fn indexing() {
+        let mut value = json!({"users": [{"name": "Alice"}, {"name": "Bob"}]});
+        let key = String::from("users");
+        assert_eq!(value["users"][1]["name"], json!("Bob"));
+        assert_eq!(value[&key][0]["name"], json!("Alice"));
+        assert_eq!(value["users"][5]["name"], JsonType::JsonNull);
+        assert_eq!(value["missing"]["deeper"][0], JsonType::JsonNull);
+        assert_eq!(value[0], JsonType::JsonNull);
+        assert_eq!(value.get("users").and_then(|u| u.get(0)), Some(&json!({"name": "Alice"})));
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(value["users"].get("name"), None);
+
+        let taken = value.get_mut("users").unwrap().take();
+        assert_eq!(taken.as_array().map(Vec::len), Some(2));
+        assert_eq!(value, json!({"users": null}));
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(JsonType::from(vec![1, 2]), json!([1, 2]));
+        assert_eq!(JsonType::from(vec![Some("a"), None]), json!(["a", null]));
+        assert_eq!(JsonType::from(HashMap::from([("k".to_string(), vec![true])])), json!({"k": [true]}));
+        assert_eq!(JsonType::from(None::<i32>), JsonType::JsonNull);
+        assert_eq!(JsonType::from(Some(2.5f32)), json!(2.5));
+        assert_eq!(JsonType::from('x'), json!("x"));
+        assert_eq!(JsonType::from(u64::MAX), JsonType::JsonNumber(u64::MAX as f64));
+        let owned: JsonType = String::from("s").into();
+        assert_eq!(owned, json!("s"));
+    }
+}
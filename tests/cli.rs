@@ -0,0 +1,250 @@
+//! The `jsonp` binary, run as a subprocess.
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+fn jsonp(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jsonp"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Commands that fail on their arguments exit without reading their input
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    return child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    return String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    return String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn usage_errors() {
+    let output = jsonp(&[], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("jsonp: missing command\n\nusage: jsonp <command>"));
+    assert_eq!(jsonp(&["frobnicate"], "").status.code(), Some(2));
+    assert_eq!(jsonp(&["codegen", "--bogus"], "").status.code(), Some(2));
+    assert_eq!(jsonp(&["codegen", "--name"], "").status.code(), Some(2));
+    assert!(jsonp(&["help"], "").status.success());
+}
+
+#[test]
+fn codegen() {
+    let output = jsonp(&["codegen", "--name", "Event"], r#"{"id": 1, "user-name": "x"}"#);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("pub struct Event {\n    pub id: i64,\n    #[json(rename = \"user-name\")]\n    pub user_name: String\n}"));
+
+    let output = jsonp(&["codegen", "--schema", "--name=Order", "tests/codegen/schema.json"], "");
+    assert_eq!(stdout(&output), include_str!("codegen/order.rs"));
+
+    let output = jsonp(&["codegen", "-"], "{\n  \"a\": ]\n}");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "jsonp: <stdin>:2:8: unexpected ']', expected \",{,[,T,t,F,f,n,is_numeric\n");
+    assert_eq!(jsonp(&["codegen", "missing.json"], "").status.code(), Some(1));
+}
+
+#[test]
+fn validate() {
+    let output = jsonp(&["validate"], "[1, 2]");
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "<stdin>: ok\n");
+    assert_eq!(stdout(&jsonp(&["validate", "--quiet"], "[1, 2]")), "");
+
+    let output = jsonp(&["validate"], "{\n  \"a\": ]\n}");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "\
+error: unexpected ']', expected \",{,[,T,t,F,f,n,is_numeric
+ --> <stdin>:2:8
+  |
+2 |   \"a\": ]
+  |        ^
+
+");
+    assert_eq!(jsonp(&["validate"], "{a: 1, // c\n}").status.code(), Some(1));
+    assert!(jsonp(&["validate", "--json5"], "{a: 1, // c\n}").status.success());
+
+    let output = jsonp(&["validate", "--schema", "tests/codegen/schema.json", "-"], r#"{"id": 1, "status": "lost", "lines": []}"#);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains(" --> <stdin>:1:21\n  |\n1 | {\"id\": 1, \"status\": \"lost\", \"lines\": []}\n  |                     ^^^^^^\n"));
+    assert!(jsonp(&["validate", "--schema", "tests/codegen/schema.json"], r#"{"id": 1, "status": "new", "lines": []}"#).status.success());
+}
+
+#[test]
+fn deep_input() {
+    // Reported as an error instead of overflowing the stack
+    let input = "[".repeat(200_000);
+    for command in ["validate", "fmt", "minify"] {
+        let output = jsonp(&[command], &input);
+        assert_eq!(output.status.code(), Some(1), "{command}");
+        assert!(stderr(&output).starts_with("error: nesting is too deep\n --> <stdin>:1:129\n"), "{command}");
+    }
+}
+
+#[test]
+fn fmt_and_minify() {
+    let input = "{\"b\": [1.50, {}], // note\n \"a\": 'x'}";
+    let output = jsonp(&["fmt", "--json5"], input);
+    assert_eq!(stdout(&output), "{\n  \"b\": [\n    1.50,\n    {}\n  ],\n  \"a\": \"x\"\n}\n");
+    assert_eq!(stdout(&jsonp(&["fmt", "--json5", "--indent=4"], input)), "{\n    \"b\": [\n        1.50,\n        {}\n    ],\n    \"a\": \"x\"\n}\n");
+    assert_eq!(stdout(&jsonp(&["fmt", "--json5", "--tab"], "[1]")), "[\n\t1\n]\n");
+    assert_eq!(stdout(&jsonp(&["minify", "--json5"], input)), "{\"b\":[1.50,{}],\"a\":\"x\"}\n");
+    assert_eq!(jsonp(&["minify"], input).status.code(), Some(1));
+
+    assert_eq!(jsonp(&["fmt", "--indent", "x"], "").status.code(), Some(2));
+    assert_eq!(jsonp(&["fmt", "--write", "--check", "a.json"], "").status.code(), Some(2));
+    assert_eq!(jsonp(&["fmt", "--write"], "[]").status.code(), Some(2));
+
+    let path = std::env::temp_dir().join(format!("jsonp-fmt-{}.json", std::process::id()));
+    let path_arg = path.to_str().unwrap();
+    std::fs::write(&path, "[1,\n2]").unwrap();
+    let output = jsonp(&["fmt", "--check", path_arg], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), format!("{path_arg}: not formatted\n"));
+    assert!(jsonp(&["fmt", "--write", path_arg], "").status.success());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "[\n  1,\n  2\n]\n");
+    assert!(jsonp(&["fmt", "--check", path_arg], "").status.success());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn extracting() {
+    let document = r#"{"users": [{"name": "Al", "age": 40}, {"name": "Bo", "age": 20}], "a/b": {}}"#;
+    assert_eq!(stdout(&jsonp(&["get", "/users/0"], document)), "{\"age\":40,\"name\":\"Al\"}\n");
+    assert_eq!(stdout(&jsonp(&["get", "--pretty", "/users/1"], document)), "{\n  \"age\": 20,\n  \"name\": \"Bo\"\n}\n");
+    assert_eq!(stdout(&jsonp(&["get", "--raw", "/users/0/name"], document)), "Al\n");
+    assert_eq!(jsonp(&["get", "/users/2"], document).status.code(), Some(1));
+    assert_eq!(jsonp(&["get", "users"], document).status.code(), Some(2));
+    assert_eq!(jsonp(&["get"], document).status.code(), Some(2));
+
+    assert_eq!(stdout(&jsonp(&["query", "--raw", "$.users[?@.age > 30].name"], document)), "Al\n");
+    assert_eq!(stdout(&jsonp(&["query", "--locations", "$..age"], document)), "$['users'][0]['age']\n$['users'][1]['age']\n");
+    assert_eq!(jsonp(&["query", "$.missing"], document).status.code(), Some(1));
+    assert_eq!(jsonp(&["query", "$["], document).status.code(), Some(2));
+
+    assert_eq!(stdout(&jsonp(&["keys"], document)), "\"a/b\"\n\"users\"\n");
+    assert_eq!(stdout(&jsonp(&["keys"], "[true, false]")), "0\n1\n");
+    assert_eq!(jsonp(&["keys"], "{}").status.code(), Some(1));
+    assert_eq!(stdout(&jsonp(&["paths", "--raw"], r#"{"a/b": {}, "c": [1]}"#)), "/a~1b\n/c\n/c/0\n");
+    assert_eq!(stdout(&jsonp(&["paths", "--leaves"], r#"{"a/b": {}, "c": [1]}"#)), "\"/a~1b\"\n\"/c/0\"\n");
+    assert_eq!(jsonp(&["paths"], "1").status.code(), Some(1));
+
+    // Errors are told apart from finding nothing
+    for args in [&["get", "/a"][..], &["query", "$.a"], &["keys"], &["paths"]] {
+        assert_eq!(jsonp(args, "{").status.code(), Some(2), "{args:?}");
+        let mut args = args.to_vec();
+        args.push("missing.json");
+        let output = jsonp(&args, "");
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(stderr(&output).starts_with("jsonp: missing.json: "));
+    }
+}
+
+#[test]
+fn ndjson() {
+    let lines = "{\"id\": 1, \"tag\": \"x\"}\n\n{\"id\": 2}\n{\"id\": 3, \"tag\": \"z\"}\n";
+    assert_eq!(stdout(&jsonp(&["get", "--ndjson", "--raw", "/tag"], lines)), "x\nz\n");
+    assert_eq!(stdout(&jsonp(&["query", "--ndjson", "$.id"], lines)), "1\n2\n3\n");
+    assert_eq!(stdout(&jsonp(&["keys", "--ndjson", "--raw"], lines)), "id\ntag\nid\nid\ntag\n");
+
+    let output = jsonp(&["get", "--ndjson", "/id"], "{\"id\": 1}\n{\"id\": }\n");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("jsonp: <stdin>:2:8: "));
+}
+
+#[test]
+fn diff_and_patch() {
+    let directory = std::env::temp_dir().join(format!("jsonp-diff-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let old = directory.join("old.json");
+    let new = directory.join("new.json");
+    std::fs::write(&old, r#"{"a": 1, "l": [1, 2], "x": true}"#).unwrap();
+    std::fs::write(&new, r#"{"a": 2, "l": [2, 1]}"#).unwrap();
+    let (old, new) = (old.to_str().unwrap(), new.to_str().unwrap());
+
+    let output = jsonp(&["diff", old, new], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "--- old\n+++ new\n@@ /a @@\n- 1\n+ 2\n@@ /l/0 @@\n- 1\n+ 2\n@@ /l/1 @@\n- 2\n+ 1\n@@ /x @@\n- true\n");
+    assert_eq!(stdout(&jsonp(&["diff", "--ignore-order", old, new], "")), "--- old\n+++ new\n@@ /a @@\n- 1\n+ 2\n@@ /x @@\n- true\n");
+    let output = jsonp(&["diff", "--quiet", old, new], "");
+    assert_eq!((output.status.code(), stdout(&output)), (Some(1), String::new()));
+    assert_eq!(jsonp(&["diff", old, "-"], r#"{"x": true, "l": [1, 2], "a": 1}"#).status.code(), Some(0));
+    assert_eq!(jsonp(&["diff", old, "missing.json"], "").status.code(), Some(2));
+    assert_eq!(jsonp(&["diff", old], "").status.code(), Some(2));
+    assert_eq!(jsonp(&["diff", "--format", "patch", "--ignore-order", old, new], "").status.code(), Some(2));
+    assert_eq!(stdout(&jsonp(&["diff", "--format=merge", old, "-"], r#"{"a": 1, "l": [1, 2]}"#)), "{\n  \"x\": null\n}\n");
+    assert_eq!(jsonp(&["diff", "--format=merge", old, "-"], r#"{"a": 1, "l": [1, 2], "x": true, "n": null}"#).status.code(), Some(2));
+
+    // A JSON Patch from diff turns the old document into the new one
+    let patch = stdout(&jsonp(&["diff", "--format", "patch", old, new], ""));
+    let output = jsonp(&["patch", old, "-"], &patch);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "{\"a\":2,\"l\":[2,1]}\n");
+    assert_eq!(stdout(&jsonp(&["patch", "--merge", "--pretty", old, "-"], r#"{"l": null, "x": false}"#)), "{\n  \"a\": 1,\n  \"x\": false\n}\n");
+
+    let output = jsonp(&["patch", old, "-"], r#"[{"op": "test", "path": "/a", "value": 5}]"#);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "jsonp: <stdin>: operation 0: test failed\n");
+    let output = jsonp(&["patch", old, "-"], r#"[{"op": "add", "path": "/b", "value": 1, "op": "remove"}]"#);
+    assert_eq!((output.status.code(), stderr(&output)), (Some(1), "jsonp: <stdin>: operation 0 is invalid\n".to_string()));
+    assert_eq!(jsonp(&["patch", old, "-"], "[").status.code(), Some(2));
+    assert_eq!(jsonp(&["patch", "--write", "-", old], "{}").status.code(), Some(2));
+
+    assert!(jsonp(&["patch", "--write", old, "-"], &patch).status.success());
+    assert_eq!(jsonp(&["diff", old, new], "").status.code(), Some(0));
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn stats() {
+    let input = "[{\"name\": \"Al\", \"age\": 40, \"tags\": [\"x\"]}, {\"name\": \"Bo\", \"age\": 20.5, \"name\": \"B\", \"tags\": []}]";
+    let output = jsonp(&["stats"], input);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "\
+documents       1
+values          11 (array 3, number 2, object 2, string 4)
+max depth       3
+
+largest arrays
+           2  document 0  (root)
+           1  document 0  /0/tags
+
+keys (3 distinct)
+           2  age
+           2  name
+           2  tags
+
+string lengths (longest 2)
+           0  0
+           2  1
+           2  2-3
+
+numbers
+           2  /*/age  min 20.5  max 40  mean 30.25
+
+duplicate keys
+    document 0  /1/name
+");
+    assert!(stdout(&jsonp(&["stats", "--top", "1"], input)).contains("keys (3 distinct)\n           2  age\n\n"));
+
+    let output = jsonp(&["stats", "--json"], "{\"a\": 1}\n{\"a\": 3}\n");
+    assert!(stdout(&output).contains("\"numbers\": {\n    \"/a\": {\n      \"count\": 2,\n      \"max\": 3,\n      \"min\": 1,\n      \"sum\": 4\n    }\n  }"));
+
+    let output = jsonp(&["stats"], "{\"a\": 1}\n{\"a\": }");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr(&output), "jsonp: <stdin>: byte 15: unexpected '}', expected \",{,[,T,t,F,f,n,is_numeric\n");
+    assert_eq!(jsonp(&["stats", "--top", "x"], "").status.code(), Some(2));
+
+    // Nested containers are streamed as well, without a nesting limit
+    let deep = format!("{{\"data\": {}1{}}}", "[".repeat(200_000), "]".repeat(200_000));
+    let output = jsonp(&["stats"], &deep);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("max depth       200001\n"));
+}
@@ -0,0 +1,69 @@
+//! Code generated by `codegen::generate` compiles against the crate and
+//! converts real documents. `codegen/order.rs` is the generator's output for
+//! `codegen/schema.json` and `codegen/tree.rs` for `codegen/tree.json`, the
+//! first test keeps them in sync.
+
+use JSON_parser::codegen::{generate, generate_from_samples};
+use JSON_parser::convert::{FromJson, ToJson};
+use JSON_parser::parser::{parse_document, JsonType};
+
+#[allow(dead_code)]
+mod generated {
+    include!("codegen/order.rs");
+}
+
+#[allow(dead_code)]
+mod tree {
+    include!("codegen/tree.rs");
+}
+
+use generated::*;
+
+#[test]
+fn output_is_up_to_date() {
+    let schema = parse_document(include_str!("codegen/schema.json")).unwrap();
+    assert_eq!(generate(&schema, "Order").unwrap(), include_str!("codegen/order.rs"));
+    let schema = parse_document(include_str!("codegen/tree.json")).unwrap();
+    assert_eq!(generate(&schema, "Tree").unwrap(), include_str!("codegen/tree.rs"));
+}
+
+#[test]
+fn generated_types_convert() {
+    let document = parse_document(r#"{
+        "@type": "Order",
+        "id": 7,
+        "status": "shipped",
+        "customer": {"name": "Alice", "e-mail": null},
+        "lines": [{"sku": "a-1", "quantity": 2, "bundle": [{"sku": "b-2", "quantity": 1}]}],
+        "discount": "WINTER",
+        "labels": {"gift": "yes"}
+    }"#).unwrap();
+    let order = Order::from_json(&document).unwrap();
+    assert_eq!(order.status, OrderStatus::Shipped);
+    assert_eq!(order.customer.as_ref().map(|c| c.name.as_str()), Some("Alice"));
+    assert_eq!(order.lines[0].bundle.as_ref().unwrap()[0].sku, "b-2");
+    assert_eq!(order.discount, Some(OrderDiscount::String("WINTER".to_string())));
+    assert_eq!(order.r#type, JsonType::JsonString("Order".to_string()));
+    assert_eq!(Order::from_json(&order.to_json()), Ok(order));
+
+    let minimal = Order::from_json(&parse_document(r#"{"id": 1, "status": "new", "lines": []}"#).unwrap()).unwrap();
+    assert_eq!(minimal.customer, None);
+    assert!(Order::from_json(&parse_document(r#"{"id": 1, "status": "lost", "lines": []}"#).unwrap()).is_err());
+}
+
+#[test]
+fn nullable_and_recursive_definitions() {
+    // A nullable struct is still a struct, the referencing fields hold the `Option`
+    let document = parse_document(r#"{"nested": [[], [[]]], "point": {"x": 3}}"#).unwrap();
+    let value = tree::Tree::from_json(&document).unwrap();
+    assert_eq!(value.nested, tree::List(vec![tree::List(vec![]), tree::List(vec![tree::List(vec![])])]));
+    assert_eq!(value.point, Some(tree::Point { x: 3 }));
+    assert_eq!(value.to_json(), document);
+    let document = parse_document(r#"{"nested": [], "point": null}"#).unwrap();
+    assert_eq!(tree::Tree::from_json(&document).unwrap().point, None);
+
+    let samples = [parse_document(r#"{"a": 1}"#).unwrap(), JsonType::JsonNull];
+    let code = generate_from_samples(&samples, "Root");
+    assert!(code.contains("pub struct Root {"));
+    assert!(!code.contains("pub type"));
+}
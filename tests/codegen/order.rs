@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use JSON_parser::convert::{FromJson, ToJson};
+use JSON_parser::parser::JsonType;
+
+/// An order as returned by the shop API.
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct Order {
+    #[json(rename = "@type")]
+    pub r#type: JsonType,
+    pub customer: Option<OrderCustomer>,
+    pub discount: Option<OrderDiscount>,
+    pub id: i64,
+    pub labels: Option<HashMap<String, String>>,
+    pub lines: Vec<Line>,
+    pub status: OrderStatus
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct OrderCustomer {
+    #[json(rename = "e-mail")]
+    pub e_mail: Option<String>,
+    pub name: String
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+#[json(untagged)]
+pub enum OrderDiscount {
+    Number(f64),
+    String(String)
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct Line {
+    pub bundle: Option<Vec<Line>>,
+    pub quantity: i64,
+    pub sku: String
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub enum OrderStatus {
+    #[json(rename = "new")]
+    New,
+    #[json(rename = "shipped")]
+    Shipped,
+    #[json(rename = "cancelled")]
+    Cancelled
+}
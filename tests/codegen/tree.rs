@@ -0,0 +1,15 @@
+use JSON_parser::convert::{FromJson, ToJson};
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct Tree {
+    pub nested: List,
+    pub point: Option<Point>
+}
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct List(pub Vec<List>);
+
+#[derive(Debug, Clone, PartialEq, FromJson, ToJson)]
+pub struct Point {
+    pub x: i64
+}
@@ -0,0 +1,237 @@
+//! `#[derive(FromJson, ToJson)]` from `JSON_parser_derive`, used the way a
+//! downstream crate would.
+
+use std::collections::HashMap;
+
+use JSON_parser::convert::{ConvertError, FromJson, ToJson};
+use JSON_parser::json;
+use JSON_parser::parser::JsonType;
+
+fn round_trip<T: FromJson + ToJson + PartialEq + std::fmt::Debug>(value: T, json: JsonType) {
+    assert_eq!(value.to_json(), json);
+    assert_eq!(T::from_json(&json), Ok(value));
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+struct User {
+    name: String,
+    age: u8,
+    email: Option<String>,
+    tags: Vec<String>
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+struct Meters(f64);
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+struct Point(i32, i32);
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+struct Marker;
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+struct Wrapper<T> {
+    pub(crate) inner: T,
+    list: Vec<T>
+}
+
+#[test]
+fn structs() {
+    round_trip(
+        User { name: "Alice".to_string(), age: 31, email: None, tags: vec!["admin".to_string()] },
+        json!({"name": "Alice", "age": 31, "email": null, "tags": ["admin"]})
+    );
+    round_trip(Meters(1.5), json!(1.5));
+    round_trip(Point(1, -2), json!([1, -2]));
+    round_trip(Marker, json!(null));
+    round_trip(Wrapper { inner: Point(0, 0), list: vec![] }, json!({"inner": [0, 0], "list": []}));
+
+    // Missing `Option` members read as `None`, unknown members are ignored
+    let user = User::from_json(&json!({"name": "Bob", "age": 25, "tags": [], "extra": 1})).unwrap();
+    assert_eq!(user.email, None);
+}
+
+#[test]
+fn struct_errors() {
+    assert_eq!(
+        User::from_json(&json!({"name": "Bob", "tags": []})),
+        Err(ConvertError::MissingField { path: String::new(), field: "age".to_string() })
+    );
+    assert_eq!(
+        User::from_json(&json!({"name": "Bob", "age": 25, "tags": ["a", 1]})),
+        Err(ConvertError::TypeMismatch { path: "/tags/1".to_string(), expected: "string", found: "number" })
+    );
+    assert_eq!(
+        Vec::<Wrapper<Point>>::from_json(&json!([{"inner": [1, 2], "list": [[1, 2, 3]]}])),
+        Err(ConvertError::WrongLength { path: "/0/list/0".to_string(), expected: 2, found: 3 })
+    );
+    assert_eq!(User::from_json(&json!([])), Err(ConvertError::TypeMismatch { path: String::new(), expected: "object", found: "array" }));
+    assert_eq!(Marker::from_json(&json!(1)), Err(ConvertError::TypeMismatch { path: String::new(), expected: "null", found: "number" }));
+}
+
+fn default_limit() -> u32 {
+    return 10
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug, Default)]
+struct Paging {
+    page: u32,
+    #[json(default = "default_limit")]
+    limit: u32
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+#[json(rename_all = "camelCase")]
+struct Query {
+    /// Doc comments and other attributes are left alone
+    user_id: u64,
+    #[json(rename = "q")]
+    search_text: String,
+    #[json(default)]
+    include_deleted: bool,
+    #[json(skip)]
+    cache: HashMap<String, String>,
+    #[json(flatten)]
+    paging: Paging,
+    r#type: String
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+struct Extra {
+    name: String,
+    #[json(flatten)]
+    extra: HashMap<String, JsonType>
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+#[json(default)]
+struct Settings {
+    verbose: bool,
+    level: u8,
+    name: String
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        return Settings { verbose: true, level: 3, name: "default".to_string() }
+    }
+}
+
+#[test]
+fn field_attributes() {
+    let query = Query {
+        user_id: 7,
+        search_text: "rust".to_string(),
+        include_deleted: false,
+        cache: HashMap::new(),
+        paging: Paging { page: 2, limit: 10 },
+        r#type: "full".to_string()
+    };
+    round_trip(query, json!({"userId": 7, "q": "rust", "includeDeleted": false, "page": 2, "limit": 10, "type": "full"}));
+
+    let minimal = Query::from_json(&json!({"userId": 1, "q": "", "page": 0, "type": "", "cache": {"ignored": "x"}})).unwrap();
+    assert!(!minimal.include_deleted);
+    assert!(minimal.cache.is_empty());
+    assert_eq!(minimal.paging, Paging { page: 0, limit: 10 });
+
+    let extra = Extra { name: "a".to_string(), extra: HashMap::from([("b".to_string(), json!(1))]) };
+    round_trip(extra, json!({"name": "a", "b": 1}));
+
+    assert_eq!(Settings::from_json(&json!({"level": 5})), Ok(Settings { verbose: true, level: 5, name: "default".to_string() }));
+    assert_eq!(
+        Query::from_json(&json!({"userId": 1, "q": "", "type": ""})),
+        Err(ConvertError::MissingField { path: String::new(), field: "page".to_string() })
+    );
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+enum External {
+    Unit,
+    #[json(rename = "new")]
+    Newtype(u8),
+    Tuple(u8, String),
+    Struct { id: u8, label: Option<String> }
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+#[json(tag = "type", rename_all = "snake_case")]
+enum Internal {
+    Ping,
+    Message { text: String },
+    Moved(Point2)
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+struct Point2 {
+    x: i32,
+    y: i32
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+#[json(rename_all = "camelCase")]
+enum Renamed {
+    SentMessage { user_id: u8 }
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+#[json(tag = "t", content = "c")]
+enum Adjacent {
+    Empty,
+    Value(i32),
+    Pair(i32, i32),
+    Named { a: bool }
+}
+
+#[derive(FromJson, ToJson, PartialEq, Debug)]
+#[json(untagged)]
+enum Untagged {
+    Number(f64),
+    Text(String),
+    Point { x: i32, y: i32 },
+    Nothing
+}
+
+#[test]
+fn enum_representations() {
+    round_trip(External::Unit, json!("Unit"));
+    round_trip(External::Newtype(1), json!({"new": 1}));
+    round_trip(External::Tuple(1, "a".to_string()), json!({"Tuple": [1, "a"]}));
+    round_trip(External::Struct { id: 1, label: None }, json!({"Struct": {"id": 1, "label": null}}));
+
+    round_trip(Internal::Ping, json!({"type": "ping"}));
+    round_trip(Internal::Message { text: "hi".to_string() }, json!({"type": "message", "text": "hi"}));
+    round_trip(Internal::Moved(Point2 { x: 1, y: 2 }), json!({"type": "moved", "x": 1, "y": 2}));
+
+    round_trip(Renamed::SentMessage { user_id: 1 }, json!({"sentMessage": {"user_id": 1}}));
+
+    round_trip(Adjacent::Empty, json!({"t": "Empty"}));
+    round_trip(Adjacent::Value(3), json!({"t": "Value", "c": 3}));
+    round_trip(Adjacent::Pair(1, 2), json!({"t": "Pair", "c": [1, 2]}));
+    round_trip(Adjacent::Named { a: true }, json!({"t": "Named", "c": {"a": true}}));
+
+    round_trip(Untagged::Number(1.5), json!(1.5));
+    round_trip(Untagged::Text("x".to_string()), json!("x"));
+    round_trip(Untagged::Point { x: 1, y: 2 }, json!({"x": 1, "y": 2}));
+    round_trip(Untagged::Nothing, json!(null));
+}
+
+#[test]
+fn enum_errors() {
+    assert_eq!(External::from_json(&json!("Other")), Err(ConvertError::UnknownVariant { path: String::new(), found: "Other".to_string() }));
+    assert_eq!(
+        External::from_json(&json!({"Struct": {"id": "x"}})),
+        Err(ConvertError::TypeMismatch { path: "/Struct/id".to_string(), expected: "number", found: "string" })
+    );
+    assert_eq!(
+        External::from_json(&json!({"a": 1, "b": 2})),
+        Err(ConvertError::TypeMismatch { path: String::new(), expected: "string or object with one member", found: "object" })
+    );
+    assert_eq!(Internal::from_json(&json!({"text": "hi"})), Err(ConvertError::MissingField { path: String::new(), field: "type".to_string() }));
+    assert_eq!(Internal::from_json(&json!({"type": "pong"})), Err(ConvertError::UnknownVariant { path: "/type".to_string(), found: "pong".to_string() }));
+    assert_eq!(
+        Adjacent::from_json(&json!({"t": "Value", "c": "3"})),
+        Err(ConvertError::TypeMismatch { path: "/c".to_string(), expected: "number", found: "string" })
+    );
+    assert_eq!(Adjacent::from_json(&json!({"t": "Value"})), Err(ConvertError::MissingField { path: String::new(), field: "c".to_string() }));
+    assert_eq!(Untagged::from_json(&json!([1])), Err(ConvertError::NoVariantMatched { path: String::new() }));
+}
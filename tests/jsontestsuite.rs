@@ -0,0 +1,116 @@
+//! Runs the parsing cases of the JSONTestSuite corpus (https://github.com/nst/JSONTestSuite)
+//! vendored under `tests/JSONTestSuite/test_parsing`.
+//!
+//! File names carry the expected outcome: `y_` must be accepted, `n_` must be
+//! rejected and `i_` is left to the implementation. Every case is run against
+//! the default parser and the strict mode, and a conformance table is printed
+//! (`cargo test --test jsontestsuite -- --nocapture` to see it). Only strict
+//! mode is required to pass all `y_` and `n_` cases.
+//!
+//! The harness picks up every `.json` file in that directory, so the vendored
+//! copy can be refreshed by dropping in a newer upstream `test_parsing` folder.
+
+use std::fs;
+use std::path::Path;
+
+use JSON_parser::parser::{parse_document_with, ParserOptions};
+use JSON_parser::recover::parse_recovering_with;
+
+struct Case {
+    name: String,
+    data: Vec<u8>,
+}
+
+fn load_cases() -> Vec<Case> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/JSONTestSuite/test_parsing");
+    let mut cases: Vec<Case> = fs::read_dir(&dir)
+        .expect("JSONTestSuite corpus is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .map(|path| Case {
+            name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+            data: fs::read(&path).unwrap(),
+        })
+        .collect();
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    return cases
+}
+
+fn accepts(data: &[u8], options: ParserOptions) -> bool {
+    // The parser works on &str, so invalid UTF-8 is rejected up front
+    match std::str::from_utf8(data) {
+        Ok(text) => parse_document_with(text, options).is_ok(),
+        Err(_) => false
+    }
+}
+
+#[derive(Default)]
+struct Tally {
+    accepted: usize,
+    rejected: usize,
+    failures: Vec<String>,
+}
+
+fn run(cases: &[Case], options: ParserOptions) -> [Tally; 3] {
+    let mut tallies: [Tally; 3] = Default::default();
+    for case in cases {
+        let accepted = accepts(&case.data, options);
+        let (index, expected) = match case.name.get(..2) {
+            Some("y_") => (0, Some(true)),
+            Some("n_") => (1, Some(false)),
+            _ => (2, None)
+        };
+        let tally = &mut tallies[index];
+        if accepted {
+            tally.accepted += 1;
+        } else {
+            tally.rejected += 1;
+        }
+        if expected.is_some_and(|e| e != accepted) {
+            tally.failures.push(case.name.clone());
+        }
+    }
+    return tallies
+}
+
+fn print_table(cases: &[Case], modes: &[(&str, &[Tally; 3])]) {
+    println!();
+    println!("JSONTestSuite conformance ({} cases)", cases.len());
+    println!("{:<10} {:<6} {:>8} {:>8} {:>8}", "mode", "kind", "accepted", "rejected", "failing");
+    for (mode, tallies) in modes {
+        for (kind, tally) in ["y_", "n_", "i_"].iter().zip(tallies.iter()) {
+            let failing = if *kind == "i_" { "-".to_string() } else { tally.failures.len().to_string() };
+            println!("{:<10} {:<6} {:>8} {:>8} {:>8}", mode, kind, tally.accepted, tally.rejected, failing);
+        }
+    }
+    for (mode, tallies) in modes {
+        for failure in tallies.iter().flat_map(|t| t.failures.iter()) {
+            println!("{mode}: {failure}");
+        }
+    }
+}
+
+// The default depth limit rejects the 100000 level n_ cases before they can
+// overflow the stack, so this runs on the ordinary test thread
+#[test]
+fn jsontestsuite_conformance() {
+    let cases = load_cases();
+    assert!(!cases.is_empty());
+
+    let default = ParserOptions::default();
+    let strict = ParserOptions { strict: true, ..default };
+    let default_tallies = run(&cases, default);
+    let strict_tallies = run(&cases, strict);
+    print_table(&cases, &[("default", &default_tallies), ("strict", &strict_tallies)]);
+
+    assert_eq!(strict_tallies[0].failures, Vec::<String>::new(), "strict mode rejected valid documents");
+    assert_eq!(strict_tallies[1].failures, Vec::<String>::new(), "strict mode accepted invalid documents");
+
+    // The recovering parser must finish on every case and agree on validity
+    for case in cases.iter().filter(|c| c.name.starts_with("y_") || c.name.starts_with("n_")) {
+        if let Ok(text) = std::str::from_utf8(&case.data) {
+            let (_, errors) = parse_recovering_with(text, strict);
+            assert_eq!(errors.is_empty(), case.name.starts_with("y_"), "recovering parser disagrees on {}", case.name);
+        }
+    }
+}